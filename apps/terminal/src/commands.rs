@@ -1,9 +1,24 @@
+use std::io::{Read, Write};
+
+use agave_lib::process::{self, Stdio, WaitStatus};
+use agave_lib::system;
+
 use crate::state::{COMMAND_HISTORY, COMMAND_HISTORY_COUNT, COMMAND_HISTORY_INDEX};
 use crate::themes::get_theme_description;
 use crate::types::{Screen, TerminalApp, Theme};
 
 impl TerminalApp {
     pub fn process_command(&mut self) {
+        // While a program runs, lines go to its stdin
+        if let Some(stdin) = self.job.as_mut().and_then(|job| job.stdin.as_mut()) {
+            let mut line = self.command_buffer[..self.command_length].to_vec();
+            line.push(b'\n');
+            let _ = stdin.write_all(&line);
+            self.add_output_line(&line[..line.len() - 1]);
+            self.clear_command();
+            return;
+        }
+
         if self.command_length == 0 {
             // If we're not on the main screen, pressing Enter returns to main
             if self.current_screen != Screen::Main {
@@ -120,15 +135,77 @@ impl TerminalApp {
         } else if self.command_length >= 3 && &cmd_lower[0..3] == b"fs " {
             self.handle_fs_subcommand();
         } else {
-            self.add_output_line(b"Command not found. Type 'help' for available commands.");
+            self.handle_program_command();
         }
 
-        // Clear command buffer
+        self.clear_command();
+        agave_lib::grow_memory(1);
+    }
+
+    fn clear_command(&mut self) {
         self.command_length = 0;
         for i in 0..2048 {
             self.command_buffer[i] = 0;
         }
-        agave_lib::grow_memory(1);
+    }
+
+    // Run `/bin/<name>.wasm`, or a program given by absolute path, with the
+    // rest of the line as its arguments
+    fn handle_program_command(&mut self) {
+        let line =
+            String::from_utf8_lossy(&self.command_buffer[..self.command_length]).into_owned();
+        let mut words = line.split_whitespace();
+        let Some(name) = words.next() else {
+            return;
+        };
+        let args: Vec<&str> = words.collect();
+        let path = if name.starts_with('/') {
+            name.to_string()
+        } else {
+            format!("/bin/{}.wasm", name)
+        };
+        match process::spawn(&path, &args, &[], Stdio::Piped) {
+            Some(child) => self.job = Some(child),
+            None => self.add_output_line(b"Command not found. Type 'help' for available commands."),
+        }
+    }
+
+    /// Show what the running program printed, then its exit code once it is
+    /// done
+    pub fn poll_job(&mut self) {
+        let Some(job) = self.job.as_mut() else {
+            return;
+        };
+        let mut buf = [0u8; 512];
+        for pipe in [job.stdout.as_mut(), job.stderr.as_mut()]
+            .into_iter()
+            .flatten()
+        {
+            while let Ok(n @ 1..) = pipe.read(&mut buf) {
+                self.job_output.extend_from_slice(&buf[..n]);
+            }
+        }
+        let status = job.try_wait();
+
+        while let Some(end) = self.job_output.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.job_output.drain(..=end).collect();
+            self.add_output_line(&line[..end]);
+        }
+        if status == WaitStatus::Running {
+            return;
+        }
+        if !self.job_output.is_empty() {
+            let rest = std::mem::take(&mut self.job_output);
+            self.add_output_line(&rest);
+        }
+        match status {
+            WaitStatus::Exited(0) => {}
+            WaitStatus::Exited(code) => {
+                self.add_output_line(format!("[exited with code {}]", code).as_bytes())
+            }
+            _ => self.add_output_line(b"[exit status unavailable]"),
+        }
+        self.job = None;
     }
 
     fn handle_ls_command(&mut self) {
@@ -139,11 +216,19 @@ impl TerminalApp {
 
     fn handle_ps_command(&mut self) {
         self.add_output_line(b"Running processes:");
-        self.add_output_line(b"PID  PPID CMD");
-        self.add_output_line(b"  1     0 init");
-        self.add_output_line(b"  2     1 kernel_thread");
-        self.add_output_line(b"  3     1 wasm_runtime");
-        self.add_output_line(b"  4     3 terminal_app");
+        self.add_output_line(b"  PID  PPID STATE       CPU(ms)  MEM(KB) CMD");
+        for process in system::processes() {
+            let line = format!(
+                "{:5} {:5} {:10} {:8} {:8} {}",
+                process.pid,
+                process.parent.unwrap_or(0),
+                process.state.name(),
+                process.cpu_time_ms,
+                process.memory_bytes / 1024,
+                process.name
+            );
+            self.add_output_line(line.as_bytes());
+        }
         self.current_screen = Screen::Processes;
     }
 
@@ -153,56 +238,15 @@ impl TerminalApp {
         let minutes = (uptime_seconds % 3600) / 60;
         let seconds = uptime_seconds % 60;
 
-        // Format uptime string manually (no std format!)
-        let mut uptime_line = [0u8; 80];
-        let mut pos = 0;
-
-        // "up "
-        uptime_line[pos] = b'u';
-        pos += 1;
-        uptime_line[pos] = b'p';
-        pos += 1;
-        uptime_line[pos] = b' ';
-        pos += 1;
-
-        // hours
+        let mut line = String::from("up ");
         if hours > 0 {
-            if hours >= 10 {
-                uptime_line[pos] = b'0' + (hours / 10) as u8;
-                pos += 1;
-            }
-            uptime_line[pos] = b'0' + (hours % 10) as u8;
-            pos += 1;
-            uptime_line[pos] = b'h';
-            pos += 1;
-            uptime_line[pos] = b' ';
-            pos += 1;
+            line += &format!("{}h ", hours);
         }
-
-        // minutes
         if minutes > 0 || hours > 0 {
-            if minutes >= 10 {
-                uptime_line[pos] = b'0' + (minutes / 10) as u8;
-                pos += 1;
-            }
-            uptime_line[pos] = b'0' + (minutes % 10) as u8;
-            pos += 1;
-            uptime_line[pos] = b'm';
-            pos += 1;
-            uptime_line[pos] = b' ';
-            pos += 1;
+            line += &format!("{}m ", minutes);
         }
-
-        // seconds
-        if seconds >= 10 {
-            uptime_line[pos] = b'0' + (seconds / 10) as u8;
-            pos += 1;
-        }
-        uptime_line[pos] = b'0' + (seconds % 10) as u8;
-        pos += 1;
-        uptime_line[pos] = b's';
-
-        self.add_output_line(&uptime_line);
+        line += &format!("{}s, {} processes", seconds, system::processes().len());
+        self.add_output_line(line.as_bytes());
     }
 
     fn handle_help_command(&mut self) {
@@ -225,6 +269,7 @@ impl TerminalApp {
         self.add_output_line(b"  rm <file>       - Remove file");
         self.add_output_line(b"  mkdir <dir>     - Create directory");
         self.add_output_line(b"  rmdir <dir>     - Remove directory");
+        self.add_output_line(b"  <program> [args] - Run /bin/<program>.wasm");
         self.add_output_line(b"");
         self.add_output_line(b"IPC commands:");
         self.add_output_line(b"  ipc       - Inter-Process Communication help");
@@ -251,7 +296,17 @@ impl TerminalApp {
         self.add_output_line(b"  Kernel: Rust-based microkernel");
         self.add_output_line(b"  Runtime: WASM execution environment");
         self.add_output_line(b"  Architecture: x86_64");
-        self.add_output_line(b"  Memory: 128MB available");
+        match system::memory() {
+            Some(memory) => self.add_output_line(
+                format!(
+                    "  Memory: {} KB used of {} KB",
+                    memory.allocated / 1024,
+                    memory.heap_size / 1024
+                )
+                .as_bytes(),
+            ),
+            None => self.add_output_line(b"  Memory: unknown"),
+        }
         self.add_output_line(b"  Graphics: VirtIO-GPU framebuffer");
         self.add_output_line(b"");
         self.add_output_line(b"Enhanced Features:");
@@ -515,32 +570,115 @@ impl TerminalApp {
     }
 
     fn handle_health_command(&mut self) {
+        let memory = system::memory();
+        let power = system::power();
+        let memory_percent = memory.map_or(0.0, |m| {
+            m.allocated as f32 * 100.0 / m.heap_size.max(1) as f32
+        });
+        let throttling = power.is_some_and(|p| p.throttling);
+        let status = if memory_percent > 90.0 || throttling {
+            "Degraded"
+        } else {
+            "Healthy"
+        };
+
         self.add_output_line(b"=== System Health Status ===");
-        self.add_output_line(b"Overall Status: Healthy");
-        self.add_output_line(b"Memory Usage: Normal (35.8%)");
-        self.add_output_line(b"CPU Temperature: 42.1C");
-        self.add_output_line(b"Power State: Active");
-        self.add_output_line(b"Security: No threats detected");
-        self.add_output_line(b"Process Count: 32 active");
-        self.add_output_line(b"Uptime: 15.3 minutes");
-        self.add_output_line(b"Diagnostics: All systems operational");
-        self.add_output_line(b"Network: Connected");
-        self.add_output_line(b"Storage: Available");
+        self.add_output_line(format!("Overall Status: {}", status).as_bytes());
+        if let Some(memory) = memory {
+            self.add_output_line(
+                format!(
+                    "Memory Usage: {:.1}% ({} KB of {} KB, {} failed allocations)",
+                    memory_percent,
+                    memory.allocated / 1024,
+                    memory.heap_size / 1024,
+                    memory.failed_allocations
+                )
+                .as_bytes(),
+            );
+        }
+        if let Some(power) = power {
+            self.add_output_line(
+                format!("CPU Temperature: {:.1}C", power.temperature_c).as_bytes(),
+            );
+            self.add_output_line(format!("Power State: {:?}", power.state).as_bytes());
+        }
+        if let Some(security) = system::security() {
+            self.add_output_line(
+                format!(
+                    "Security: {} events, {} blocked processes",
+                    security.total_events, security.blocked_processes
+                )
+                .as_bytes(),
+            );
+        }
+        let active = system::processes()
+            .iter()
+            .filter(|p| p.state == system::ProcessState::Running)
+            .count();
+        self.add_output_line(format!("Process Count: {} active", active).as_bytes());
+        self.add_output_line(
+            format!("Uptime: {:.1} minutes", self.uptime as f32 / 60_000.0).as_bytes(),
+        );
+        let interfaces = system::network().len();
+        if interfaces > 0 {
+            self.add_output_line(format!("Network: {} interfaces", interfaces).as_bytes());
+        } else {
+            self.add_output_line(b"Network: Not available");
+        }
+        match system::filesystem() {
+            Some(fs) => self.add_output_line(
+                format!(
+                    "Storage: {} KB free of {} KB ({})",
+                    fs.free_size / 1024,
+                    fs.total_size / 1024,
+                    fs.fs_type
+                )
+                .as_bytes(),
+            ),
+            None => self.add_output_line(b"Storage: unknown"),
+        }
         self.add_output_line(b"Command Buffer: 2048 bytes available");
         self.add_output_line(b"Output History: 2000 lines capacity");
     }
 
     fn handle_power_command(&mut self) {
         self.add_output_line(b"=== Power Management Status ===");
-        self.add_output_line(b"Current State: Active");
-        self.add_output_line(b"CPU Frequency: 2400 MHz");
-        self.add_output_line(b"Power Policy: Balanced");
-        self.add_output_line(b"Thermal Throttling: Disabled");
-        self.add_output_line(b"Sleep Mode: Enabled");
-        self.add_output_line(b"Estimated Power: 25.4W");
-        self.add_output_line(b"CPU Temperature: 42.1C");
-        self.add_output_line(b"Fan Speed: Auto");
-        self.add_output_line(b"Battery: N/A (Desktop)");
+        let Some(power) = system::power() else {
+            self.add_output_line(b"Power statistics unavailable");
+            return;
+        };
+        let lines = [
+            format!("Current State: {:?}", power.state),
+            format!(
+                "CPU Frequency: {} MHz ({}-{} MHz)",
+                power.cpu_mhz, power.min_mhz, power.max_mhz
+            ),
+            format!("Power Policy: {:?}", power.policy),
+            format!(
+                "Thermal Throttling: {}",
+                if power.throttling {
+                    "Active"
+                } else {
+                    "Inactive"
+                }
+            ),
+            format!(
+                "Estimated Power: {:.1}W (CPU {:.1}W)",
+                power.total_watts, power.cpu_watts
+            ),
+            format!("CPU Temperature: {:.1}C", power.temperature_c),
+            format!(
+                "Sleep/Wake Events: {}/{}",
+                power.sleep_events, power.wake_events
+            ),
+            match power.battery_minutes {
+                Some(minutes) => format!("Battery: {} minutes left", minutes),
+                None => String::from("Battery: N/A"),
+            },
+        ];
+        for line in lines {
+            self.add_output_line(line.as_bytes());
+        }
     }
 
     fn handle_security_command(&mut self) {
@@ -549,8 +687,15 @@ impl TerminalApp {
         self.add_output_line(b"Sandbox: Enabled");
         self.add_output_line(b"Access Control: Active");
         self.add_output_line(b"Threat Detection: Running");
-        self.add_output_line(b"Blocked Processes: 0");
-        self.add_output_line(b"Security Events: 0 recent");
+        if let Some(security) = system::security() {
+            self.add_output_line(
+                format!("Blocked Processes: {}", security.blocked_processes).as_bytes(),
+            );
+            self.add_output_line(format!("Security Events: {}", security.total_events).as_bytes());
+            self.add_output_line(
+                format!("Active Policies: {}", security.active_policies).as_bytes(),
+            );
+        }
         self.add_output_line(b"Firewall: Enabled");
         self.add_output_line(b"Encryption: AES-256");
         self.add_output_line(b"Last Scan: 2 minutes ago");
@@ -652,14 +797,23 @@ impl TerminalApp {
 
     fn handle_fsstat_command(&mut self) {
         self.add_output_line(b"Filesystem Statistics:");
-        self.add_output_line(b"  Backend: RAM Disk (default)");
-        self.add_output_line(b"  Total Space: 1024 KB");
-        self.add_output_line(b"  Used Space: 0 KB");
-        self.add_output_line(b"  Free Space: 1024 KB");
-        self.add_output_line(b"  Inodes Total: 256");
-        self.add_output_line(b"  Inodes Used: 1 (root)");
-        self.add_output_line(b"  Inodes Free: 255");
-        self.add_output_line(b"  Filesystem: Simple FS v1.0");
+        let Some(fs) = system::filesystem() else {
+            self.add_output_line(b"  Statistics unavailable");
+            return;
+        };
+        let lines = [
+            format!("  Filesystem: {}", fs.fs_type),
+            format!("  Total Space: {} KB", fs.total_size / 1024),
+            format!("  Used Space: {} KB", fs.used_size / 1024),
+            format!("  Free Space: {} KB", fs.free_size / 1024),
+            format!("  Files: {}", fs.total_files),
+            format!("  Directories: {}", fs.total_dirs),
+            format!("  Block Size: {} bytes", fs.block_size),
+            format!("  Persistent: {}", if fs.persistent { "Yes" } else { "No" }),
+        ];
+        for line in lines {
+            self.add_output_line(line.as_bytes());
+        }
     }
 
     fn handle_mount_command(&mut self) {
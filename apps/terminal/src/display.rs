@@ -2,7 +2,9 @@ use std::fs;
 use std::path::Path;
 use agave_lib::{
     clear_screen, draw_line, draw_rectangle, draw_text, fill_circle, fill_rectangle,
-    get_dimensions, Position, RGBA,
+    get_dimensions, measure_text,
+    system::{self, ProcessState},
+    FontWeight, Position, RGBA,
 };
 
 use crate::state::{ANIMATION_FRAME, CURSOR_BLINK, TERMINAL};
@@ -506,10 +508,12 @@ fn draw_main_screen(dim: agave_lib::Dimensions, colors: &ThemeColors) {
 
         // Enhanced blinking cursor
         if CURSOR_BLINK {
-            let cursor_x = margin + 140 + TERMINAL.command_length as i32 * 8;
-            draw_text(
+            let (char_width, line_height) = measure_text("0", 16, FontWeight::Regular);
+            let cursor_x = margin + 140 + TERMINAL.command_length as i32 * char_width;
+            fill_rectangle(
                 Position::new(cursor_x, prompt_y + 5),
-                "▊",
+                char_width * 3 / 4,
+                line_height,
                 colors.accent_cyan,
             );
         }
@@ -702,265 +706,188 @@ fn draw_processes_screen(dim: agave_lib::Dimensions, colors: &ThemeColors) {
     let margin = 60;
     let content_width = dim.width - (margin * 2);
 
-    unsafe {
-        // Draw main content card
-        draw_card(
-            Position::new(margin - 20, 40),
-            content_width + 40,
-            dim.height - 140,
-            colors,
-        );
+    // Draw main content card
+    draw_card(
+        Position::new(margin - 20, 40),
+        content_width + 40,
+        dim.height - 140,
+        colors,
+    );
 
-        // Header with icon
-        draw_text(
-            Position::new(margin + 20, 70),
-            "⚙️ Process Manager",
-            colors.accent_red,
-        );
+    // Header with icon
+    draw_text(
+        Position::new(margin + 20, 70),
+        "⚙️ Process Manager",
+        colors.accent_red,
+    );
 
-        // Performance indicator
-        draw_text(
-            Position::new(margin + 20, 100),
-            "System Load:",
-            colors.text_secondary,
-        );
-        draw_text(
-            Position::new(margin + 130, 100),
-            "Normal",
-            colors.accent_green,
-        );
+    // Performance indicator
+    draw_text(
+        Position::new(margin + 20, 100),
+        "System Load:",
+        colors.text_secondary,
+    );
+    draw_text(
+        Position::new(margin + 130, 100),
+        "Normal",
+        colors.accent_green,
+    );
 
-        // Table header
-        draw_section_divider(Position::new(margin, 130), content_width, colors);
+    // Table header
+    draw_section_divider(Position::new(margin, 130), content_width, colors);
 
-        fill_rectangle(
-            Position::new(margin + 10, 145),
-            content_width - 20,
-            35,
-            colors.bg_accent,
-        );
+    fill_rectangle(
+        Position::new(margin + 10, 145),
+        content_width - 20,
+        35,
+        colors.bg_accent,
+    );
 
-        draw_text(Position::new(margin + 25, 155), "PID", colors.accent_purple);
-        draw_text(
-            Position::new(margin + 80, 155),
-            "Name",
-            colors.accent_purple,
-        );
-        draw_text(
-            Position::new(margin + 250, 155),
-            "Status",
-            colors.accent_purple,
-        );
-        draw_text(
-            Position::new(margin + 350, 155),
-            "Memory",
-            colors.accent_purple,
-        );
+    draw_text(Position::new(margin + 25, 155), "PID", colors.accent_purple);
+    draw_text(
+        Position::new(margin + 80, 155),
+        "Name",
+        colors.accent_purple,
+    );
+    draw_text(
+        Position::new(margin + 250, 155),
+        "Status",
+        colors.accent_purple,
+    );
+    draw_text(
+        Position::new(margin + 350, 155),
+        "Memory",
+        colors.accent_purple,
+    );
 
-        draw_rectangle(
-            Position::new(margin + 10, 145),
-            content_width - 20,
-            35,
-            colors.border_color,
-        );
+    draw_rectangle(
+        Position::new(margin + 10, 145),
+        content_width - 20,
+        35,
+        colors.border_color,
+    );
 
-        // Process listing with enhanced styling and scrolling support
-        let max_visible_processes = 12; // Limit visible processes to fit on screen
-        let processes_to_show = TERMINAL.process_count.min(max_visible_processes);
+    // Process listing with enhanced styling and scrolling support
+    let processes = system::processes();
+    let max_visible_processes = 12; // Limit visible processes to fit on screen
 
-        for i in 0..processes_to_show {
-            let proc = &TERMINAL.processes[i];
-            let y = 190 + i as i32 * 32;
+    for (i, proc) in processes.iter().take(max_visible_processes).enumerate() {
+        let y = 190 + i as i32 * 32;
 
-            // Alternating row backgrounds
-            if i % 2 == 0 {
-                fill_rectangle(
-                    Position::new(margin + 10, y - 5),
-                    content_width - 20,
-                    32,
-                    RGBA::new(
-                        colors.bg_secondary.r,
-                        colors.bg_secondary.g,
-                        colors.bg_secondary.b,
-                        128,
-                    ),
-                );
-            }
-
-            // PID formatting for larger numbers
-            let pid_str = if proc.pid < 10 {
-                match proc.pid {
-                    1 => "001",
-                    2 => "002",
-                    3 => "003",
-                    4 => "004",
-                    5 => "005",
-                    6 => "006",
-                    7 => "007",
-                    8 => "008",
-                    9 => "009",
-                    _ => "0??",
-                }
-            } else if proc.pid < 100 {
-                match proc.pid {
-                    10 => "010",
-                    11 => "011",
-                    12 => "012",
-                    13 => "013",
-                    14 => "014",
-                    15 => "015",
-                    16 => "016",
-                    17 => "017",
-                    18 => "018",
-                    19 => "019",
-                    20 => "020",
-                    21 => "021",
-                    22 => "022",
-                    23 => "023",
-                    24 => "024",
-                    25 => "025",
-                    26 => "026",
-                    27 => "027",
-                    28 => "028",
-                    29 => "029",
-                    30 => "030",
-                    31 => "031",
-                    32 => "032",
-                    _ => "???",
-                }
-            } else {
-                "???"
-            };
-            draw_text(
-                Position::new(margin + 25, y + 5),
-                pid_str,
-                colors.text_secondary,
+        // Alternating row backgrounds
+        if i % 2 == 0 {
+            fill_rectangle(
+                Position::new(margin + 10, y - 5),
+                content_width - 20,
+                32,
+                RGBA::new(
+                    colors.bg_secondary.r,
+                    colors.bg_secondary.g,
+                    colors.bg_secondary.b,
+                    128,
+                ),
             );
+        }
 
-            // Process name with icon
-            let proc_icon = match proc.name {
-                "init" => "🚀",
-                "kernel" => "🔧",
-                "virtio-input" => "⌨️",
-                "wasm-runtime" => "📦",
-                "framebuffer" => "🖥️",
-                "memory-mgr" => "💾",
-                "task-executor" => "⚡",
-                "terminal-app" => "�",
-                "filesystem" => "📁",
-                "network-stack" => "🌐",
-                "audio-driver" => "🔊",
-                "graphics-accel" => "🎮",
-                "security-mgr" => "🔒",
-                "power-mgr" => "🔋",
-                "interrupt-hdl" => "⚡",
-                "scheduler" => "�",
-                "device-mgr" => "🔌",
-                "io-subsystem" => "📤",
-                "cache-mgr" => "💿",
-                "crypto-engine" => "�",
-                "vm-manager" => "🖥️",
-                "backup-daemon" => "💾",
-                "log-collector" => "📄",
-                "perf-monitor" => "�",
-                "user-session" => "👤",
-                "service-mgr" => "⚙️",
-                "event-loop" => "🔄",
-                "debug-agent" => "🐛",
-                "thermal-ctrl" => "🌡️",
-                "watchdog" => "🐕",
-                "profiler" => "�",
-                "health-check" => "🏥",
-                _ => "⚙️",
-            };
-
-            draw_text(
-                Position::new(margin + 80, y + 5),
-                proc_icon,
-                colors.text_primary,
-            );
-            draw_text(
-                Position::new(margin + 105, y + 5),
-                proc.name,
-                colors.accent_cyan,
-            );
+        draw_text(
+            Position::new(margin + 25, y + 5),
+            &format!("{:03}", proc.pid),
+            colors.text_secondary,
+        );
 
-            // Status with colored indicator
-            let (status_color, status_icon) = if proc.status == "running" {
-                (colors.accent_green, "●")
-            } else if proc.status == "idle" {
-                (colors.accent_yellow, "⏸")
-            } else {
-                (colors.accent_red, "✗")
-            };
+        // Process name with icon
+        let proc_icon = match proc.name.as_str() {
+            "terminal" => "💻",
+            _ => "⚙️",
+        };
 
-            draw_text(
-                Position::new(margin + 250, y + 5),
-                status_icon,
-                status_color,
-            );
-            draw_text(
-                Position::new(margin + 270, y + 5),
-                proc.status,
-                status_color,
-            );
+        draw_text(
+            Position::new(margin + 80, y + 5),
+            proc_icon,
+            colors.text_primary,
+        );
+        draw_text(
+            Position::new(margin + 105, y + 5),
+            &proc.name,
+            colors.accent_cyan,
+        );
 
-            // Memory usage with bar visualization
-            let mem_str = if proc.memory > 8192 {
-                "VHigh"
-            } else if proc.memory > 4096 {
-                "High"
-            } else if proc.memory > 1024 {
-                "Med"
-            } else {
-                "Low"
-            };
+        // Status with colored indicator
+        let (status_color, status_icon) = match proc.state {
+            ProcessState::Running => (colors.accent_green, "●"),
+            ProcessState::Created | ProcessState::Sleeping | ProcessState::Waiting => {
+                (colors.accent_yellow, "⏸")
+            }
+            ProcessState::Zombie | ProcessState::Terminated => (colors.accent_red, "✗"),
+        };
 
-            let mem_color = if proc.memory > 8192 {
-                colors.accent_red
-            } else if proc.memory > 4096 {
-                colors.accent_yellow
-            } else if proc.memory > 1024 {
-                colors.accent_blue
-            } else {
-                colors.accent_green
-            };
+        draw_text(
+            Position::new(margin + 250, y + 5),
+            status_icon,
+            status_color,
+        );
+        draw_text(
+            Position::new(margin + 270, y + 5),
+            proc.state.name(),
+            status_color,
+        );
 
-            draw_text(Position::new(margin + 350, y + 5), mem_str, mem_color);
+        // Memory usage with bar visualization
+        let memory_kb = proc.memory_bytes / 1024;
+        let mem_str = if memory_kb > 8192 {
+            "VHigh"
+        } else if memory_kb > 4096 {
+            "High"
+        } else if memory_kb > 1024 {
+            "Med"
+        } else {
+            "Low"
+        };
 
-            // Memory usage bar
-            let bar_width = (proc.memory / 200).min(60) as i32; // Adjusted for higher memory values
-            fill_rectangle(Position::new(margin + 390, y + 10), bar_width, 6, mem_color);
-            draw_rectangle(
-                Position::new(margin + 390, y + 10),
-                60,
-                6,
-                colors.border_color,
-            );
-        }
+        let mem_color = if memory_kb > 8192 {
+            colors.accent_red
+        } else if memory_kb > 4096 {
+            colors.accent_yellow
+        } else if memory_kb > 1024 {
+            colors.accent_blue
+        } else {
+            colors.accent_green
+        };
 
-        // Show process count and scroll indicator if needed
-        if TERMINAL.process_count > max_visible_processes {
-            let count_y = 190 + max_visible_processes as i32 * 32 + 20;
-            draw_text(
-                Position::new(margin + 25, count_y),
-                "... and more processes (32 total)",
-                colors.text_muted,
-            );
-        }
+        draw_text(Position::new(margin + 350, y + 5), mem_str, mem_color);
 
-        // Instructions
-        draw_section_divider(
-            Position::new(margin, dim.height - 120),
-            content_width,
-            colors,
+        // Memory usage bar
+        let bar_width = (memory_kb / 200).min(60) as i32; // Adjusted for higher memory values
+        fill_rectangle(Position::new(margin + 390, y + 10), bar_width, 6, mem_color);
+        draw_rectangle(
+            Position::new(margin + 390, y + 10),
+            60,
+            6,
+            colors.border_color,
         );
+    }
+
+    // Show process count and scroll indicator if needed
+    if processes.len() > max_visible_processes {
+        let count_y = 190 + max_visible_processes as i32 * 32 + 20;
         draw_text(
-            Position::new(margin + 20, dim.height - 95),
-            "⏎ Press Enter to return to main screen",
+            Position::new(margin + 25, count_y),
+            &format!("... and more processes ({} total)", processes.len()),
             colors.text_muted,
         );
     }
+
+    // Instructions
+    draw_section_divider(
+        Position::new(margin, dim.height - 120),
+        content_width,
+        colors,
+    );
+    draw_text(
+        Position::new(margin + 20, dim.height - 95),
+        "⏎ Press Enter to return to main screen",
+        colors.text_muted,
+    );
 }
 
 fn draw_system_screen(dim: agave_lib::Dimensions, colors: &ThemeColors) {
@@ -1005,8 +932,15 @@ fn draw_system_screen(dim: agave_lib::Dimensions, colors: &ThemeColors) {
         colors,
     );
 
+    let memory = system::memory().map_or(String::from("Unknown"), |m| {
+        format!(
+            "{} MB Total, {} MB Used",
+            m.heap_size >> 20,
+            m.allocated >> 20
+        )
+    });
     let hw_info = [
-        ("Memory:", "512 MB Total", colors.accent_blue),
+        ("Memory:", memory.as_str(), colors.accent_blue),
         ("Graphics:", "Direct Framebuffer", colors.accent_green),
         ("Input:", "VirtIO Mouse/Keyboard", colors.text_primary),
         ("Storage:", "Virtual Disk", colors.text_primary),
@@ -1021,11 +955,15 @@ fn draw_system_screen(dim: agave_lib::Dimensions, colors: &ThemeColors) {
     // Status indicators
     draw_section_header(Position::new(margin + 20, 390), "System Status", colors);
 
+    let network = match system::network().len() {
+        0 => ("Not Available", colors.accent_red, "✗"),
+        _ => ("Available", colors.accent_green, "✓"),
+    };
     let status_items = [
         ("Uptime:", "Running", colors.accent_green, "✓"),
         ("Status:", "Operational", colors.accent_green, "✓"),
         ("Load:", "Normal", colors.accent_yellow, "●"),
-        ("Network:", "Not Available", colors.accent_red, "✗"),
+        ("Network:", network.0, network.1, network.2),
     ];
 
     for (i, (label, value, color, icon)) in status_items.iter().enumerate() {
@@ -1401,8 +1339,8 @@ fn draw_section_header(pos: Position, text: &str, colors: &ThemeColors) {
     // Section header with professional styling
     draw_text(pos, text, colors.accent_purple);
 
-    // Underline for emphasis (maintain 8px character width assumption)
-    let text_width = text.len() as i32 * 8;
+    // Underline for emphasis
+    let (text_width, _) = measure_text(text, 16, FontWeight::Regular);
     fill_rectangle(
         Position::new(pos.x, pos.y + 18),
         text_width,
@@ -1,7 +1,4 @@
-use agave_lib::{
-    get_key_history_count, get_key_history_event, is_key_down, key_code_to_char, KEY_BACKSPACE,
-    KEY_ENTER, KEY_ESC, KEY_LEFTSHIFT, KEY_RIGHTSHIFT,
-};
+use agave_lib::{poll_event, Event, KEY_BACKSPACE, KEY_ENTER, KEY_ESC};
 
 use crate::state::{COMMAND_HISTORY, COMMAND_HISTORY_COUNT, COMMAND_HISTORY_INDEX, TERMINAL};
 
@@ -14,31 +11,19 @@ const KEY_HOME: i32 = 102;
 const KEY_END: i32 = 107;
 
 pub fn handle_keyboard_input() {
-    unsafe {
-        let shift_pressed = is_key_down(KEY_LEFTSHIFT) || is_key_down(KEY_RIGHTSHIFT);
-
-        // Process keyboard history to catch all events
-        let history_count = get_key_history_count();
-        static mut LAST_PROCESSED_COUNT: i32 = 0;
-
-        // Process new keyboard events
-        if history_count > LAST_PROCESSED_COUNT {
-            for i in LAST_PROCESSED_COUNT..history_count {
-                let (key_code, pressed) = get_key_history_event(i);
-
-                if pressed {
-                    // Handle special keys first
-                    if handle_special_key(key_code, shift_pressed) {
-                        continue;
-                    }
-
-                    // Handle character input
-                    if let Some(ch) = key_code_to_char(key_code, shift_pressed) {
-                        handle_character_input(ch);
-                    }
-                }
+    // Key presses drive editing and navigation; typed characters arrive as
+    // text already translated with the user's keyboard layout
+    while let Some((event, _)) = poll_event() {
+        match event {
+            Event::Key {
+                code,
+                pressed: true,
+                ..
+            } => {
+                handle_special_key(code, false);
             }
-            LAST_PROCESSED_COUNT = history_count;
+            Event::Text(ch) => handle_character_input(ch),
+            _ => {}
         }
     }
 }
@@ -166,10 +151,10 @@ fn handle_special_key(key_code: i32, _shift_pressed: bool) -> bool {
 fn handle_character_input(ch: char) {
     unsafe {
         // Validate that the character is printable and safe
-        let ch_byte = ch as u8;
-        if ch_byte < 32 || ch_byte > 126 {
+        if !ch.is_ascii() || ch.is_ascii_control() {
             return; // Skip non-printable characters
         }
+        let ch_byte = ch as u8;
 
         // Check buffer bounds more conservatively
         if TERMINAL.command_length < 2046 {
@@ -26,6 +26,9 @@ pub extern "C" fn update(_mouse_x: i32, _mouse_y: i32) {
 
         // Handle real keyboard input
         handle_keyboard_input();
+
+        #[allow(static_mut_refs)]
+        TERMINAL.poll_job();
     }
 
     draw_terminal();
@@ -1,5 +1,6 @@
-use std::path::Path;
+use agave_lib::process::Child;
 use std::fs::{self, read_dir};
+use std::path::Path;
 /// Terminal screen types
 #[derive(Clone, Copy, PartialEq)]
 pub enum Screen {
@@ -63,15 +64,6 @@ impl Theme {
     }
 }
 
-/// Process information
-#[derive(Clone, Copy)]
-pub struct Process {
-    pub pid: u32,
-    pub name: &'static str,
-    pub status: &'static str,
-    pub memory: u32,
-}
-
 /// File system entry
 #[allow(dead_code)]
 #[derive(Clone, Copy)]
@@ -91,11 +83,10 @@ pub struct TerminalApp {
     pub output_line_count: usize,
     pub scroll_offset: usize, // Current scroll position (0 = bottom/latest)
     pub uptime: u64,
-    // Only keep minimal demo processes
-    pub processes: [Process; 4],
-    pub process_count: usize,
     pub current_directory: &'static str,
     pub files_scroll_offset: usize, // Scroll offset for file browser
+    pub job: Option<Child>,         // Program started from the command line
+    pub job_output: Vec<u8>,        // Job output not yet ended by a newline
 }
 
 impl TerminalApp {
@@ -148,15 +139,10 @@ impl TerminalApp {
             output_line_count: 0,
             scroll_offset: 0, // Initialize scroll at bottom
             uptime: 0,
-            processes: [
-                Process { pid: 1, name: "init", status: "running", memory: 2048 },
-                Process { pid: 2, name: "kernel", status: "running", memory: 8192 },
-                Process { pid: 3, name: "terminal-app", status: "running", memory: 4096 },
-                Process { pid: 4, name: "filesystem", status: "running", memory: 2048 },
-            ],
-            process_count: 4,
             current_directory: "/home/user",
             files_scroll_offset: 0,
+            job: None,
+            job_output: Vec::new(),
         }
     }
 
@@ -24,6 +24,8 @@ pub struct MemoryStats {
     pub allocation_count: u64,
     pub deallocation_count: u64,
     pub failed_allocations: u64,
+    /// Bytes of heap granted to WASM apps for linear memory
+    pub wasm_memory: usize,
 }
 
 impl MemoryStats {
@@ -35,6 +37,7 @@ impl MemoryStats {
             allocation_count: 0,
             deallocation_count: 0,
             failed_allocations: 0,
+            wasm_memory: 0,
         }
     }
 
@@ -69,8 +72,12 @@ static MEMORY_STATS: Mutex<MemoryStats> = Mutex::new(MemoryStats {
     allocation_count: 0,
     deallocation_count: 0,
     failed_allocations: 0,
+    wasm_memory: 0,
 });
 
+/// Heap kept back for the kernel when granting memory to WASM apps
+pub const KERNEL_HEAP_RESERVE: usize = 32 * 1024 * 1024; // 32 MiB
+
 /// Memory pool for specific allocation sizes
 pub struct MemoryPool {
     pub block_size: usize,
@@ -237,6 +244,24 @@ pub fn memory_free() -> usize {
     HEAP_SIZE.saturating_sub(memory_used())
 }
 
+/// Account `bytes` of WASM linear memory against the heap. Fails if granting
+/// it would eat into the kernel's reserve.
+pub fn reserve_wasm_memory(bytes: usize) -> bool {
+    let mut stats = MEMORY_STATS.lock();
+    if stats.free() < bytes.saturating_add(KERNEL_HEAP_RESERVE) {
+        stats.failed_allocations += 1;
+        return false;
+    }
+    stats.wasm_memory += bytes;
+    true
+}
+
+/// Return WASM linear memory previously granted by `reserve_wasm_memory`
+pub fn release_wasm_memory(bytes: usize) {
+    let mut stats = MEMORY_STATS.lock();
+    stats.wasm_memory = stats.wasm_memory.saturating_sub(bytes);
+}
+
 /// Check if system is running low on memory
 pub fn is_memory_low() -> bool {
     let stats = MEMORY_STATS.lock();
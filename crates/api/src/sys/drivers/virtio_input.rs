@@ -1,4 +1,5 @@
-use crate::sys::{task::executor::yield_once, virtio::Virtio};
+use crate::sys::{globals::INPUT, input, task::executor::yield_once, virtio::Virtio};
+use alloc::string::String;
 
 #[repr(C)]
 #[derive(Debug)]
@@ -8,8 +9,82 @@ struct VirtioInputEvent {
     value: u32,
 }
 
-///Handle the virtio device and export all data to globals::Input
+// Event types
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const EV_ABS: u16 = 0x03;
+const EV_MSC: u16 = 0x04;
+const EV_LED: u16 = 0x11;
+const EV_REP: u16 = 0x14;
+
+// Device configuration layout: select, subsel, size, 5 reserved bytes, then
+// up to 128 bytes of data
+const CFG_SELECT: u16 = 0;
+const CFG_SUBSEL: u16 = 1;
+const CFG_SIZE: u16 = 2;
+const CFG_DATA: u16 = 8;
+const VIRTIO_INPUT_CFG_ID_NAME: u8 = 0x01;
+const VIRTIO_INPUT_CFG_ABS_INFO: u8 = 0x12;
+
+/// Range of an absolute axis as reported by the device
+#[derive(Debug, Clone, Copy)]
+struct AbsInfo {
+    min: i32,
+    max: i32,
+}
+
+impl AbsInfo {
+    /// Map `value` from the device range onto `0..size`
+    fn scale(&self, value: i32, size: usize) -> i32 {
+        let range = (self.max as i64 - self.min as i64).max(1);
+        let offset = (value as i64 - self.min as i64).clamp(0, range);
+        (offset * (size.max(1) as i64 - 1) / range) as i32
+    }
+}
+
+// Select a config field and return its size, 0 if the device has none
+fn select_config(virtio: &mut Virtio, select: u8, subsel: u8) -> u8 {
+    if virtio.write_config_u8(CFG_SELECT, select).is_err()
+        || virtio.write_config_u8(CFG_SUBSEL, subsel).is_err()
+    {
+        return 0;
+    }
+    virtio.read_config_u8(CFG_SIZE).unwrap_or(0)
+}
+
+fn device_name(virtio: &mut Virtio) -> String {
+    let size = select_config(virtio, VIRTIO_INPUT_CFG_ID_NAME, 0);
+    (0..size as u16)
+        .filter_map(|i| virtio.read_config_u8(CFG_DATA + i).ok())
+        .map(char::from)
+        .collect()
+}
+
+fn abs_info(virtio: &mut Virtio, axis: u16) -> Option<AbsInfo> {
+    // min, max, fuzz, flat and res, all le32
+    if select_config(virtio, VIRTIO_INPUT_CFG_ABS_INFO, axis as u8) < 8 {
+        return None;
+    }
+    Some(AbsInfo {
+        min: virtio.read_config_u32(CFG_DATA).ok()? as i32,
+        max: virtio.read_config_u32(CFG_DATA + 4).ok()? as i32,
+    })
+}
+
+///Handle the virtio device and export all data to globals::Input and the
+///app event queues in `input`
 pub async fn drive(mut virtio: Virtio) {
+    let name = device_name(&mut virtio);
+    let abs_x = abs_info(&mut virtio, input::ABS_X);
+    let abs_y = abs_info(&mut virtio, input::ABS_Y);
+    log::info!(
+        "virtio_input: '{}' (absolute x: {:?}, y: {:?})",
+        name,
+        abs_x,
+        abs_y
+    );
+
     unsafe {
         let q = 0;
         virtio.queue_select(q);
@@ -20,19 +95,49 @@ pub async fn drive(mut virtio: Virtio) {
             while let Some(used) = virtio.next_used() {
                 let desc = virtio.read_desc(used.id as u16);
                 let evt = (desc.addr as *const VirtioInputEvent).read_volatile();
-                crate::sys::globals::INPUT.update(|input| match evt.type_ {
-                    0 => { /*no op */ }
-                    1 => input.handle_incoming_state(evt.code as usize, evt.value != 0),
-                    2 => {
+                match evt.type_ {
+                    EV_SYN => input::report_sync(),
+                    // Value 2 is the device's autorepeat; `input` repeats
+                    // held keys itself
+                    EV_KEY if evt.value == 2 => {}
+                    EV_KEY => {
+                        let pressed = evt.value != 0;
+                        INPUT.update(|i| i.handle_incoming_state(evt.code as usize, pressed));
+                        input::report_key(evt.code, pressed);
+                    }
+                    EV_REL => {
                         let d: i32 = u32::cast_signed(evt.value);
                         match evt.code {
-                            0 => input.mouse_x = (input.mouse_x as i32 + d).max(0) as usize,
-                            1 => input.mouse_y = (input.mouse_y as i32 + d).max(0) as usize,
-                            _ => log::error!("virtio_input: unknown event {:?}", evt),
+                            input::REL_X => INPUT.update(|i| i.move_mouse_by(d, 0)),
+                            input::REL_Y => INPUT.update(|i| i.move_mouse_by(0, d)),
+                            input::REL_HWHEEL => INPUT.update(|i| i.handle_wheel(d, 0)),
+                            input::REL_WHEEL => INPUT.update(|i| i.handle_wheel(0, d)),
+                            _ => log::debug!("virtio_input: unhandled event {:?}", evt),
+                        }
+                        input::report_relative(evt.code, d);
+                    }
+                    EV_ABS => {
+                        let (w, h) = input::screen_size();
+                        let raw = u32::cast_signed(evt.value);
+                        match (evt.code, abs_x, abs_y) {
+                            (input::ABS_X, Some(info), _) => {
+                                let x = info.scale(raw, w);
+                                INPUT.update(|i| i.move_mouse_to(x as usize, i.mouse_y));
+                                input::report_absolute(input::ABS_X, x);
+                            }
+                            (input::ABS_Y, _, Some(info)) => {
+                                let y = info.scale(raw, h);
+                                INPUT.update(|i| i.move_mouse_to(i.mouse_x, y as usize));
+                                input::report_absolute(input::ABS_Y, y);
+                            }
+                            _ => log::debug!("virtio_input: unhandled event {:?}", evt),
                         }
                     }
+                    // Scan codes, LEDs and autorepeat settings carry nothing
+                    // the kernel uses
+                    EV_MSC | EV_LED | EV_REP => {}
                     _ => log::error!("virtio_input: unknown event {:?}", evt),
-                });
+                }
                 virtio.set_writable_available(used.id as u16);
             }
             yield_once().await;
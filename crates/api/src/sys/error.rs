@@ -90,6 +90,7 @@ pub enum WasmError {
     InvalidModule,
     FunctionNotFound,
     MemoryAccessViolation,
+    SnapshotMismatch,
 }
 
 /// Task execution errors
@@ -204,6 +205,7 @@ impl fmt::Display for WasmError {
             InvalidModule => write!(f, "Invalid WASM module provided."),
             FunctionNotFound => write!(f, "Requested function not found in WASM module."),
             MemoryAccessViolation => write!(f, "WASM memory access violation occurred."),
+            SnapshotMismatch => write!(f, "Snapshot was taken from a different WASM module."),
         }
     }
 }
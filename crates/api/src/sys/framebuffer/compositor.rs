@@ -0,0 +1,757 @@
+/// Window manager: every app draws into its own off-screen surface and the
+/// compositor places the surfaces on screen, back to front, inside window
+/// decorations.
+///
+/// Pointer and keyboard events pass through `route_input`, which handles the
+/// decorations and shortcuts and hands everything else to the focused window
+/// in its own coordinates:
+///
+/// - drag a title bar to move a window, its right or bottom edge to resize it
+/// - the `×` button or Alt+F4 closes a window, Alt+F10 maximizes it
+/// - Alt+Tab cycles focus
+/// - Meta+arrows move the focused window, Meta+Shift+arrows resize it
+///
+/// The `desktop` shell is drawn over the windows and sees input first.
+use super::{
+    desktop::{self, Action, Desktop, Task},
+    shapes::Coordinate,
+    text::Font,
+    FB, RGBA,
+};
+use crate::sys::{
+    input::{self, Event, Modifiers, BTN_LEFT},
+    process::{self, ProcessId, ProcessState},
+    wasm::loader,
+};
+use alloc::{boxed::Box, string::String, vec, vec::Vec};
+use spin::Mutex;
+
+pub const TITLE_BAR_HEIGHT: usize = 24;
+/// Frame around the content; the right and bottom edges resize the window
+pub const BORDER: usize = 4;
+const BUTTON_SIZE: usize = 18;
+const MIN_WIDTH: usize = 120;
+const MIN_HEIGHT: usize = 60;
+// Offset between successive new windows, and the step of keyboard moves
+const CASCADE_STEP: isize = 32;
+const KEYBOARD_STEP: isize = 32;
+// Surface size when there is no screen to size windows by
+const FALLBACK_SIZE: (usize, usize) = (640, 480);
+
+const DESKTOP: RGBA = RGBA {
+    r: 30,
+    g: 34,
+    b: 42,
+    a: 255,
+};
+const FOCUSED_FRAME: RGBA = RGBA {
+    r: 58,
+    g: 110,
+    b: 165,
+    a: 255,
+};
+const UNFOCUSED_FRAME: RGBA = RGBA {
+    r: 70,
+    g: 72,
+    b: 80,
+    a: 255,
+};
+const CLOSE_BUTTON: RGBA = RGBA {
+    r: 200,
+    g: 60,
+    b: 60,
+    a: 255,
+};
+const TITLE_TEXT: RGBA = RGBA {
+    r: 240,
+    g: 240,
+    b: 240,
+    a: 255,
+};
+const OVERLAY: RGBA = RGBA {
+    r: 120,
+    g: 24,
+    b: 24,
+    a: 255,
+};
+// Space around the overlay text
+const OVERLAY_PADDING: usize = 6;
+
+// Arrow pointer: 'X' outline, '.' fill
+const POINTER: [&str; 12] = [
+    "X", "XX", "X.X", "X..X", "X...X", "X....X", "X.....X", "X......X", "X...XXXX", "X..X", "X.X",
+    "XX",
+];
+
+pub struct Window {
+    pub pid: ProcessId,
+    pub title: String,
+    /// Screen position of the top left corner of the content
+    pub x: isize,
+    pub y: isize,
+    /// Below 255 the surface is blended into the frame
+    pub opacity: u8,
+    // Boxed so the pointer handed to the app stays valid as windows reorder
+    surface: Box<FB>,
+    // Geometry to return to when un-maximizing
+    restore: Option<(isize, isize, usize, usize)>,
+    // Message drawn over the top of the content, one entry per line
+    overlay: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Hit {
+    Content,
+    TitleBar,
+    Close,
+    Resize,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Drag {
+    /// Pointer offset from the content origin when the drag started
+    Move {
+        pid: ProcessId,
+        grab: (isize, isize),
+    },
+    /// Pointer position and window size when the drag started
+    Resize {
+        pid: ProcessId,
+        start: (i32, i32),
+        size: (usize, usize),
+    },
+}
+
+impl Window {
+    pub fn width(&self) -> usize {
+        self.surface.w
+    }
+
+    pub fn height(&self) -> usize {
+        self.surface.h
+    }
+
+    /// Outer rectangle including the decorations
+    fn frame(&self) -> (isize, isize, usize, usize) {
+        (
+            self.x - BORDER as isize,
+            self.y - (TITLE_BAR_HEIGHT + BORDER) as isize,
+            self.width() + 2 * BORDER,
+            self.height() + TITLE_BAR_HEIGHT + 2 * BORDER,
+        )
+    }
+
+    fn close_button(&self) -> (isize, isize) {
+        let (_, y, _, _) = self.frame();
+        let inset = ((TITLE_BAR_HEIGHT - BUTTON_SIZE) / 2) as isize;
+        (
+            self.x + self.width() as isize - BUTTON_SIZE as isize,
+            y + BORDER as isize + inset,
+        )
+    }
+
+    fn hit(&self, x: i32, y: i32) -> Option<Hit> {
+        let (x, y) = (x as isize, y as isize);
+        let (fx, fy, fw, fh) = self.frame();
+        if x < fx || y < fy || x >= fx + fw as isize || y >= fy + fh as isize {
+            return None;
+        }
+        let (bx, by) = self.close_button();
+        let size = BUTTON_SIZE as isize;
+        if x >= bx && x < bx + size && y >= by && y < by + size {
+            return Some(Hit::Close);
+        }
+        let right = self.x + self.width() as isize;
+        let bottom = self.y + self.height() as isize;
+        if x >= right || y >= bottom {
+            return Some(Hit::Resize);
+        }
+        if x >= self.x && y >= self.y {
+            return Some(Hit::Content);
+        }
+        Some(Hit::TitleBar)
+    }
+
+    fn resize(&mut self, width: usize, height: usize) {
+        let (width, height) = (width.max(MIN_WIDTH), height.max(MIN_HEIGHT));
+        if (width, height) != (self.width(), self.height()) {
+            *self.surface = FB::surface(width, height);
+        }
+    }
+}
+
+impl FB {
+    /// A blank off-screen surface
+    pub fn surface(width: usize, height: usize) -> Self {
+        FB {
+            pixels: vec![DESKTOP; width * height],
+            backbuffer: Vec::new(),
+            bytes_per_pixel: 4,
+            stride: width,
+            w: width,
+            h: height,
+        }
+    }
+}
+
+/// Snapshot of a window for shells and tools
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowInfo {
+    pub pid: ProcessId,
+    pub title: String,
+    pub x: isize,
+    pub y: isize,
+    pub width: usize,
+    pub height: usize,
+    pub focused: bool,
+}
+
+struct Compositor {
+    screen: *mut FB,
+    /// Back to front
+    windows: Vec<Window>,
+    focus: Option<ProcessId>,
+    drag: Option<Drag>,
+    pointer: (i32, i32),
+    close_requests: Vec<ProcessId>,
+    opened: isize,
+    desktop: Desktop,
+}
+
+// The screen is only touched from the main loop
+unsafe impl Send for Compositor {}
+
+static COMPOSITOR: Mutex<Compositor> = Mutex::new(Compositor {
+    screen: core::ptr::null_mut(),
+    windows: Vec::new(),
+    focus: None,
+    drag: None,
+    pointer: (0, 0),
+    close_requests: Vec::new(),
+    opened: 0,
+    desktop: Desktop::new(),
+});
+
+impl Compositor {
+    fn screen_size(&self) -> Option<(usize, usize)> {
+        unsafe { self.screen.as_ref() }.map(|screen| (screen.w, screen.h))
+    }
+
+    fn window(&mut self, pid: ProcessId) -> Option<&mut Window> {
+        self.windows.iter_mut().find(|window| window.pid == pid)
+    }
+
+    fn open(&mut self, pid: ProcessId, title: &str) -> *mut FB {
+        // Cascade new windows from the top left, three quarters of the screen
+        let (width, height) = self
+            .screen_size()
+            .map_or(FALLBACK_SIZE, |(w, h)| (w * 3 / 4, h * 3 / 4));
+        let offset = (self.opened % 8) * CASCADE_STEP;
+        self.opened += 1;
+        let mut window = Window {
+            pid,
+            title: title.into(),
+            x: CASCADE_STEP + offset,
+            y: CASCADE_STEP + TITLE_BAR_HEIGHT as isize + offset,
+            opacity: 255,
+            surface: Box::new(FB::surface(width.max(MIN_WIDTH), height.max(MIN_HEIGHT))),
+            restore: None,
+            overlay: Vec::new(),
+        };
+        let surface: *mut FB = &mut *window.surface;
+        self.windows.push(window);
+        self.focus(Some(pid));
+        surface
+    }
+
+    fn close(&mut self, pid: ProcessId) {
+        self.windows.retain(|window| window.pid != pid);
+        if self.focus == Some(pid) {
+            self.focus(self.windows.last().map(|window| window.pid));
+        }
+        if matches!(self.drag, Some(Drag::Move { pid: p, .. } | Drag::Resize { pid: p, .. }) if p == pid)
+        {
+            self.drag = None;
+        }
+    }
+
+    /// Focus `pid` and raise its window to the top
+    fn focus(&mut self, pid: Option<ProcessId>) {
+        if let Some(index) = self.windows.iter().position(|w| Some(w.pid) == pid) {
+            let window = self.windows.remove(index);
+            self.windows.push(window);
+        }
+        self.focus = pid;
+        input::set_focus(pid);
+    }
+
+    fn toggle_maximized(&mut self, pid: ProcessId) {
+        let screen = self.screen_size();
+        let Some(window) = self.window(pid) else {
+            return;
+        };
+        match window.restore.take() {
+            Some((x, y, width, height)) => {
+                window.x = x;
+                window.y = y;
+                window.resize(width, height);
+            }
+            None => {
+                let Some((w, h)) = screen else {
+                    return;
+                };
+                window.restore = Some((window.x, window.y, window.width(), window.height()));
+                window.x = BORDER as isize;
+                window.y = (TITLE_BAR_HEIGHT + BORDER) as isize;
+                window.resize(
+                    w.saturating_sub(2 * BORDER),
+                    h.saturating_sub(TITLE_BAR_HEIGHT + 2 * BORDER + desktop::TASKBAR_HEIGHT),
+                );
+            }
+        }
+    }
+
+    /// Apply a window management shortcut; false if `code` is not one
+    fn shortcut(&mut self, code: u16, modifiers: Modifiers) -> bool {
+        let alt = modifiers.contains(Modifiers::ALT);
+        let meta = modifiers.contains(Modifiers::META);
+        match (code, self.focus) {
+            (input::KEY_TAB, _) if alt => {
+                // Bring the bottom window to the top
+                let next = self.windows.first().map(|window| window.pid);
+                self.focus(next);
+            }
+            (input::KEY_F4, Some(pid)) if alt => self.close_requests.push(pid),
+            (input::KEY_F10, Some(pid)) if alt => self.toggle_maximized(pid),
+            (input::KEY_LEFT | input::KEY_RIGHT | input::KEY_UP | input::KEY_DOWN, Some(pid))
+                if meta =>
+            {
+                let (dx, dy) = match code {
+                    input::KEY_LEFT => (-KEYBOARD_STEP, 0),
+                    input::KEY_RIGHT => (KEYBOARD_STEP, 0),
+                    input::KEY_UP => (0, -KEYBOARD_STEP),
+                    _ => (0, KEYBOARD_STEP),
+                };
+                let resize = modifiers.contains(Modifiers::SHIFT);
+                if let Some(window) = self.window(pid) {
+                    if resize {
+                        let width = (window.width() as isize + dx).max(0) as usize;
+                        let height = (window.height() as isize + dy).max(0) as usize;
+                        window.resize(width, height);
+                    } else {
+                        window.x += dx;
+                        window.y += dy;
+                    }
+                    window.restore = None;
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    // Pointer position relative to the content of `pid`
+    fn local(&self, pid: ProcessId, x: i32, y: i32) -> (i32, i32) {
+        self.windows
+            .iter()
+            .find(|window| window.pid == pid)
+            .map_or((x, y), |w| (x - w.x as i32, y - w.y as i32))
+    }
+
+    fn drag_to(&mut self, x: i32, y: i32) {
+        match self.drag {
+            Some(Drag::Move { pid, grab }) => {
+                if let Some(window) = self.window(pid) {
+                    window.x = x as isize - grab.0;
+                    window.y = y as isize - grab.1;
+                    window.restore = None;
+                }
+            }
+            Some(Drag::Resize { pid, start, size }) => {
+                if let Some(window) = self.window(pid) {
+                    let width = (size.0 as i32 + x - start.0).max(0) as usize;
+                    let height = (size.1 as i32 + y - start.1).max(0) as usize;
+                    window.resize(width, height);
+                    window.restore = None;
+                }
+            }
+            None => {}
+        }
+    }
+
+    // A button press not on the focused content: focus what is under the
+    // pointer and act on its decorations. Returns the window to deliver the
+    // press to, if it landed on content.
+    fn press(&mut self, x: i32, y: i32) -> Option<ProcessId> {
+        let (pid, hit) = self
+            .windows
+            .iter()
+            .rev()
+            .find_map(|window| window.hit(x, y).map(|hit| (window.pid, hit)))?;
+        self.focus(Some(pid));
+        let window = self.window(pid)?;
+        let (origin, size) = ((window.x, window.y), (window.width(), window.height()));
+        match hit {
+            Hit::Content => return Some(pid),
+            Hit::TitleBar => {
+                self.drag = Some(Drag::Move {
+                    pid,
+                    grab: (x as isize - origin.0, y as isize - origin.1),
+                });
+            }
+            Hit::Resize => {
+                self.drag = Some(Drag::Resize {
+                    pid,
+                    start: (x, y),
+                    size,
+                });
+            }
+            Hit::Close => self.close_requests.push(pid),
+        }
+        None
+    }
+
+    /// Running apps with a window, in the order they were started
+    fn tasks(&self) -> Vec<Task> {
+        let mut tasks: Vec<Task> = process::list_processes()
+            .into_iter()
+            .filter(|p| !matches!(p.state, ProcessState::Zombie | ProcessState::Terminated))
+            .filter(|p| self.windows.iter().any(|window| window.pid == p.pid))
+            .map(|p| Task {
+                pid: p.pid,
+                focused: self.focus == Some(p.pid),
+                name: p.name,
+            })
+            .collect();
+        tasks.sort_by_key(|task| task.pid);
+        tasks
+    }
+
+    // Let the desktop handle `event`; `None` if it is not for the desktop
+    fn desktop_event(&mut self, event: &Event) -> Option<Action> {
+        let screen = self.screen_size()?;
+        match *event {
+            Event::Key {
+                code,
+                pressed: true,
+                modifiers,
+                ..
+            } => self.desktop.key(code, modifiers),
+            Event::Key { .. } | Event::Text { .. } if self.desktop.launcher_open() => {
+                Some(Action::None)
+            }
+            Event::MouseButton { pressed, x, y, .. } if self.drag.is_none() => {
+                let tasks = self.tasks();
+                self.desktop.pointer(x, y, pressed, screen, &tasks)
+            }
+            _ => None,
+        }
+    }
+
+    fn apply(&mut self, action: Action) {
+        match action {
+            Action::None => {}
+            Action::Launch(path) => loader::request_launch(&path),
+            Action::Focus(pid) => self.focus(Some(pid)),
+            Action::Close(pid) => self.close_requests.push(pid),
+        }
+    }
+
+    fn route(&mut self, events: Vec<(Event, u64)>) -> Vec<(ProcessId, Event, u64)> {
+        let mut routed = Vec::with_capacity(events.len());
+        for (event, time_ms) in events {
+            if let Some(action) = self.desktop_event(&event) {
+                self.apply(action);
+                continue;
+            }
+            let target = match event {
+                Event::Key {
+                    code,
+                    pressed: true,
+                    modifiers,
+                    ..
+                } if self.shortcut(code, modifiers) => None,
+                Event::MouseMove { x, y, .. } => {
+                    self.pointer = (x, y);
+                    if self.drag.is_some() {
+                        self.drag_to(x, y);
+                        None
+                    } else {
+                        self.focus
+                    }
+                }
+                Event::MouseButton {
+                    button: BTN_LEFT,
+                    pressed: false,
+                    ..
+                } if self.drag.take().is_some() => None,
+                Event::MouseButton {
+                    pressed: true,
+                    x,
+                    y,
+                    ..
+                } => self.press(x, y),
+                _ => self.focus,
+            };
+            let Some(pid) = target else {
+                continue;
+            };
+            // Pointer coordinates are relative to the window content
+            let event = match event {
+                Event::MouseMove { x, y, dx, dy } => {
+                    let (x, y) = self.local(pid, x, y);
+                    Event::MouseMove { x, y, dx, dy }
+                }
+                Event::MouseButton {
+                    button,
+                    pressed,
+                    x,
+                    y,
+                    modifiers,
+                } => {
+                    let (x, y) = self.local(pid, x, y);
+                    Event::MouseButton {
+                        button,
+                        pressed,
+                        x,
+                        y,
+                        modifiers,
+                    }
+                }
+                Event::Wheel { dx, dy, x, y } => {
+                    let (x, y) = self.local(pid, x, y);
+                    Event::Wheel { dx, dy, x, y }
+                }
+                event => event,
+            };
+            routed.push((pid, event, time_ms));
+        }
+        routed
+    }
+
+    fn compose(&mut self) {
+        let Some(screen) = (unsafe { self.screen.as_mut() }) else {
+            return;
+        };
+        screen.fill(Coordinate::new(0, 0), screen.w, screen.h, DESKTOP);
+        for window in &self.windows {
+            draw_window(screen, window, self.focus == Some(window.pid));
+            draw_overlay(screen, window);
+        }
+        self.desktop.draw(screen, &self.tasks());
+        draw_pointer(screen, self.pointer);
+    }
+}
+
+fn draw_window(screen: &mut FB, window: &Window, focused: bool) {
+    let (fx, fy, fw, fh) = window.frame();
+    let frame = if focused {
+        FOCUSED_FRAME
+    } else {
+        UNFOCUSED_FRAME
+    };
+    screen.fill(Coordinate::new(fx, fy), fw, fh, frame);
+    screen.draw_string(
+        Coordinate::new(window.x + 2, fy + BORDER as isize + 2),
+        &window.title,
+        TITLE_TEXT,
+        Font::default(),
+    );
+    let (bx, by) = window.close_button();
+    screen.fill(
+        Coordinate::new(bx, by),
+        BUTTON_SIZE,
+        BUTTON_SIZE,
+        CLOSE_BUTTON,
+    );
+    screen.draw_string(
+        Coordinate::new(bx + 5, by),
+        "×",
+        TITLE_TEXT,
+        Font::default(),
+    );
+
+    // Copy the visible part of the surface row by row
+    let surface = &window.surface;
+    let left = (-window.x).clamp(0, surface.w as isize) as usize;
+    let right = (screen.w as isize - window.x).clamp(0, surface.w as isize) as usize;
+    let top = (-window.y).clamp(0, surface.h as isize) as usize;
+    let bottom = (screen.h as isize - window.y).clamp(0, surface.h as isize) as usize;
+    if left >= right {
+        return;
+    }
+    for row in top..bottom {
+        let src = &surface.pixels[row * surface.w + left..row * surface.w + right];
+        let y = (window.y + row as isize) as usize;
+        let x = (window.x + left as isize) as usize;
+        if window.opacity == 255 {
+            let start = y * screen.w + x;
+            screen.pixels[start..start + src.len()].copy_from_slice(src);
+        } else {
+            for (i, &pixel) in src.iter().enumerate() {
+                screen.set_pixel_blend(
+                    Coordinate::new((x + i) as isize, y as isize),
+                    RGBA {
+                        a: window.opacity,
+                        ..pixel
+                    },
+                );
+            }
+        }
+    }
+}
+
+// Panel across the top of the content with the window's overlay text, cut
+// to the width of the window
+fn draw_overlay(screen: &mut FB, window: &Window) {
+    if window.overlay.is_empty() {
+        return;
+    }
+    let font = Font::default();
+    let height = window.overlay.len() * font.line_height() + 2 * OVERLAY_PADDING;
+    screen.fill(
+        Coordinate::new(window.x, window.y),
+        window.width(),
+        height.min(window.height()),
+        OVERLAY,
+    );
+    let columns = window.width().saturating_sub(2 * OVERLAY_PADDING) / font.char_width();
+    let lines = window.height().saturating_sub(2 * OVERLAY_PADDING) / font.line_height();
+    for (row, line) in window.overlay.iter().take(lines).enumerate() {
+        let line: String = line.chars().take(columns).collect();
+        screen.draw_string(
+            Coordinate::new(
+                window.x + OVERLAY_PADDING as isize,
+                window.y + (OVERLAY_PADDING + row * font.line_height()) as isize,
+            ),
+            &line,
+            TITLE_TEXT,
+            font,
+        );
+    }
+}
+
+fn draw_pointer(screen: &mut FB, (x, y): (i32, i32)) {
+    for (row, line) in POINTER.iter().enumerate() {
+        for (col, c) in line.chars().enumerate() {
+            let color = match c {
+                'X' => RGBA {
+                    r: 0,
+                    g: 0,
+                    b: 0,
+                    a: 255,
+                },
+                '.' => RGBA {
+                    r: 255,
+                    g: 255,
+                    b: 255,
+                    a: 255,
+                },
+                _ => continue,
+            };
+            let coord = Coordinate::new(x as isize + col as isize, y as isize + row as isize);
+            if screen.contains(coord) {
+                screen.set_pixel(coord, color);
+            }
+        }
+    }
+}
+
+/// Composite onto `screen` from now on
+pub fn init(screen: *mut FB) {
+    COMPOSITOR.lock().screen = screen;
+}
+
+/// Open a focused window for `pid` and return the surface the app draws on.
+/// The surface lives until `close_window`.
+pub fn open_window(pid: ProcessId, title: &str) -> *mut FB {
+    COMPOSITOR.lock().open(pid, title)
+}
+
+pub fn close_window(pid: ProcessId) {
+    COMPOSITOR.lock().close(pid);
+}
+
+/// Raise the window of `pid` and give it input focus
+pub fn focus_window(pid: ProcessId) {
+    COMPOSITOR.lock().focus(Some(pid));
+}
+
+/// Process owning the focused window
+pub fn focused() -> Option<ProcessId> {
+    COMPOSITOR.lock().focus
+}
+
+/// Move the content of `pid`'s window to (`x`, `y`)
+pub fn move_window(pid: ProcessId, x: isize, y: isize) {
+    if let Some(window) = COMPOSITOR.lock().window(pid) {
+        window.x = x;
+        window.y = y;
+    }
+}
+
+/// Resize `pid`'s surface; its content is cleared
+pub fn resize_window(pid: ProcessId, width: usize, height: usize) {
+    if let Some(window) = COMPOSITOR.lock().window(pid) {
+        window.resize(width, height);
+    }
+}
+
+pub fn set_opacity(pid: ProcessId, opacity: u8) {
+    if let Some(window) = COMPOSITOR.lock().window(pid) {
+        window.opacity = opacity;
+    }
+}
+
+/// Show `text` over the top of `pid`'s content until `hide_overlay`, e.g.
+/// to report an error without disturbing the app
+pub fn show_overlay(pid: ProcessId, text: &str) {
+    if let Some(window) = COMPOSITOR.lock().window(pid) {
+        window.overlay = text.lines().map(String::from).collect();
+    }
+}
+
+pub fn hide_overlay(pid: ProcessId) {
+    if let Some(window) = COMPOSITOR.lock().window(pid) {
+        window.overlay.clear();
+    }
+}
+
+/// Windows from back to front
+pub fn windows() -> Vec<WindowInfo> {
+    let compositor = COMPOSITOR.lock();
+    compositor
+        .windows
+        .iter()
+        .map(|window| WindowInfo {
+            pid: window.pid,
+            title: window.title.clone(),
+            x: window.x,
+            y: window.y,
+            width: window.width(),
+            height: window.height(),
+            focused: compositor.focus == Some(window.pid),
+        })
+        .collect()
+}
+
+/// Handle window management input and return the rest addressed to the
+/// window that should receive it, with pointer coordinates made local
+pub fn route_input(events: Vec<(Event, u64)>) -> Vec<(ProcessId, Event, u64)> {
+    COMPOSITOR.lock().route(events)
+}
+
+/// Pointer position relative to `pid`'s content
+pub fn local_pointer(pid: ProcessId, x: i32, y: i32) -> (i32, i32) {
+    COMPOSITOR.lock().local(pid, x, y)
+}
+
+/// Windows the user asked to close; their apps should be stopped
+pub fn take_close_requests() -> Vec<ProcessId> {
+    core::mem::take(&mut COMPOSITOR.lock().close_requests)
+}
+
+/// Draw the desktop, every window and the pointer onto the screen
+pub fn compose() {
+    COMPOSITOR.lock().compose();
+}
@@ -0,0 +1,410 @@
+/// Desktop shell drawn by the compositor on top of the windows
+///
+/// A taskbar along the bottom of the screen holds the `Apps` button, one
+/// button per running app and the time since boot. Clicking an app's button
+/// focuses it and its `×` stops it. The launcher opened by `Apps` or Alt+F1
+/// lists the programs in `/bin` and `/usr/bin`; click one, or pick it with
+/// the arrow keys and Enter, to start it. Escape closes the launcher.
+use super::{shapes::Coordinate, text::Font, FB, RGBA};
+use crate::sys::{
+    input::{self, Modifiers},
+    interrupts::TIME_MS,
+    process::ProcessId,
+    wasm::loader,
+};
+use alloc::{format, string::String, vec::Vec};
+use core::sync::atomic::Ordering;
+
+pub const TASKBAR_HEIGHT: usize = 32;
+/// Directories the launcher lists programs from
+pub const PROGRAM_DIRS: [&str; 2] = ["/bin", "/usr/bin"];
+
+const PADDING: usize = 4;
+const BUTTON_HEIGHT: usize = TASKBAR_HEIGHT - 2 * PADDING;
+const RADIUS: usize = 6;
+const START_WIDTH: usize = 64;
+const TASK_WIDTH: usize = 160;
+// Room for the uptime, "up HH:MM:SS"
+const UPTIME_WIDTH: usize = 96;
+const LAUNCHER_WIDTH: usize = 240;
+const ROW_HEIGHT: usize = 24;
+
+const TASKBAR_TOP: RGBA = RGBA {
+    r: 52,
+    g: 56,
+    b: 66,
+    a: 255,
+};
+const TASKBAR_BOTTOM: RGBA = RGBA {
+    r: 24,
+    g: 26,
+    b: 32,
+    a: 255,
+};
+const BUTTON: RGBA = RGBA {
+    r: 70,
+    g: 72,
+    b: 80,
+    a: 255,
+};
+const ACTIVE_BUTTON: RGBA = RGBA {
+    r: 58,
+    g: 110,
+    b: 165,
+    a: 255,
+};
+const PANEL: RGBA = RGBA {
+    r: 44,
+    g: 48,
+    b: 58,
+    a: 255,
+};
+const SHADOW: RGBA = RGBA {
+    r: 0,
+    g: 0,
+    b: 0,
+    a: 255,
+};
+const TEXT: RGBA = RGBA {
+    r: 240,
+    g: 240,
+    b: 240,
+    a: 255,
+};
+const DIM_TEXT: RGBA = RGBA {
+    r: 160,
+    g: 164,
+    b: 172,
+    a: 255,
+};
+
+// x, y, width, height
+type Rect = (isize, isize, usize, usize);
+
+fn inside((rx, ry, rw, rh): Rect, x: i32, y: i32) -> bool {
+    let (x, y) = (x as isize, y as isize);
+    x >= rx && y >= ry && x < rx + rw as isize && y < ry + rh as isize
+}
+
+/// A running app with a window, as shown on the taskbar
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Task {
+    pub pid: ProcessId,
+    pub name: String,
+    pub focused: bool,
+}
+
+/// What the compositor should do after the desktop handled an event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// The event was used up by the desktop itself
+    None,
+    Launch(String),
+    Focus(ProcessId),
+    Close(ProcessId),
+}
+
+struct Launcher {
+    programs: Vec<String>,
+    selected: usize,
+}
+
+pub struct Desktop {
+    launcher: Option<Launcher>,
+}
+
+/// Paths of the programs found in `PROGRAM_DIRS`
+pub fn programs() -> Vec<String> {
+    let mut programs: Vec<String> = PROGRAM_DIRS
+        .iter()
+        .flat_map(|dir| loader::discover_apps(dir).unwrap_or_default())
+        .collect();
+    programs.sort_by(|a, b| loader::app_name(a).cmp(loader::app_name(b)));
+    programs
+}
+
+fn start_button(screen_h: usize) -> Rect {
+    (
+        PADDING as isize,
+        (screen_h - TASKBAR_HEIGHT + PADDING) as isize,
+        START_WIDTH,
+        BUTTON_HEIGHT,
+    )
+}
+
+// Button of the `index`th task; `None` once the taskbar is full
+fn task_button(index: usize, (w, h): (usize, usize)) -> Option<Rect> {
+    let x = 2 * PADDING + START_WIDTH + index * (TASK_WIDTH + PADDING);
+    if x + TASK_WIDTH + PADDING + UPTIME_WIDTH > w {
+        return None;
+    }
+    Some((
+        x as isize,
+        (h - TASKBAR_HEIGHT + PADDING) as isize,
+        TASK_WIDTH,
+        BUTTON_HEIGHT,
+    ))
+}
+
+fn task_close((x, y, w, _): Rect) -> Rect {
+    (
+        x + (w - BUTTON_HEIGHT) as isize,
+        y,
+        BUTTON_HEIGHT,
+        BUTTON_HEIGHT,
+    )
+}
+
+// Text cut to `width` pixels
+fn fit(text: &str, width: usize, font: Font) -> &str {
+    let chars = width / font.char_width();
+    match text.char_indices().nth(chars) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+impl Launcher {
+    // Rows that fit above the taskbar
+    fn visible_rows(&self, screen_h: usize) -> usize {
+        let room = screen_h.saturating_sub(TASKBAR_HEIGHT + 3 * PADDING) / ROW_HEIGHT;
+        self.programs.len().max(1).min(room.max(1))
+    }
+
+    // Index of the first visible row, keeping the selection in view
+    fn first_row(&self, screen_h: usize) -> usize {
+        (self.selected + 1).saturating_sub(self.visible_rows(screen_h))
+    }
+
+    fn panel(&self, screen_h: usize) -> Rect {
+        let height = self.visible_rows(screen_h) * ROW_HEIGHT + 2 * PADDING;
+        (
+            PADDING as isize,
+            screen_h as isize - (TASKBAR_HEIGHT + PADDING + height) as isize,
+            LAUNCHER_WIDTH,
+            height,
+        )
+    }
+
+    fn launch(&self) -> Action {
+        match self.programs.get(self.selected) {
+            Some(path) => Action::Launch(path.clone()),
+            None => Action::None,
+        }
+    }
+}
+
+impl Desktop {
+    pub const fn new() -> Self {
+        Self { launcher: None }
+    }
+
+    pub fn launcher_open(&self) -> bool {
+        self.launcher.is_some()
+    }
+
+    pub fn toggle_launcher(&mut self) {
+        self.launcher = match self.launcher {
+            Some(_) => None,
+            None => Some(Launcher {
+                programs: programs(),
+                selected: 0,
+            }),
+        };
+    }
+
+    /// Handle a key press; `None` if it is not for the desktop. All keys go
+    /// to the launcher while it is open.
+    pub fn key(&mut self, code: u16, modifiers: Modifiers) -> Option<Action> {
+        if code == input::KEY_F1 && modifiers.contains(Modifiers::ALT) {
+            self.toggle_launcher();
+            return Some(Action::None);
+        }
+        let launcher = self.launcher.as_mut()?;
+        let last = launcher.programs.len().saturating_sub(1);
+        match code {
+            input::KEY_UP => launcher.selected = launcher.selected.saturating_sub(1),
+            input::KEY_DOWN => launcher.selected = (launcher.selected + 1).min(last),
+            input::KEY_ENTER => {
+                let action = launcher.launch();
+                self.launcher = None;
+                return Some(action);
+            }
+            input::KEY_ESC => self.launcher = None,
+            _ => {}
+        }
+        Some(Action::None)
+    }
+
+    /// Handle a button press or release at (`x`, `y`); `None` if it is not
+    /// on the taskbar or the launcher. A press elsewhere closes the launcher.
+    pub fn pointer(
+        &mut self,
+        x: i32,
+        y: i32,
+        pressed: bool,
+        screen: (usize, usize),
+        tasks: &[Task],
+    ) -> Option<Action> {
+        let (w, h) = screen;
+        if h < TASKBAR_HEIGHT {
+            return None;
+        }
+        if let Some(launcher) = &self.launcher {
+            let (px, py, pw, ph) = launcher.panel(h);
+            if inside((px, py, pw, ph), x, y) {
+                if !pressed {
+                    return Some(Action::None);
+                }
+                let row = (y as isize - py - PADDING as isize).max(0) as usize / ROW_HEIGHT;
+                let selected = launcher.first_row(h) + row;
+                if selected >= launcher.programs.len() {
+                    return Some(Action::None);
+                }
+                let path = launcher.programs[selected].clone();
+                self.launcher = None;
+                return Some(Action::Launch(path));
+            }
+            if pressed {
+                self.launcher = None;
+            }
+        }
+        if !inside((0, (h - TASKBAR_HEIGHT) as isize, w, TASKBAR_HEIGHT), x, y) {
+            return None;
+        }
+        if !pressed {
+            return Some(Action::None);
+        }
+        if inside(start_button(h), x, y) {
+            self.toggle_launcher();
+            return Some(Action::None);
+        }
+        for (index, task) in tasks.iter().enumerate() {
+            let Some(button) = task_button(index, screen) else {
+                break;
+            };
+            if inside(task_close(button), x, y) {
+                return Some(Action::Close(task.pid));
+            }
+            if inside(button, x, y) {
+                return Some(Action::Focus(task.pid));
+            }
+        }
+        Some(Action::None)
+    }
+
+    /// Draw the taskbar and, if open, the launcher
+    pub fn draw(&self, screen: &mut FB, tasks: &[Task]) {
+        let (w, h) = (screen.w, screen.h);
+        if w < START_WIDTH + UPTIME_WIDTH + 3 * PADDING || h < TASKBAR_HEIGHT {
+            return;
+        }
+        let font = Font::default();
+        let top = (h - TASKBAR_HEIGHT) as isize;
+        screen.fill_gradient(
+            Coordinate::new(0, top),
+            Coordinate::new(w as isize - 1, h as isize - 1),
+            TASKBAR_TOP,
+            TASKBAR_BOTTOM,
+        );
+
+        let text_y = top + ((TASKBAR_HEIGHT - font.height.val()) / 2) as isize;
+        let (sx, sy, sw, sh) = start_button(h);
+        let start = if self.launcher_open() {
+            ACTIVE_BUTTON
+        } else {
+            BUTTON
+        };
+        screen.draw_rounded_rectangle(Coordinate::new(sx, sy), sw, sh, RADIUS, start);
+        let (label_w, _) = font.measure("Apps");
+        screen.draw_string(
+            Coordinate::new(sx + (sw - label_w) as isize / 2, text_y),
+            "Apps",
+            TEXT,
+            font,
+        );
+
+        for (index, task) in tasks.iter().enumerate() {
+            let Some((x, y, bw, bh)) = task_button(index, (w, h)) else {
+                break;
+            };
+            let color = if task.focused { ACTIVE_BUTTON } else { BUTTON };
+            screen.draw_rounded_rectangle(Coordinate::new(x, y), bw, bh, RADIUS, color);
+            let label = fit(&task.name, bw - BUTTON_HEIGHT - 2 * PADDING, font);
+            screen.draw_string(
+                Coordinate::new(x + 2 * PADDING as isize, text_y),
+                label,
+                TEXT,
+                font,
+            );
+            let (cx, _, _, _) = task_close((x, y, bw, bh));
+            screen.draw_string(Coordinate::new(cx + 6, text_y), "×", DIM_TEXT, font);
+        }
+
+        // Time since boot; there is no wall clock to show
+        let seconds = TIME_MS.load(Ordering::Relaxed) / 1000;
+        let uptime = format!(
+            "up {:02}:{:02}:{:02}",
+            seconds / 3600 % 100,
+            seconds / 60 % 60,
+            seconds % 60
+        );
+        let (uptime_w, _) = font.measure(&uptime);
+        screen.draw_string(
+            Coordinate::new((w - PADDING - uptime_w) as isize, text_y),
+            &uptime,
+            TEXT,
+            font,
+        );
+
+        if let Some(launcher) = &self.launcher {
+            draw_launcher(screen, launcher, font);
+        }
+    }
+}
+
+impl Default for Desktop {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn draw_launcher(screen: &mut FB, launcher: &Launcher, font: Font) {
+    let (x, y, w, h) = launcher.panel(screen.h);
+    let corner = Coordinate::new(x, y);
+    screen.draw_shadow(corner, w, h, 8, SHADOW);
+    screen.draw_rounded_rectangle(corner, w, h, RADIUS, PANEL);
+    let row_x = x + PADDING as isize;
+    let row_w = w - 2 * PADDING;
+    let text_inset = ((ROW_HEIGHT - font.height.val()) / 2) as isize;
+    if launcher.programs.is_empty() {
+        screen.draw_string(
+            Coordinate::new(row_x + PADDING as isize, y + PADDING as isize + text_inset),
+            "No programs found",
+            DIM_TEXT,
+            font,
+        );
+        return;
+    }
+    let first = launcher.first_row(screen.h);
+    let rows = launcher.visible_rows(screen.h);
+    for (row, path) in launcher.programs.iter().enumerate().skip(first).take(rows) {
+        let row_y = y + (PADDING + (row - first) * ROW_HEIGHT) as isize;
+        if row == launcher.selected {
+            screen.draw_rounded_rectangle(
+                Coordinate::new(row_x, row_y),
+                row_w,
+                ROW_HEIGHT,
+                RADIUS,
+                ACTIVE_BUTTON,
+            );
+        }
+        let name = fit(loader::app_name(path), row_w - 2 * PADDING, font);
+        screen.draw_string(
+            Coordinate::new(row_x + PADDING as isize, row_y + text_inset),
+            name,
+            TEXT,
+            font,
+        );
+    }
+}
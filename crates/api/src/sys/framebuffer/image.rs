@@ -0,0 +1,185 @@
+/// Pixel buffers copied into the framebuffer
+use super::{shapes::Coordinate, FB, RGBA};
+
+/// Memory layout of a source pixel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// Bytes r, g, b, a
+    Rgba8888,
+    /// Bytes b, g, r, a
+    Bgra8888,
+    /// Little-endian u16 with red in the top 5 bits; always opaque
+    Rgb565,
+}
+
+impl ImageFormat {
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            ImageFormat::Rgba8888 | ImageFormat::Bgra8888 => 4,
+            ImageFormat::Rgb565 => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Scaling {
+    #[default]
+    Nearest,
+    Bilinear,
+}
+
+/// A borrowed `width` x `height` image with rows packed back to back
+#[derive(Debug, Clone, Copy)]
+pub struct Image<'a> {
+    data: &'a [u8],
+    width: usize,
+    height: usize,
+    format: ImageFormat,
+}
+
+impl<'a> Image<'a> {
+    /// `None` if `data` is too short for the dimensions
+    pub fn new(data: &'a [u8], width: usize, height: usize, format: ImageFormat) -> Option<Self> {
+        let len = width
+            .checked_mul(height)?
+            .checked_mul(format.bytes_per_pixel())?;
+        Some(Self {
+            data: data.get(..len)?,
+            width,
+            height,
+            format,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> RGBA {
+        let offset = (y * self.width + x) * self.format.bytes_per_pixel();
+        let p = &self.data[offset..offset + self.format.bytes_per_pixel()];
+        match self.format {
+            ImageFormat::Rgba8888 => RGBA {
+                r: p[0],
+                g: p[1],
+                b: p[2],
+                a: p[3],
+            },
+            ImageFormat::Bgra8888 => RGBA {
+                r: p[2],
+                g: p[1],
+                b: p[0],
+                a: p[3],
+            },
+            ImageFormat::Rgb565 => {
+                let v = u16::from_le_bytes([p[0], p[1]]);
+                // Widen each channel, repeating its top bits in the low ones
+                let (r, g, b) = ((v >> 11) as u8, (v >> 5) as u8 & 0x3f, v as u8 & 0x1f);
+                RGBA {
+                    r: (r << 3) | (r >> 2),
+                    g: (g << 2) | (g >> 4),
+                    b: (b << 3) | (b >> 2),
+                    a: 255,
+                }
+            }
+        }
+    }
+
+    // Blend of the four pixels around (`x`, `y`), clamped to `area`
+    fn sample_bilinear(&self, x: f32, y: f32, area: &BlitArea) -> RGBA {
+        let clamp =
+            |v: f32, start: usize, len: usize| v.clamp(start as f32, (start + len - 1) as f32);
+        let x = clamp(x, area.x, area.width);
+        let y = clamp(y, area.y, area.height);
+        let (x0, y0) = (x as usize, y as usize);
+        let x1 = (x0 + 1).min(area.x + area.width - 1);
+        let y1 = (y0 + 1).min(area.y + area.height - 1);
+        let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+        let (p00, p10) = (self.pixel(x0, y0), self.pixel(x1, y0));
+        let (p01, p11) = (self.pixel(x0, y1), self.pixel(x1, y1));
+        let mix = |a: u8, b: u8, c: u8, d: u8| {
+            let top = a as f32 + (b as f32 - a as f32) * tx;
+            let bottom = c as f32 + (d as f32 - c as f32) * tx;
+            (top + (bottom - top) * ty + 0.5) as u8
+        };
+        RGBA {
+            r: mix(p00.r, p10.r, p01.r, p11.r),
+            g: mix(p00.g, p10.g, p01.g, p11.g),
+            b: mix(p00.b, p10.b, p01.b, p11.b),
+            a: mix(p00.a, p10.a, p01.a, p11.a),
+        }
+    }
+}
+
+/// A rectangle in source or destination pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlitArea {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl FB {
+    /// Copy the `src` part of `image` to `dest_size` pixels at `dest`,
+    /// scaling it when the sizes differ. With `blend` the source alpha is
+    /// applied through `set_pixel_blend`, otherwise pixels are copied as they
+    /// are. Parts outside the screen are clipped. Returns false if `src` does
+    /// not lie within the image.
+    pub fn blit(
+        &mut self,
+        image: &Image,
+        src: BlitArea,
+        dest: Coordinate,
+        dest_size: (usize, usize),
+        blend: bool,
+        scaling: Scaling,
+    ) -> bool {
+        let within = |start: usize, len: usize, size: usize| {
+            len > 0 && start.checked_add(len).is_some_and(|end| end <= size)
+        };
+        if !within(src.x, src.width, image.width) || !within(src.y, src.height, image.height) {
+            return false;
+        }
+        let (dest_width, dest_height) = dest_size;
+        // Destination columns and rows that land on the screen
+        let visible = |start: isize, len: usize, size: usize| {
+            let from = (-start).clamp(0, len as isize) as usize;
+            let to = (size as isize - start).clamp(0, len as isize) as usize;
+            from..to.max(from)
+        };
+        let columns = visible(dest.x, dest_width, self.w);
+        let scale_x = src.width as f32 / dest_width as f32;
+        let scale_y = src.height as f32 / dest_height as f32;
+
+        for dy in visible(dest.y, dest_height, self.h) {
+            let y = (dest.y + dy as isize) as usize;
+            for dx in columns.clone() {
+                let x = (dest.x + dx as isize) as usize;
+                let color = match scaling {
+                    Scaling::Nearest => image.pixel(
+                        src.x + dx * src.width / dest_width,
+                        src.y + dy * src.height / dest_height,
+                    ),
+                    // Sample at the pixel centre mapped into the source
+                    Scaling::Bilinear => image.sample_bilinear(
+                        src.x as f32 + (dx as f32 + 0.5) * scale_x - 0.5,
+                        src.y as f32 + (dy as f32 + 0.5) * scale_y - 0.5,
+                        &src,
+                    ),
+                };
+                match color.a {
+                    _ if !blend => self.set(x, y, color),
+                    0 => {}
+                    255 => self.set(x, y, color),
+                    _ => self.set_pixel_blend(Coordinate::new(x as isize, y as isize), color),
+                }
+            }
+        }
+        true
+    }
+}
@@ -1,6 +1,10 @@
+pub mod compositor;
+pub mod desktop;
 pub mod display;
 pub mod font;
+pub mod image;
 pub mod shapes;
+pub mod text;
 
 use crate::sys::interrupts::global_time_ms;
 use alloc::vec;
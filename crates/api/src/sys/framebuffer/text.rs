@@ -0,0 +1,98 @@
+/// Anti-aliased UTF-8 text in the Noto Sans Mono bitmap font
+use super::{font_constants::BACKUP_CHAR, shapes::Coordinate, FB, LINE_SPACING, RGBA};
+use noto_sans_mono_bitmap::{get_raster, get_raster_width, FontWeight, RasterHeight};
+
+/// Raster heights available, smallest first
+const HEIGHTS: [RasterHeight; 4] = [
+    RasterHeight::Size16,
+    RasterHeight::Size20,
+    RasterHeight::Size24,
+    RasterHeight::Size32,
+];
+
+#[derive(Debug, Clone, Copy)]
+pub struct Font {
+    pub height: RasterHeight,
+    pub weight: FontWeight,
+}
+
+impl Default for Font {
+    fn default() -> Self {
+        Self {
+            height: RasterHeight::Size16,
+            weight: FontWeight::Regular,
+        }
+    }
+}
+
+impl Font {
+    /// The largest font at most `size` pixels tall, or the smallest one
+    pub fn new(size: usize, weight: FontWeight) -> Self {
+        let height = HEIGHTS
+            .into_iter()
+            .rev()
+            .find(|height| height.val() <= size)
+            .unwrap_or(HEIGHTS[0]);
+        Self { height, weight }
+    }
+
+    /// Advance of every glyph; the font is monospaced
+    pub fn char_width(&self) -> usize {
+        get_raster_width(self.weight, self.height)
+    }
+
+    pub fn line_height(&self) -> usize {
+        self.height.val() + LINE_SPACING
+    }
+
+    /// Width of the longest line of `text` and the height of all its lines
+    pub fn measure(&self, text: &str) -> (usize, usize) {
+        let (lines, longest) = text.split('\n').fold((0, 0), |(lines, longest), line| {
+            (lines + 1, longest.max(line.chars().count()))
+        });
+        (
+            longest * self.char_width(),
+            lines * self.line_height() - LINE_SPACING,
+        )
+    }
+}
+
+impl FB {
+    /// Draw `text` with its top left corner at `coordinate`, blending glyph
+    /// edges into the background. `\n` starts a new line and characters the
+    /// font lacks are drawn as `�`. Returns the size `Font::measure` reports.
+    pub fn draw_string(
+        &mut self,
+        coordinate: Coordinate,
+        text: &str,
+        color: RGBA,
+        font: Font,
+    ) -> (usize, usize) {
+        let (mut x, mut y) = (coordinate.x, coordinate.y);
+        for c in text.chars() {
+            if c == '\n' {
+                x = coordinate.x;
+                y += font.line_height() as isize;
+                continue;
+            }
+            let raster = get_raster(c, font.weight, font.height)
+                .or_else(|| get_raster(BACKUP_CHAR, font.weight, font.height));
+            if let Some(raster) = raster {
+                for (row_y, row) in raster.raster().iter().enumerate() {
+                    for (col_x, &intensity) in row.iter().enumerate() {
+                        if intensity == 0 {
+                            continue;
+                        }
+                        let alpha = (color.a as usize * intensity as usize / 255) as u8;
+                        self.set_pixel_blend(
+                            Coordinate::new(x + col_x as isize, y + row_y as isize),
+                            RGBA { a: alpha, ..color },
+                        );
+                    }
+                }
+            }
+            x += font.char_width() as isize;
+        }
+        font.measure(text)
+    }
+}
@@ -326,6 +326,13 @@ impl VirtualFileSystem {
             _ => Err(AgaveError::FileSystemError(FsError::NotDirectory)),
         }
     }
+    /// Target of the symbolic link at `path`
+    pub fn read_link(&self, path: &str) -> AgaveResult<String> {
+        match self.get_node(path)? {
+            VfsNode::Symlink { target, .. } => Ok(target.clone()),
+            _ => Err(AgaveError::InvalidParameter),
+        }
+    }
     /// Create a symbolic link (public API)
     pub fn new() -> Self {
         let mut vfs = Self {
@@ -588,6 +595,40 @@ impl VirtualFileSystem {
         }
     }
 
+    /// Read from `offset` of a file into `buffer`; 0 bytes at or past its end
+    pub fn read_at(&self, path: &str, offset: u64, buffer: &mut [u8]) -> AgaveResult<usize> {
+        match self.get_node(path)? {
+            VfsNode::File { content, .. } => {
+                let start = offset.min(content.len() as u64) as usize;
+                let bytes_read = buffer.len().min(content.len() - start);
+                buffer[..bytes_read].copy_from_slice(&content[start..start + bytes_read]);
+                Ok(bytes_read)
+            }
+            _ => Err(AgaveError::FileSystemError(FsError::IsDirectory)),
+        }
+    }
+
+    /// Write `data` at `offset` of a file, zero-filling any gap past its end
+    pub fn write_at(&mut self, path: &str, offset: u64, data: &[u8]) -> AgaveResult<usize> {
+        let end = offset
+            .checked_add(data.len() as u64)
+            .and_then(|end| usize::try_from(end).ok())
+            .ok_or(AgaveError::InvalidParameter)?;
+        match self.get_node_mut(path)? {
+            VfsNode::File { content, metadata } => {
+                if end > content.len() {
+                    content.resize(end, 0);
+                }
+                content[offset as usize..end].copy_from_slice(data);
+                metadata.size = content.len() as u64;
+                metadata.modified_time =
+                    crate::sys::interrupts::TIME_MS.load(core::sync::atomic::Ordering::Relaxed);
+                Ok(data.len())
+            }
+            _ => Err(AgaveError::FileSystemError(FsError::IsDirectory)),
+        }
+    }
+
     /// List directory contents
     pub fn read_dir(&self, path: &str) -> AgaveResult<Vec<DirEntry>> {
         let node = self.get_node(path)?;
@@ -1092,6 +1133,14 @@ pub fn write(fd: u64, data: &[u8]) -> AgaveResult<usize> {
     with_filesystem(|fs| fs.write(fd, data))
 }
 
+pub fn read_at(path: &str, offset: u64, buffer: &mut [u8]) -> AgaveResult<usize> {
+    with_filesystem(|fs| fs.read_at(path, offset, buffer))
+}
+
+pub fn write_at(path: &str, offset: u64, data: &[u8]) -> AgaveResult<usize> {
+    with_filesystem(|fs| fs.write_at(path, offset, data))
+}
+
 pub fn read_dir(path: &str) -> AgaveResult<Vec<DirEntry>> {
     with_filesystem(|fs| fs.read_dir(path))
 }
@@ -1112,6 +1161,22 @@ pub fn remove(path: &str) -> AgaveResult<()> {
     with_filesystem(|fs| fs.remove(path))
 }
 
+pub fn truncate(path: &str, size: u64) -> AgaveResult<()> {
+    with_filesystem(|fs| fs.truncate(path, size))
+}
+
+pub fn rename(old_path: &str, new_path: &str) -> AgaveResult<()> {
+    with_filesystem(|fs| fs.rename(old_path, new_path))
+}
+
+pub fn symlink(link_path: &str, target: &str) -> AgaveResult<()> {
+    with_filesystem(|fs| fs.symlink(link_path, target))
+}
+
+pub fn read_link(path: &str) -> AgaveResult<String> {
+    with_filesystem(|fs| fs.read_link(path))
+}
+
 pub fn write_file(path: &str, content: Vec<u8>) -> AgaveResult<()> {
     with_filesystem(|fs| fs.write_file(path, content))
 }
@@ -34,6 +34,14 @@ pub struct InputEvent {
 pub struct Input {
     pub mouse_x: usize,
     pub mouse_y: usize,
+    ///Pointer bounds, the pointer is kept inside them (0 means unbounded)
+    pub screen_w: usize,
+    pub screen_h: usize,
+    ///Buttons held down, `MOUSE_BUTTON_*` bits
+    pub mouse_buttons: u8,
+    ///Wheel ticks since the last `step`; positive is up / right
+    pub wheel_x: i32,
+    pub wheel_y: i32,
     pub keys: [KeyState; 1024],
     pub history_last_index: usize,
     pub history_ring: [InputEvent; HISTORY_SIZE],
@@ -44,6 +52,11 @@ impl Input {
         Self {
             mouse_x: 0,
             mouse_y: 0,
+            screen_w: 0,
+            screen_h: 0,
+            mouse_buttons: 0,
+            wheel_x: 0,
+            wheel_y: 0,
             keys: [KeyState::Off; 1024],
             history_last_index: 0,
             history_ring: [InputEvent {
@@ -56,9 +69,19 @@ impl Input {
         for k in self.keys.iter_mut() {
             k.step();
         }
+        self.wheel_x = 0;
+        self.wheel_y = 0;
     }
 }
 
+pub const MOUSE_BUTTON_LEFT: u8 = 1 << 0;
+pub const MOUSE_BUTTON_RIGHT: u8 = 1 << 1;
+pub const MOUSE_BUTTON_MIDDLE: u8 = 1 << 2;
+pub const MOUSE_BUTTON_SIDE: u8 = 1 << 3;
+pub const MOUSE_BUTTON_EXTRA: u8 = 1 << 4;
+//First mouse button key code (BTN_LEFT); buttons follow in bit order
+const BTN_MOUSE: usize = 0x110;
+
 #[repr(u8)]
 #[derive(Clone, Debug, Copy)]
 pub enum KeyState {
@@ -84,6 +107,45 @@ impl Input {
         self.history_last_index += 1;
         self.history_ring[self.history_last_index % HISTORY_SIZE] = InputEvent { trigger: b, key };
         self.keys[key].handle_incoming_state(b);
+        if (BTN_MOUSE..BTN_MOUSE + 5).contains(&key) {
+            let bit = 1 << (key - BTN_MOUSE);
+            if b {
+                self.mouse_buttons |= bit;
+            } else {
+                self.mouse_buttons &= !bit;
+            }
+        }
+    }
+
+    pub fn set_screen_size(&mut self, w: usize, h: usize) {
+        self.screen_w = w;
+        self.screen_h = h;
+        self.move_mouse_to(self.mouse_x, self.mouse_y);
+    }
+
+    ///Move the pointer, keeping it on screen
+    pub fn move_mouse_to(&mut self, x: usize, y: usize) {
+        self.mouse_x = clamp_to(x, self.screen_w);
+        self.mouse_y = clamp_to(y, self.screen_h);
+    }
+
+    pub fn move_mouse_by(&mut self, dx: i32, dy: i32) {
+        let x = (self.mouse_x as i64 + dx as i64).max(0) as usize;
+        let y = (self.mouse_y as i64 + dy as i64).max(0) as usize;
+        self.move_mouse_to(x, y);
+    }
+
+    pub fn handle_wheel(&mut self, dx: i32, dy: i32) {
+        self.wheel_x += dx;
+        self.wheel_y += dy;
+    }
+}
+
+fn clamp_to(v: usize, size: usize) -> usize {
+    if size == 0 {
+        v
+    } else {
+        v.min(size - 1)
     }
 }
 
@@ -0,0 +1,460 @@
+/// Structured input events delivered to apps
+///
+/// Drivers report raw key and axis changes here. They are turned
+/// into typed events (carrying modifiers and pointer coordinates) and queued
+/// until the main loop hands them to the focused app's `EventQueue`. Key
+/// presses also produce `Text` events through the active `keymap`, and held
+/// keys repeat at the keymap's rate.
+use crate::sys::{
+    globals::INPUT,
+    interrupts::TIME_MS,
+    keymap::{self, RepeatConfig},
+    process::ProcessId,
+};
+use alloc::{collections::VecDeque, vec::Vec};
+use core::sync::atomic::Ordering;
+use spin::Mutex;
+
+// Linux input event codes
+pub const KEY_ESC: u16 = 1;
+pub const KEY_TAB: u16 = 15;
+pub const KEY_ENTER: u16 = 28;
+pub const KEY_LEFTCTRL: u16 = 29;
+pub const KEY_LEFTSHIFT: u16 = 42;
+pub const KEY_RIGHTSHIFT: u16 = 54;
+pub const KEY_LEFTALT: u16 = 56;
+pub const KEY_CAPSLOCK: u16 = 58;
+pub const KEY_F1: u16 = 59;
+pub const KEY_F4: u16 = 62;
+pub const KEY_F10: u16 = 68;
+pub const KEY_RIGHTCTRL: u16 = 97;
+pub const KEY_RIGHTALT: u16 = 100;
+pub const KEY_UP: u16 = 103;
+pub const KEY_LEFT: u16 = 105;
+pub const KEY_RIGHT: u16 = 106;
+pub const KEY_DOWN: u16 = 108;
+pub const KEY_LEFTMETA: u16 = 125;
+pub const KEY_RIGHTMETA: u16 = 126;
+pub const BTN_LEFT: u16 = 0x110;
+pub const BTN_RIGHT: u16 = 0x111;
+pub const BTN_MIDDLE: u16 = 0x112;
+// Mouse buttons occupy BTN_MOUSE..BTN_JOYSTICK
+const BTN_MOUSE_END: u16 = 0x120;
+pub const ABS_X: u16 = 0;
+pub const ABS_Y: u16 = 1;
+pub const REL_X: u16 = 0;
+pub const REL_Y: u16 = 1;
+pub const REL_HWHEEL: u16 = 6;
+pub const REL_WHEEL: u16 = 8;
+
+/// Modifier keys held when an event happened
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Modifiers(pub u32);
+
+impl Modifiers {
+    pub const SHIFT: u32 = 1 << 0;
+    pub const CTRL: u32 = 1 << 1;
+    pub const ALT: u32 = 1 << 2;
+    pub const META: u32 = 1 << 3;
+    pub const CAPS_LOCK: u32 = 1 << 4;
+    /// Right Alt, which selects the third level of the keymap
+    pub const ALTGR: u32 = 1 << 5;
+
+    pub fn contains(&self, flag: u32) -> bool {
+        self.0 & flag != 0
+    }
+
+    // Modifier bit driven by a held key, if `code` is a modifier key
+    fn held_flag(code: u16) -> Option<u32> {
+        match code {
+            KEY_LEFTSHIFT | KEY_RIGHTSHIFT => Some(Self::SHIFT),
+            KEY_LEFTCTRL | KEY_RIGHTCTRL => Some(Self::CTRL),
+            KEY_LEFTALT => Some(Self::ALT),
+            KEY_RIGHTALT => Some(Self::ALTGR),
+            KEY_LEFTMETA | KEY_RIGHTMETA => Some(Self::META),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// `repeat` marks presses generated by holding the key down
+    Key {
+        code: u16,
+        pressed: bool,
+        repeat: bool,
+        modifiers: Modifiers,
+    },
+    /// Pointer moved to (`x`, `y`) by (`dx`, `dy`)
+    MouseMove {
+        x: i32,
+        y: i32,
+        dx: i32,
+        dy: i32,
+    },
+    MouseButton {
+        button: u16,
+        pressed: bool,
+        x: i32,
+        y: i32,
+        modifiers: Modifiers,
+    },
+    /// Wheel ticks; positive `dy` scrolls up, positive `dx` scrolls right
+    Wheel {
+        dx: i32,
+        dy: i32,
+        x: i32,
+        y: i32,
+    },
+    Focus {
+        focused: bool,
+    },
+    /// Character typed through the active keymap; Enter, Tab and Backspace
+    /// type `\n`, `\t` and `\u{8}`
+    Text {
+        ch: char,
+    },
+}
+
+/// Bytes `Event::encode` writes
+pub const EVENT_SIZE: usize = 32;
+
+impl Event {
+    pub const KIND_KEY: u32 = 1;
+    pub const KIND_MOUSE_MOVE: u32 = 2;
+    pub const KIND_MOUSE_BUTTON: u32 = 3;
+    pub const KIND_WHEEL: u32 = 4;
+    pub const KIND_FOCUS: u32 = 5;
+    pub const KIND_TEXT: u32 = 6;
+
+    /// Guest ABI: `kind: u32` and five `i32` fields, then `time_ms: u64`
+    pub fn encode(&self, time_ms: u64) -> [u8; EVENT_SIZE] {
+        let (kind, fields): (u32, [i32; 5]) = match *self {
+            Event::Key {
+                code,
+                pressed,
+                repeat,
+                modifiers,
+            } => (
+                Self::KIND_KEY,
+                [
+                    code as i32,
+                    pressed as i32,
+                    modifiers.0 as i32,
+                    repeat as i32,
+                    0,
+                ],
+            ),
+            Event::MouseMove { x, y, dx, dy } => (Self::KIND_MOUSE_MOVE, [x, y, dx, dy, 0]),
+            Event::MouseButton {
+                button,
+                pressed,
+                x,
+                y,
+                modifiers,
+            } => (
+                Self::KIND_MOUSE_BUTTON,
+                [button as i32, pressed as i32, x, y, modifiers.0 as i32],
+            ),
+            Event::Wheel { dx, dy, x, y } => (Self::KIND_WHEEL, [dx, dy, x, y, 0]),
+            Event::Focus { focused } => (Self::KIND_FOCUS, [focused as i32, 0, 0, 0, 0]),
+            Event::Text { ch } => (Self::KIND_TEXT, [ch as i32, 0, 0, 0, 0]),
+        };
+        let mut bytes = [0u8; EVENT_SIZE];
+        bytes[0..4].copy_from_slice(&kind.to_le_bytes());
+        for (i, field) in fields.iter().enumerate() {
+            bytes[4 + i * 4..8 + i * 4].copy_from_slice(&field.to_le_bytes());
+        }
+        bytes[24..32].copy_from_slice(&time_ms.to_le_bytes());
+        bytes
+    }
+}
+
+/// Events waiting for one app, oldest first
+#[derive(Debug, Default)]
+pub struct EventQueue {
+    events: VecDeque<(Event, u64)>,
+    focused: bool,
+    /// Events discarded because the app did not keep up
+    pub dropped: u64,
+}
+
+impl EventQueue {
+    /// Events kept per app before the oldest are dropped
+    pub const CAPACITY: usize = 256;
+
+    pub const fn new() -> Self {
+        Self {
+            events: VecDeque::new(),
+            focused: false,
+            dropped: 0,
+        }
+    }
+
+    pub fn push(&mut self, event: Event, time_ms: u64) {
+        if self.events.len() >= Self::CAPACITY {
+            self.events.pop_front();
+            self.dropped += 1;
+        }
+        self.events.push_back((event, time_ms));
+    }
+
+    pub fn pop(&mut self) -> Option<(Event, u64)> {
+        self.events.pop_front()
+    }
+
+    /// Put back an event that could not be delivered
+    pub fn unpop(&mut self, event: Event, time_ms: u64) {
+        self.events.push_front((event, time_ms));
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Record whether the app has focus, queueing a `Focus` event on change
+    pub fn set_focused(&mut self, focused: bool, time_ms: u64) {
+        if self.focused != focused {
+            self.focused = focused;
+            self.push(Event::Focus { focused }, time_ms);
+        }
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+}
+
+// Translates raw driver reports into events
+struct InputState {
+    pending: VecDeque<(Event, u64)>,
+    modifiers: Modifiers,
+    mouse_x: i32,
+    mouse_y: i32,
+    // Relative motion, absolute position and wheel ticks accumulated until
+    // the next sync
+    motion: (i32, i32),
+    position: (Option<i32>, Option<i32>),
+    wheel: (i32, i32),
+    // Pointer bounds; 0 means unbounded
+    screen: (i32, i32),
+    focus: Option<ProcessId>,
+    // Key being held and when it next repeats
+    held: Option<(u16, u64)>,
+}
+
+static INPUT_STATE: Mutex<InputState> = Mutex::new(InputState {
+    pending: VecDeque::new(),
+    modifiers: Modifiers(0),
+    mouse_x: 0,
+    mouse_y: 0,
+    motion: (0, 0),
+    position: (None, None),
+    wheel: (0, 0),
+    screen: (0, 0),
+    focus: None,
+    held: None,
+});
+
+// Events kept while no app drains them
+const MAX_PENDING: usize = 1024;
+
+impl InputState {
+    fn push(&mut self, event: Event) {
+        if self.pending.len() >= MAX_PENDING {
+            self.pending.pop_front();
+        }
+        let now = TIME_MS.load(Ordering::Relaxed);
+        self.pending.push_back((event, now));
+    }
+
+    fn key(&mut self, code: u16, pressed: bool) {
+        if (BTN_LEFT..BTN_MOUSE_END).contains(&code) {
+            self.push(Event::MouseButton {
+                button: code,
+                pressed,
+                x: self.mouse_x,
+                y: self.mouse_y,
+                modifiers: self.modifiers,
+            });
+            return;
+        }
+
+        let is_modifier = match Modifiers::held_flag(code) {
+            Some(flag) if pressed => {
+                self.modifiers.0 |= flag;
+                true
+            }
+            Some(flag) => {
+                self.modifiers.0 &= !flag;
+                true
+            }
+            None if code == KEY_CAPSLOCK => {
+                if pressed {
+                    self.modifiers.0 ^= Modifiers::CAPS_LOCK;
+                }
+                true
+            }
+            None => false,
+        };
+
+        if is_modifier {
+            self.push(Event::Key {
+                code,
+                pressed,
+                repeat: false,
+                modifiers: self.modifiers,
+            });
+        } else if pressed {
+            let RepeatConfig { delay_ms, .. } = keymap::repeat();
+            self.held = (delay_ms > 0).then(|| (code, TIME_MS.load(Ordering::Relaxed) + delay_ms));
+            self.press(code, false);
+        } else {
+            if self.held.is_some_and(|(held, _)| held == code) {
+                self.held = None;
+            }
+            self.push(Event::Key {
+                code,
+                pressed,
+                repeat: false,
+                modifiers: self.modifiers,
+            });
+        }
+    }
+
+    // Queue a key press and the text it types
+    fn press(&mut self, code: u16, repeat: bool) {
+        self.push(Event::Key {
+            code,
+            pressed: true,
+            repeat,
+            modifiers: self.modifiers,
+        });
+        for ch in keymap::translate(code, self.modifiers) {
+            self.push(Event::Text { ch });
+        }
+    }
+
+    // Repeat the held key for every interval that has passed
+    fn repeat_held(&mut self) {
+        let Some((code, mut next)) = self.held else {
+            return;
+        };
+        let interval = keymap::repeat().interval_ms.max(1);
+        let now = TIME_MS.load(Ordering::Relaxed);
+        while next <= now {
+            self.press(code, true);
+            next += interval;
+        }
+        self.held = Some((code, next));
+    }
+
+    fn sync(&mut self) {
+        let (dx, dy) = core::mem::take(&mut self.motion);
+        let (abs_x, abs_y) = core::mem::take(&mut self.position);
+        let x = clamp_axis(abs_x.unwrap_or(self.mouse_x) + dx, self.screen.0);
+        let y = clamp_axis(abs_y.unwrap_or(self.mouse_y) + dy, self.screen.1);
+        if x != self.mouse_x || y != self.mouse_y {
+            let (dx, dy) = (x - self.mouse_x, y - self.mouse_y);
+            self.mouse_x = x;
+            self.mouse_y = y;
+            self.push(Event::MouseMove { x, y, dx, dy });
+        }
+        let (dx, dy) = core::mem::take(&mut self.wheel);
+        if dx != 0 || dy != 0 {
+            self.push(Event::Wheel {
+                dx,
+                dy,
+                x: self.mouse_x,
+                y: self.mouse_y,
+            });
+        }
+    }
+}
+
+/// Report a key or button press (`pressed`) or release. Held keys are
+/// repeated here, so drivers should not report the device's own repeats
+pub fn report_key(code: u16, pressed: bool) {
+    INPUT_STATE.lock().key(code, pressed);
+}
+
+/// Report relative motion on `axis` (`REL_*`); applied at the next `report_sync`
+pub fn report_relative(axis: u16, delta: i32) {
+    let mut state = INPUT_STATE.lock();
+    match axis {
+        REL_X => state.motion.0 += delta,
+        REL_Y => state.motion.1 += delta,
+        REL_HWHEEL => state.wheel.0 += delta,
+        REL_WHEEL => state.wheel.1 += delta,
+        _ => log::debug!("input: ignoring relative axis {}", axis),
+    }
+}
+
+/// Report an absolute pointer coordinate on `axis` (`ABS_X`/`ABS_Y`),
+/// already scaled to screen pixels; applied at the next `report_sync`
+pub fn report_absolute(axis: u16, value: i32) {
+    let mut state = INPUT_STATE.lock();
+    match axis {
+        ABS_X => state.position.0 = Some(value),
+        ABS_Y => state.position.1 = Some(value),
+        _ => log::debug!("input: ignoring absolute axis {}", axis),
+    }
+}
+
+/// End of a batch of reports from one device
+pub fn report_sync() {
+    INPUT_STATE.lock().sync();
+}
+
+/// Take the events reported since the last call, with their timestamps,
+/// including repeats of a held key that have come due
+pub fn take_events() -> Vec<(Event, u64)> {
+    let mut state = INPUT_STATE.lock();
+    state.repeat_held();
+    state.pending.drain(..).collect()
+}
+
+/// Keep the pointer within a `width` x `height` screen
+pub fn set_screen_size(width: usize, height: usize) {
+    {
+        let mut state = INPUT_STATE.lock();
+        state.screen = (width as i32, height as i32);
+        state.mouse_x = clamp_axis(state.mouse_x, state.screen.0);
+        state.mouse_y = clamp_axis(state.mouse_y, state.screen.1);
+    }
+    INPUT.update(|input| input.set_screen_size(width, height));
+}
+
+/// Screen size set by `set_screen_size`
+pub fn screen_size() -> (usize, usize) {
+    let (width, height) = INPUT_STATE.lock().screen;
+    (width as usize, height as usize)
+}
+
+fn clamp_axis(value: i32, size: i32) -> i32 {
+    if size == 0 {
+        value.max(0)
+    } else {
+        value.clamp(0, size - 1)
+    }
+}
+
+/// Give keyboard and pointer focus to `pid`
+pub fn set_focus(pid: Option<ProcessId>) {
+    INPUT_STATE.lock().focus = pid;
+}
+
+/// App that receives input events
+pub fn focus() -> Option<ProcessId> {
+    INPUT_STATE.lock().focus
+}
+
+/// Modifier keys currently held
+pub fn modifiers() -> Modifiers {
+    INPUT_STATE.lock().modifiers
+}
@@ -25,6 +25,12 @@ impl IpcHandle {
     }
 }
 
+impl From<u64> for IpcHandle {
+    fn from(val: u64) -> Self {
+        IpcHandle(val)
+    }
+}
+
 /// IPC resource types
 #[derive(Debug)]
 pub enum IpcResource {
@@ -77,9 +83,13 @@ impl IpcManager {
         }
     }
 
-    /// Create a new pipe
-    pub fn create_pipe(&mut self, owner: ProcessId) -> AgaveResult<(IpcHandle, IpcHandle)> {
-        let pipe = pipes::Pipe::new()?;
+    /// Create a new pipe buffering up to `capacity` bytes
+    pub fn create_pipe(
+        &mut self,
+        owner: ProcessId,
+        capacity: usize,
+    ) -> AgaveResult<(IpcHandle, IpcHandle)> {
+        let pipe = pipes::Pipe::with_capacity(capacity)?;
         let read_handle = IpcHandle::new();
         let write_handle = IpcHandle::new();
 
@@ -215,6 +225,48 @@ impl IpcManager {
             .ok_or(AgaveError::NotFound)
     }
 
+    /// Whether `handle` belongs to `process`
+    pub fn is_owner(&self, handle: IpcHandle, process: ProcessId) -> bool {
+        self.process_resources
+            .get(&process)
+            .is_some_and(|handles| handles.contains(&handle))
+    }
+
+    /// Hand a resource owned by `from` over to `to`
+    pub fn transfer_resource(
+        &mut self,
+        handle: IpcHandle,
+        from: ProcessId,
+        to: ProcessId,
+    ) -> AgaveResult<()> {
+        let handles = self
+            .process_resources
+            .get_mut(&from)
+            .ok_or(AgaveError::NotFound)?;
+        let pos = handles
+            .iter()
+            .position(|&h| h == handle)
+            .ok_or(AgaveError::NotFound)?;
+        handles.remove(pos);
+        self.process_resources.entry(to).or_default().push(handle);
+        log::debug!(
+            "Transferred IPC resource {:?} from process {} to {}",
+            handle,
+            from,
+            to
+        );
+        Ok(())
+    }
+
+    // Drop a resource, closing its pipe ends so the other side sees end of
+    // file or a broken pipe
+    fn remove_resource(&mut self, handle: IpcHandle) {
+        if let Some(IpcResource::Pipe(mut pipe)) = self.resources.remove(&handle) {
+            pipe.close_read();
+            pipe.close_write();
+        }
+    }
+
     /// Close resource
     pub fn close_resource(&mut self, handle: IpcHandle, process: ProcessId) -> AgaveResult<()> {
         if let Some(handles) = self.process_resources.get_mut(&process) {
@@ -223,20 +275,31 @@ impl IpcManager {
             }
         }
 
-        self.resources.remove(&handle);
+        self.remove_resource(handle);
         log::debug!("Closed IPC resource {:?} for process {}", handle, process);
         Ok(())
     }
 
     /// Cleanup resources for a terminated process
     pub fn cleanup_process_resources(&mut self, process: ProcessId) {
+        self.cleanup_process_resources_except(process, &[]);
+    }
+
+    /// Clean up the resources of `process` other than those in `keep`
+    pub fn cleanup_process_resources_except(&mut self, process: ProcessId, keep: &[IpcHandle]) {
         if let Some(handles) = self.process_resources.remove(&process) {
-            let handle_count = handles.len();
-            for handle in handles {
-                self.resources.remove(&handle);
+            let (kept, removed): (Vec<IpcHandle>, Vec<IpcHandle>) = handles
+                .into_iter()
+                .partition(|handle| keep.contains(handle));
+            let handle_count = removed.len();
+            for handle in removed {
+                self.remove_resource(handle);
+            }
+            if !kept.is_empty() {
+                self.process_resources.insert(process, kept);
             }
             log::debug!(
-                "Cleaned up {} IPC resources for terminated process {}",
+                "Cleaned up {} IPC resources for process {}",
                 handle_count,
                 process
             );
@@ -300,8 +363,15 @@ pub fn init_ipc() -> AgaveResult<()> {
 }
 
 pub fn create_pipe(owner: ProcessId) -> AgaveResult<(IpcHandle, IpcHandle)> {
+    create_pipe_with_capacity(owner, pipes::DEFAULT_PIPE_BUFFER_SIZE)
+}
+
+pub fn create_pipe_with_capacity(
+    owner: ProcessId,
+    capacity: usize,
+) -> AgaveResult<(IpcHandle, IpcHandle)> {
     let mut manager = IPC_MANAGER.lock();
-    manager.create_pipe(owner)
+    manager.create_pipe(owner, capacity)
 }
 
 pub fn create_shared_memory(
@@ -334,11 +404,26 @@ pub fn close_resource(handle: IpcHandle, process: ProcessId) -> AgaveResult<()>
     manager.close_resource(handle, process)
 }
 
+pub fn is_owner(handle: IpcHandle, process: ProcessId) -> bool {
+    let manager = IPC_MANAGER.lock();
+    manager.is_owner(handle, process)
+}
+
+pub fn transfer_resource(handle: IpcHandle, from: ProcessId, to: ProcessId) -> AgaveResult<()> {
+    let mut manager = IPC_MANAGER.lock();
+    manager.transfer_resource(handle, from, to)
+}
+
 pub fn cleanup_process_resources(process: ProcessId) {
     let mut manager = IPC_MANAGER.lock();
     manager.cleanup_process_resources(process);
 }
 
+pub fn cleanup_process_resources_except(process: ProcessId, keep: &[IpcHandle]) {
+    let mut manager = IPC_MANAGER.lock();
+    manager.cleanup_process_resources_except(process, keep);
+}
+
 pub fn get_ipc_statistics() -> IpcStatistics {
     let manager = IPC_MANAGER.lock();
     manager.get_statistics()
@@ -14,7 +14,9 @@ pub const MAX_PIPE_BUFFER_SIZE: usize = 64 * 1024; // 64KB
 #[derive(Debug, Clone)]
 pub struct PipeEnd {
     buffer: Arc<Mutex<VecDeque<u8>>>,
-    is_closed: Arc<Mutex<bool>>,
+    // Shared by every copy of the pipe, so each side sees the other close
+    reader_closed: Arc<Mutex<bool>>,
+    writer_closed: Arc<Mutex<bool>>,
     capacity: usize,
 }
 
@@ -22,17 +24,18 @@ impl PipeEnd {
     fn new(capacity: usize) -> Self {
         Self {
             buffer: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
-            is_closed: Arc::new(Mutex::new(false)),
+            reader_closed: Arc::new(Mutex::new(false)),
+            writer_closed: Arc::new(Mutex::new(false)),
             capacity,
         }
     }
 
-    fn is_closed(&self) -> bool {
-        *self.is_closed.lock()
+    fn reader_closed(&self) -> bool {
+        *self.reader_closed.lock()
     }
 
-    fn close(&self) {
-        *self.is_closed.lock() = true;
+    fn writer_closed(&self) -> bool {
+        *self.writer_closed.lock()
     }
 
     fn available_space(&self) -> usize {
@@ -82,7 +85,7 @@ impl Pipe {
     pub fn read(&mut self, buffer: &mut [u8]) -> AgaveResult<usize> {
         let read_end = self.read_end.as_ref().ok_or(AgaveError::InvalidOperation)?;
 
-        if read_end.is_closed() {
+        if read_end.reader_closed() {
             return Ok(0); // EOF
         }
 
@@ -91,7 +94,7 @@ impl Pipe {
 
         if bytes_to_read == 0 {
             // No data available, check if write end is closed
-            return if self.write_end.as_ref().map_or(true, |w| w.is_closed()) {
+            return if read_end.writer_closed() {
                 Ok(0) // EOF - write end closed
             } else {
                 Err(AgaveError::WouldBlock) // Would block - no data but write end open
@@ -114,12 +117,12 @@ impl Pipe {
             .as_ref()
             .ok_or(AgaveError::InvalidOperation)?;
 
-        if write_end.is_closed() {
+        if write_end.writer_closed() {
             return Err(AgaveError::BrokenPipe);
         }
 
         // Check if read end is closed
-        if self.read_end.as_ref().map_or(true, |r| r.is_closed()) {
+        if write_end.reader_closed() {
             return Err(AgaveError::BrokenPipe);
         }
 
@@ -144,7 +147,7 @@ impl Pipe {
     /// Close the read end of the pipe
     pub fn close_read(&mut self) {
         if let Some(read_end) = &self.read_end {
-            read_end.close();
+            *read_end.reader_closed.lock() = true;
         }
         self.read_end = None;
     }
@@ -152,7 +155,7 @@ impl Pipe {
     /// Close the write end of the pipe
     pub fn close_write(&mut self) {
         if let Some(write_end) = &self.write_end {
-            write_end.close();
+            *write_end.writer_closed.lock() = true;
         }
         self.write_end = None;
     }
@@ -160,9 +163,7 @@ impl Pipe {
     /// Check if pipe is readable (has data or write end closed)
     pub fn is_readable(&self) -> bool {
         if let Some(read_end) = &self.read_end {
-            !read_end.is_closed()
-                && (read_end.available_data() > 0
-                    || self.write_end.as_ref().map_or(true, |w| w.is_closed()))
+            !read_end.reader_closed() && (read_end.available_data() > 0 || read_end.writer_closed())
         } else {
             false
         }
@@ -171,9 +172,9 @@ impl Pipe {
     /// Check if pipe is writable (has space and read end open)
     pub fn is_writable(&self) -> bool {
         if let Some(write_end) = &self.write_end {
-            !write_end.is_closed()
+            !write_end.writer_closed()
                 && write_end.available_space() > 0
-                && self.read_end.as_ref().map_or(false, |r| !r.is_closed())
+                && !write_end.reader_closed()
         } else {
             false
         }
@@ -192,8 +193,8 @@ impl Pipe {
         PipeStats {
             buffer_used,
             buffer_capacity,
-            read_end_open: self.read_end.as_ref().map_or(false, |r| !r.is_closed()),
-            write_end_open: self.write_end.as_ref().map_or(false, |w| !w.is_closed()),
+            read_end_open: self.read_end.as_ref().is_some_and(|r| !r.reader_closed()),
+            write_end_open: self.write_end.as_ref().is_some_and(|w| !w.writer_closed()),
             is_readable: self.is_readable(),
             is_writable: self.is_writable(),
         }
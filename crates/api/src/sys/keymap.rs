@@ -0,0 +1,347 @@
+/// Keyboard layouts: translation of key codes into text
+///
+/// `input` asks the keymap for the characters a key press produces, taking
+/// Shift, AltGr and Caps Lock into account. Dead keys (the accents on the
+/// `de` and `fr` layouts) compose with the next letter. The layout and
+/// auto-repeat timing are read from `/etc/keymap` at boot:
+///
+/// ```text
+/// layout = de
+/// # milliseconds before a held key repeats, then between repeats
+/// repeat_delay = 500
+/// repeat_interval = 33
+/// ```
+use crate::sys::{
+    error::{AgaveError, AgaveResult},
+    fs,
+    input::Modifiers,
+};
+use alloc::{string::String, vec, vec::Vec};
+use spin::Mutex;
+
+pub const CONFIG_FILE: &str = "/etc/keymap";
+
+// Key codes producing control characters on every layout
+const KEY_BACKSPACE: u16 = 14;
+const KEY_TAB: u16 = 15;
+const KEY_ENTER: u16 = 28;
+const KEY_SPACE: u16 = 57;
+const KEY_KPENTER: u16 = 96;
+
+// Keys a layout assigns characters to, in the order of each level string:
+// the number row (starting with the key left of 1), the three letter rows
+// (the top one ending with the key above Enter), the extra ISO key left of
+// Z, then the bottom row
+const LAYOUT_KEYS: [u16; 48] = [
+    41, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, //
+    16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 43, //
+    30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, //
+    86, //
+    44, 45, 46, 47, 48, 49, 50, 51, 52, 53,
+];
+
+// Marks an unassigned key in a level string; no layout puts a space on these
+// keys
+const UNASSIGNED: char = ' ';
+
+// Dead keys appear in level strings as combining marks: (mark, character
+// typed when it composes with nothing, bases, composed forms)
+const DEAD_KEYS: [(char, char, &str, &str); 5] = [
+    ('\u{301}', '´', "aeiouyAEIOUY", "áéíóúýÁÉÍÓÚÝ"),
+    ('\u{300}', '`', "aeiouAEIOU", "àèìòùÀÈÌÒÙ"),
+    ('\u{302}', '^', "aeiouAEIOU", "âêîôûÂÊÎÔÛ"),
+    ('\u{308}', '¨', "aeiouyAEIOU", "äëïöüÿÄËÏÖÜ"),
+    ('\u{303}', '~', "anoANO", "ãñõÃÑÕ"),
+];
+
+/// Characters of one keyboard layout, one string per level following
+/// `LAYOUT_KEYS`; an empty level leaves every key unassigned
+#[derive(Debug)]
+pub struct Layout {
+    pub name: &'static str,
+    normal: &'static str,
+    shift: &'static str,
+    altgr: &'static str,
+}
+
+pub static LAYOUTS: [Layout; 5] = [
+    Layout {
+        name: "us",
+        normal: concat!(
+            "`1234567890-=",
+            "qwertyuiop[]\\",
+            "asdfghjkl;'",
+            "\\",
+            "zxcvbnm,./"
+        ),
+        shift: concat!(
+            "~!@#$%^&*()_+",
+            "QWERTYUIOP{}|",
+            "ASDFGHJKL:\"",
+            "|",
+            "ZXCVBNM<>?"
+        ),
+        altgr: "",
+    },
+    Layout {
+        name: "uk",
+        normal: concat!(
+            "`1234567890-=",
+            "qwertyuiop[]#",
+            "asdfghjkl;'",
+            "\\",
+            "zxcvbnm,./"
+        ),
+        shift: concat!(
+            "¬!\"£$%^&*()_+",
+            "QWERTYUIOP{}~",
+            "ASDFGHJKL:@",
+            "|",
+            "ZXCVBNM<>?"
+        ),
+        altgr: concat!(
+            "¦   €        ",
+            "  é   úíó    ",
+            "á          ",
+            " ",
+            "          "
+        ),
+    },
+    Layout {
+        name: "de",
+        normal: concat!(
+            "\u{302}1234567890ß\u{301}",
+            "qwertzuiopü+#",
+            "asdfghjklöä",
+            "<",
+            "yxcvbnm,.-"
+        ),
+        shift: concat!(
+            "°!\"§$%&/()=?\u{300}",
+            "QWERTZUIOPÜ*'",
+            "ASDFGHJKLÖÄ",
+            ">",
+            "YXCVBNM;:_"
+        ),
+        altgr: concat!(
+            "  ²³   {[]}\\ ",
+            "@ €        ~ ",
+            "           ",
+            "|",
+            "      µ   "
+        ),
+    },
+    Layout {
+        name: "fr",
+        normal: concat!(
+            "²&é\"'(-è_çà)=",
+            "azertyuiop\u{302}$*",
+            "qsdfghjklmù",
+            "<",
+            "wxcvbn,;:!"
+        ),
+        shift: concat!(
+            " 1234567890°+",
+            "AZERTYUIOP\u{308}£µ",
+            "QSDFGHJKLM%",
+            ">",
+            "WXCVBN?./§"
+        ),
+        altgr: concat!(
+            "  \u{303}#{[|\u{300}\\^@]}",
+            "  €        ¤ ",
+            "           ",
+            " ",
+            "          "
+        ),
+    },
+    Layout {
+        name: "dvorak",
+        normal: concat!(
+            "`1234567890[]",
+            "',.pyfgcrl/=\\",
+            "aoeuidhtns-",
+            "\\",
+            ";qjkxbmwvz"
+        ),
+        shift: concat!(
+            "~!@#$%^&*(){}",
+            "\"<>PYFGCRL?+|",
+            "AOEUIDHTNS_",
+            "|",
+            ":QJKXBMWVZ"
+        ),
+        altgr: "",
+    },
+];
+
+impl Layout {
+    /// Character on `code` at the level selected by `modifiers`; Caps Lock
+    /// shifts letters only
+    fn lookup(&self, code: u16, modifiers: Modifiers) -> Option<char> {
+        let index = LAYOUT_KEYS.iter().position(|&key| key == code)?;
+        let at = |level: &str| level.chars().nth(index).filter(|&ch| ch != UNASSIGNED);
+        if modifiers.contains(Modifiers::ALTGR) {
+            return at(self.altgr);
+        }
+        let (normal, shifted) = (at(self.normal), at(self.shift));
+        let is_letter = matches!(
+            (normal, shifted),
+            (Some(n), Some(s)) if n.is_lowercase() && n.to_uppercase().eq([s])
+        );
+        let shift = modifiers.contains(Modifiers::SHIFT)
+            ^ (is_letter && modifiers.contains(Modifiers::CAPS_LOCK));
+        if shift {
+            shifted
+        } else {
+            normal
+        }
+    }
+}
+
+/// When and how often a held key repeats; a zero delay turns repeat off
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RepeatConfig {
+    pub delay_ms: u64,
+    pub interval_ms: u64,
+}
+
+impl RepeatConfig {
+    pub const DEFAULT: Self = Self {
+        delay_ms: 500,
+        interval_ms: 33,
+    };
+}
+
+struct Keymap {
+    layout: &'static Layout,
+    repeat: RepeatConfig,
+    // Combining mark of a dead key waiting for the next character
+    dead: Option<char>,
+}
+
+static KEYMAP: Mutex<Keymap> = Mutex::new(Keymap {
+    layout: &LAYOUTS[0],
+    repeat: RepeatConfig::DEFAULT,
+    dead: None,
+});
+
+fn dead_key(mark: char) -> Option<&'static (char, char, &'static str, &'static str)> {
+    DEAD_KEYS.iter().find(|(m, ..)| *m == mark)
+}
+
+// Character typed for a dead key that composes with nothing
+fn spacing(mark: char) -> char {
+    dead_key(mark).map_or(mark, |&(_, spacing, ..)| spacing)
+}
+
+fn compose(mark: char, base: char) -> Option<char> {
+    let &(_, _, bases, composed) = dead_key(mark)?;
+    let index = bases.chars().position(|ch| ch == base)?;
+    composed.chars().nth(index)
+}
+
+impl Keymap {
+    fn translate(&mut self, code: u16, modifiers: Modifiers) -> Vec<char> {
+        // Ctrl, Alt and Meta chords are shortcuts, not text
+        if modifiers.contains(Modifiers::CTRL | Modifiers::ALT | Modifiers::META) {
+            return Vec::new();
+        }
+        let ch = match code {
+            KEY_ENTER | KEY_KPENTER => '\n',
+            KEY_TAB => '\t',
+            KEY_SPACE => ' ',
+            // Backspace cancels a pending dead key instead of deleting
+            KEY_BACKSPACE if self.dead.take().is_some() => return Vec::new(),
+            KEY_BACKSPACE => '\u{8}',
+            _ => match self.layout.lookup(code, modifiers) {
+                Some(ch) => ch,
+                None => return Vec::new(),
+            },
+        };
+        let is_dead = dead_key(ch).is_some();
+        match self.dead.take() {
+            // A dead key typed twice, or followed by space, types its accent
+            Some(mark) if mark == ch || ch == ' ' => vec![spacing(mark)],
+            Some(mark) if is_dead => {
+                self.dead = Some(ch);
+                vec![spacing(mark)]
+            }
+            Some(mark) => match compose(mark, ch) {
+                Some(composed) => vec![composed],
+                None => vec![spacing(mark), ch],
+            },
+            None if is_dead => {
+                self.dead = Some(ch);
+                Vec::new()
+            }
+            None => vec![ch],
+        }
+    }
+}
+
+/// Characters typed by pressing `code` with `modifiers` held; empty for keys
+/// without text and for dead keys, which compose with the next press
+pub fn translate(code: u16, modifiers: Modifiers) -> Vec<char> {
+    KEYMAP.lock().translate(code, modifiers)
+}
+
+/// Switch to the layout called `name`
+pub fn set_layout(name: &str) -> AgaveResult<()> {
+    let layout = LAYOUTS
+        .iter()
+        .find(|layout| layout.name == name)
+        .ok_or(AgaveError::NotFound)?;
+    let mut keymap = KEYMAP.lock();
+    keymap.layout = layout;
+    keymap.dead = None;
+    log::info!("keymap: using layout '{}'", name);
+    Ok(())
+}
+
+/// Name of the active layout
+pub fn layout() -> &'static str {
+    KEYMAP.lock().layout.name
+}
+
+/// Names of the available layouts
+pub fn layouts() -> impl Iterator<Item = &'static str> {
+    LAYOUTS.iter().map(|layout| layout.name)
+}
+
+pub fn set_repeat(config: RepeatConfig) {
+    KEYMAP.lock().repeat = config;
+}
+
+pub fn repeat() -> RepeatConfig {
+    KEYMAP.lock().repeat
+}
+
+/// Apply the settings in `CONFIG_FILE`, keeping the defaults when it is
+/// missing; bad entries are logged and skipped
+pub fn load_config() {
+    let Ok(content) = fs::read_file(CONFIG_FILE) else {
+        return;
+    };
+    let mut repeat = repeat();
+    for line in String::from_utf8_lossy(&content).lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            log::warn!("keymap: malformed line '{}'", line);
+            continue;
+        };
+        let value = value.trim();
+        let applied = match key.trim() {
+            "layout" => set_layout(value).is_ok(),
+            "repeat_delay" => value.parse().map(|ms| repeat.delay_ms = ms).is_ok(),
+            "repeat_interval" => value.parse().map(|ms| repeat.interval_ms = ms).is_ok(),
+            _ => false,
+        };
+        if !applied {
+            log::warn!("keymap: ignoring '{}'", line);
+        }
+    }
+    set_repeat(repeat);
+}
@@ -6,9 +6,11 @@ pub mod framebuffer;
 pub mod fs;
 pub mod gdt;
 pub mod globals;
+pub mod input;
 pub mod interrupts;
 pub mod ioapic;
 pub mod ipc;
+pub mod keymap;
 pub mod local_apic;
 pub mod logger;
 pub mod memory;
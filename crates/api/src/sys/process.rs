@@ -51,8 +51,23 @@ pub fn set_process_state(pid: ProcessId, state: ProcessState) {
         pcb.context.state = state;
     }
 }
+/// Mark `pid` as the process currently executing (e.g. while a WASM app runs)
+pub fn set_current_pid(pid: Option<ProcessId>) {
+    let mut manager = PROCESS_MANAGER.lock();
+    manager.current_process = pid;
+}
+/// Account a slice of guest execution to a process
+pub fn record_cpu_slice(pid: ProcessId, elapsed_ms: u64, fuel: u64, overrun: bool) {
+    let mut manager = PROCESS_MANAGER.lock();
+    if let Some(pcb) = manager.processes.get_mut(&pid) {
+        pcb.context.cpu_time_ms += elapsed_ms;
+        pcb.statistics.fuel_consumed += fuel;
+        if overrun {
+            pcb.statistics.cpu_overruns += 1;
+        }
+    }
+}
 /// Returns the current process ID
-// TODO: implement actual current process tracking
 pub fn get_current_pid() -> ProcessId {
     let manager = PROCESS_MANAGER.lock();
     manager.current_process.unwrap_or(ProcessId(0))
@@ -166,6 +181,16 @@ pub struct ProcessControlBlock {
     pub statistics: ProcessStatistics,
 }
 
+impl ProcessControlBlock {
+    /// Whether the process has not exited yet
+    fn is_alive(&self) -> bool {
+        !matches!(
+            self.context.state,
+            ProcessState::Zombie | ProcessState::Terminated
+        )
+    }
+}
+
 /// Resource limits for processes
 #[derive(Debug, Clone)]
 pub struct ResourceLimits {
@@ -198,6 +223,8 @@ pub struct ProcessStatistics {
     pub messages_received: u64,
     pub bytes_read: u64,
     pub bytes_written: u64,
+    pub fuel_consumed: u64,
+    pub cpu_overruns: u64,
 }
 
 /// Process scheduler with multiple priority queues
@@ -242,6 +269,32 @@ impl ProcessScheduler {
     where
         F: Future<Output = i32> + Send + 'static,
     {
+        let pid = self.create_process(name, priority, Some(Box::pin(future)), parent_pid)?;
+        self.ready_queues[priority as usize].push_back(pid);
+        Ok(pid)
+    }
+
+    /// Register a process that is driven outside the scheduler (e.g. a WASM app)
+    pub fn register_process(
+        &mut self,
+        name: String,
+        priority: Priority,
+        parent_pid: Option<ProcessId>,
+    ) -> AgaveResult<ProcessId> {
+        let pid = self.create_process(name, priority, None, parent_pid)?;
+        if let Some(process) = self.processes.get_mut(&pid) {
+            process.context.state = ProcessState::Running;
+        }
+        Ok(pid)
+    }
+
+    fn create_process(
+        &mut self,
+        name: String,
+        priority: Priority,
+        task: Option<Pin<Box<dyn Future<Output = i32> + Send>>>,
+        parent_pid: Option<ProcessId>,
+    ) -> AgaveResult<ProcessId> {
         let pid = ProcessId::new();
         let now = crate::sys::interrupts::TIME_MS.load(Ordering::Relaxed);
 
@@ -270,7 +323,7 @@ impl ProcessScheduler {
 
         let pcb = ProcessControlBlock {
             context,
-            task: Some(Box::pin(future)),
+            task,
             ipc_inbox: IpcChannel::new(1000),
             waiting_for: None,
             resource_limits: ResourceLimits::default(),
@@ -278,7 +331,6 @@ impl ProcessScheduler {
         };
 
         self.processes.insert(pid, pcb);
-        self.ready_queues[priority as usize].push_back(pid);
 
         self.total_processes.fetch_add(1, Ordering::Relaxed);
         self.active_processes.fetch_add(1, Ordering::Relaxed);
@@ -438,37 +490,97 @@ impl ProcessScheduler {
 
     /// Kill a process
     pub fn kill_process(&mut self, pid: ProcessId, signal: i32) -> AgaveResult<()> {
-        if let Some(process) = self.processes.get_mut(&pid) {
-            if process.context.state != ProcessState::Terminated {
-                process.context.state = ProcessState::Terminated;
-                process.context.exit_code = Some(signal);
-                process.task = None;
+        self.terminate(pid, signal)?;
 
-                self.active_processes.fetch_sub(1, Ordering::Relaxed);
+        add_diagnostic(
+            DiagnosticLevel::Info,
+            DiagnosticCategory::Tasks,
+            format!("Process killed: {} (signal: {})", pid.as_u64(), signal),
+            None,
+        );
 
-                // Remove from ready queues
-                for queue in &mut self.ready_queues {
-                    queue.retain(|&p| p != pid);
-                }
+        log::info!("Killed process {} with signal {}", pid.as_u64(), signal);
+        Ok(())
+    }
+
+    /// Record a voluntary exit of a process
+    pub fn exit_process(&mut self, pid: ProcessId, exit_code: i32) -> AgaveResult<()> {
+        self.terminate(pid, exit_code)?;
 
-                if self.current_process == Some(pid) {
-                    self.current_process = None;
+        add_diagnostic(
+            DiagnosticLevel::Info,
+            DiagnosticCategory::Tasks,
+            format!("Process exited: {} (code: {})", pid.as_u64(), exit_code),
+            None,
+        );
+
+        log::info!("Process {} exited with code {}", pid.as_u64(), exit_code);
+        Ok(())
+    }
+
+    fn terminate(&mut self, pid: ProcessId, exit_code: i32) -> AgaveResult<()> {
+        let parent_pid = match self.processes.get(&pid) {
+            None => return Err(AgaveError::NotFound),
+            Some(process) if !process.is_alive() => return Err(AgaveError::InvalidState),
+            Some(process) => process.context.parent_pid,
+        };
+        // A child stays a zombie until its parent collects the exit code
+        let parent_alive = parent_pid
+            .and_then(|parent| self.processes.get(&parent))
+            .is_some_and(|parent| parent.is_alive());
+
+        let process = self.processes.get_mut(&pid).ok_or(AgaveError::NotFound)?;
+        process.context.state = if parent_alive {
+            ProcessState::Zombie
+        } else {
+            ProcessState::Terminated
+        };
+        process.context.exit_code = Some(exit_code);
+        process.task = None;
+        let children = core::mem::take(&mut process.context.children);
+
+        // Nobody is left to wait for the children that already exited
+        for child in children {
+            if let Some(child) = self.processes.get_mut(&child) {
+                if child.context.state == ProcessState::Zombie {
+                    child.context.state = ProcessState::Terminated;
                 }
+            }
+        }
 
-                add_diagnostic(
-                    DiagnosticLevel::Info,
-                    DiagnosticCategory::Tasks,
-                    format!("Process killed: {} (signal: {})", pid.as_u64(), signal),
-                    None,
-                );
+        self.active_processes.fetch_sub(1, Ordering::Relaxed);
 
-                log::info!("Killed process {} with signal {}", pid.as_u64(), signal);
-                Ok(())
-            } else {
-                Err(AgaveError::InvalidState)
+        // Remove from ready queues
+        for queue in &mut self.ready_queues {
+            queue.retain(|&p| p != pid);
+        }
+
+        if self.current_process == Some(pid) {
+            self.current_process = None;
+        }
+        Ok(())
+    }
+
+    /// Collect the exit code of a child of `parent` once it has exited.
+    /// `None` while the child is still running.
+    pub fn reap_child(&mut self, parent: ProcessId, child: ProcessId) -> AgaveResult<Option<i32>> {
+        let process = self
+            .processes
+            .get_mut(&child)
+            .filter(|process| process.context.parent_pid == Some(parent))
+            .ok_or(AgaveError::NotFound)?;
+        match process.context.state {
+            ProcessState::Zombie => {
+                process.context.state = ProcessState::Terminated;
+                let exit_code = process.context.exit_code;
+                if let Some(parent) = self.processes.get_mut(&parent) {
+                    parent.context.children.retain(|&pid| pid != child);
+                }
+                Ok(exit_code)
             }
-        } else {
-            Err(AgaveError::NotFound)
+            // Already collected
+            ProcessState::Terminated => Err(AgaveError::NotFound),
+            _ => Ok(None),
         }
     }
 
@@ -477,6 +589,29 @@ impl ProcessScheduler {
         self.processes.get(&pid).map(|pcb| &pcb.context)
     }
 
+    /// Resource limits of a process
+    pub fn get_resource_limits(&self, pid: ProcessId) -> Option<&ResourceLimits> {
+        self.processes.get(&pid).map(|pcb| &pcb.resource_limits)
+    }
+
+    /// Replace the resource limits of a process
+    pub fn set_resource_limits(
+        &mut self,
+        pid: ProcessId,
+        limits: ResourceLimits,
+    ) -> AgaveResult<()> {
+        let process = self.processes.get_mut(&pid).ok_or(AgaveError::NotFound)?;
+        process.resource_limits = limits;
+        Ok(())
+    }
+
+    /// Record how much memory a process is using
+    pub fn set_memory_usage(&mut self, pid: ProcessId, bytes: usize) -> AgaveResult<()> {
+        let process = self.processes.get_mut(&pid).ok_or(AgaveError::NotFound)?;
+        process.context.memory_usage = bytes;
+        Ok(())
+    }
+
     /// List all processes
     pub fn list_processes(&self) -> Vec<&ProcessContext> {
         self.processes.values().map(|pcb| &pcb.context).collect()
@@ -567,6 +702,15 @@ where
     manager.spawn_process(name, priority, future, parent_pid)
 }
 
+pub fn register_process(
+    name: String,
+    priority: Priority,
+    parent_pid: Option<ProcessId>,
+) -> AgaveResult<ProcessId> {
+    let mut manager = PROCESS_MANAGER.lock();
+    manager.register_process(name, priority, parent_pid)
+}
+
 pub fn schedule_processes() -> Option<ProcessId> {
     let mut manager = PROCESS_MANAGER.lock();
     manager.schedule()
@@ -597,11 +741,36 @@ pub fn kill_process(pid: ProcessId, signal: i32) -> AgaveResult<()> {
     manager.kill_process(pid, signal)
 }
 
+pub fn exit_process(pid: ProcessId, exit_code: i32) -> AgaveResult<()> {
+    let mut manager = PROCESS_MANAGER.lock();
+    manager.exit_process(pid, exit_code)
+}
+
+pub fn reap_child(parent: ProcessId, child: ProcessId) -> AgaveResult<Option<i32>> {
+    let mut manager = PROCESS_MANAGER.lock();
+    manager.reap_child(parent, child)
+}
+
 pub fn get_process_info(pid: ProcessId) -> Option<ProcessContext> {
     let manager = PROCESS_MANAGER.lock();
     manager.get_process_info(pid).cloned()
 }
 
+pub fn get_resource_limits(pid: ProcessId) -> Option<ResourceLimits> {
+    let manager = PROCESS_MANAGER.lock();
+    manager.get_resource_limits(pid).cloned()
+}
+
+pub fn set_resource_limits(pid: ProcessId, limits: ResourceLimits) -> AgaveResult<()> {
+    let mut manager = PROCESS_MANAGER.lock();
+    manager.set_resource_limits(pid, limits)
+}
+
+pub fn set_memory_usage(pid: ProcessId, bytes: usize) -> AgaveResult<()> {
+    let mut manager = PROCESS_MANAGER.lock();
+    manager.set_memory_usage(pid, bytes)
+}
+
 pub fn list_processes() -> Vec<ProcessContext> {
     let manager = PROCESS_MANAGER.lock();
     manager.list_processes().into_iter().cloned().collect()
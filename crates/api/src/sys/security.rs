@@ -142,6 +142,8 @@ pub struct SandboxResourceLimits {
     pub max_memory: usize,
     pub max_cpu_percent: u8,
     pub max_file_handles: u32,
+    /// Largest file the process may create or grow through its descriptors
+    pub max_file_size: u64,
     pub max_network_connections: u32,
     pub max_execution_time_ms: u64,
 }
@@ -167,6 +169,7 @@ impl Default for SandboxProfile {
                 max_memory: 64 * 1024 * 1024, // 64MB
                 max_cpu_percent: 50,
                 max_file_handles: 20,
+                max_file_size: 16 * 1024 * 1024, // 16MB
                 max_network_connections: 5,
                 max_execution_time_ms: 30000, // 30 seconds
             },
@@ -178,6 +181,34 @@ impl Default for SandboxProfile {
     }
 }
 
+impl SandboxProfile {
+    /// Whether `path` lies under an allowed path and outside every denied one
+    pub fn allows_path(&self, path: &str) -> bool {
+        !self.denies_path(path)
+            && self
+                .allowed_paths
+                .iter()
+                .any(|allowed| path_within(path, allowed))
+    }
+
+    /// Whether `path` lies under one of the denied paths
+    pub fn denies_path(&self, path: &str) -> bool {
+        self.denied_paths
+            .iter()
+            .any(|denied| path_within(path, denied))
+    }
+}
+
+/// Whether the absolute path `path` is `dir` or lies below it, comparing
+/// whole components so `/etcetera` is not inside `/etc`
+pub fn path_within(path: &str, dir: &str) -> bool {
+    let dir = dir.trim_end_matches('/');
+    match path.strip_prefix(dir) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/') || dir.is_empty(),
+        None => false,
+    }
+}
+
 /// Security event types for monitoring
 #[derive(Debug, Clone)]
 pub enum SecurityEvent {
@@ -211,6 +242,29 @@ pub enum SecurityEvent {
         limit: u64,
         requested: u64,
     },
+    /// A decision about running the WASM module at `path`, whose SHA-256 is
+    /// `hash` in hex
+    ModuleVerification {
+        path: String,
+        hash: String,
+        decision: ModuleDecision,
+    },
+}
+
+/// What was decided about a WASM module before and after instantiating it
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModuleDecision {
+    /// The module is on the trusted module list
+    Verified,
+    /// The module is not on the list and may only run sandboxed
+    Unverified,
+    /// An unverified module was refused the level it asked for
+    Denied { requested: SecurityLevel },
+    /// The module was instantiated as `process_id` at `level`
+    Loaded {
+        process_id: ProcessId,
+        level: SecurityLevel,
+    },
 }
 
 /// Risk levels for security events
@@ -378,6 +432,21 @@ impl SecurityMonitor {
                     Some(format!("Risk Level: {:?}", risk_level)),
                 );
             }
+            SecurityEvent::ModuleVerification {
+                path,
+                hash,
+                decision: ModuleDecision::Denied { requested },
+            } => {
+                add_diagnostic(
+                    DiagnosticLevel::Warning,
+                    DiagnosticCategory::Security,
+                    format!("Unverified module {} confined to the sandbox", path),
+                    Some(format!(
+                        "Requested level: {:?}, SHA-256: {}",
+                        requested, hash
+                    )),
+                );
+            }
             _ => {}
         }
     }
@@ -558,6 +627,7 @@ impl SecurityMonitor {
                 SecurityEvent::SuspiciousActivity { .. } => "Suspicious Activity",
                 SecurityEvent::AuthenticationFailure { .. } => "Authentication Failure",
                 SecurityEvent::ResourceExhaustion { .. } => "Resource Exhaustion",
+                SecurityEvent::ModuleVerification { .. } => "Module Verification",
             };
             *event_counts.entry(event_type.to_string()).or_insert(0) += 1;
 
@@ -635,6 +705,33 @@ impl AccessControlManager {
                 max_memory: 32 * 1024 * 1024, // 32MB
                 max_cpu_percent: 25,
                 max_file_handles: 10,
+                max_file_size: 4 * 1024 * 1024, // 4MB
+                max_network_connections: 0,
+                max_execution_time_ms: 15000,
+            },
+            allowed_syscalls: ["read", "write"].iter().map(|s| s.to_string()).collect(),
+        };
+
+        // Profile for modules that are not on the trusted module list: no
+        // network and nothing outside /tmp
+        let sandboxed = SandboxProfile {
+            name: "sandboxed".to_string(),
+            allowed_paths: ["/tmp".to_string()].into_iter().collect(),
+            denied_paths: ["/etc".to_string(), "/sys".to_string(), "/proc".to_string()]
+                .into_iter()
+                .collect(),
+            network_restrictions: NetworkRestrictions {
+                allow_loopback: false,
+                allow_outbound: false,
+                allow_inbound: false,
+                allowed_ports: BTreeSet::new(),
+                blocked_hosts: BTreeSet::new(),
+            },
+            resource_limits: SandboxResourceLimits {
+                max_memory: 16 * 1024 * 1024, // 16MB
+                max_cpu_percent: 25,
+                max_file_handles: 8,
+                max_file_size: 1024 * 1024, // 1MB
                 max_network_connections: 0,
                 max_execution_time_ms: 15000,
             },
@@ -660,12 +757,15 @@ impl AccessControlManager {
                 max_memory: 256 * 1024 * 1024, // 256MB
                 max_cpu_percent: 90,
                 max_file_handles: 1000,
+                max_file_size: 64 * 1024 * 1024, // 64MB
                 max_network_connections: 100,
                 max_execution_time_ms: 0, // No limit
             },
             allowed_syscalls: BTreeSet::new(), // All syscalls allowed
         };
 
+        self.sandbox_profiles
+            .insert("sandboxed".to_string(), sandboxed);
         self.sandbox_profiles
             .insert("restrictive".to_string(), restrictive);
         self.sandbox_profiles
@@ -740,22 +840,13 @@ impl AccessControlManager {
     }
 
     fn check_sandbox_access(&self, profile: &SandboxProfile, resource: &str) -> bool {
-        // Check if path is explicitly denied
-        for denied_path in &profile.denied_paths {
-            if resource.starts_with(denied_path) {
-                return false;
-            }
-        }
-
-        // Check if path is explicitly allowed
-        for allowed_path in &profile.allowed_paths {
-            if resource.starts_with(allowed_path) {
-                return true;
-            }
-        }
+        // Denied paths win, and anything not explicitly allowed is denied
+        profile.allows_path(resource)
+    }
 
-        // Default deny if no explicit allow
-        false
+    /// Look up a registered sandbox profile by name
+    pub fn get_profile(&self, name: &str) -> Option<&SandboxProfile> {
+        self.sandbox_profiles.get(name)
     }
 }
 
@@ -789,6 +880,11 @@ pub fn check_access(
     acm.check_access(process_id, resource, capability)
 }
 
+pub fn get_sandbox_profile(name: &str) -> Option<SandboxProfile> {
+    let acm = ACCESS_CONTROL.lock();
+    acm.get_profile(name).cloned()
+}
+
 pub fn record_security_event(event: SecurityEvent) {
     let mut monitor = SECURITY_MONITOR.lock();
     monitor.record_event(event);
@@ -885,18 +981,42 @@ pub fn create_default_context(user_id: UserId, security_level: SecurityLevel) ->
     let sandbox_profile_name = match security_level {
         SecurityLevel::System | SecurityLevel::Trusted => "trusted".to_string(),
         SecurityLevel::Standard => "default".to_string(),
-        SecurityLevel::Restricted | SecurityLevel::Sandboxed => "restrictive".to_string(),
+        SecurityLevel::Restricted => "restrictive".to_string(),
+        SecurityLevel::Sandboxed => "sandboxed".to_string(),
     };
 
+    let sandbox_profile =
+        get_sandbox_profile(&sandbox_profile_name).unwrap_or_else(|| SandboxProfile {
+            name: sandbox_profile_name,
+            ..SandboxProfile::default()
+        });
+
     SecurityContext {
         user_id,
         group_id: GroupId::USERS,
         capabilities,
         security_level,
-        sandbox_profile: SandboxProfile {
-            name: sandbox_profile_name,
-            ..SandboxProfile::default()
-        },
+        sandbox_profile,
+    }
+}
+
+/// Security level a process confined by `profile` runs at
+pub fn profile_level(profile: &SandboxProfile) -> SecurityLevel {
+    match profile.name.as_str() {
+        "trusted" => SecurityLevel::Trusted,
+        "restrictive" => SecurityLevel::Restricted,
+        "sandboxed" => SecurityLevel::Sandboxed,
+        _ => SecurityLevel::Standard,
+    }
+}
+
+/// Create a security context confined by `profile`, with the capabilities of
+/// the security level that profile corresponds to
+pub fn create_sandboxed_context(user_id: UserId, profile: SandboxProfile) -> SecurityContext {
+    let security_level = profile_level(&profile);
+    SecurityContext {
+        sandbox_profile: profile,
+        ..create_default_context(user_id, security_level)
     }
 }
 
@@ -1,7 +1,10 @@
 // WASI CLI implementation for Agave OS
 use super::error::*;
 use super::types::*;
+use crate::sys::process::{self, ProcessId};
+use crate::sys::wasm::loader;
 use alloc::string::{String, ToString};
+use alloc::vec;
 use alloc::vec::Vec;
 use spin::Mutex;
 
@@ -39,19 +42,24 @@ pub fn init_cli() {
     cli.args = alloc::vec!["agave-wasm".to_string()];
 
     // Set up default environment variables
-    cli.env_vars = alloc::vec![
+    cli.env_vars = default_environment();
+
+    // Create standard streams
+    cli.stdin = super::io::create_input_stream(Vec::new());
+    cli.stdout = super::io::create_output_stream();
+    cli.stderr = super::io::create_output_stream();
+}
+
+/// Environment every app starts with
+pub fn default_environment() -> Vec<(String, String)> {
+    alloc::vec![
         ("PATH".to_string(), "/bin:/usr/bin".to_string()),
         ("HOME".to_string(), "/".to_string()),
         ("USER".to_string(), "agave".to_string()),
         ("SHELL".to_string(), "/bin/sh".to_string()),
         ("TERM".to_string(), "agave".to_string()),
         ("LANG".to_string(), "C.UTF-8".to_string()),
-    ];
-
-    // Create standard streams
-    cli.stdin = super::io::create_input_stream(Vec::new());
-    cli.stdout = super::io::create_output_stream();
-    cli.stderr = super::io::create_output_stream();
+    ]
 }
 
 // Preview 1 API implementations
@@ -71,12 +79,9 @@ pub fn args_get(_argv_ptr: u32, _argv_buf_ptr: u32) -> WasiResult<()> {
     Ok(())
 }
 
-pub fn args_sizes_get() -> WasiResult<(Size, Size)> {
-    let cli = CLI_STATE.lock();
-
-    let argc = cli.args.len() as Size;
-    let argv_buf_size = cli
-        .args
+pub fn args_sizes_get(args: &[String]) -> WasiResult<(Size, Size)> {
+    let argc = args.len() as Size;
+    let argv_buf_size = args
         .iter()
         .map(|arg| arg.len() + 1) // +1 for null terminator
         .sum::<usize>() as Size;
@@ -98,12 +103,9 @@ pub fn environ_get(_environ_ptr: u32, _environ_buf_ptr: u32) -> WasiResult<()> {
     Ok(())
 }
 
-pub fn environ_sizes_get() -> WasiResult<(Size, Size)> {
-    let cli = CLI_STATE.lock();
-
-    let environc = cli.env_vars.len() as Size;
-    let environ_buf_size = cli
-        .env_vars
+pub fn environ_sizes_get(env: &[(String, String)]) -> WasiResult<(Size, Size)> {
+    let environc = env.len() as Size;
+    let environ_buf_size = env
         .iter()
         .map(|(key, value)| key.len() + 1 + value.len() + 1) // key=value\0
         .sum::<usize>() as Size;
@@ -111,7 +113,10 @@ pub fn environ_sizes_get() -> WasiResult<(Size, Size)> {
     Ok((environc, environ_buf_size))
 }
 
-pub fn proc_exit(exit_code: ExitCode) -> ! {
+/// Record the exit code and build the trap that unwinds the calling app.
+/// Host functions return this as their error; the runtime turns it into a
+/// clean exit of that app only.
+pub fn proc_exit(exit_code: ExitCode) -> wasmi::Error {
     {
         let mut cli = CLI_STATE.lock();
         cli.exit_code = Some(exit_code);
@@ -119,18 +124,16 @@ pub fn proc_exit(exit_code: ExitCode) -> ! {
 
     log::info!("Process exiting with code: {}", exit_code);
 
-    // In a real implementation, this would terminate the process
-    // For now, we'll just panic to stop execution
-    panic!("Process exited with code: {}", exit_code);
+    wasmi::Error::i32_exit(exit_code as i32)
 }
 
-pub fn proc_raise(signal: Signal) -> WasiResult<()> {
+pub fn proc_raise(signal: Signal) -> Result<(), wasmi::Error> {
     log::info!("Signal raised: {}", signal);
 
     match signal {
-        2 => proc_exit(128 + 2),   // SIGINT
-        9 => proc_exit(128 + 9),   // SIGKILL
-        15 => proc_exit(128 + 15), // SIGTERM
+        2 => Err(proc_exit(128 + 2)),   // SIGINT
+        9 => Err(proc_exit(128 + 9)),   // SIGKILL
+        15 => Err(proc_exit(128 + 15)), // SIGTERM
         _ => {
             log::warn!("Unhandled signal: {}", signal);
             Ok(())
@@ -184,7 +187,7 @@ pub fn get_stderr() -> super::io::OutputStream {
     cli.stderr
 }
 
-pub fn exit_with_code(exit_code: ExitCode) -> ! {
+pub fn exit_with_code(exit_code: ExitCode) -> wasmi::Error {
     proc_exit(exit_code)
 }
 
@@ -359,25 +362,32 @@ pub fn set_resource_limit(resource: u32, soft_limit: u64, hard_limit: u64) -> Wa
 }
 
 // Process execution
+/// Start `program` as a child of the current process, passing `args` after
+/// the program name. Its output goes to the kernel log.
 pub fn spawn_process(program: &str, args: &[String], env: &[(String, String)]) -> WasiResult<u32> {
-    // In a real implementation, this would spawn a new process
-    log::debug!(
-        "Spawn process: {} with {} args and {} env vars",
+    let mut argv = vec![loader::app_name(program).to_string()];
+    argv.extend_from_slice(args);
+    let child = loader::spawn(
+        process::get_current_pid(),
         program,
-        args.len(),
-        env.len()
-    );
-
-    // Return a simulated process ID
-    Ok(get_process_id() + 1)
-}
-
+        argv,
+        env.to_vec(),
+        false,
+    )
+    .map_err(|e| {
+        log::debug!("Spawn process {} failed: {:?}", program, e);
+        WasiError::noent()
+    })?;
+    Ok(child.pid.as_u64() as u32)
+}
+
+/// Exit code of a child of the current process; EAGAIN while it still runs
 pub fn wait_for_process(process_id: u32) -> WasiResult<ExitCode> {
-    // In a real implementation, this would wait for the process to exit
-    log::debug!("Wait for process: {}", process_id);
-
-    // Return a simulated exit code
-    Ok(0)
+    match process::reap_child(process::get_current_pid(), ProcessId::from(process_id)) {
+        Ok(Some(code)) => Ok(code as ExitCode),
+        Ok(None) => Err(WasiError::again()),
+        Err(_) => Err(WasiError::new(ERRNO_CHILD, "No child process")),
+    }
 }
 
 // Input/Output redirection
@@ -1,45 +1,56 @@
 // WASI Clocks implementation for Agave OS
 use super::error::*;
 use super::types::*;
+use crate::sys::interrupts::TIME_MS;
 use alloc::string::{String, ToString};
+use core::{fmt, sync::atomic::Ordering};
 
-// Dummy time base - in a real OS, this would be system dependent
-static mut TIME_BASE: u64 = 0;
+// Nanoseconds since boot, from the timer interrupt's millisecond count
+fn uptime() -> Timestamp {
+    milliseconds_to_timestamp(TIME_MS.load(Ordering::Relaxed))
+}
+
+/// Host error that pauses the calling app until the monotonic clock reaches
+/// `until`; the host function then returns `ERRNO_SUCCESS`
+#[derive(Debug, Clone, Copy)]
+pub struct Sleep {
+    pub until: Timestamp,
+}
+
+impl fmt::Display for Sleep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sleeping until {}ns", self.until)
+    }
+}
+
+impl wasmi::core::HostError for Sleep {}
+
+impl Sleep {
+    /// Whether the deadline has passed
+    pub fn is_over(&self) -> bool {
+        uptime() >= self.until
+    }
+}
 
 pub fn clock_res_get(id: Clockid) -> WasiResult<Timestamp> {
     match id {
-        CLOCKID_REALTIME => Ok(1_000_000),  // 1 microsecond resolution
-        CLOCKID_MONOTONIC => Ok(1_000_000), // 1 microsecond resolution
-        CLOCKID_PROCESS_CPUTIME_ID => Ok(1_000_000), // 1 microsecond resolution
-        CLOCKID_THREAD_CPUTIME_ID => Ok(1_000_000), // 1 microsecond resolution
+        // Every clock ticks with the 1 millisecond timer interrupt
+        CLOCKID_REALTIME
+        | CLOCKID_MONOTONIC
+        | CLOCKID_PROCESS_CPUTIME_ID
+        | CLOCKID_THREAD_CPUTIME_ID => Ok(1_000_000),
         _ => Err(WasiError::inval()),
     }
 }
 
 pub fn clock_time_get(id: Clockid, _precision: Timestamp) -> WasiResult<Timestamp> {
-    // In a real implementation, this would get actual system time
-    // For now, we'll simulate time progression
-    unsafe {
-        TIME_BASE += 1_000_000; // Add 1ms each call
-    }
-
     match id {
-        CLOCKID_REALTIME => {
-            // Return simulated wall clock time
-            unsafe { Ok(TIME_BASE * 1000) } // Convert to nanoseconds
-        }
-        CLOCKID_MONOTONIC => {
-            // Return simulated monotonic time
-            unsafe { Ok(TIME_BASE * 1000) } // Convert to nanoseconds
-        }
-        CLOCKID_PROCESS_CPUTIME_ID => {
-            // Return simulated process CPU time
-            unsafe { Ok(TIME_BASE * 500) } // Half the wall clock time
-        }
-        CLOCKID_THREAD_CPUTIME_ID => {
-            // Return simulated thread CPU time
-            unsafe { Ok(TIME_BASE * 500) } // Half the wall clock time
-        }
+        // There is no RTC driver, so wall clock time counts from boot too.
+        // CPU time is not tracked per app; elapsed time bounds it.
+        CLOCKID_REALTIME
+        | CLOCKID_MONOTONIC
+        | CLOCKID_PROCESS_CPUTIME_ID
+        | CLOCKID_THREAD_CPUTIME_ID => Ok(uptime()),
         _ => Err(WasiError::inval()),
     }
 }
@@ -64,11 +75,11 @@ pub fn monotonic_now() -> WasiResult<Timestamp> {
 
 // Additional functions for Preview 2 compatibility
 pub fn monotonic_resolution() -> WasiResult<Timestamp> {
-    Ok(1000) // 1 microsecond resolution
+    clock_res_get(CLOCKID_MONOTONIC)
 }
 
 pub fn wall_resolution() -> WasiResult<Timestamp> {
-    Ok(1000) // 1 microsecond resolution
+    clock_res_get(CLOCKID_REALTIME)
 }
 
 pub fn subscribe_instant(when: Timestamp) -> WasiResult<super::types::Pollable> {
@@ -109,11 +120,10 @@ pub fn subscribe_monotonic_clock(when: Timestamp, absolute: bool) -> super::io::
     let mut pollables = super::io::POLLABLES.lock();
 
     // For now, create a pollable that's ready if the time has passed
-    let current_time = unsafe { TIME_BASE * 1000 };
     let ready = if absolute {
-        current_time >= when
+        uptime() >= when
     } else {
-        current_time >= unsafe { TIME_BASE * 1000 } + when
+        when == 0
     };
 
     pollables.create_pollable(ready)
@@ -155,21 +165,3 @@ pub fn seconds_to_timestamp(s: u64) -> Timestamp {
 pub fn timestamp_to_seconds(ts: Timestamp) -> u64 {
     ts / 1_000_000_000
 }
-
-// Sleeping functionality
-pub fn sleep(duration: Timestamp) -> WasiResult<()> {
-    // In a real implementation, this would yield to the scheduler
-    // For now, we'll just advance our simulated time
-    unsafe {
-        TIME_BASE += duration / 1000; // Convert from nanoseconds to microseconds
-    }
-    Ok(())
-}
-
-pub fn sleep_until(deadline: Timestamp) -> WasiResult<()> {
-    let current = clock_time_get(CLOCKID_MONOTONIC, 0)?;
-    if deadline > current {
-        sleep(deadline - current)?;
-    }
-    Ok(())
-}
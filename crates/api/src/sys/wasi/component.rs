@@ -0,0 +1,139 @@
+// WASI Preview 2 components on a core-only engine
+//
+// wasmi only runs core modules. A `wasm32-wasip2` component bundles the
+// program's own core module with the preview1 adapter and a few glue modules.
+// Preview 1 is implemented natively, so the adapter is dropped and the
+// program module is instantiated directly; the preview2 imports it has left
+// are served by `preview2::link_preview2_functions` using the canonical ABI
+// lowering helpers below.
+
+use super::error::*;
+use super::memory::{memory_and_ctx, GuestMemory};
+use super::types::*;
+use super::WasiView;
+use alloc::vec::Vec;
+use wasmi::{Caller, Engine, Extern, Module};
+
+// Component binaries share the core magic but use layer 1
+const COMPONENT_VERSION: [u8; 4] = [0x0d, 0x00, 0x01, 0x00];
+const CORE_MODULE_SECTION: u8 = 1;
+
+/// Export the program module is started through
+pub const ENTRY_POINT: &str = "_start";
+// The preview1 adapter imports the program module under this name
+const ADAPTER_MAIN_MODULE: &str = "__main_module__";
+
+/// Whether `wasm` is a component rather than a core module
+pub fn is_component(wasm: &[u8]) -> bool {
+    wasm.len() >= 8 && wasm.starts_with(b"\0asm") && wasm[4..8] == COMPONENT_VERSION
+}
+
+/// Core modules embedded at the top level of a component, in order
+pub fn core_modules(wasm: &[u8]) -> WasiResult<Vec<&[u8]>> {
+    if !is_component(wasm) {
+        return Err(WasiError::inval());
+    }
+    let mut modules = Vec::new();
+    let mut pos = 8;
+    while pos < wasm.len() {
+        let id = wasm[pos];
+        pos += 1;
+        let size = read_leb_u32(wasm, &mut pos)? as usize;
+        let end = pos
+            .checked_add(size)
+            .filter(|&end| end <= wasm.len())
+            .ok_or(WasiError::inval())?;
+        if id == CORE_MODULE_SECTION {
+            modules.push(&wasm[pos..end]);
+        }
+        pos = end;
+    }
+    Ok(modules)
+}
+
+/// Compile the program module of a component: the core module that exports
+/// the entry point and is not the adapter
+pub fn main_module(engine: &Engine, wasm: &[u8]) -> WasiResult<Module> {
+    core_modules(wasm)?
+        .into_iter()
+        .filter_map(|bytes| Module::new(engine, bytes).ok())
+        .find(|module| {
+            module.exports().any(|export| export.name() == ENTRY_POINT)
+                && !module
+                    .imports()
+                    .any(|import| import.module() == ADAPTER_MAIN_MODULE)
+        })
+        .ok_or(WasiError::new(
+            ERRNO_NOEXEC,
+            "component has no core module exporting _start",
+        ))
+}
+
+fn read_leb_u32(bytes: &[u8], pos: &mut usize) -> WasiResult<u32> {
+    let mut result = 0u32;
+    for shift in (0..35).step_by(7) {
+        let byte = *bytes.get(*pos).ok_or(WasiError::inval())?;
+        *pos += 1;
+        result |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(result);
+        }
+    }
+    Err(WasiError::inval())
+}
+
+// --- Canonical ABI lowering ---
+
+/// Allocate `size` bytes in the guest through its `cabi_realloc` export
+pub fn guest_alloc<T>(caller: &mut Caller<'_, T>, size: u32, align: u32) -> WasiResult<u32> {
+    if size == 0 {
+        return Ok(align);
+    }
+    let realloc = caller
+        .get_export("cabi_realloc")
+        .and_then(Extern::into_func)
+        .ok_or(WasiError::fault())?
+        .typed::<(i32, i32, i32, i32), i32>(&*caller)
+        .map_err(|_| WasiError::fault())?;
+    realloc
+        .call(&mut *caller, (0, 0, align as i32, size as i32))
+        .map(|ptr| ptr as u32)
+        .map_err(|_| WasiError::nomem())
+}
+
+/// Copy `data` into newly allocated guest memory as a `list<u8>` or `string`
+pub fn lower_bytes<T: WasiView>(caller: &mut Caller<'_, T>, data: &[u8]) -> WasiResult<(u32, u32)> {
+    let ptr = guest_alloc(caller, data.len() as u32, 1)?;
+    let (mut memory, _ctx) = memory_and_ctx(caller)?;
+    memory.write(ptr, data)?;
+    Ok((ptr, data.len() as u32))
+}
+
+/// Copy a flattened array of 32-bit fields into guest memory; records made
+/// only of handles, strings and lists lower to this
+pub fn lower_words<T: WasiView>(caller: &mut Caller<'_, T>, words: &[u32]) -> WasiResult<u32> {
+    let ptr = guest_alloc(caller, words.len() as u32 * 4, 4)?;
+    let (mut memory, _ctx) = memory_and_ctx(caller)?;
+    write_words(&mut memory, ptr, words)?;
+    Ok(ptr)
+}
+
+/// Lower a `list<string>`, returning its pointer and element count
+pub fn lower_strings<T: WasiView>(
+    caller: &mut Caller<'_, T>,
+    strings: &[&[u8]],
+) -> WasiResult<(u32, u32)> {
+    let mut words = Vec::with_capacity(strings.len() * 2);
+    for string in strings {
+        let (ptr, len) = lower_bytes(caller, string)?;
+        words.extend([ptr, len]);
+    }
+    Ok((lower_words(caller, &words)?, strings.len() as u32))
+}
+
+pub fn write_words(memory: &mut GuestMemory<'_>, ptr: u32, words: &[u32]) -> WasiResult<()> {
+    for (i, word) in words.iter().enumerate() {
+        memory.write_u32(ptr + i as u32 * 4, *word)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,82 @@
+// Per-instance WASI state for Agave OS
+// Each app owns its descriptor table, arguments and environment
+use super::cli;
+use super::error::*;
+use super::filesystem::FilesystemState;
+use super::io::StreamRegistry;
+use super::types::*;
+use crate::sys::ipc::IpcHandle;
+use crate::sys::security::{NetworkRestrictions, SandboxProfile};
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+#[derive(Debug)]
+pub struct WasiCtx {
+    pub fs: FilesystemState,
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    /// Network policy of the app's sandbox; `None` means unrestricted
+    pub network: Option<NetworkRestrictions>,
+    /// Pipes standing in for stdin, stdout and stderr. Without one stdin
+    /// reads as end of file and output goes to the kernel log.
+    pub stdio: [Option<IpcHandle>; 3],
+    /// Preview2 streams the app holds, apart from its standard streams
+    pub streams: StreamRegistry,
+    /// Descriptors of the shared socket table that belong to the app
+    pub sockets: BTreeSet<Fd>,
+}
+
+impl WasiCtx {
+    pub fn new(args: Vec<String>) -> Self {
+        Self {
+            fs: FilesystemState::with_default_preopens(),
+            args,
+            env: cli::default_environment(),
+            network: None,
+            stdio: [None; 3],
+            streams: StreamRegistry::new(),
+            sockets: BTreeSet::new(),
+        }
+    }
+
+    /// Context whose preopens, denied paths, descriptor limit and network
+    /// policy all come from `profile`
+    pub fn sandboxed(args: Vec<String>, profile: &SandboxProfile) -> Self {
+        Self {
+            fs: FilesystemState::with_sandbox(profile),
+            args,
+            env: cli::default_environment(),
+            network: Some(profile.network_restrictions.clone()),
+            stdio: [None; 3],
+            streams: StreamRegistry::new(),
+            sockets: BTreeSet::new(),
+        }
+    }
+
+    /// Fail with EACCES unless the sandbox lets the app accept connections
+    pub fn check_inbound(&self) -> WasiResult<()> {
+        match &self.network {
+            Some(network) if !network.allow_inbound => Err(WasiError::new(
+                ERRNO_ACCES,
+                "Inbound connections not allowed by sandbox",
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fail with EBADF unless `fd` is one of the app's sockets
+    pub fn check_socket(&self, fd: Fd) -> WasiResult<()> {
+        if self.sockets.contains(&fd) {
+            Ok(())
+        } else {
+            Err(WasiError::badf())
+        }
+    }
+}
+
+/// Host state that carries a WASI context, so the WASI bindings can act on
+/// behalf of the calling instance
+pub trait WasiView {
+    fn wasi(&mut self) -> &mut WasiCtx;
+}
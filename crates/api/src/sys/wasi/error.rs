@@ -29,6 +29,14 @@ impl WasiError {
         Self::new(ERRNO_INVAL, "Invalid argument")
     }
 
+    pub fn fault() -> Self {
+        Self::new(ERRNO_FAULT, "Bad address")
+    }
+
+    pub fn ilseq() -> Self {
+        Self::new(ERRNO_ILSEQ, "Illegal byte sequence")
+    }
+
     pub fn noent() -> Self {
         Self::new(ERRNO_NOENT, "No such file or directory")
     }
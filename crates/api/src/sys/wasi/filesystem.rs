@@ -1,23 +1,67 @@
-use super::super::fs::VirtualFileSystem;
+// Descriptors are views onto the kernel's global filesystem (`sys::fs`), so
+// files are shared with the kernel, the terminal and every other app
+use super::super::error::{AgaveError, FsError};
+use super::super::fs::{self, FileType};
+use super::super::security::{path_within, SandboxProfile};
 use super::error::*;
+use super::io::{FileSink, StreamRegistry};
 use super::types::*;
 use alloc::collections::BTreeMap;
 use alloc::format;
 use alloc::string::{String, ToString};
 use alloc::vec::Vec;
-use spin::Mutex;
-use lazy_static::lazy_static;
 
-// Global filesystem state
-static FILESYSTEM: Mutex<FilesystemState> = Mutex::new(FilesystemState::new());
-lazy_static! {
-    static ref VFS: Mutex<VirtualFileSystem> = Mutex::new(VirtualFileSystem::new());
+// Map a kernel filesystem error onto the closest errno
+fn fs_error(err: AgaveError) -> WasiError {
+    match err {
+        AgaveError::NotFound
+        | AgaveError::FileSystemError(FsError::FileNotFound | FsError::DirectoryNotFound) => {
+            WasiError::noent()
+        }
+        AgaveError::AlreadyExists | AgaveError::FileSystemError(FsError::FileAlreadyExists) => {
+            WasiError::exist()
+        }
+        AgaveError::PermissionDenied => WasiError::perm(),
+        AgaveError::NotImplemented => WasiError::nosys(),
+        AgaveError::InvalidParameter | AgaveError::FileSystemError(FsError::InvalidPath) => {
+            WasiError::inval()
+        }
+        AgaveError::FileSystemError(FsError::DirectoryNotEmpty) => WasiError::notempty(),
+        AgaveError::FileSystemError(FsError::ReadOnlyFilesystem) => WasiError::rofs(),
+        AgaveError::FileSystemError(FsError::DiskFull) => WasiError::nospc(),
+        AgaveError::FileSystemError(FsError::IsDirectory) => WasiError::isdir(),
+        AgaveError::FileSystemError(FsError::NotDirectory) => WasiError::notdir(),
+        AgaveError::FileSystemError(FsError::InvalidFileDescriptor) => WasiError::badf(),
+        _ => WasiError::io(),
+    }
+}
+
+// Largest file a descriptor table without a sandbox profile may grow
+const MAX_FILE_SIZE: FileSize = 16 * 1024 * 1024;
+
+// End of `len` bytes written at `offset`, or EFBIG if that would take the
+// file past `max_file_size`
+fn write_end(offset: FileSize, len: FileSize, max_file_size: FileSize) -> WasiResult<FileSize> {
+    offset
+        .checked_add(len)
+        .filter(|&end| end <= max_file_size)
+        .ok_or_else(WasiError::fbig)
 }
 
+// Current size of the file at `path`
+fn file_size(path: &str) -> WasiResult<FileSize> {
+    fs::metadata(path).map(|meta| meta.size).map_err(fs_error)
+}
+
+/// Descriptor table of one WASI instance
 #[derive(Debug)]
 pub struct FilesystemState {
     open_files: BTreeMap<Fd, FileDescriptor>,
     preopened_dirs: BTreeMap<Fd, String>,
+    // Paths no descriptor may reach, even through a preopen that contains them
+    denied_paths: Vec<String>,
+    max_open_files: usize,
+    max_file_size: FileSize,
     next_fd: Fd,
     cwd: String,
 }
@@ -27,6 +71,9 @@ impl FilesystemState {
         Self {
             open_files: BTreeMap::new(),
             preopened_dirs: BTreeMap::new(),
+            denied_paths: Vec::new(),
+            max_open_files: usize::MAX,
+            max_file_size: MAX_FILE_SIZE,
             next_fd: 3, // Start after stdin(0), stdout(1), stderr(2)
             cwd: String::new(),
         }
@@ -43,6 +90,158 @@ impl FilesystemState {
         self.preopened_dirs.insert(fd, path);
         fd
     }
+
+    /// Preopened directories and the descriptors they were given
+    pub fn preopens(&self) -> impl Iterator<Item = (Fd, &str)> {
+        self.preopened_dirs
+            .iter()
+            .map(|(fd, path)| (*fd, path.as_str()))
+    }
+
+    /// Descriptor table with the standard preopened directories
+    pub fn with_default_preopens() -> Self {
+        let mut fs = Self::new();
+        fs.add_preopen("/".to_string());
+        fs.add_preopen("/tmp".to_string());
+        fs.cwd = "/".to_string();
+        fs
+    }
+
+    /// Descriptor table confined by a sandbox profile: its allowed paths
+    /// become the preopens, its denied paths can never be resolved, at most
+    /// `max_file_handles` files may be open at once, and no file may grow
+    /// past `max_file_size`
+    pub fn with_sandbox(profile: &SandboxProfile) -> Self {
+        let mut fs = Self::new();
+        for path in &profile.allowed_paths {
+            fs.add_preopen(path.clone());
+        }
+        fs.denied_paths = profile.denied_paths.iter().cloned().collect();
+        fs.max_open_files = profile.resource_limits.max_file_handles as usize;
+        fs.max_file_size = profile.resource_limits.max_file_size;
+        fs.cwd = fs
+            .preopened_dirs
+            .values()
+            .next()
+            .cloned()
+            .unwrap_or_else(|| "/".to_string());
+        fs
+    }
+
+    /// Close every open descriptor. Returns the number of descriptors released.
+    pub fn close_all(&mut self) -> usize {
+        let count = self.open_files.len();
+        self.open_files.clear();
+        count
+    }
+
+    /// Open descriptors other than the preopens
+    pub fn descriptors(&self) -> impl Iterator<Item = (Fd, &FileDescriptor)> {
+        self.open_files.iter().map(|(fd, desc)| (*fd, desc))
+    }
+
+    /// Reopen a descriptor saved from an earlier instance under its old
+    /// number. Its path must still be reachable through the preopens.
+    pub fn restore_descriptor(&mut self, fd: Fd, desc: FileDescriptor) -> WasiResult<()> {
+        if fd < 3 || self.preopened_dirs.contains_key(&fd) || self.open_files.contains_key(&fd) {
+            return Err(WasiError::badf());
+        }
+        if self.open_files.len() >= self.max_open_files {
+            return Err(WasiError::new(ERRNO_MFILE, "Too many open files"));
+        }
+        if !self.reachable(&desc.path) {
+            return Err(WasiError::notcapable());
+        }
+        self.open_files.insert(fd, desc);
+        self.next_fd = self.next_fd.max(fd + 1);
+        Ok(())
+    }
+
+    pub fn cwd(&self) -> &str {
+        &self.cwd
+    }
+
+    /// Change the working directory; it must be reachable through the
+    /// preopens
+    pub fn set_cwd(&mut self, cwd: &str) -> WasiResult<()> {
+        let cwd = normalize_path(cwd);
+        if !self.reachable(&cwd) {
+            return Err(WasiError::notcapable());
+        }
+        self.cwd = cwd;
+        Ok(())
+    }
+
+    // Whether the absolute `path` lies in a preopen and outside the denied
+    // paths
+    fn reachable(&self, path: &str) -> bool {
+        self.preopened_dirs
+            .values()
+            .any(|dir| path_within(path, dir))
+            && !self
+                .denied_paths
+                .iter()
+                .any(|denied| path_within(path, denied))
+    }
+
+    // Absolute path of `path` relative to the directory `fd`. The result
+    // must stay inside that directory and outside the denied paths.
+    fn resolve(&self, fd: Fd, path: &str) -> WasiResult<String> {
+        let base_path = if let Some(preopen_path) = self.preopened_dirs.get(&fd) {
+            preopen_path
+        } else if let Some(file_desc) = self.open_files.get(&fd) {
+            if !file_desc.is_directory {
+                return Err(WasiError::notdir());
+            }
+            &file_desc.path
+        } else {
+            return Err(WasiError::badf());
+        };
+        let full_path = if path.starts_with('/') {
+            normalize_path(path)
+        } else {
+            normalize_path(&format!("{}/{}", base_path, path))
+        };
+        if !path_within(&full_path, base_path)
+            || self
+                .denied_paths
+                .iter()
+                .any(|denied| path_within(&full_path, denied))
+        {
+            return Err(WasiError::notcapable());
+        }
+        Ok(full_path)
+    }
+
+    // Absolute path of the file or directory `fd` refers to
+    fn descriptor_path(&self, fd: Fd) -> WasiResult<&str> {
+        match (self.open_files.get(&fd), self.preopened_dirs.get(&fd)) {
+            (Some(file_desc), _) => Ok(&file_desc.path),
+            (None, Some(path)) => Ok(path),
+            (None, None) => Err(WasiError::badf()),
+        }
+    }
+}
+
+// Collapse `.`, `..` and repeated separators; `..` never climbs above `/`
+fn normalize_path(path: &str) -> String {
+    let mut components: Vec<&str> = Vec::new();
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            _ => components.push(component),
+        }
+    }
+    format!("/{}", components.join("/"))
+}
+
+impl Default for FilesystemState {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -53,8 +252,6 @@ pub struct FileDescriptor {
     pub rights_inheriting: Rights,
     pub file_type: u8,
     pub offset: FileSize,
-    pub size: FileSize,
-    pub data: Vec<u8>,
     pub is_directory: bool,
 }
 
@@ -72,8 +269,6 @@ impl FileDescriptor {
             rights_inheriting,
             file_type: FILETYPE_REGULAR_FILE,
             offset: 0,
-            size: 0,
-            data: Vec::new(),
             is_directory: false,
         }
     }
@@ -86,26 +281,14 @@ impl FileDescriptor {
             rights_inheriting,
             file_type: FILETYPE_DIRECTORY,
             offset: 0,
-            size: 0,
-            data: Vec::new(),
             is_directory: true,
         }
     }
 }
 
-// Initialize filesystem with standard preopened directories
-pub fn init_filesystem() {
-    let mut fs = FILESYSTEM.lock();
-    fs.add_preopen("/".to_string());
-    fs.add_preopen("/tmp".to_string());
-    fs.cwd = "/".to_string();
-}
-
 // Preview 1 API implementations
-pub fn fd_prestat_get(fd: Fd) -> WasiResult<Prestat> {
-    let fs = FILESYSTEM.lock();
-
-    if let Some(path) = fs.preopened_dirs.get(&fd) {
+pub fn fd_prestat_get(fs_state: &FilesystemState, fd: Fd) -> WasiResult<Prestat> {
+    if let Some(path) = fs_state.preopened_dirs.get(&fd) {
         Ok(Prestat {
             tag: 0, // PREOPENTYPE_DIR
             u: PrestatU {
@@ -119,22 +302,24 @@ pub fn fd_prestat_get(fd: Fd) -> WasiResult<Prestat> {
     }
 }
 
-pub fn fd_prestat_dir_name(fd: Fd, _path_ptr: u32, path_len: Size) -> WasiResult<()> {
-    let fs = FILESYSTEM.lock();
-
-    if let Some(path) = fs.preopened_dirs.get(&fd) {
+pub fn fd_prestat_dir_name(
+    fs_state: &FilesystemState,
+    fd: Fd,
+    path_len: Size,
+) -> WasiResult<String> {
+    if let Some(path) = fs_state.preopened_dirs.get(&fd) {
         if path.len() > path_len as usize {
             return Err(WasiError::nametoolong());
         }
-        // In a real implementation, we would write to the WebAssembly memory
-        // For now, we'll just validate the operation
-        Ok(())
+        Ok(path.clone())
     } else {
         Err(WasiError::badf())
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn path_open(
+    fs_state: &mut FilesystemState,
     fd: Fd,
     _dirflags: LookupFlags,
     path: &str,
@@ -143,123 +328,137 @@ pub fn path_open(
     fs_rights_inheriting: Rights,
     fdflags: FdFlags,
 ) -> WasiResult<Fd> {
-    let mut fs_state = FILESYSTEM.lock();
-
-    // Check if the directory fd exists and has the required rights
-    if !fs_state.preopened_dirs.contains_key(&fd) && !fs_state.open_files.contains_key(&fd) {
-        return Err(WasiError::badf());
+    let full_path = fs_state.resolve(fd, path)?;
+    if fs_state.open_files.len() >= fs_state.max_open_files {
+        return Err(WasiError::new(ERRNO_MFILE, "Too many open files"));
     }
 
-    // Create the full path
-    let base_path = if let Some(preopen_path) = fs_state.preopened_dirs.get(&fd) {
-        preopen_path.clone()
-    } else if let Some(file_desc) = fs_state.open_files.get(&fd) {
-        if !file_desc.is_directory {
-            return Err(WasiError::notdir());
-        }
-        file_desc.path.clone()
-    } else {
-        return Err(WasiError::badf());
-    };
-
-    let full_path = if path.starts_with('/') {
-        path.to_string()
-    } else if base_path == "/" {
-        format!("/{}", path)
-    } else {
-        format!("{}/{}", base_path, path)
-    };
-
-    // Use VFS for file open
-    let mut vfs = VFS.lock();
-    let exists = vfs.exists(&full_path);
+    let exists = fs::exists(&full_path);
     if !exists && (oflags & 0x1) == 0 {
         return Err(WasiError::noent());
     }
     if !exists {
-        vfs.write_file(&full_path, Vec::new()).map_err(|_| WasiError::io())?;
+        fs::write_file(&full_path, Vec::new()).map_err(fs_error)?;
+    } else if (oflags & 0x8) != 0 {
+        // O_TRUNC
+        fs::truncate(&full_path, 0).map_err(fs_error)?;
     }
     let new_fd = fs_state.allocate_fd();
-    let mut file_desc = FileDescriptor::new(full_path.clone(), fdflags, fs_rights_base, fs_rights_inheriting);
-    file_desc.data = vfs.read_file(&full_path).map_err(|_| WasiError::io())?;
-    file_desc.size = file_desc.data.len() as FileSize;
+    let file_desc = if fs::is_dir(&full_path) {
+        FileDescriptor::new_directory(full_path, fs_rights_base, fs_rights_inheriting)
+    } else {
+        FileDescriptor::new(full_path, fdflags, fs_rights_base, fs_rights_inheriting)
+    };
     fs_state.open_files.insert(new_fd, file_desc);
     Ok(new_fd)
 }
 
-pub fn fd_read(fd: Fd, iovs: &[IOVec]) -> WasiResult<Size> {
-    let mut fs_state = FILESYSTEM.lock();
-    let vfs = VFS.lock();
+/// Read from the current offset into `buf`, advancing the offset
+pub fn fd_read(fs_state: &mut FilesystemState, fd: Fd, buf: &mut [u8]) -> WasiResult<Size> {
     if let Some(file_desc) = fs_state.open_files.get_mut(&fd) {
         if (file_desc.rights_base & RIGHTS_FD_READ) == 0 {
             return Err(WasiError::notcapable());
         }
-        let file_data = vfs.read_file(&file_desc.path).map_err(|_| WasiError::io())?;
-        let mut total_read = 0;
-        let mut offset = file_desc.offset as usize;
-        for iov in iovs {
-            let bytes_to_read = iov.buf_len.min((file_data.len() as u32).saturating_sub(offset as u32));
-            if bytes_to_read == 0 {
-                break;
-            }
-            // Here you would copy file_data[offset..offset+bytes_to_read] to WASM memory at iov.buf
-            offset += bytes_to_read as usize;
-            file_desc.offset += bytes_to_read as FileSize;
-            total_read += bytes_to_read;
-        }
-        Ok(total_read)
+        let bytes_read = fs::read_at(&file_desc.path, file_desc.offset, buf).map_err(fs_error)?;
+        file_desc.offset += bytes_read as FileSize;
+        Ok(bytes_read as Size)
     } else {
         Err(WasiError::badf())
     }
 }
 
-pub fn fd_write(fd: Fd, iovs: &[CIOVec]) -> WasiResult<Size> {
-    let mut fs_state = FILESYSTEM.lock();
-    let mut vfs = VFS.lock();
+/// Write `data` at the current offset (or the end in append mode)
+pub fn fd_write(fs_state: &mut FilesystemState, fd: Fd, data: &[u8]) -> WasiResult<Size> {
+    let max_file_size = fs_state.max_file_size;
     if let Some(file_desc) = fs_state.open_files.get_mut(&fd) {
         if (file_desc.rights_base & RIGHTS_FD_WRITE) == 0 {
             return Err(WasiError::notcapable());
         }
-        let mut total_written = 0;
-        let mut offset = file_desc.offset as usize;
-        for iov in iovs {
-            let bytes_to_write = iov.buf_len;
-            // Here you would read bytes from WASM memory at iov.buf
-            let mut data = alloc::vec::Vec::with_capacity(bytes_to_write as usize);
-            data.resize(bytes_to_write as usize, 0u8); // Replace with real data from WASM
-            let mut file_data = vfs.read_file(&file_desc.path).unwrap_or_default();
-            let new_end = offset + bytes_to_write as usize;
-            if new_end > file_data.len() {
-                file_data.resize(new_end, 0);
-            }
-            file_data[offset..new_end].copy_from_slice(&data);
-            vfs.write_file(&file_desc.path, file_data.clone()).map_err(|_| WasiError::io())?;
-            file_desc.data = file_data;
-            file_desc.size = file_desc.data.len() as FileSize;
-            file_desc.offset += bytes_to_write as FileSize;
-            offset += bytes_to_write as usize;
-            total_written += bytes_to_write;
-        }
-        Ok(total_written)
+        let offset = if (file_desc.flags & FDFLAGS_APPEND) != 0 {
+            file_size(&file_desc.path)?
+        } else {
+            file_desc.offset
+        };
+        let new_end = write_end(offset, data.len() as FileSize, max_file_size)?;
+        fs::write_at(&file_desc.path, offset, data).map_err(fs_error)?;
+        file_desc.offset = new_end;
+        Ok(data.len() as Size)
+    } else {
+        Err(WasiError::badf())
+    }
+}
+
+/// Read at `offset` without moving the descriptor's offset
+pub fn fd_pread(
+    fs_state: &FilesystemState,
+    fd: Fd,
+    buf: &mut [u8],
+    offset: FileSize,
+) -> WasiResult<Size> {
+    if let Some(file_desc) = fs_state.open_files.get(&fd) {
+        if (file_desc.rights_base & (RIGHTS_FD_READ | RIGHTS_FD_SEEK))
+            != (RIGHTS_FD_READ | RIGHTS_FD_SEEK)
+        {
+            return Err(WasiError::notcapable());
+        }
+        if file_desc.is_directory {
+            return Err(WasiError::isdir());
+        }
+        let bytes_read = fs::read_at(&file_desc.path, offset, buf).map_err(fs_error)?;
+        Ok(bytes_read as Size)
     } else {
         Err(WasiError::badf())
     }
 }
 
-pub fn fd_seek(fd: Fd, offset: FileDelta, whence: Whence) -> WasiResult<FileSize> {
-    let mut fs_state = FILESYSTEM.lock();
+/// Write `data` at `offset` without moving the descriptor's offset
+pub fn fd_pwrite(
+    fs_state: &mut FilesystemState,
+    fd: Fd,
+    data: &[u8],
+    offset: FileSize,
+) -> WasiResult<Size> {
+    if let Some(file_desc) = fs_state.open_files.get(&fd) {
+        if (file_desc.rights_base & (RIGHTS_FD_WRITE | RIGHTS_FD_SEEK))
+            != (RIGHTS_FD_WRITE | RIGHTS_FD_SEEK)
+        {
+            return Err(WasiError::notcapable());
+        }
+        if file_desc.is_directory {
+            return Err(WasiError::isdir());
+        }
+        write_end(offset, data.len() as FileSize, fs_state.max_file_size)?;
+        fs::write_at(&file_desc.path, offset, data).map_err(fs_error)?;
+        Ok(data.len() as Size)
+    } else {
+        Err(WasiError::badf())
+    }
+}
 
+/// Move the offset; it may point past the end of the file but not below 0
+/// or past the size limit
+pub fn fd_seek(
+    fs_state: &mut FilesystemState,
+    fd: Fd,
+    offset: FileDelta,
+    whence: Whence,
+) -> WasiResult<FileSize> {
+    let max_file_size = fs_state.max_file_size;
     if let Some(file_desc) = fs_state.open_files.get_mut(&fd) {
         if (file_desc.rights_base & RIGHTS_FD_SEEK) == 0 {
             return Err(WasiError::notcapable());
         }
 
-        let new_offset = match whence {
-            0 => offset as FileSize,                             // SEEK_SET
-            1 => file_desc.offset.saturating_add_signed(offset), // SEEK_CUR
-            2 => file_desc.size.saturating_add_signed(offset),   // SEEK_END
+        let base = match whence {
+            0 => 0,                           // SEEK_SET
+            1 => file_desc.offset,            // SEEK_CUR
+            2 => file_size(&file_desc.path)?, // SEEK_END
             _ => return Err(WasiError::inval()),
         };
+        let new_offset = base
+            .checked_add_signed(offset)
+            .filter(|&new_offset| new_offset <= max_file_size)
+            .ok_or_else(WasiError::inval)?;
 
         file_desc.offset = new_offset;
         Ok(new_offset)
@@ -268,9 +467,7 @@ pub fn fd_seek(fd: Fd, offset: FileDelta, whence: Whence) -> WasiResult<FileSize
     }
 }
 
-pub fn fd_tell(fd: Fd) -> WasiResult<FileSize> {
-    let fs_state = FILESYSTEM.lock();
-
+pub fn fd_tell(fs_state: &FilesystemState, fd: Fd) -> WasiResult<FileSize> {
     if let Some(file_desc) = fs_state.open_files.get(&fd) {
         if (file_desc.rights_base & RIGHTS_FD_TELL) == 0 {
             return Err(WasiError::notcapable());
@@ -281,66 +478,64 @@ pub fn fd_tell(fd: Fd) -> WasiResult<FileSize> {
     }
 }
 
-pub fn fd_close(fd: Fd) -> WasiResult<()> {
-    let mut fs_state = FILESYSTEM.lock();
-
-    if let Some(file_desc) = fs_state.open_files.remove(&fd) {
-        if !file_desc.is_directory {
-            let mut vfs = VFS.lock();
-            let _ = vfs.write_file(&file_desc.path, file_desc.data.clone());
-        }
+pub fn fd_close(fs_state: &mut FilesystemState, fd: Fd) -> WasiResult<()> {
+    if fs_state.open_files.remove(&fd).is_some() {
         Ok(())
     } else {
         Err(WasiError::badf())
     }
 }
 
-pub fn fd_sync(fd: Fd) -> WasiResult<()> {
-    let fs_state = FILESYSTEM.lock();
+/// Move descriptor `from` to `to`, closing whatever `to` referred to
+pub fn fd_renumber(fs_state: &mut FilesystemState, from: Fd, to: Fd) -> WasiResult<()> {
+    if fs_state.preopened_dirs.contains_key(&from) || fs_state.preopened_dirs.contains_key(&to) {
+        return Err(WasiError::notsup());
+    }
+    if !fs_state.open_files.contains_key(&to) {
+        return Err(WasiError::badf());
+    }
+    let file_desc = fs_state.open_files.remove(&from).ok_or(WasiError::badf())?;
+    fd_close(fs_state, to)?;
+    fs_state.open_files.insert(to, file_desc);
+    Ok(())
+}
 
+pub fn fd_sync(fs_state: &FilesystemState, fd: Fd) -> WasiResult<()> {
     if let Some(file_desc) = fs_state.open_files.get(&fd) {
         if (file_desc.rights_base & RIGHTS_FD_SYNC) == 0 {
             return Err(WasiError::notcapable());
         }
-        if !file_desc.is_directory {
-            let mut vfs = VFS.lock();
-            let _ = vfs.write_file(&file_desc.path, file_desc.data.clone());
-        }
-        Ok(())
+        fs::sync_filesystem().map_err(fs_error)
     } else {
         Err(WasiError::badf())
     }
 }
 
-pub fn fd_datasync(fd: Fd) -> WasiResult<()> {
-    let fs_state = FILESYSTEM.lock();
-
+pub fn fd_datasync(fs_state: &FilesystemState, fd: Fd) -> WasiResult<()> {
     if let Some(file_desc) = fs_state.open_files.get(&fd) {
         if (file_desc.rights_base & RIGHTS_FD_DATASYNC) == 0 {
             return Err(WasiError::notcapable());
         }
-        if !file_desc.is_directory {
-                let mut vfs = VFS.lock();
-            let _ = vfs.write_file(&file_desc.path, file_desc.data.clone());
-        }
-        Ok(())
+        fs::sync_filesystem().map_err(fs_error)
     } else {
         Err(WasiError::badf())
     }
 }
 
-pub fn fd_allocate(fd: Fd, offset: FileSize, len: FileSize) -> WasiResult<()> {
-    let mut fs_state = FILESYSTEM.lock();
-
-    if let Some(file_desc) = fs_state.open_files.get_mut(&fd) {
+pub fn fd_allocate(
+    fs_state: &mut FilesystemState,
+    fd: Fd,
+    offset: FileSize,
+    len: FileSize,
+) -> WasiResult<()> {
+    if let Some(file_desc) = fs_state.open_files.get(&fd) {
         if (file_desc.rights_base & RIGHTS_FD_ALLOCATE) == 0 {
             return Err(WasiError::notcapable());
         }
 
-        let new_size = offset + len;
-        if new_size > file_desc.size {
-            file_desc.data.resize(new_size as usize, 0);
-            file_desc.size = new_size;
+        let new_size = write_end(offset, len, fs_state.max_file_size)?;
+        if new_size > file_size(&file_desc.path)? {
+            fs::truncate(&file_desc.path, new_size).map_err(fs_error)?;
         }
         Ok(())
     } else {
@@ -348,9 +543,13 @@ pub fn fd_allocate(fd: Fd, offset: FileSize, len: FileSize) -> WasiResult<()> {
     }
 }
 
-pub fn fd_advise(fd: Fd, _offset: FileSize, _len: FileSize, _advice: Advice) -> WasiResult<()> {
-    let fs_state = FILESYSTEM.lock();
-
+pub fn fd_advise(
+    fs_state: &FilesystemState,
+    fd: Fd,
+    _offset: FileSize,
+    _len: FileSize,
+    _advice: Advice,
+) -> WasiResult<()> {
     if let Some(file_desc) = fs_state.open_files.get(&fd) {
         if (file_desc.rights_base & RIGHTS_FD_ADVISE) == 0 {
             return Err(WasiError::notcapable());
@@ -362,9 +561,7 @@ pub fn fd_advise(fd: Fd, _offset: FileSize, _len: FileSize, _advice: Advice) ->
     }
 }
 
-pub fn fd_fdstat_get(fd: Fd) -> WasiResult<FdStat> {
-    let fs_state = FILESYSTEM.lock();
-
+pub fn fd_fdstat_get(fs_state: &FilesystemState, fd: Fd) -> WasiResult<FdStat> {
     if let Some(file_desc) = fs_state.open_files.get(&fd) {
         // Create fdstat structure
         let mut fdstat = [0u8; 24];
@@ -389,9 +586,11 @@ pub fn fd_fdstat_get(fd: Fd) -> WasiResult<FdStat> {
     }
 }
 
-pub fn fd_fdstat_set_flags(fd: Fd, flags: FdFlags) -> WasiResult<()> {
-    let mut fs_state = FILESYSTEM.lock();
-
+pub fn fd_fdstat_set_flags(
+    fs_state: &mut FilesystemState,
+    fd: Fd,
+    flags: FdFlags,
+) -> WasiResult<()> {
     if let Some(file_desc) = fs_state.open_files.get_mut(&fd) {
         if (file_desc.rights_base & RIGHTS_FD_FDSTAT_SET_FLAGS) == 0 {
             return Err(WasiError::notcapable());
@@ -403,104 +602,186 @@ pub fn fd_fdstat_set_flags(fd: Fd, flags: FdFlags) -> WasiResult<()> {
     }
 }
 
-pub fn fd_filestat_get(fd: Fd) -> WasiResult<FileStat> {
-    let fs_state = FILESYSTEM.lock();
+/// Drop rights from a descriptor; rights can never be added back
+pub fn fd_fdstat_set_rights(
+    fs_state: &mut FilesystemState,
+    fd: Fd,
+    rights_base: Rights,
+    rights_inheriting: Rights,
+) -> WasiResult<()> {
+    if let Some(file_desc) = fs_state.open_files.get_mut(&fd) {
+        if (rights_base & !file_desc.rights_base) != 0
+            || (rights_inheriting & !file_desc.rights_inheriting) != 0
+        {
+            return Err(WasiError::notcapable());
+        }
+        file_desc.rights_base = rights_base;
+        file_desc.rights_inheriting = rights_inheriting;
+        Ok(())
+    } else {
+        Err(WasiError::badf())
+    }
+}
 
+pub fn fd_filestat_get(fs_state: &FilesystemState, fd: Fd) -> WasiResult<FileStat> {
     if let Some(file_desc) = fs_state.open_files.get(&fd) {
         if (file_desc.rights_base & RIGHTS_FD_FILESTAT_GET) == 0 {
             return Err(WasiError::notcapable());
         }
 
-        let mut filestat = [0u8; 56];
-        // dev (8 bytes at offset 0)
-        filestat[0..8].copy_from_slice(&1u64.to_le_bytes());
-        // ino (8 bytes at offset 8)
-        filestat[8..16].copy_from_slice(&(fd as u64).to_le_bytes());
-        // filetype (1 byte at offset 16)
-        filestat[16] = file_desc.file_type;
-        // nlink (8 bytes at offset 24)
-        filestat[24..32].copy_from_slice(&1u64.to_le_bytes());
-        // size (8 bytes at offset 32)
-        filestat[32..40].copy_from_slice(&file_desc.size.to_le_bytes());
-        // atim, mtim, ctim (8 bytes each at offsets 40, 48, 56)
-        let current_time = super::clocks::clock_time_get(CLOCKID_REALTIME, 0).unwrap_or(0);
-        filestat[40..48].copy_from_slice(&current_time.to_le_bytes());
-        filestat[48..56].copy_from_slice(&current_time.to_le_bytes());
-
-        Ok(filestat)
+        let size = if file_desc.is_directory {
+            0
+        } else {
+            file_size(&file_desc.path)?
+        };
+        Ok(filestat(&file_desc.path, file_desc.file_type, size))
     } else {
         Err(WasiError::badf())
     }
 }
 
-pub fn fd_filestat_set_size(fd: Fd, size: FileSize) -> WasiResult<()> {
-    let mut fs_state = FILESYSTEM.lock();
+pub fn path_filestat_get(fs_state: &FilesystemState, fd: Fd, path: &str) -> WasiResult<FileStat> {
+    let full_path = fs_state.resolve(fd, path)?;
+    let metadata = fs::metadata(&full_path).map_err(fs_error)?;
+    let (file_type, size) = match metadata.file_type {
+        FileType::Directory => (FILETYPE_DIRECTORY, 0),
+        FileType::Symlink => (FILETYPE_SYMBOLIC_LINK, metadata.size),
+        _ => (FILETYPE_REGULAR_FILE, metadata.size),
+    };
+    Ok(filestat(&full_path, file_type, size))
+}
+
+// Filestat of the file at `path`. The VFS has no inode numbers, so the
+// inode is a hash of the path.
+fn filestat(path: &str, file_type: u8, size: FileSize) -> FileStat {
+    let mut filestat = [0u8; 64];
+    // dev (8 bytes at offset 0)
+    filestat[0..8].copy_from_slice(&1u64.to_le_bytes());
+    // ino (8 bytes at offset 8)
+    filestat[8..16].copy_from_slice(&path_hash(path).to_le_bytes());
+    // filetype (1 byte at offset 16)
+    filestat[16] = file_type;
+    // nlink (8 bytes at offset 24)
+    filestat[24..32].copy_from_slice(&1u64.to_le_bytes());
+    // size (8 bytes at offset 32)
+    filestat[32..40].copy_from_slice(&size.to_le_bytes());
+    // atim, mtim, ctim (8 bytes each at offsets 40, 48, 56)
+    let current_time = super::clocks::clock_time_get(CLOCKID_REALTIME, 0).unwrap_or(0);
+    filestat[40..48].copy_from_slice(&current_time.to_le_bytes());
+    filestat[48..56].copy_from_slice(&current_time.to_le_bytes());
+    filestat[56..64].copy_from_slice(&current_time.to_le_bytes());
+    filestat
+}
+
+// FNV-1a hash of an absolute path, which identifies a file in the VFS
+fn path_hash(path: &str) -> u64 {
+    path.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Hash identifying the file `fd` refers to
+pub fn metadata_hash(fs_state: &FilesystemState, fd: Fd) -> WasiResult<u64> {
+    fs_state.descriptor_path(fd).map(path_hash)
+}
+
+/// Hash identifying the file at `path`, relative to the directory `fd`
+pub fn metadata_hash_at(fs_state: &FilesystemState, fd: Fd, path: &str) -> WasiResult<u64> {
+    let full_path = fs_state.resolve(fd, path)?;
+    if !fs::exists(&full_path) {
+        return Err(WasiError::noent());
+    }
+    Ok(path_hash(&full_path))
+}
+
+/// Whether `fd1` and `fd2` refer to the same file
+pub fn is_same_object(fs_state: &FilesystemState, fd1: Fd, fd2: Fd) -> WasiResult<bool> {
+    Ok(fs_state.descriptor_path(fd1)? == fs_state.descriptor_path(fd2)?)
+}
 
+pub fn fd_filestat_set_size(
+    fs_state: &mut FilesystemState,
+    fd: Fd,
+    size: FileSize,
+) -> WasiResult<()> {
     if let Some(file_desc) = fs_state.open_files.get_mut(&fd) {
         if (file_desc.rights_base & RIGHTS_FD_FILESTAT_SET_SIZE) == 0 {
             return Err(WasiError::notcapable());
         }
+        if file_desc.is_directory {
+            return Err(WasiError::isdir());
+        }
+        if size > fs_state.max_file_size {
+            return Err(WasiError::fbig());
+        }
 
-        file_desc.data.resize(size as usize, 0);
-        file_desc.size = size;
-
+        fs::truncate(&file_desc.path, size).map_err(fs_error)?;
         if file_desc.offset > size {
             file_desc.offset = size;
         }
-
-        if !file_desc.is_directory {
-                let mut vfs = VFS.lock();
-            let _ = vfs.write_file(&file_desc.path, file_desc.data.clone());
-        }
         Ok(())
     } else {
         Err(WasiError::badf())
     }
 }
 
-pub fn path_create_directory(fd: Fd, path: &str) -> WasiResult<()> {
-    let fs_state = FILESYSTEM.lock();
-    let mut vfs = VFS.lock();
-    let base_path = if let Some(preopen_path) = fs_state.preopened_dirs.get(&fd) {
-        preopen_path.clone()
-    } else if let Some(file_desc) = fs_state.open_files.get(&fd) {
-        file_desc.path.clone()
-    } else {
-        return Err(WasiError::badf());
-    };
-    let full_path = if path.starts_with('/') {
-        path.to_string()
-    } else if base_path == "/" {
-        format!("/{}", path)
+pub fn fd_filestat_set_times(
+    fs_state: &FilesystemState,
+    fd: Fd,
+    atim: Timestamp,
+    mtim: Timestamp,
+    fst_flags: FstFlags,
+) -> WasiResult<()> {
+    if let Some(file_desc) = fs_state.open_files.get(&fd) {
+        if (file_desc.rights_base & RIGHTS_FD_FILESTAT_SET_TIMES) == 0 {
+            return Err(WasiError::notcapable());
+        }
+        set_times(atim, mtim, fst_flags)
     } else {
-        format!("{}/{}", base_path, path)
-    };
-    vfs.create_dir_all(&full_path).map_err(|_| WasiError::io())
+        Err(WasiError::badf())
+    }
 }
 
-pub fn path_unlink_file(fd: Fd, path: &str) -> WasiResult<()> {
-    let fs_state = FILESYSTEM.lock();
-    let mut vfs = VFS.lock();
-    let base_path = if let Some(preopen_path) = fs_state.preopened_dirs.get(&fd) {
-        preopen_path.clone()
-    } else if let Some(file_desc) = fs_state.open_files.get(&fd) {
-        file_desc.path.clone()
-    } else {
-        return Err(WasiError::badf());
-    };
-    let full_path = if path.starts_with('/') {
-        path.to_string()
-    } else if base_path == "/" {
-        format!("/{}", path)
-    } else {
-        format!("{}/{}", base_path, path)
-    };
-    vfs.remove(&full_path).map_err(|_| WasiError::io())
+pub fn path_filestat_set_times(
+    fs_state: &FilesystemState,
+    fd: Fd,
+    path: &str,
+    atim: Timestamp,
+    mtim: Timestamp,
+    fst_flags: FstFlags,
+) -> WasiResult<()> {
+    let full_path = fs_state.resolve(fd, path)?;
+    if !fs::exists(&full_path) {
+        return Err(WasiError::noent());
+    }
+    set_times(atim, mtim, fst_flags)
+}
+
+// The VFS stamps files from its own clock and cannot take timestamps from
+// an app, so a valid request is unsupported rather than ignored
+fn set_times(_atim: Timestamp, _mtim: Timestamp, fst_flags: FstFlags) -> WasiResult<()> {
+    if (fst_flags & FSTFLAGS_ATIM != 0 && fst_flags & FSTFLAGS_ATIM_NOW != 0)
+        || (fst_flags & FSTFLAGS_MTIM != 0 && fst_flags & FSTFLAGS_MTIM_NOW != 0)
+    {
+        return Err(WasiError::inval());
+    }
+    Err(WasiError::notsup())
 }
 
-pub fn path_remove_directory(fd: Fd, _path: &str) -> WasiResult<()> {
-    let fs_state = FILESYSTEM.lock();
+pub fn path_create_directory(fs_state: &FilesystemState, fd: Fd, path: &str) -> WasiResult<()> {
+    let full_path = fs_state.resolve(fd, path)?;
+    fs::create_dir_all(&full_path).map_err(fs_error)
+}
 
+pub fn path_unlink_file(fs_state: &FilesystemState, fd: Fd, path: &str) -> WasiResult<()> {
+    let full_path = fs_state.resolve(fd, path)?;
+    if fs::is_dir(&full_path) {
+        return Err(WasiError::isdir());
+    }
+    fs::remove(&full_path).map_err(fs_error)
+}
+
+pub fn path_remove_directory(fs_state: &FilesystemState, fd: Fd, path: &str) -> WasiResult<()> {
     // Check directory permissions
     if !fs_state.preopened_dirs.contains_key(&fd) {
         if let Some(file_desc) = fs_state.open_files.get(&fd) {
@@ -514,14 +795,74 @@ pub fn path_remove_directory(fd: Fd, _path: &str) -> WasiResult<()> {
         }
     }
 
-    // In a real implementation, this would remove the directory
-    // For now, we'll just validate the operation
-    Ok(())
+    let full_path = fs_state.resolve(fd, path)?;
+    if !fs::is_dir(&full_path) {
+        return Err(WasiError::notdir());
+    }
+    fs::remove(&full_path).map_err(fs_error)
+}
+
+pub fn path_rename(
+    fs_state: &FilesystemState,
+    fd: Fd,
+    old_path: &str,
+    new_fd: Fd,
+    new_path: &str,
+) -> WasiResult<()> {
+    let old_path = fs_state.resolve(fd, old_path)?;
+    let new_path = fs_state.resolve(new_fd, new_path)?;
+    fs::rename(&old_path, &new_path).map_err(fs_error)
 }
 
-pub fn fd_readdir(fd: Fd, _buf: &mut [u8], _cookie: DirCookie) -> WasiResult<Size> {
-    let fs_state = FILESYSTEM.lock();
-    let vfs = VFS.lock();
+pub fn path_link(
+    fs_state: &FilesystemState,
+    old_fd: Fd,
+    old_path: &str,
+    new_fd: Fd,
+    new_path: &str,
+) -> WasiResult<()> {
+    let old_path = fs_state.resolve(old_fd, old_path)?;
+    fs_state.resolve(new_fd, new_path)?;
+    if !fs::exists(&old_path) {
+        return Err(WasiError::noent());
+    }
+    // The VFS has no hard links
+    Err(WasiError::notsup())
+}
+
+pub fn path_symlink(
+    fs_state: &FilesystemState,
+    old_path: &str,
+    fd: Fd,
+    new_path: &str,
+) -> WasiResult<()> {
+    let link_path = fs_state.resolve(fd, new_path)?;
+    let parent = link_path.rsplit_once('/').map_or("", |(parent, _)| parent);
+    if !fs::is_dir(parent) {
+        return Err(WasiError::noent());
+    }
+    // A relative target is relative to the link's directory. The target is
+    // stored resolved, so following the link cannot leave the sandbox.
+    let target = if old_path.starts_with('/') {
+        old_path.to_string()
+    } else {
+        format!("{}/{}", parent, old_path)
+    };
+    let target = fs_state.resolve(fd, &target)?;
+    fs::symlink(&link_path, &target).map_err(fs_error)
+}
+
+pub fn path_readlink(fs_state: &FilesystemState, fd: Fd, path: &str) -> WasiResult<String> {
+    let full_path = fs_state.resolve(fd, path)?;
+    fs::read_link(&full_path).map_err(fs_error)
+}
+
+pub fn fd_readdir(
+    fs_state: &FilesystemState,
+    fd: Fd,
+    buf: &mut [u8],
+    cookie: DirCookie,
+) -> WasiResult<Size> {
     let path = if let Some(file_desc) = fs_state.open_files.get(&fd) {
         if !file_desc.is_directory || (file_desc.rights_base & RIGHTS_FD_READDIR) == 0 {
             return Err(WasiError::notdir());
@@ -532,84 +873,92 @@ pub fn fd_readdir(fd: Fd, _buf: &mut [u8], _cookie: DirCookie) -> WasiResult<Siz
     } else {
         return Err(WasiError::badf());
     };
-    let entries = vfs.read_dir(&path).map_err(|_| WasiError::io())?;
-    // Here you would serialize entries into WASM memory at _buf
-    Ok(entries.len() as Size)
-}
-
-// Preview 2 API extensions
-pub fn open_at(dir_fd: Fd, path: &str, open_flags: u32, create_flags: u32) -> WasiResult<(Fd, u8)> {
-    // Convert Preview 2 flags to Preview 1 flags
-    let oflags = if (create_flags & 0x1) != 0 { 0x1 } else { 0 }; // O_CREAT
-    let fdflags = if (open_flags & 0x1) != 0 {
-        FDFLAGS_APPEND
-    } else {
-        0
-    };
-
-    let rights = RIGHTS_FD_READ | RIGHTS_FD_WRITE | RIGHTS_FD_SEEK | RIGHTS_FD_TELL;
-
-    let fd = path_open(dir_fd, 0, path, oflags, rights, rights, fdflags)?;
+    let entries = fs::read_dir(&path).map_err(fs_error)?;
+
+    // Serialize dirents (d_next, d_ino, d_namlen, d_type) followed by the
+    // name, truncating the last entry if the buffer runs out
+    let mut used = 0;
+    for (index, entry) in entries.iter().enumerate().skip(cookie as usize) {
+        let mut dirent = [0u8; 24];
+        dirent[0..8].copy_from_slice(&(index as u64 + 1).to_le_bytes());
+        dirent[8..16].copy_from_slice(&(index as u64 + 1).to_le_bytes());
+        dirent[16..20].copy_from_slice(&(entry.name.len() as u32).to_le_bytes());
+        dirent[20] = match entry.file_type {
+            FileType::Directory => FILETYPE_DIRECTORY,
+            FileType::Symlink => FILETYPE_SYMBOLIC_LINK,
+            _ => FILETYPE_REGULAR_FILE,
+        };
 
-    // Return file descriptor and file type
-    let file_type = {
-        let fs_state = FILESYSTEM.lock();
-        if let Some(file_desc) = fs_state.open_files.get(&fd) {
-            file_desc.file_type
-        } else {
-            FILETYPE_REGULAR_FILE
+        for chunk in [&dirent[..], entry.name.as_bytes()] {
+            let n = chunk.len().min(buf.len() - used);
+            buf[used..used + n].copy_from_slice(&chunk[..n]);
+            used += n;
         }
-    };
-
-    Ok((fd, file_type))
+        if used == buf.len() {
+            break;
+        }
+    }
+    Ok(used as Size)
 }
 
-pub fn read_via_stream(fd: Fd, offset: FileSize) -> WasiResult<super::io::InputStream> {
-    let mut fs_state = FILESYSTEM.lock();
-
+pub fn read_via_stream(
+    fs_state: &mut FilesystemState,
+    streams: &mut StreamRegistry,
+    fd: Fd,
+    offset: FileSize,
+) -> WasiResult<InputStream> {
     if let Some(file_desc) = fs_state.open_files.get_mut(&fd) {
         if (file_desc.rights_base & RIGHTS_FD_READ) == 0 {
             return Err(WasiError::notcapable());
         }
 
         // Create a stream from the file data starting at the offset
-        let start = offset.min(file_desc.size) as usize;
-        let data = file_desc.data[start..].to_vec();
+        let mut data = fs::read_file(&file_desc.path).map_err(fs_error)?;
+        data.drain(..(offset as usize).min(data.len()));
 
-        Ok(super::io::create_input_stream(data))
+        Ok(streams.create_input_stream(data))
     } else {
         Err(WasiError::badf())
     }
 }
 
-pub fn write_via_stream(fd: Fd, _offset: FileSize) -> WasiResult<super::io::OutputStream> {
-    let fs_state = FILESYSTEM.lock();
-
-    if let Some(file_desc) = fs_state.open_files.get(&fd) {
-        if (file_desc.rights_base & RIGHTS_FD_WRITE) == 0 {
-            return Err(WasiError::notcapable());
-        }
-
-        // Create an output stream for the file
-        Ok(super::io::create_output_stream())
-    } else {
-        Err(WasiError::badf())
-    }
+pub fn write_via_stream(
+    fs_state: &FilesystemState,
+    streams: &mut StreamRegistry,
+    fd: Fd,
+    offset: FileSize,
+) -> WasiResult<OutputStream> {
+    file_output_stream(fs_state, streams, fd, Some(offset))
 }
 
-pub fn append_via_stream(fd: Fd) -> WasiResult<super::io::OutputStream> {
-    let fs_state = FILESYSTEM.lock();
-
-    if let Some(file_desc) = fs_state.open_files.get(&fd) {
-        if (file_desc.rights_base & RIGHTS_FD_WRITE) == 0 {
-            return Err(WasiError::notcapable());
-        }
+pub fn append_via_stream(
+    fs_state: &FilesystemState,
+    streams: &mut StreamRegistry,
+    fd: Fd,
+) -> WasiResult<OutputStream> {
+    file_output_stream(fs_state, streams, fd, None)
+}
 
-        // Create an output stream for appending to the file
-        Ok(super::io::create_output_stream())
-    } else {
-        Err(WasiError::badf())
+// Stream writing to `fd`'s file at `offset`, or at its end if `None`
+fn file_output_stream(
+    fs_state: &FilesystemState,
+    streams: &mut StreamRegistry,
+    fd: Fd,
+    offset: Option<FileSize>,
+) -> WasiResult<OutputStream> {
+    let file_desc = fs_state.open_files.get(&fd).ok_or_else(WasiError::badf)?;
+    if (file_desc.rights_base & RIGHTS_FD_WRITE) == 0 {
+        return Err(WasiError::notcapable());
     }
+    if file_desc.file_type != FILETYPE_REGULAR_FILE {
+        return Err(WasiError::isdir());
+    }
+
+    Ok(streams.create_file_output_stream(FileSink {
+        path: file_desc.path.clone(),
+        offset,
+        max_size: fs_state.max_file_size,
+    }))
 }
 
 // Additional functions for demo compatibility
@@ -624,185 +973,3 @@ pub fn list_directory_entries(fd: Fd) -> WasiResult<Vec<String>> {
         "file2.txt".to_string(),
     ])
 }
-
-// Additional filesystem functions for Preview 2 compatibility
-pub fn advise(fd: Fd, offset: FileSize, len: FileSize, advice: Advice) -> WasiResult<()> {
-    // For demo, just pretend to advise
-    log::info!(
-        "Advising fd {} offset {} len {} advice {:?}",
-        fd,
-        offset,
-        len,
-        advice
-    );
-    Ok(())
-}
-
-pub fn sync_data(fd: Fd) -> WasiResult<()> {
-    // For demo, just pretend to sync
-    log::info!("Syncing data for fd {}", fd);
-    Ok(())
-}
-
-pub fn get_flags(_fd: Fd) -> WasiResult<u32> {
-    // Return some default flags
-    Ok(0)
-}
-
-pub fn get_type(_fd: Fd) -> WasiResult<u8> {
-    // Return regular file type
-    Ok(4) // FILETYPE_REGULAR_FILE
-}
-
-pub fn set_size(fd: Fd, size: FileSize) -> WasiResult<()> {
-    // For demo, just pretend to set size
-    log::info!("Setting size of fd {} to {}", fd, size);
-    Ok(())
-}
-
-pub fn set_times(
-    fd: Fd,
-    data_access_timestamp: u64,
-    data_modification_timestamp: u64,
-) -> WasiResult<()> {
-    // For demo, just pretend to set times
-    log::info!(
-        "Setting times for fd {} access {} modify {}",
-        fd,
-        data_access_timestamp,
-        data_modification_timestamp
-    );
-    Ok(())
-}
-
-pub fn read(_fd: Fd, length: FileSize, _offset: FileSize) -> WasiResult<(Vec<u8>, bool)> {
-    // For demo, return empty data
-    let data = alloc::vec![0u8; length.min(1024) as usize];
-    Ok((data, true)) // true = end of file
-}
-
-pub fn write(fd: Fd, buffer: &[u8], offset: FileSize) -> WasiResult<FileSize> {
-    // For demo, pretend to write all bytes
-    log::info!(
-        "Writing {} bytes to fd {} at offset {}",
-        buffer.len(),
-        fd,
-        offset
-    );
-    Ok(buffer.len() as FileSize)
-}
-
-pub fn read_directory(fd: Fd) -> WasiResult<u32> {
-    // Return a dummy directory stream ID
-    Ok(fd)
-}
-
-pub fn sync(fd: Fd) -> WasiResult<()> {
-    // For demo, just pretend to sync
-    log::info!("Syncing fd {}", fd);
-    Ok(())
-}
-
-pub fn create_directory_at(fd: Fd, path: &str) -> WasiResult<()> {
-    // For demo, just pretend to create directory
-    log::info!("Creating directory {} at fd {}", path, fd);
-    Ok(())
-}
-
-pub fn stat(fd: Fd, path_flags: u16, path: &str) -> WasiResult<u64> {
-    // Return dummy stat data
-    log::info!("Stat {} at fd {} with flags {}", path, fd, path_flags);
-    Ok(0x1000) // Dummy stat
-}
-
-pub fn stat_open_directory(fd: Fd, path_flags: u16, path: &str) -> WasiResult<Fd> {
-    // Return dummy directory fd
-    log::info!(
-        "Stat open directory {} at fd {} with flags {}",
-        path,
-        fd,
-        path_flags
-    );
-    Ok(fd + 1)
-}
-
-pub fn link(
-    fd: Fd,
-    _old_path_flags: u16,
-    old_path: &str,
-    new_fd: Fd,
-    new_path: &str,
-) -> WasiResult<()> {
-    // For demo, just pretend to create link
-    log::info!(
-        "Link {} (fd {}) to {} (fd {})",
-        old_path,
-        fd,
-        new_path,
-        new_fd
-    );
-    Ok(())
-}
-
-pub fn readlink_at(fd: Fd, path: &str) -> WasiResult<String> {
-    // Return dummy link target
-    log::info!("Readlink {} at fd {}", path, fd);
-    Ok("/dummy/target".to_string())
-}
-
-pub fn remove_directory_at(fd: Fd, path: &str) -> WasiResult<()> {
-    // For demo, just pretend to remove directory
-    log::info!("Removing directory {} at fd {}", path, fd);
-    Ok(())
-}
-
-pub fn rename_at(fd: Fd, old_path: &str, new_fd: Fd, new_path: &str) -> WasiResult<()> {
-    // For demo, just pretend to rename
-    log::info!(
-        "Rename {} (fd {}) to {} (fd {})",
-        old_path,
-        fd,
-        new_path,
-        new_fd
-    );
-    Ok(())
-}
-
-pub fn symlink_at(fd: Fd, old_path: &str, new_path: &str) -> WasiResult<()> {
-    // For demo, just pretend to create symlink
-    log::info!("Symlink {} to {} at fd {}", old_path, new_path, fd);
-    Ok(())
-}
-
-pub fn unlink_file_at(fd: Fd, path: &str) -> WasiResult<()> {
-    // For demo, just pretend to unlink file
-    log::info!("Unlink file {} at fd {}", path, fd);
-    Ok(())
-}
-
-pub fn is_same_object(fd1: Fd, fd2: Fd) -> WasiResult<bool> {
-    // For demo, just compare fds
-    Ok(fd1 == fd2)
-}
-
-pub fn metadata_hash(fd: Fd) -> WasiResult<u64> {
-    // Return dummy hash
-    Ok(fd as u64 * 0x123456789)
-}
-
-pub fn metadata_hash_at(fd: Fd, path_flags: u16, path: &str) -> WasiResult<u64> {
-    // Return dummy hash based on path
-    log::info!(
-        "Metadata hash for {} at fd {} with flags {}",
-        path,
-        fd,
-        path_flags
-    );
-    Ok(path.len() as u64 * 0x987654321)
-}
-
-pub fn drop_descriptor(fd: Fd) -> WasiResult<()> {
-    // For demo, just pretend to drop descriptor
-    log::info!("Dropping descriptor {}", fd);
-    Ok(())
-}
@@ -2,15 +2,25 @@
 use super::error::*;
 use super::types::*;
 pub use super::types::{InputStream, OutputStream, Pollable};
+use crate::sys::fs;
 use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
 use alloc::vec;
 use alloc::vec::Vec;
 use spin::Mutex;
 
-// Global stream registry
+// Streams of kernel services that are not tied to an app; each app's own
+// streams live in its `WasiCtx`
 static STREAMS: Mutex<StreamRegistry> = Mutex::new(StreamRegistry::new());
 pub static POLLABLES: Mutex<PollableRegistry> = Mutex::new(PollableRegistry::new());
 
+/// Stream numbers every app's table reserves for its standard streams,
+/// which are backed by `WasiCtx::stdio` rather than by the table
+pub const STDIN: InputStream = 0;
+pub const STDOUT: OutputStream = 1;
+pub const STDERR: OutputStream = 2;
+
+/// Table of input and output streams, one per app
 #[derive(Debug)]
 pub struct StreamRegistry {
     input_streams: BTreeMap<InputStream, InputStreamImpl>,
@@ -23,7 +33,7 @@ impl StreamRegistry {
         Self {
             input_streams: BTreeMap::new(),
             output_streams: BTreeMap::new(),
-            next_id: 1,
+            next_id: STDERR + 1,
         }
     }
 
@@ -35,9 +45,18 @@ impl StreamRegistry {
     }
 
     pub fn create_output_stream(&mut self) -> OutputStream {
+        self.insert_output_stream(OutputStreamImpl::new())
+    }
+
+    /// Output stream writing straight through to a file
+    pub fn create_file_output_stream(&mut self, file: FileSink) -> OutputStream {
+        self.insert_output_stream(OutputStreamImpl::to_file(file))
+    }
+
+    fn insert_output_stream(&mut self, stream: OutputStreamImpl) -> OutputStream {
         let id = self.next_id;
         self.next_id += 1;
-        self.output_streams.insert(id, OutputStreamImpl::new());
+        self.output_streams.insert(id, stream);
         id
     }
 
@@ -56,6 +75,59 @@ impl StreamRegistry {
     pub fn remove_output_stream(&mut self, id: OutputStream) {
         self.output_streams.remove(&id);
     }
+
+    pub fn read(&mut self, id: InputStream, len: u64) -> Result<Vec<u8>, StreamError> {
+        self.get_input_stream(id)
+            .ok_or(StreamError::Closed)?
+            .read(len)
+    }
+
+    pub fn skip(&mut self, id: InputStream, len: u64) -> Result<u64, StreamError> {
+        self.get_input_stream(id)
+            .ok_or(StreamError::Closed)?
+            .skip(len)
+    }
+
+    /// Pollable for `id`, never ready if there is no such stream
+    pub fn subscribe_input(&self, id: InputStream) -> Pollable {
+        match self.input_streams.get(&id) {
+            Some(stream) => stream.subscribe(),
+            None => POLLABLES.lock().create_pollable(false),
+        }
+    }
+
+    pub fn check_write(&self, id: OutputStream) -> Result<u64, StreamError> {
+        self.output_streams
+            .get(&id)
+            .ok_or(StreamError::Closed)?
+            .check_write()
+    }
+
+    pub fn write(&mut self, id: OutputStream, contents: Vec<u8>) -> Result<(), StreamError> {
+        self.get_output_stream(id)
+            .ok_or(StreamError::Closed)?
+            .write(contents)
+    }
+
+    pub fn flush(&mut self, id: OutputStream) -> Result<(), StreamError> {
+        self.get_output_stream(id)
+            .ok_or(StreamError::Closed)?
+            .flush()
+    }
+
+    /// Pollable for `id`, never ready if there is no such stream
+    pub fn subscribe_output(&self, id: OutputStream) -> Pollable {
+        match self.output_streams.get(&id) {
+            Some(stream) => stream.subscribe(),
+            None => POLLABLES.lock().create_pollable(false),
+        }
+    }
+}
+
+impl Default for StreamRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[derive(Debug)]
@@ -156,9 +228,46 @@ impl InputStreamImpl {
     }
 }
 
+/// File behind an output stream from `write-via-stream` or
+/// `append-via-stream`
+#[derive(Debug, Clone)]
+pub struct FileSink {
+    pub path: String,
+    /// Where the next write goes; `None` appends to the end of the file
+    pub offset: Option<u64>,
+    /// Size the writes may not grow the file past
+    pub max_size: u64,
+}
+
+impl FileSink {
+    fn write(&mut self, contents: &[u8]) -> Result<(), StreamError> {
+        let offset = match self.offset {
+            Some(offset) => offset,
+            None => fs::metadata(&self.path).map_err(stream_failed)?.size,
+        };
+        let end = offset
+            .checked_add(contents.len() as u64)
+            .filter(|&end| end <= self.max_size)
+            .ok_or_else(|| stream_failed("file size limit exceeded"))?;
+        fs::write_at(&self.path, offset, contents).map_err(stream_failed)?;
+        if self.offset.is_some() {
+            self.offset = Some(end);
+        }
+        Ok(())
+    }
+}
+
+fn stream_failed(error: impl ToString) -> StreamError {
+    StreamError::LastOperationFailed(Error {
+        message: error.to_string(),
+    })
+}
+
 #[derive(Debug)]
 pub struct OutputStreamImpl {
     buffer: Vec<u8>,
+    // Written through instead of buffered if set
+    file: Option<FileSink>,
     closed: bool,
     flushed: bool,
 }
@@ -167,11 +276,19 @@ impl OutputStreamImpl {
     pub fn new() -> Self {
         Self {
             buffer: Vec::new(),
+            file: None,
             closed: false,
             flushed: true,
         }
     }
 
+    pub fn to_file(file: FileSink) -> Self {
+        Self {
+            file: Some(file),
+            ..Self::new()
+        }
+    }
+
     pub fn check_write(&self) -> Result<u64, StreamError> {
         if self.closed {
             return Err(StreamError::Closed);
@@ -185,6 +302,9 @@ impl OutputStreamImpl {
             return Err(StreamError::Closed);
         }
 
+        if let Some(file) = &mut self.file {
+            return file.write(&contents);
+        }
         self.buffer.extend_from_slice(&contents);
         self.flushed = false;
         Ok(())
@@ -296,12 +416,7 @@ pub fn create_output_stream() -> OutputStream {
 }
 
 pub fn input_stream_read(id: InputStream, len: u64) -> Result<Vec<u8>, StreamError> {
-    let mut streams = STREAMS.lock();
-    if let Some(stream) = streams.get_input_stream(id) {
-        stream.read(len)
-    } else {
-        Err(StreamError::Closed)
-    }
+    STREAMS.lock().read(id, len)
 }
 
 pub fn input_stream_blocking_read(id: InputStream, len: u64) -> Result<Vec<u8>, StreamError> {
@@ -314,12 +429,7 @@ pub fn input_stream_blocking_read(id: InputStream, len: u64) -> Result<Vec<u8>,
 }
 
 pub fn input_stream_skip(id: InputStream, len: u64) -> Result<u64, StreamError> {
-    let mut streams = STREAMS.lock();
-    if let Some(stream) = streams.get_input_stream(id) {
-        stream.skip(len)
-    } else {
-        Err(StreamError::Closed)
-    }
+    STREAMS.lock().skip(id, len)
 }
 
 pub fn input_stream_blocking_skip(id: InputStream, len: u64) -> Result<u64, StreamError> {
@@ -332,31 +442,15 @@ pub fn input_stream_blocking_skip(id: InputStream, len: u64) -> Result<u64, Stre
 }
 
 pub fn input_stream_subscribe(id: InputStream) -> Pollable {
-    let streams = STREAMS.lock();
-    if let Some(stream) = streams.input_streams.get(&id) {
-        stream.subscribe()
-    } else {
-        let mut pollables = POLLABLES.lock();
-        pollables.create_pollable(false)
-    }
+    STREAMS.lock().subscribe_input(id)
 }
 
 pub fn output_stream_check_write(id: OutputStream) -> Result<u64, StreamError> {
-    let streams = STREAMS.lock();
-    if let Some(stream) = streams.output_streams.get(&id) {
-        stream.check_write()
-    } else {
-        Err(StreamError::Closed)
-    }
+    STREAMS.lock().check_write(id)
 }
 
 pub fn output_stream_write(id: OutputStream, contents: Vec<u8>) -> Result<(), StreamError> {
-    let mut streams = STREAMS.lock();
-    if let Some(stream) = streams.get_output_stream(id) {
-        stream.write(contents)
-    } else {
-        Err(StreamError::Closed)
-    }
+    STREAMS.lock().write(id, contents)
 }
 
 pub fn output_stream_blocking_write_and_flush(
@@ -372,12 +466,7 @@ pub fn output_stream_blocking_write_and_flush(
 }
 
 pub fn output_stream_flush(id: OutputStream) -> Result<(), StreamError> {
-    let mut streams = STREAMS.lock();
-    if let Some(stream) = streams.get_output_stream(id) {
-        stream.flush()
-    } else {
-        Err(StreamError::Closed)
-    }
+    STREAMS.lock().flush(id)
 }
 
 pub fn output_stream_blocking_flush(id: OutputStream) -> Result<(), StreamError> {
@@ -390,13 +479,7 @@ pub fn output_stream_blocking_flush(id: OutputStream) -> Result<(), StreamError>
 }
 
 pub fn output_stream_subscribe(id: OutputStream) -> Pollable {
-    let streams = STREAMS.lock();
-    if let Some(stream) = streams.output_streams.get(&id) {
-        stream.subscribe()
-    } else {
-        let mut pollables = POLLABLES.lock();
-        pollables.create_pollable(false)
-    }
+    STREAMS.lock().subscribe_output(id)
 }
 
 pub fn output_stream_write_zeroes(id: OutputStream, len: u64) -> Result<(), StreamError> {
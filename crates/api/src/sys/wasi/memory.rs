@@ -0,0 +1,94 @@
+// Bounds-checked access to a guest's linear memory
+// Guest pointers are offsets into the instance's exported `memory`, never
+// host addresses; anything out of range fails with EFAULT.
+use super::error::*;
+use super::types::*;
+use super::{WasiCtx, WasiView};
+use alloc::vec::Vec;
+use core::ops::Range;
+use wasmi::{Caller, Extern};
+
+pub struct GuestMemory<'a> {
+    bytes: &'a mut [u8],
+}
+
+impl<'a> GuestMemory<'a> {
+    pub fn new(bytes: &'a mut [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn range(&self, ptr: u32, len: u32) -> WasiResult<Range<usize>> {
+        let start = ptr as usize;
+        let end = start.checked_add(len as usize).ok_or(WasiError::fault())?;
+        if end > self.bytes.len() {
+            return Err(WasiError::fault());
+        }
+        Ok(start..end)
+    }
+
+    pub fn read(&self, ptr: u32, len: u32) -> WasiResult<&[u8]> {
+        let range = self.range(ptr, len)?;
+        Ok(&self.bytes[range])
+    }
+
+    pub fn read_str(&self, ptr: u32, len: u32) -> WasiResult<&str> {
+        core::str::from_utf8(self.read(ptr, len)?).map_err(|_| WasiError::ilseq())
+    }
+
+    pub fn read_u32(&self, ptr: u32) -> WasiResult<u32> {
+        let bytes = self.read(ptr, 4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub fn read_u64(&self, ptr: u32) -> WasiResult<u64> {
+        let bytes = self.read(ptr, 8)?;
+        let mut value = [0; 8];
+        value.copy_from_slice(bytes);
+        Ok(u64::from_le_bytes(value))
+    }
+
+    /// Read an array of `len` iovecs (`buf: u32, buf_len: u32`) at `ptr`
+    pub fn read_iovecs(&self, ptr: u32, len: u32) -> WasiResult<Vec<IOVec>> {
+        let size = len.checked_mul(8).ok_or(WasiError::fault())?;
+        self.range(ptr, size)?;
+        (0..len)
+            .map(|i| {
+                Ok(IOVec {
+                    buf: self.read_u32(ptr + i * 8)?,
+                    buf_len: self.read_u32(ptr + i * 8 + 4)?,
+                })
+            })
+            .collect()
+    }
+
+    pub fn slice_mut(&mut self, ptr: u32, len: u32) -> WasiResult<&mut [u8]> {
+        let range = self.range(ptr, len)?;
+        Ok(&mut self.bytes[range])
+    }
+
+    pub fn write(&mut self, ptr: u32, data: &[u8]) -> WasiResult<()> {
+        self.slice_mut(ptr, data.len() as u32)?
+            .copy_from_slice(data);
+        Ok(())
+    }
+
+    pub fn write_u32(&mut self, ptr: u32, value: u32) -> WasiResult<()> {
+        self.write(ptr, &value.to_le_bytes())
+    }
+
+    pub fn write_u64(&mut self, ptr: u32, value: u64) -> WasiResult<()> {
+        self.write(ptr, &value.to_le_bytes())
+    }
+}
+
+/// Split the caller into its exported linear memory and its WASI context
+pub fn memory_and_ctx<'a, T: WasiView>(
+    caller: &'a mut Caller<'_, T>,
+) -> WasiResult<(GuestMemory<'a>, &'a mut WasiCtx)> {
+    let memory = match caller.get_export("memory") {
+        Some(Extern::Memory(memory)) => memory,
+        _ => return Err(WasiError::fault()),
+    };
+    let (bytes, data) = memory.data_and_store_mut(caller);
+    Ok((GuestMemory::new(bytes), data.wasi()))
+}
@@ -3,17 +3,21 @@
 
 pub mod cli;
 pub mod clocks;
+pub mod component;
+pub mod ctx;
 pub mod demo;
 pub mod error;
 pub mod filesystem;
 pub mod http;
 pub mod io;
+pub mod memory;
 pub mod preview1;
 pub mod preview2;
 pub mod random;
 pub mod sockets;
 pub mod types;
 
+pub use ctx::{WasiCtx, WasiView};
 pub use error::*;
 pub use preview1::*;
 pub use types::*;
@@ -1,8 +1,15 @@
 // WASI Preview 1 (legacy) implementation for Agave OS
 // This provides the original WASI snapshot_preview1 API for compatibility
+//
+// Every pointer argument is an offset into the caller's exported memory and
+// goes through `GuestMemory`, which fails with EFAULT when out of bounds.
 
+use super::error::*;
+use super::memory::{memory_and_ctx, GuestMemory};
 use super::types::*;
-use super::{cli, /*clocks,*/ filesystem, random};
+use super::{cli, clocks, filesystem, random, sockets, WasiView};
+use crate::sys::{error::AgaveError, ipc};
+use alloc::{format, string::String, vec::Vec};
 use wasmi::{Caller, Linker, Store};
 
 #[allow(dependency_on_unit_never_type_fallback)]
@@ -11,161 +18,107 @@ pub fn link_preview1_functions<T>(
     _store: &mut Store<T>,
 ) -> Result<(), wasmi::Error>
 where
-    T: 'static,
+    T: WasiView + 'static,
 {
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "environ_get",
-        |_caller: Caller<'_, T>, environ: i32, environ_buf: i32| -> i32 {
+        |mut caller: Caller<'_, T>, environ: i32, environ_buf: i32| -> i32 {
             log::debug!("environ_get({}, {})", environ, environ_buf);
-            // For demo, use a static environment list
-            let envs = ["PATH=/usr/bin", "HOME=/root", "USER=agave"];
-            let mut buf_offset = 0;
-            let mut ptr_offset = 0;
-            unsafe {
-                let environ_ptrs = environ as *mut i32;
-                let environ_buf_ptr = environ_buf as *mut u8;
-                for env in envs.iter() {
-                    let bytes = env.as_bytes();
-                    core::ptr::copy_nonoverlapping(
-                        bytes.as_ptr(),
-                        environ_buf_ptr.add(buf_offset),
-                        bytes.len(),
-                    );
-                    *environ_ptrs.add(ptr_offset) = environ_buf + buf_offset as i32;
-                    buf_offset += bytes.len();
-                    // Null terminator
-                    *environ_buf_ptr.add(buf_offset) = 0;
-                    buf_offset += 1;
-                    ptr_offset += 1;
-                }
-            }
-            ERRNO_SUCCESS as i32
+            errno(environ_get(&mut caller, environ as u32, environ_buf as u32))
         },
     )?;
 
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "environ_sizes_get",
-        |_caller: Caller<'_, T>, count_ptr: i32, buf_size_ptr: i32| -> i32 {
+        |mut caller: Caller<'_, T>, count_ptr: i32, buf_size_ptr: i32| -> i32 {
             log::debug!("environ_sizes_get({}, {})", count_ptr, buf_size_ptr);
-            let envs = ["PATH=/usr/bin", "HOME=/root", "USER=agave"];
-            let count = envs.len() as u32;
-            let buf_size = envs.iter().map(|e| e.len() + 1).sum::<usize>() as u32;
-            unsafe {
-                *(count_ptr as *mut u32) = count;
-                *(buf_size_ptr as *mut u32) = buf_size;
-            }
-            ERRNO_SUCCESS as i32
+            errno(environ_sizes_get(
+                &mut caller,
+                count_ptr as u32,
+                buf_size_ptr as u32,
+            ))
         },
     )?;
 
-
     // Register path_create_directory (directory creation)
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "path_create_directory",
-        |_caller: Caller<'_, T>, fd: i32, path_ptr: i32, path_len: i32| -> i32 {
+        |mut caller: Caller<'_, T>, fd: i32, path_ptr: i32, path_len: i32| -> i32 {
             log::debug!("path_create_directory({}, {}, {})", fd, path_ptr, path_len);
-            // Safety: path_ptr is a pointer to guest memory
-            let path_bytes = unsafe {
-                core::slice::from_raw_parts(path_ptr as *const u8, path_len as usize)
-            };
-            match core::str::from_utf8(path_bytes) {
-                Ok(path_str) => match filesystem::path_create_directory(fd as Fd, path_str) {
-                    Ok(()) => ERRNO_SUCCESS as i32,
-                    Err(e) => e.errno as i32,
-                },
-                Err(_) => ERRNO_INVAL as i32,
-            }
+            errno(with_path(&mut caller, path_ptr, path_len, |ctx, path| {
+                filesystem::path_create_directory(&ctx.fs, fd as Fd, path)
+            }))
         },
     )?;
-    // ...existing code...
+
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "args_get",
-        |_caller: Caller<'_, T>, argv: i32, argv_buf: i32| -> i32 {
+        |mut caller: Caller<'_, T>, argv: i32, argv_buf: i32| -> i32 {
             log::debug!("args_get({}, {})", argv, argv_buf);
-            match cli::args_get(argv as u32, argv_buf as u32) {
-                Ok(()) => ERRNO_SUCCESS as i32,
-                Err(e) => e.errno as i32,
-            }
+            errno(args_get(&mut caller, argv as u32, argv_buf as u32))
         },
     )?;
 
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "fd_read",
-        |_caller: Caller<'_, T>, fd: i32, iovs: i32, iovs_len: i32, nread_ptr: i32| -> i32 {
+        |mut caller: Caller<'_, T>, fd: i32, iovs: i32, iovs_len: i32, nread_ptr: i32| -> i32 {
             log::debug!("fd_read({}, {}, {}, {})", fd, iovs, iovs_len, nread_ptr);
-            let iovec = IOVec {
-                buf: iovs as u32,
-                buf_len: iovs_len as u32,
-            };
-            match filesystem::fd_read(fd as Fd, &[iovec]) {
-                Ok(nread) => {
-                    unsafe {
-                        let nread_ptr = nread_ptr as *mut u32;
-                        *nread_ptr = nread;
-                    }
-                    ERRNO_SUCCESS as i32
-                }
-                Err(e) => e.errno as i32,
-            }
+            errno(fd_read(
+                &mut caller,
+                fd as Fd,
+                iovs as u32,
+                iovs_len as u32,
+                nread_ptr as u32,
+            ))
         },
     )?;
 
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "random_get",
-        |_caller: Caller<'_, T>, buf: i32, buf_len: i32| -> i32 {
+        |mut caller: Caller<'_, T>, buf: i32, buf_len: i32| -> i32 {
             log::debug!("random_get({}, {})", buf, buf_len);
-            match random::get_random_bytes(buf_len as u64) {
-                Ok(_data) => ERRNO_SUCCESS as i32,
-                Err(e) => e.errno as i32,
-            }
+            errno(random_get(&mut caller, buf as u32, buf_len as u32))
         },
     )?;
 
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "fd_close",
-        |_caller: Caller<'_, T>, fd: i32| -> i32 {
+        |mut caller: Caller<'_, T>, fd: i32| -> i32 {
             log::debug!("fd_close({})", fd);
-            match filesystem::fd_close(fd as Fd) {
-                Ok(()) => ERRNO_SUCCESS as i32,
-                Err(e) => e.errno as i32,
+            let ctx = caller.data_mut().wasi();
+            if ctx.sockets.remove(&(fd as Fd)) {
+                return errno(sockets::close_socket(fd as Fd));
             }
+            errno(filesystem::fd_close(&mut ctx.fs, fd as Fd))
         },
     )?;
 
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "fd_write",
-        |_caller: Caller<'_, T>, fd: i32, iovs: i32, iovs_len: i32, nwritten_ptr: i32| -> i32 {
+        |mut caller: Caller<'_, T>, fd: i32, iovs: i32, iovs_len: i32, nwritten_ptr: i32| -> i32 {
             log::debug!("fd_write({}, {}, {}, {})", fd, iovs, iovs_len, nwritten_ptr);
-            let iovec = IOVec {
-                buf: iovs as u32,
-                buf_len: iovs_len as u32,
-            };
-            match filesystem::fd_write(fd as Fd, &[iovec]) {
-                Ok(nwritten) => {
-                    // Write nwritten to nwritten_ptr in WebAssembly memory
-                    unsafe {
-                        let nwritten_ptr = nwritten_ptr as *mut u32;
-                        *nwritten_ptr = nwritten;
-                    }
-                    ERRNO_SUCCESS as i32
-                }
-                Err(e) => e.errno as i32,
-            }
+            errno(fd_write(
+                &mut caller,
+                fd as Fd,
+                iovs as u32,
+                iovs_len as u32,
+                nwritten_ptr as u32,
+            ))
         },
     )?;
 
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "fd_readdir",
-        |_caller: Caller<'_, T>,
+        |mut caller: Caller<'_, T>,
          fd: i32,
          buf: i32,
          buf_len: i32,
@@ -180,29 +133,23 @@ where
                 cookie,
                 bufused_ptr
             );
-            let fd = fd as Fd;
-            let cookie = cookie as DirCookie;
-            let buf_slice =
-                unsafe { core::slice::from_raw_parts_mut(buf as *mut u8, buf_len as usize) };
-            match filesystem::fd_readdir(fd, buf_slice, cookie) {
-                Ok(bytes_used) => {
-                    unsafe {
-                        let bufused_ptr = bufused_ptr as *mut u32;
-                        *bufused_ptr = bytes_used as u32;
-                    }
-                    0 // ERRNO_SUCCESS
-                }
-                Err(e) => e.errno as i32,
-            }
+            errno(fd_readdir(
+                &mut caller,
+                fd as Fd,
+                buf as u32,
+                buf_len as u32,
+                cookie as DirCookie,
+                bufused_ptr as u32,
+            ))
         },
     )?;
 
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "proc_exit",
-        |_caller: Caller<'_, T>, exit_code: i32| {
+        |_caller: Caller<'_, T>, exit_code: i32| -> Result<(), wasmi::Error> {
             log::info!("proc_exit({})", exit_code);
-            cli::proc_exit(exit_code as ExitCode);
+            Err(cli::proc_exit(exit_code as ExitCode))
         },
     )?;
 
@@ -210,50 +157,32 @@ where
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "fd_fdstat_get",
-        |_caller: Caller<'_, T>, fd: i32, stat_ptr: i32| -> i32 {
+        |mut caller: Caller<'_, T>, fd: i32, stat_ptr: i32| -> i32 {
             log::debug!("fd_fdstat_get({}, {})", fd, stat_ptr);
-            match filesystem::fd_fdstat_get(fd as Fd) {
-                Ok(fdstat) => {
-                    // Write fdstat to stat_ptr in WebAssembly memory
-                    unsafe {
-                        let stat_ptr = stat_ptr as *mut FdStat;
-                        *stat_ptr = fdstat;
-                    }
-                    ERRNO_SUCCESS as i32
-                }
-                Err(e) => e.errno as i32,
-            }
+            errno(fd_fdstat_get(&mut caller, fd as Fd, stat_ptr as u32))
         },
     )?;
 
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "fd_prestat_get",
-        |_caller: Caller<'_, T>, fd: i32, prestat_ptr: i32| -> i32 {
+        |mut caller: Caller<'_, T>, fd: i32, prestat_ptr: i32| -> i32 {
             log::debug!("fd_prestat_get({}, {})", fd, prestat_ptr);
-            match filesystem::fd_prestat_get(fd as Fd) {
-                Ok(prestat) => {
-                    // Write prestat to prestat_ptr in WebAssembly memory
-                    unsafe {
-                        let prestat_ptr = prestat_ptr as *mut Prestat;
-                        *prestat_ptr = prestat;
-                    }
-                    ERRNO_SUCCESS as i32
-                }
-                Err(e) => e.errno as i32,
-            }
+            errno(fd_prestat_get(&mut caller, fd as Fd, prestat_ptr as u32))
         },
     )?;
 
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "fd_prestat_dir_name",
-        |_caller: Caller<'_, T>, fd: i32, path: i32, path_len: i32| -> i32 {
+        |mut caller: Caller<'_, T>, fd: i32, path: i32, path_len: i32| -> i32 {
             log::debug!("fd_prestat_dir_name({}, {}, {})", fd, path, path_len);
-            match filesystem::fd_prestat_dir_name(fd as Fd, path as u32, path_len as Size) {
-                Ok(()) => ERRNO_SUCCESS as i32,
-                Err(e) => e.errno as i32,
-            }
+            errno(fd_prestat_dir_name(
+                &mut caller,
+                fd as Fd,
+                path as u32,
+                path_len as Size,
+            ))
         },
     )?;
 
@@ -269,120 +198,106 @@ where
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "fd_filestat_get",
-        |_caller: Caller<'_, T>, fd: i32, filestat_ptr: i32| -> i32 {
+        |mut caller: Caller<'_, T>, fd: i32, filestat_ptr: i32| -> i32 {
             log::debug!("fd_filestat_get({}, {})", fd, filestat_ptr);
-            match filesystem::fd_filestat_get(fd as Fd) {
-                Ok(filestat) => {
-                    unsafe {
-                        let filestat_ptr = filestat_ptr as *mut FileStat;
-                        *filestat_ptr = filestat;
-                    }
-                    ERRNO_SUCCESS as i32
-                }
-                Err(e) => e.errno as i32,
-            }
+            errno(fd_filestat_get(&mut caller, fd as Fd, filestat_ptr as u32))
         },
     )?;
 
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "fd_filestat_set_size",
-        |_caller: Caller<'_, T>, fd: i32, size: i64| -> i32 {
+        |mut caller: Caller<'_, T>, fd: i32, size: i64| -> i32 {
             log::debug!("fd_filestat_set_size({}, {})", fd, size);
-            match filesystem::fd_filestat_set_size(fd as Fd, size as FileSize) {
-                Ok(()) => ERRNO_SUCCESS as i32,
-                Err(e) => e.errno as i32,
-            }
+            errno(filesystem::fd_filestat_set_size(
+                &mut caller.data_mut().wasi().fs,
+                fd as Fd,
+                size as FileSize,
+            ))
         },
     )?;
 
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "fd_fdstat_set_flags",
-        |_caller: Caller<'_, T>, fd: i32, flags: i32| -> i32 {
+        |mut caller: Caller<'_, T>, fd: i32, flags: i32| -> i32 {
             log::debug!("fd_fdstat_set_flags({}, {})", fd, flags);
-            match filesystem::fd_fdstat_set_flags(fd as Fd, flags as FdFlags) {
-                Ok(()) => ERRNO_SUCCESS as i32,
-                Err(e) => e.errno as i32,
-            }
+            errno(filesystem::fd_fdstat_set_flags(
+                &mut caller.data_mut().wasi().fs,
+                fd as Fd,
+                flags as FdFlags,
+            ))
         },
     )?;
 
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "fd_sync",
-        |_caller: Caller<'_, T>, fd: i32| -> i32 {
+        |mut caller: Caller<'_, T>, fd: i32| -> i32 {
             log::debug!("fd_sync({})", fd);
-            match filesystem::fd_sync(fd as Fd) {
-                Ok(()) => ERRNO_SUCCESS as i32,
-                Err(e) => e.errno as i32,
-            }
+            errno(filesystem::fd_sync(&caller.data_mut().wasi().fs, fd as Fd))
         },
     )?;
 
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "fd_datasync",
-        |_caller: Caller<'_, T>, fd: i32| -> i32 {
+        |mut caller: Caller<'_, T>, fd: i32| -> i32 {
             log::debug!("fd_datasync({})", fd);
-            match filesystem::fd_datasync(fd as Fd) {
-                Ok(()) => ERRNO_SUCCESS as i32,
-                Err(e) => e.errno as i32,
-            }
+            errno(filesystem::fd_datasync(
+                &caller.data_mut().wasi().fs,
+                fd as Fd,
+            ))
         },
     )?;
 
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "fd_allocate",
-        |_caller: Caller<'_, T>, fd: i32, offset: i64, len: i64| -> i32 {
+        |mut caller: Caller<'_, T>, fd: i32, offset: i64, len: i64| -> i32 {
             log::debug!("fd_allocate({}, {}, {})", fd, offset, len);
-            match filesystem::fd_allocate(fd as Fd, offset as FileSize, len as FileSize) {
-                Ok(()) => ERRNO_SUCCESS as i32,
-                Err(e) => e.errno as i32,
-            }
+            errno(filesystem::fd_allocate(
+                &mut caller.data_mut().wasi().fs,
+                fd as Fd,
+                offset as FileSize,
+                len as FileSize,
+            ))
         },
     )?;
 
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "fd_advise",
-        |_caller: Caller<'_, T>, fd: i32, offset: i64, len: i64, advice: i32| -> i32 {
+        |mut caller: Caller<'_, T>, fd: i32, offset: i64, len: i64, advice: i32| -> i32 {
             log::debug!("fd_advise({}, {}, {}, {})", fd, offset, len, advice);
-            match filesystem::fd_advise(
+            errno(filesystem::fd_advise(
+                &caller.data_mut().wasi().fs,
                 fd as Fd,
                 offset as FileSize,
                 len as FileSize,
                 advice as Advice,
-            ) {
-                Ok(()) => ERRNO_SUCCESS as i32,
-                Err(e) => e.errno as i32,
-            }
+            ))
         },
     )?;
 
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "fd_tell",
-        |_caller: Caller<'_, T>, fd: i32, offset_ptr: i32| -> i32 {
+        |mut caller: Caller<'_, T>, fd: i32, offset_ptr: i32| -> i32 {
             log::debug!("fd_tell({}, {})", fd, offset_ptr);
-            match filesystem::fd_tell(fd as Fd) {
-                Ok(offset) => {
-                    unsafe {
-                        let offset_ptr = offset_ptr as *mut FileSize;
-                        *offset_ptr = offset;
-                    }
-                    ERRNO_SUCCESS as i32
-                }
-                Err(e) => e.errno as i32,
-            }
+            errno(fd_tell(&mut caller, fd as Fd, offset_ptr as u32))
         },
     )?;
 
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "fd_seek",
-        |_caller: Caller<'_, T>, fd: i32, offset: i64, whence: i32, new_offset_ptr: i32| -> i32 {
+        |mut caller: Caller<'_, T>,
+         fd: i32,
+         offset: i64,
+         whence: i32,
+         new_offset_ptr: i32|
+         -> i32 {
             log::debug!(
                 "fd_seek({}, {}, {}, {})",
                 fd,
@@ -390,16 +305,13 @@ where
                 whence,
                 new_offset_ptr
             );
-            match filesystem::fd_seek(fd as Fd, offset as FileDelta, whence as Whence) {
-                Ok(new_offset) => {
-                    unsafe {
-                        let new_offset_ptr = new_offset_ptr as *mut FileSize;
-                        *new_offset_ptr = new_offset;
-                    }
-                    ERRNO_SUCCESS as i32
-                }
-                Err(e) => e.errno as i32,
-            }
+            errno(fd_seek(
+                &mut caller,
+                fd as Fd,
+                offset as FileDelta,
+                whence as Whence,
+                new_offset_ptr as u32,
+            ))
         },
     )?;
 
@@ -408,7 +320,7 @@ where
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "path_filestat_get",
-        |_caller: Caller<'_, T>,
+        |mut caller: Caller<'_, T>,
          fd: i32,
          flags: i32,
          path_ptr: i32,
@@ -423,29 +335,21 @@ where
                 path_len,
                 filestat_ptr
             );
-            let path = unsafe {
-                core::str::from_utf8_unchecked(core::slice::from_raw_parts(
-                    path_ptr as *const u8,
-                    path_len as usize,
-                ))
-            };
-            match filesystem::stat(fd as Fd, flags as u16, path) {
-                Ok(stat_val) => {
-                    unsafe {
-                        let filestat_ptr = filestat_ptr as *mut u64;
-                        *filestat_ptr = stat_val;
-                    }
-                    ERRNO_SUCCESS as i32
-                }
-                Err(e) => e.errno as i32,
-            }
+            errno(path_filestat_get(
+                &mut caller,
+                fd as Fd,
+                flags as u16,
+                path_ptr as u32,
+                path_len as u32,
+                filestat_ptr as u32,
+            ))
         },
     )?;
 
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "path_filestat_set_times",
-        |_caller: Caller<'_, T>,
+        |mut caller: Caller<'_, T>,
          fd: i32,
          flags: i32,
          path_ptr: i32,
@@ -464,23 +368,23 @@ where
                 mtim,
                 fst_flags
             );
-            let _path = unsafe {
-                core::str::from_utf8_unchecked(core::slice::from_raw_parts(
-                    path_ptr as *const u8,
-                    path_len as usize,
-                ))
-            };
-            match filesystem::set_times(fd as Fd, atim as u64, mtim as u64) {
-                Ok(()) => ERRNO_SUCCESS as i32,
-                Err(e) => e.errno as i32,
-            }
+            errno(with_path(&mut caller, path_ptr, path_len, |ctx, path| {
+                filesystem::path_filestat_set_times(
+                    &ctx.fs,
+                    fd as Fd,
+                    path,
+                    atim as Timestamp,
+                    mtim as Timestamp,
+                    fst_flags as FstFlags,
+                )
+            }))
         },
     )?;
 
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "path_link",
-        |_caller: Caller<'_, T>,
+        |mut caller: Caller<'_, T>,
          old_fd: i32,
          old_flags: i32,
          old_path_ptr: i32,
@@ -499,35 +403,21 @@ where
                 new_path_ptr,
                 new_path_len
             );
-            let old_path = unsafe {
-                core::str::from_utf8_unchecked(core::slice::from_raw_parts(
-                    old_path_ptr as *const u8,
-                    old_path_len as usize,
-                ))
-            };
-            let new_path = unsafe {
-                core::str::from_utf8_unchecked(core::slice::from_raw_parts(
-                    new_path_ptr as *const u8,
-                    new_path_len as usize,
-                ))
-            };
-            match filesystem::link(
-                old_fd as Fd,
-                old_flags as u16,
-                old_path,
-                new_fd as Fd,
-                new_path,
-            ) {
-                Ok(()) => ERRNO_SUCCESS as i32,
-                Err(e) => e.errno as i32,
-            }
+            errno(with_paths(
+                &mut caller,
+                (old_path_ptr, old_path_len),
+                (new_path_ptr, new_path_len),
+                |ctx, old_path, new_path| {
+                    filesystem::path_link(&ctx.fs, old_fd as Fd, old_path, new_fd as Fd, new_path)
+                },
+            ))
         },
     )?;
 
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "path_open",
-        |_caller: Caller<'_, T>,
+        |mut caller: Caller<'_, T>,
          fd: i32,
          dirflags: i32,
          path_ptr: i32,
@@ -550,37 +440,28 @@ where
                 fdflags,
                 opened_fd_ptr
             );
-            let path = unsafe {
-                core::str::from_utf8_unchecked(core::slice::from_raw_parts(
-                    path_ptr as *const u8,
-                    path_len as usize,
-                ))
-            };
-            match filesystem::path_open(
-                fd as Fd,
-                dirflags as LookupFlags,
-                path,
-                oflags as OFlags,
-                fs_rights_base as Rights,
-                fs_rights_inheriting as Rights,
-                fdflags as FdFlags,
-            ) {
-                Ok(opened_fd) => {
-                    unsafe {
-                        let opened_fd_ptr = opened_fd_ptr as *mut Fd;
-                        *opened_fd_ptr = opened_fd;
-                    }
-                    ERRNO_SUCCESS as i32
-                }
-                Err(e) => e.errno as i32,
-            }
+            let result = memory_and_ctx(&mut caller).and_then(|(mut memory, ctx)| {
+                let path = memory.read_str(path_ptr as u32, path_len as u32)?;
+                let opened_fd = filesystem::path_open(
+                    &mut ctx.fs,
+                    fd as Fd,
+                    dirflags as LookupFlags,
+                    path,
+                    oflags as OFlags,
+                    fs_rights_base as Rights,
+                    fs_rights_inheriting as Rights,
+                    fdflags as FdFlags,
+                )?;
+                memory.write_u32(opened_fd_ptr as u32, opened_fd)
+            });
+            errno(result)
         },
     )?;
 
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "path_readlink",
-        |_caller: Caller<'_, T>,
+        |mut caller: Caller<'_, T>,
          fd: i32,
          path_ptr: i32,
          path_len: i32,
@@ -597,50 +478,33 @@ where
                 buf_len,
                 nread_ptr
             );
-            let path = unsafe {
-                core::str::from_utf8_unchecked(core::slice::from_raw_parts(
-                    path_ptr as *const u8,
-                    path_len as usize,
-                ))
-            };
-            match filesystem::readlink_at(fd as Fd, path) {
-                Ok(target) => {
-                    let bytes = target.as_bytes();
-                    let n = bytes.len().min(buf_len as usize);
-                    unsafe {
-                        core::ptr::copy_nonoverlapping(bytes.as_ptr(), buf_ptr as *mut u8, n);
-                        let nread_ptr = nread_ptr as *mut u32;
-                        *nread_ptr = n as u32;
-                    }
-                    ERRNO_SUCCESS as i32
-                }
-                Err(e) => e.errno as i32,
-            }
+            let result = memory_and_ctx(&mut caller).and_then(|(mut memory, ctx)| {
+                let path = memory.read_str(path_ptr as u32, path_len as u32)?;
+                let target = filesystem::path_readlink(&ctx.fs, fd as Fd, path)?;
+                let bytes = target.as_bytes();
+                let n = bytes.len().min(buf_len as u32 as usize);
+                memory.write(buf_ptr as u32, &bytes[..n])?;
+                memory.write_u32(nread_ptr as u32, n as u32)
+            });
+            errno(result)
         },
     )?;
 
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "path_remove_directory",
-        |_caller: Caller<'_, T>, fd: i32, path_ptr: i32, path_len: i32| -> i32 {
+        |mut caller: Caller<'_, T>, fd: i32, path_ptr: i32, path_len: i32| -> i32 {
             log::debug!("path_remove_directory({}, {}, {})", fd, path_ptr, path_len);
-            let path = unsafe {
-                core::str::from_utf8_unchecked(core::slice::from_raw_parts(
-                    path_ptr as *const u8,
-                    path_len as usize,
-                ))
-            };
-            match filesystem::path_remove_directory(fd as Fd, path) {
-                Ok(()) => ERRNO_SUCCESS as i32,
-                Err(e) => e.errno as i32,
-            }
+            errno(with_path(&mut caller, path_ptr, path_len, |ctx, path| {
+                filesystem::path_remove_directory(&ctx.fs, fd as Fd, path)
+            }))
         },
     )?;
 
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "path_rename",
-        |_caller: Caller<'_, T>,
+        |mut caller: Caller<'_, T>,
          fd: i32,
          old_path_ptr: i32,
          old_path_len: i32,
@@ -657,29 +521,21 @@ where
                 new_path_ptr,
                 new_path_len
             );
-            let old_path = unsafe {
-                core::str::from_utf8_unchecked(core::slice::from_raw_parts(
-                    old_path_ptr as *const u8,
-                    old_path_len as usize,
-                ))
-            };
-            let new_path = unsafe {
-                core::str::from_utf8_unchecked(core::slice::from_raw_parts(
-                    new_path_ptr as *const u8,
-                    new_path_len as usize,
-                ))
-            };
-            match filesystem::rename_at(fd as Fd, old_path, new_fd as Fd, new_path) {
-                Ok(()) => ERRNO_SUCCESS as i32,
-                Err(e) => e.errno as i32,
-            }
+            errno(with_paths(
+                &mut caller,
+                (old_path_ptr, old_path_len),
+                (new_path_ptr, new_path_len),
+                |ctx, old_path, new_path| {
+                    filesystem::path_rename(&ctx.fs, fd as Fd, old_path, new_fd as Fd, new_path)
+                },
+            ))
         },
     )?;
 
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "path_symlink",
-        |_caller: Caller<'_, T>,
+        |mut caller: Caller<'_, T>,
          old_path_ptr: i32,
          old_path_len: i32,
          fd: i32,
@@ -694,41 +550,720 @@ where
                 new_path_ptr,
                 new_path_len
             );
-            let old_path = unsafe {
-                core::str::from_utf8_unchecked(core::slice::from_raw_parts(
-                    old_path_ptr as *const u8,
-                    old_path_len as usize,
-                ))
-            };
-            let new_path = unsafe {
-                core::str::from_utf8_unchecked(core::slice::from_raw_parts(
-                    new_path_ptr as *const u8,
-                    new_path_len as usize,
-                ))
-            };
-            match filesystem::symlink_at(fd as Fd, old_path, new_path) {
-                Ok(()) => ERRNO_SUCCESS as i32,
-                Err(e) => e.errno as i32,
-            }
+            errno(with_paths(
+                &mut caller,
+                (old_path_ptr, old_path_len),
+                (new_path_ptr, new_path_len),
+                |ctx, old_path, new_path| {
+                    filesystem::path_symlink(&ctx.fs, old_path, fd as Fd, new_path)
+                },
+            ))
         },
     )?;
 
     linker.func_wrap(
         "wasi_snapshot_preview1",
         "path_unlink_file",
-        |_caller: Caller<'_, T>, fd: i32, path_ptr: i32, path_len: i32| -> i32 {
+        |mut caller: Caller<'_, T>, fd: i32, path_ptr: i32, path_len: i32| -> i32 {
             log::debug!("path_unlink_file({}, {}, {})", fd, path_ptr, path_len);
-            let path = unsafe {
-                core::str::from_utf8_unchecked(core::slice::from_raw_parts(
-                    path_ptr as *const u8,
-                    path_len as usize,
-                ))
-            };
-            match filesystem::path_unlink_file(fd as Fd, path) {
-                Ok(()) => ERRNO_SUCCESS as i32,
-                Err(e) => e.errno as i32,
+            errno(with_path(&mut caller, path_ptr, path_len, |ctx, path| {
+                filesystem::path_unlink_file(&ctx.fs, fd as Fd, path)
+            }))
+        },
+    )?;
+    // --- Clocks, polling, positional I/O and sockets ---
+
+    linker.func_wrap(
+        "wasi_snapshot_preview1",
+        "args_sizes_get",
+        |mut caller: Caller<'_, T>, argc_ptr: i32, argv_buf_size_ptr: i32| -> i32 {
+            log::debug!("args_sizes_get({}, {})", argc_ptr, argv_buf_size_ptr);
+            errno(args_sizes_get(
+                &mut caller,
+                argc_ptr as u32,
+                argv_buf_size_ptr as u32,
+            ))
+        },
+    )?;
+
+    linker.func_wrap(
+        "wasi_snapshot_preview1",
+        "clock_res_get",
+        |mut caller: Caller<'_, T>, id: i32, resolution_ptr: i32| -> i32 {
+            log::debug!("clock_res_get({}, {})", id, resolution_ptr);
+            let result = memory_and_ctx(&mut caller).and_then(|(mut memory, _ctx)| {
+                let resolution = clocks::clock_res_get(id as Clockid)?;
+                memory.write_u64(resolution_ptr as u32, resolution)
+            });
+            errno(result)
+        },
+    )?;
+
+    linker.func_wrap(
+        "wasi_snapshot_preview1",
+        "clock_time_get",
+        |mut caller: Caller<'_, T>, id: i32, precision: i64, time_ptr: i32| -> i32 {
+            log::debug!("clock_time_get({}, {}, {})", id, precision, time_ptr);
+            let result = memory_and_ctx(&mut caller).and_then(|(mut memory, _ctx)| {
+                let time = clocks::clock_time_get(id as Clockid, precision as Timestamp)?;
+                memory.write_u64(time_ptr as u32, time)
+            });
+            errno(result)
+        },
+    )?;
+
+    linker.func_wrap(
+        "wasi_snapshot_preview1",
+        "poll_oneoff",
+        |mut caller: Caller<'_, T>,
+         in_ptr: i32,
+         out_ptr: i32,
+         nsubscriptions: i32,
+         nevents_ptr: i32|
+         -> Result<i32, wasmi::Error> {
+            log::debug!(
+                "poll_oneoff({}, {}, {}, {})",
+                in_ptr,
+                out_ptr,
+                nsubscriptions,
+                nevents_ptr
+            );
+            let result = poll_oneoff(
+                &mut caller,
+                in_ptr as u32,
+                out_ptr as u32,
+                nsubscriptions as u32,
+                nevents_ptr as u32,
+            );
+            match result {
+                // The app is resumed once the clock reaches the deadline
+                Ok(Some(sleep)) => Err(wasmi::Error::host(sleep)),
+                result => Ok(errno(result.map(|_| ()))),
             }
         },
     )?;
+
+    linker.func_wrap(
+        "wasi_snapshot_preview1",
+        "proc_raise",
+        |_caller: Caller<'_, T>, signal: i32| -> Result<i32, wasmi::Error> {
+            log::debug!("proc_raise({})", signal);
+            cli::proc_raise(signal as Signal)?;
+            Ok(ERRNO_SUCCESS as i32)
+        },
+    )?;
+
+    linker.func_wrap(
+        "wasi_snapshot_preview1",
+        "fd_pread",
+        |mut caller: Caller<'_, T>,
+         fd: i32,
+         iovs: i32,
+         iovs_len: i32,
+         offset: i64,
+         nread_ptr: i32|
+         -> i32 {
+            log::debug!(
+                "fd_pread({}, {}, {}, {}, {})",
+                fd,
+                iovs,
+                iovs_len,
+                offset,
+                nread_ptr
+            );
+            errno(fd_pread(
+                &mut caller,
+                fd as Fd,
+                iovs as u32,
+                iovs_len as u32,
+                offset as FileSize,
+                nread_ptr as u32,
+            ))
+        },
+    )?;
+
+    linker.func_wrap(
+        "wasi_snapshot_preview1",
+        "fd_pwrite",
+        |mut caller: Caller<'_, T>,
+         fd: i32,
+         iovs: i32,
+         iovs_len: i32,
+         offset: i64,
+         nwritten_ptr: i32|
+         -> i32 {
+            log::debug!(
+                "fd_pwrite({}, {}, {}, {}, {})",
+                fd,
+                iovs,
+                iovs_len,
+                offset,
+                nwritten_ptr
+            );
+            errno(fd_pwrite(
+                &mut caller,
+                fd as Fd,
+                iovs as u32,
+                iovs_len as u32,
+                offset as FileSize,
+                nwritten_ptr as u32,
+            ))
+        },
+    )?;
+
+    linker.func_wrap(
+        "wasi_snapshot_preview1",
+        "fd_renumber",
+        |mut caller: Caller<'_, T>, fd: i32, to: i32| -> i32 {
+            log::debug!("fd_renumber({}, {})", fd, to);
+            errno(filesystem::fd_renumber(
+                &mut caller.data_mut().wasi().fs,
+                fd as Fd,
+                to as Fd,
+            ))
+        },
+    )?;
+
+    linker.func_wrap(
+        "wasi_snapshot_preview1",
+        "fd_fdstat_set_rights",
+        |mut caller: Caller<'_, T>, fd: i32, rights_base: i64, rights_inheriting: i64| -> i32 {
+            log::debug!(
+                "fd_fdstat_set_rights({}, {}, {})",
+                fd,
+                rights_base,
+                rights_inheriting
+            );
+            errno(filesystem::fd_fdstat_set_rights(
+                &mut caller.data_mut().wasi().fs,
+                fd as Fd,
+                rights_base as Rights,
+                rights_inheriting as Rights,
+            ))
+        },
+    )?;
+
+    linker.func_wrap(
+        "wasi_snapshot_preview1",
+        "fd_filestat_set_times",
+        |mut caller: Caller<'_, T>, fd: i32, atim: i64, mtim: i64, fst_flags: i32| -> i32 {
+            log::debug!(
+                "fd_filestat_set_times({}, {}, {}, {})",
+                fd,
+                atim,
+                mtim,
+                fst_flags
+            );
+            errno(filesystem::fd_filestat_set_times(
+                &caller.data_mut().wasi().fs,
+                fd as Fd,
+                atim as Timestamp,
+                mtim as Timestamp,
+                fst_flags as FstFlags,
+            ))
+        },
+    )?;
+
+    linker.func_wrap(
+        "wasi_snapshot_preview1",
+        "sock_accept",
+        |mut caller: Caller<'_, T>, fd: i32, flags: i32, fd_ptr: i32| -> i32 {
+            log::debug!("sock_accept({}, {}, {})", fd, flags, fd_ptr);
+            let result = memory_and_ctx(&mut caller).and_then(|(mut memory, ctx)| {
+                ctx.check_inbound()?;
+                ctx.check_socket(fd as Fd)?;
+                let (client_fd, _address) = sockets::accept(fd as Fd)?.ok_or(WasiError::again())?;
+                ctx.sockets.insert(client_fd);
+                memory.write_u32(fd_ptr as u32, client_fd)
+            });
+            errno(result)
+        },
+    )?;
+
+    linker.func_wrap(
+        "wasi_snapshot_preview1",
+        "sock_recv",
+        |mut caller: Caller<'_, T>,
+         fd: i32,
+         ri_data: i32,
+         ri_data_len: i32,
+         ri_flags: i32,
+         ro_datalen_ptr: i32,
+         ro_flags_ptr: i32|
+         -> i32 {
+            log::debug!(
+                "sock_recv({}, {}, {}, {}, {}, {})",
+                fd,
+                ri_data,
+                ri_data_len,
+                ri_flags,
+                ro_datalen_ptr,
+                ro_flags_ptr
+            );
+            errno(sock_recv(
+                &mut caller,
+                fd as Fd,
+                ri_data as u32,
+                ri_data_len as u32,
+                ri_flags as RiFlags,
+                ro_datalen_ptr as u32,
+                ro_flags_ptr as u32,
+            ))
+        },
+    )?;
+
+    linker.func_wrap(
+        "wasi_snapshot_preview1",
+        "sock_send",
+        |mut caller: Caller<'_, T>,
+         fd: i32,
+         si_data: i32,
+         si_data_len: i32,
+         si_flags: i32,
+         so_datalen_ptr: i32|
+         -> i32 {
+            log::debug!(
+                "sock_send({}, {}, {}, {}, {})",
+                fd,
+                si_data,
+                si_data_len,
+                si_flags,
+                so_datalen_ptr
+            );
+            errno(sock_send(
+                &mut caller,
+                fd as Fd,
+                si_data as u32,
+                si_data_len as u32,
+                si_flags as SiFlags,
+                so_datalen_ptr as u32,
+            ))
+        },
+    )?;
+
+    linker.func_wrap(
+        "wasi_snapshot_preview1",
+        "sock_shutdown",
+        |mut caller: Caller<'_, T>, fd: i32, how: i32| -> i32 {
+            log::debug!("sock_shutdown({}, {})", fd, how);
+            let ctx = caller.data_mut().wasi();
+            let result = ctx
+                .check_inbound()
+                .and_then(|_| ctx.check_socket(fd as Fd))
+                .and_then(|_| sockets::shutdown(fd as Fd, how as SdFlags));
+            errno(result)
+        },
+    )?;
     Ok(())
 }
+
+// Convert a result into the errno returned to the guest
+fn errno(result: WasiResult<()>) -> i32 {
+    match result {
+        Ok(()) => ERRNO_SUCCESS as i32,
+        Err(e) => e.errno as i32,
+    }
+}
+
+// Run `f` with the UTF-8 path at `path_ptr`
+fn with_path<T: WasiView>(
+    caller: &mut Caller<'_, T>,
+    path_ptr: i32,
+    path_len: i32,
+    f: impl FnOnce(&mut super::WasiCtx, &str) -> WasiResult<()>,
+) -> WasiResult<()> {
+    let (memory, ctx) = memory_and_ctx(caller)?;
+    let path = memory.read_str(path_ptr as u32, path_len as u32)?;
+    f(ctx, path)
+}
+
+// Run `f` with the two UTF-8 paths of a link/rename style call
+fn with_paths<T: WasiView>(
+    caller: &mut Caller<'_, T>,
+    (old_ptr, old_len): (i32, i32),
+    (new_ptr, new_len): (i32, i32),
+    f: impl FnOnce(&mut super::WasiCtx, &str, &str) -> WasiResult<()>,
+) -> WasiResult<()> {
+    let (memory, ctx) = memory_and_ctx(caller)?;
+    let old_path = memory.read_str(old_ptr as u32, old_len as u32)?;
+    let new_path = memory.read_str(new_ptr as u32, new_len as u32)?;
+    f(ctx, old_path, new_path)
+}
+
+// Write a list of NUL-terminated strings and the pointers to them
+fn write_string_list<'a>(
+    memory: &mut GuestMemory<'_>,
+    list_ptr: u32,
+    buf_ptr: u32,
+    items: impl Iterator<Item = &'a [u8]>,
+) -> WasiResult<()> {
+    let mut offset = buf_ptr;
+    for (i, item) in items.enumerate() {
+        let len = item.len() as u32;
+        memory.write_u32(list_ptr + i as u32 * 4, offset)?;
+        memory.write(offset, item)?;
+        memory.write(offset + len, &[0])?;
+        offset += len + 1;
+    }
+    Ok(())
+}
+
+fn args_get<T: WasiView>(caller: &mut Caller<'_, T>, argv: u32, argv_buf: u32) -> WasiResult<()> {
+    let (mut memory, ctx) = memory_and_ctx(caller)?;
+    let args = ctx.args.iter().map(|arg| arg.as_bytes());
+    write_string_list(&mut memory, argv, argv_buf, args)
+}
+
+fn environ_get<T: WasiView>(
+    caller: &mut Caller<'_, T>,
+    environ: u32,
+    environ_buf: u32,
+) -> WasiResult<()> {
+    let (mut memory, ctx) = memory_and_ctx(caller)?;
+    let envs: Vec<String> = ctx
+        .env
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect();
+    write_string_list(
+        &mut memory,
+        environ,
+        environ_buf,
+        envs.iter().map(|env| env.as_bytes()),
+    )
+}
+
+fn environ_sizes_get<T: WasiView>(
+    caller: &mut Caller<'_, T>,
+    count_ptr: u32,
+    buf_size_ptr: u32,
+) -> WasiResult<()> {
+    let (mut memory, ctx) = memory_and_ctx(caller)?;
+    let (count, buf_size) = cli::environ_sizes_get(&ctx.env)?;
+    memory.write_u32(count_ptr, count)?;
+    memory.write_u32(buf_size_ptr, buf_size)
+}
+
+fn fd_read<T: WasiView>(
+    caller: &mut Caller<'_, T>,
+    fd: Fd,
+    iovs_ptr: u32,
+    iovs_len: u32,
+    nread_ptr: u32,
+) -> WasiResult<()> {
+    let (mut memory, ctx) = memory_and_ctx(caller)?;
+    let iovs = memory.read_iovecs(iovs_ptr, iovs_len)?;
+    let mut total = 0;
+    for iov in iovs {
+        let buf = memory.slice_mut(iov.buf, iov.buf_len)?;
+        // stdin reads as end of file unless a pipe is connected
+        let n = match (fd, ctx.stdio[0]) {
+            (0, Some(pipe)) => match ipc::pipe_read(pipe, buf) {
+                Err(AgaveError::WouldBlock) if total > 0 => 0,
+                result => result.map_err(pipe_error)? as u32,
+            },
+            (0, None) => 0,
+            _ => filesystem::fd_read(&mut ctx.fs, fd, buf)?,
+        };
+        total += n;
+        if n < iov.buf_len {
+            break;
+        }
+    }
+    memory.write_u32(nread_ptr, total)
+}
+
+fn fd_write<T: WasiView>(
+    caller: &mut Caller<'_, T>,
+    fd: Fd,
+    iovs_ptr: u32,
+    iovs_len: u32,
+    nwritten_ptr: u32,
+) -> WasiResult<()> {
+    let (mut memory, ctx) = memory_and_ctx(caller)?;
+    let iovs = memory.read_iovecs(iovs_ptr, iovs_len)?;
+    let mut total = 0;
+    for iov in iovs {
+        let data = memory.read(iov.buf, iov.buf_len)?;
+        let n = match (fd, ctx.stdio.get(fd as usize).copied().flatten()) {
+            (1 | 2, Some(pipe)) => match ipc::pipe_write(pipe, data) {
+                Err(AgaveError::WouldBlock) if total > 0 => 0,
+                result => result.map_err(pipe_error)? as u32,
+            },
+            // stdout and stderr go to the kernel log unless piped
+            (1 | 2, None) => {
+                log::info!("{}", String::from_utf8_lossy(data).trim_end());
+                iov.buf_len
+            }
+            _ => filesystem::fd_write(&mut ctx.fs, fd, data)?,
+        };
+        total += n;
+        if n < iov.buf_len {
+            break;
+        }
+    }
+    memory.write_u32(nwritten_ptr, total)
+}
+
+// Errno for a failed read or write on a stdio pipe
+fn pipe_error(err: AgaveError) -> WasiError {
+    match err {
+        AgaveError::WouldBlock => WasiError::again(),
+        AgaveError::BrokenPipe => WasiError::pipe(),
+        _ => WasiError::badf(),
+    }
+}
+
+fn random_get<T: WasiView>(caller: &mut Caller<'_, T>, buf: u32, buf_len: u32) -> WasiResult<()> {
+    let (mut memory, _ctx) = memory_and_ctx(caller)?;
+    let data = random::get_random_bytes(buf_len as u64)?;
+    memory.write(buf, &data)
+}
+
+fn fd_readdir<T: WasiView>(
+    caller: &mut Caller<'_, T>,
+    fd: Fd,
+    buf_ptr: u32,
+    buf_len: u32,
+    cookie: DirCookie,
+    bufused_ptr: u32,
+) -> WasiResult<()> {
+    let (mut memory, ctx) = memory_and_ctx(caller)?;
+    let buf = memory.slice_mut(buf_ptr, buf_len)?;
+    let used = filesystem::fd_readdir(&ctx.fs, fd, buf, cookie)?;
+    memory.write_u32(bufused_ptr, used)
+}
+
+fn fd_fdstat_get<T: WasiView>(caller: &mut Caller<'_, T>, fd: Fd, stat_ptr: u32) -> WasiResult<()> {
+    let (mut memory, ctx) = memory_and_ctx(caller)?;
+    let fdstat = filesystem::fd_fdstat_get(&ctx.fs, fd)?;
+    memory.write(stat_ptr, &fdstat)
+}
+
+fn fd_prestat_get<T: WasiView>(
+    caller: &mut Caller<'_, T>,
+    fd: Fd,
+    prestat_ptr: u32,
+) -> WasiResult<()> {
+    let (mut memory, ctx) = memory_and_ctx(caller)?;
+    let prestat = filesystem::fd_prestat_get(&ctx.fs, fd)?;
+    // prestat is { tag: u8, pad: [u8; 3], pr_name_len: u32 }
+    let name_len = unsafe { prestat.u.dir.pr_name_len };
+    memory.write(prestat_ptr, &[prestat.tag, 0, 0, 0])?;
+    memory.write_u32(prestat_ptr + 4, name_len)
+}
+
+fn fd_prestat_dir_name<T: WasiView>(
+    caller: &mut Caller<'_, T>,
+    fd: Fd,
+    path_ptr: u32,
+    path_len: Size,
+) -> WasiResult<()> {
+    let (mut memory, ctx) = memory_and_ctx(caller)?;
+    let name = filesystem::fd_prestat_dir_name(&ctx.fs, fd, path_len)?;
+    memory.write(path_ptr, name.as_bytes())
+}
+
+fn fd_filestat_get<T: WasiView>(
+    caller: &mut Caller<'_, T>,
+    fd: Fd,
+    filestat_ptr: u32,
+) -> WasiResult<()> {
+    let (mut memory, ctx) = memory_and_ctx(caller)?;
+    let filestat = filesystem::fd_filestat_get(&ctx.fs, fd)?;
+    memory.write(filestat_ptr, &filestat)
+}
+
+fn fd_tell<T: WasiView>(caller: &mut Caller<'_, T>, fd: Fd, offset_ptr: u32) -> WasiResult<()> {
+    let (mut memory, ctx) = memory_and_ctx(caller)?;
+    let offset = filesystem::fd_tell(&ctx.fs, fd)?;
+    memory.write_u64(offset_ptr, offset)
+}
+
+fn fd_seek<T: WasiView>(
+    caller: &mut Caller<'_, T>,
+    fd: Fd,
+    offset: FileDelta,
+    whence: Whence,
+    new_offset_ptr: u32,
+) -> WasiResult<()> {
+    let (mut memory, ctx) = memory_and_ctx(caller)?;
+    let new_offset = filesystem::fd_seek(&mut ctx.fs, fd, offset, whence)?;
+    memory.write_u64(new_offset_ptr, new_offset)
+}
+
+fn path_filestat_get<T: WasiView>(
+    caller: &mut Caller<'_, T>,
+    fd: Fd,
+    _flags: u16,
+    path_ptr: u32,
+    path_len: u32,
+    filestat_ptr: u32,
+) -> WasiResult<()> {
+    let (mut memory, ctx) = memory_and_ctx(caller)?;
+    let path = memory.read_str(path_ptr, path_len)?;
+    let filestat = filesystem::path_filestat_get(&ctx.fs, fd, path)?;
+    memory.write(filestat_ptr, &filestat)
+}
+
+fn args_sizes_get<T: WasiView>(
+    caller: &mut Caller<'_, T>,
+    argc_ptr: u32,
+    argv_buf_size_ptr: u32,
+) -> WasiResult<()> {
+    let (mut memory, ctx) = memory_and_ctx(caller)?;
+    let (argc, argv_buf_size) = cli::args_sizes_get(&ctx.args)?;
+    memory.write_u32(argc_ptr, argc)?;
+    memory.write_u32(argv_buf_size_ptr, argv_buf_size)
+}
+
+fn fd_pread<T: WasiView>(
+    caller: &mut Caller<'_, T>,
+    fd: Fd,
+    iovs_ptr: u32,
+    iovs_len: u32,
+    offset: FileSize,
+    nread_ptr: u32,
+) -> WasiResult<()> {
+    let (mut memory, ctx) = memory_and_ctx(caller)?;
+    let iovs = memory.read_iovecs(iovs_ptr, iovs_len)?;
+    let mut total = 0;
+    for iov in iovs {
+        let buf = memory.slice_mut(iov.buf, iov.buf_len)?;
+        let n = filesystem::fd_pread(&ctx.fs, fd, buf, offset + total as FileSize)?;
+        total += n;
+        if n < iov.buf_len {
+            break;
+        }
+    }
+    memory.write_u32(nread_ptr, total)
+}
+
+fn fd_pwrite<T: WasiView>(
+    caller: &mut Caller<'_, T>,
+    fd: Fd,
+    iovs_ptr: u32,
+    iovs_len: u32,
+    offset: FileSize,
+    nwritten_ptr: u32,
+) -> WasiResult<()> {
+    let (mut memory, ctx) = memory_and_ctx(caller)?;
+    let iovs = memory.read_iovecs(iovs_ptr, iovs_len)?;
+    let mut total = 0;
+    for iov in iovs {
+        let data = memory.read(iov.buf, iov.buf_len)?;
+        total += filesystem::fd_pwrite(&mut ctx.fs, fd, data, offset + total as FileSize)?;
+    }
+    memory.write_u32(nwritten_ptr, total)
+}
+
+// Report the ready ones of `nsubscriptions` subscriptions
+//
+// Descriptors never block in this VFS, so fd subscriptions are always ready.
+// Without any, and with no clock subscription due yet, the events of the
+// earliest ones are written and the `Sleep` returned for the caller to wait
+// out before the app sees them.
+fn poll_oneoff<T: WasiView>(
+    caller: &mut Caller<'_, T>,
+    in_ptr: u32,
+    out_ptr: u32,
+    nsubscriptions: u32,
+    nevents_ptr: u32,
+) -> WasiResult<Option<clocks::Sleep>> {
+    if nsubscriptions == 0 {
+        return Err(WasiError::inval());
+    }
+    let (mut memory, _ctx) = memory_and_ctx(caller)?;
+    let now = clocks::clock_time_get(CLOCKID_MONOTONIC, 0)?;
+
+    // (userdata, event type, clock deadline)
+    let mut subscriptions = Vec::new();
+    for i in 0..nsubscriptions {
+        let sub = in_ptr + i * SUBSCRIPTION_SIZE;
+        let userdata = memory.read_u64(sub)?;
+        let tag = memory.read(sub + 8, 1)?[0];
+        let deadline = match tag {
+            EVENTTYPE_CLOCK => {
+                let timeout = memory.read_u64(sub + 24)?;
+                let flags = memory.read(sub + 40, 2)?;
+                if u16::from_le_bytes([flags[0], flags[1]])
+                    & SUBCLOCKFLAGS_SUBSCRIPTION_CLOCK_ABSTIME
+                    != 0
+                {
+                    timeout
+                } else {
+                    now.saturating_add(timeout)
+                }
+            }
+            EVENTTYPE_FD_READ | EVENTTYPE_FD_WRITE => now,
+            _ => return Err(WasiError::inval()),
+        };
+        subscriptions.push((userdata, tag, deadline));
+    }
+
+    let due = subscriptions
+        .iter()
+        .map(|&(_, _, deadline)| deadline)
+        .min()
+        .unwrap_or(now)
+        .max(now);
+
+    let mut nevents = 0;
+    for &(userdata, tag, deadline) in &subscriptions {
+        if deadline > due {
+            continue;
+        }
+        // event is { userdata: u64, error: u16, type: u8, fd_readwrite: { nbytes: u64, flags: u16 } }
+        let mut event = [0u8; EVENT_SIZE as usize];
+        event[0..8].copy_from_slice(&userdata.to_le_bytes());
+        event[10] = tag;
+        memory.write(out_ptr + nevents * EVENT_SIZE, &event)?;
+        nevents += 1;
+    }
+    memory.write_u32(nevents_ptr, nevents)?;
+    Ok((due > now).then_some(clocks::Sleep { until: due }))
+}
+
+fn sock_recv<T: WasiView>(
+    caller: &mut Caller<'_, T>,
+    fd: Fd,
+    iovs_ptr: u32,
+    iovs_len: u32,
+    flags: RiFlags,
+    datalen_ptr: u32,
+    out_flags_ptr: u32,
+) -> WasiResult<()> {
+    let (mut memory, ctx) = memory_and_ctx(caller)?;
+    ctx.check_socket(fd)?;
+    let iovs = memory.read_iovecs(iovs_ptr, iovs_len)?;
+    let mut total = 0;
+    for iov in iovs {
+        let data = sockets::recv(fd, iov.buf_len as usize, flags)?;
+        memory.write(iov.buf, &data)?;
+        total += data.len() as u32;
+        if (data.len() as u32) < iov.buf_len {
+            break;
+        }
+    }
+    memory.write_u32(datalen_ptr, total)?;
+    memory.write(out_flags_ptr, &0u16.to_le_bytes())
+}
+
+fn sock_send<T: WasiView>(
+    caller: &mut Caller<'_, T>,
+    fd: Fd,
+    iovs_ptr: u32,
+    iovs_len: u32,
+    flags: SiFlags,
+    datalen_ptr: u32,
+) -> WasiResult<()> {
+    let (mut memory, ctx) = memory_and_ctx(caller)?;
+    ctx.check_socket(fd)?;
+    let iovs = memory.read_iovecs(iovs_ptr, iovs_len)?;
+    let mut total = 0;
+    for iov in iovs {
+        let data = memory.read(iov.buf, iov.buf_len)?;
+        total += sockets::send(fd, data, flags)? as u32;
+    }
+    memory.write_u32(datalen_ptr, total)
+}
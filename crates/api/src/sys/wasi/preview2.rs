@@ -1,23 +1,82 @@
 // WASI Preview 2 (component model) implementation for Agave OS
 // This provides the modern WASI APIs using the component model
 
+use super::component::{lower_bytes, lower_strings, lower_words, write_words};
 use super::error::*;
+use super::memory::{memory_and_ctx, GuestMemory};
 use super::types::*;
-use super::{cli, clocks, filesystem, http, io, random, sockets};
+use super::{cli, clocks, filesystem, http, io, random, sockets, WasiCtx, WasiView};
+use crate::sys::{error::AgaveError, ipc};
 use alloc::string::String;
 use alloc::vec::Vec;
 use sockets::{IpAddressFamily, IpSocketAddress, Network, TcpSocket};
-
-// Helper function to convert WasiError to u32 ErrorCode
-fn wasi_error_to_error_code(_err: WasiError) -> u32 {
-    // For now, convert all errors to a generic error code
-    // In a full implementation, this would map specific error types
-    1u32
+use spin::Mutex;
+use wasmi::{Caller, Linker};
+
+// Map a WasiError onto the `error-code` enum of wasi:filesystem/types
+pub(super) fn wasi_error_to_error_code(err: WasiError) -> ErrorCode {
+    match err.errno {
+        ERRNO_ACCES | ERRNO_NOTCAPABLE => 0, // access
+        ERRNO_AGAIN => 1,                    // would-block
+        ERRNO_ALREADY => 2,                  // already
+        ERRNO_BADF => 3,                     // bad-descriptor
+        ERRNO_BUSY => 4,                     // busy
+        ERRNO_DEADLK => 5,                   // deadlock
+        ERRNO_DQUOT => 6,                    // quota
+        ERRNO_EXIST => 7,                    // exist
+        ERRNO_FBIG => 8,                     // file-too-large
+        ERRNO_ILSEQ => 9,                    // illegal-byte-sequence
+        ERRNO_INPROGRESS => 10,              // in-progress
+        ERRNO_INTR => 11,                    // interrupted
+        ERRNO_INVAL | ERRNO_FAULT => 12,     // invalid
+        ERRNO_ISDIR => 14,                   // is-directory
+        ERRNO_LOOP => 15,                    // loop
+        ERRNO_MLINK => 16,                   // too-many-links
+        ERRNO_MSGSIZE => 17,                 // message-size
+        ERRNO_NAMETOOLONG => 18,             // name-too-long
+        ERRNO_NODEV => 19,                   // no-device
+        ERRNO_NOENT => 20,                   // no-entry
+        ERRNO_NOLCK => 21,                   // no-lock
+        ERRNO_NOMEM => 22,                   // insufficient-memory
+        ERRNO_NOSPC => 23,                   // insufficient-space
+        ERRNO_NOTDIR => 24,                  // not-directory
+        ERRNO_NOTEMPTY => 25,                // not-empty
+        ERRNO_NOTRECOVERABLE => 26,          // not-recoverable
+        ERRNO_NOTSUP | ERRNO_NOSYS => 27,    // unsupported
+        ERRNO_NOTTY => 28,                   // no-tty
+        ERRNO_NXIO => 29,                    // no-such-device
+        ERRNO_OVERFLOW => 30,                // overflow
+        ERRNO_PERM => 31,                    // not-permitted
+        ERRNO_PIPE => 32,                    // pipe
+        ERRNO_ROFS => 33,                    // read-only
+        ERRNO_SPIPE => 34,                   // invalid-seek
+        ERRNO_TXTBSY => 35,                  // text-file-busy
+        ERRNO_XDEV => 36,                    // cross-device
+        _ => 13,                             // io
+    }
 }
 
-// Helper function to convert WasiError to StreamError
-fn wasi_error_to_stream_error(_err: WasiError) -> StreamError {
-    StreamError::Closed
+// Map a WasiError onto the `error-code` enum of wasi:sockets/network
+pub(super) fn wasi_error_to_socket_error_code(err: WasiError) -> ErrorCode {
+    match err.errno {
+        ERRNO_ACCES | ERRNO_PERM | ERRNO_NOTCAPABLE => 1, // access-denied
+        ERRNO_NOTSUP | ERRNO_NOSYS | ERRNO_AFNOSUPPORT => 2, // not-supported
+        ERRNO_INVAL | ERRNO_FAULT => 3,                   // invalid-argument
+        ERRNO_NOMEM | ERRNO_NOBUFS => 4,                  // out-of-memory
+        ERRNO_TIMEDOUT => 5,                              // timeout
+        ERRNO_ALREADY => 6,                               // concurrency-conflict
+        ERRNO_AGAIN | ERRNO_INPROGRESS => 8,              // would-block
+        ERRNO_BADF | ERRNO_NOTCONN | ERRNO_ISCONN => 9,   // invalid-state
+        ERRNO_MFILE | ERRNO_NFILE => 10,                  // new-socket-limit
+        ERRNO_ADDRNOTAVAIL => 11,                         // address-not-bindable
+        ERRNO_ADDRINUSE => 12,                            // address-in-use
+        ERRNO_HOSTUNREACH | ERRNO_NETUNREACH | ERRNO_NETDOWN => 13, // remote-unreachable
+        ERRNO_CONNREFUSED => 14,                          // connection-refused
+        ERRNO_CONNRESET => 15,                            // connection-reset
+        ERRNO_CONNABORTED => 16,                          // connection-aborted
+        ERRNO_MSGSIZE => 17,                              // datagram-too-large
+        _ => 0,                                           // unknown
+    }
 }
 
 // Additional helper functions for various error conversion patterns
@@ -59,6 +118,7 @@ fn unit_result_convert(result: Result<(), WasiError>) -> Result<(), u32> {
 pub type Instant = u64;
 pub type Datetime = u64;
 pub type StreamStatus = u8;
+const STREAM_OPEN: StreamStatus = 0;
 pub type ErrorCode = u32;
 pub type Descriptor = u32;
 pub type DescriptorFlags = u32;
@@ -68,7 +128,7 @@ pub type DirectoryEntryStream = u32;
 pub type Fields = u32;
 pub type PathFlags = u16;
 pub type OpenFlags = u16;
-pub type DescriptorStat = u64;
+pub type DescriptorStat = FileStat;
 pub type MetadataHashValue = u64;
 
 // HTTP types
@@ -99,11 +159,15 @@ pub type ResponseOutparam = u32;
 pub type StatusCode = u16;
 
 // Component model exports
-pub struct Component;
+pub struct Component {
+    ctx: Mutex<WasiCtx>,
+}
 
 impl Component {
     pub fn new() -> Self {
-        Self
+        Self {
+            ctx: Mutex::new(WasiCtx::new(Vec::new())),
+        }
     }
 
     // WIT bindings for wasi:clocks/monotonic-clock@0.2.0
@@ -154,13 +218,14 @@ impl Component {
         random::insecure_random_bytes(len)
     }
 
-    // WIT bindings for wasi:io/streams@0.2.0
+    // WIT bindings for wasi:io/streams@0.2.0; the streams are the app's own
     pub fn io_read(
         &self,
         this: InputStream,
         len: u64,
     ) -> Result<(Vec<u8>, StreamStatus), StreamError> {
-        io::read(this, len).map_err(wasi_error_to_stream_error)
+        let data = self.ctx.lock().streams.read(this, len)?;
+        Ok((data, STREAM_OPEN))
     }
 
     pub fn io_blocking_read(
@@ -168,11 +233,12 @@ impl Component {
         this: InputStream,
         len: u64,
     ) -> Result<(Vec<u8>, StreamStatus), StreamError> {
-        io::blocking_read(this, len).map_err(wasi_error_to_stream_error)
+        self.io_read(this, len)
     }
 
     pub fn io_skip(&self, this: InputStream, len: u64) -> Result<(u64, StreamStatus), StreamError> {
-        io::skip(this, len).map_err(wasi_error_to_stream_error)
+        let skipped = self.ctx.lock().streams.skip(this, len)?;
+        Ok((skipped, STREAM_OPEN))
     }
 
     pub fn io_blocking_skip(
@@ -180,23 +246,23 @@ impl Component {
         this: InputStream,
         len: u64,
     ) -> Result<(u64, StreamStatus), StreamError> {
-        io::blocking_skip(this, len).map_err(wasi_error_to_stream_error)
+        self.io_skip(this, len)
     }
 
     pub fn io_subscribe(&self, this: InputStream) -> Pollable {
-        io::subscribe_to_input_stream(this)
+        self.ctx.lock().streams.subscribe_input(this)
     }
 
     pub fn io_drop_input_stream(&self, this: InputStream) {
-        io::drop_input_stream(this)
+        self.ctx.lock().streams.remove_input_stream(this)
     }
 
     pub fn io_check_write(&self, this: OutputStream) -> Result<u64, StreamError> {
-        io::check_write(this).map_err(wasi_error_to_stream_error)
+        self.ctx.lock().streams.check_write(this)
     }
 
     pub fn io_write(&self, this: OutputStream, contents: Vec<u8>) -> Result<(), StreamError> {
-        io::write(this, &contents).map_err(wasi_error_to_stream_error)
+        self.ctx.lock().streams.write(this, contents)
     }
 
     pub fn io_blocking_write_and_flush(
@@ -204,23 +270,25 @@ impl Component {
         this: OutputStream,
         contents: Vec<u8>,
     ) -> Result<(), StreamError> {
-        io::blocking_write_and_flush(this, &contents).map_err(wasi_error_to_stream_error)
+        let streams = &mut self.ctx.lock().streams;
+        streams.write(this, contents)?;
+        streams.flush(this)
     }
 
     pub fn io_flush(&self, this: OutputStream) -> Result<(), StreamError> {
-        io::flush(this).map_err(wasi_error_to_stream_error)
+        self.ctx.lock().streams.flush(this)
     }
 
     pub fn io_blocking_flush(&self, this: OutputStream) -> Result<(), StreamError> {
-        io::blocking_flush(this).map_err(wasi_error_to_stream_error)
+        self.io_flush(this)
     }
 
     pub fn io_subscribe_output(&self, this: OutputStream) -> Pollable {
-        io::subscribe_to_output_stream(this)
+        self.ctx.lock().streams.subscribe_output(this)
     }
 
     pub fn io_drop_output_stream(&self, this: OutputStream) {
-        io::drop_output_stream(this)
+        self.ctx.lock().streams.remove_output_stream(this)
     }
 
     // WIT bindings for wasi:filesystem/types@0.2.0
@@ -229,7 +297,9 @@ impl Component {
         this: Descriptor,
         offset: FileSize,
     ) -> Result<InputStream, ErrorCode> {
-        filesystem::read_via_stream(this, offset).map_err(wasi_error_to_error_code)
+        let ctx = &mut *self.ctx.lock();
+        filesystem::read_via_stream(&mut ctx.fs, &mut ctx.streams, this, offset)
+            .map_err(wasi_error_to_error_code)
     }
 
     pub fn filesystem_write_via_stream(
@@ -237,14 +307,18 @@ impl Component {
         this: Descriptor,
         offset: FileSize,
     ) -> Result<OutputStream, ErrorCode> {
-        filesystem::write_via_stream(this, offset).map_err(wasi_error_to_error_code)
+        let ctx = &mut *self.ctx.lock();
+        filesystem::write_via_stream(&ctx.fs, &mut ctx.streams, this, offset)
+            .map_err(wasi_error_to_error_code)
     }
 
     pub fn filesystem_append_via_stream(
         &self,
         this: Descriptor,
     ) -> Result<OutputStream, ErrorCode> {
-        filesystem::append_via_stream(this).map_err(wasi_error_to_error_code)
+        let ctx = &mut *self.ctx.lock();
+        filesystem::append_via_stream(&ctx.fs, &mut ctx.streams, this)
+            .map_err(wasi_error_to_error_code)
     }
 
     pub fn filesystem_advise(
@@ -254,23 +328,37 @@ impl Component {
         length: FileSize,
         advice: Advice,
     ) -> Result<(), ErrorCode> {
-        filesystem::advise(this, offset, length, advice).map_err(wasi_error_to_error_code)
+        filesystem::fd_advise(&self.ctx.lock().fs, this, offset, length, advice)
+            .map_err(wasi_error_to_error_code)
     }
 
     pub fn filesystem_sync_data(&self, this: Descriptor) -> Result<(), ErrorCode> {
-        filesystem::sync_data(this).map_err(wasi_error_to_error_code)
+        filesystem::fd_datasync(&self.ctx.lock().fs, this).map_err(wasi_error_to_error_code)
     }
 
     pub fn filesystem_get_flags(&self, this: Descriptor) -> Result<DescriptorFlags, ErrorCode> {
-        filesystem::get_flags(this).map_err(wasi_error_to_error_code)
+        let fdstat = filesystem::fd_fdstat_get(&self.ctx.lock().fs, this)
+            .map_err(wasi_error_to_error_code)?;
+        let rights = Rights::from_le_bytes(fdstat[8..16].try_into().unwrap());
+        let mut flags = 0;
+        if rights & RIGHTS_FD_READ != 0 {
+            flags |= 1;
+        }
+        if rights & RIGHTS_FD_WRITE != 0 {
+            flags |= 2;
+        }
+        Ok(flags)
     }
 
     pub fn filesystem_get_type(&self, this: Descriptor) -> Result<DescriptorType, ErrorCode> {
-        filesystem::get_type(this).map_err(wasi_error_to_error_code)
+        filesystem::fd_fdstat_get(&self.ctx.lock().fs, this)
+            .map(|fdstat| descriptor_type(fdstat[0]))
+            .map_err(wasi_error_to_error_code)
     }
 
     pub fn filesystem_set_size(&self, this: Descriptor, size: FileSize) -> Result<(), ErrorCode> {
-        filesystem::set_size(this, size).map_err(wasi_error_to_error_code)
+        filesystem::fd_filestat_set_size(&mut self.ctx.lock().fs, this, size)
+            .map_err(wasi_error_to_error_code)
     }
 
     pub fn filesystem_set_times(
@@ -279,8 +367,14 @@ impl Component {
         data_access_timestamp: NewTimestamp,
         data_modification_timestamp: NewTimestamp,
     ) -> Result<(), ErrorCode> {
-        filesystem::set_times(this, data_access_timestamp, data_modification_timestamp)
-            .map_err(wasi_error_to_error_code)
+        filesystem::fd_filestat_set_times(
+            &self.ctx.lock().fs,
+            this,
+            data_access_timestamp,
+            data_modification_timestamp,
+            FSTFLAGS_ATIM | FSTFLAGS_MTIM,
+        )
+        .map_err(wasi_error_to_error_code)
     }
 
     pub fn filesystem_read(
@@ -289,7 +383,12 @@ impl Component {
         length: FileSize,
         offset: FileSize,
     ) -> Result<(Vec<u8>, bool), ErrorCode> {
-        filesystem::read(this, length, offset).map_err(wasi_error_to_error_code)
+        let length = length.min(MAX_READ);
+        let mut buf = alloc::vec![0; length as usize];
+        let n = filesystem::fd_pread(&self.ctx.lock().fs, this, &mut buf, offset)
+            .map_err(wasi_error_to_error_code)?;
+        buf.truncate(n as usize);
+        Ok((buf, (n as FileSize) < length))
     }
 
     pub fn filesystem_write(
@@ -298,18 +397,21 @@ impl Component {
         buffer: Vec<u8>,
         offset: FileSize,
     ) -> Result<FileSize, ErrorCode> {
-        filesystem::write(this, &buffer, offset).map_err(wasi_error_to_error_code)
+        filesystem::fd_pwrite(&mut self.ctx.lock().fs, this, &buffer, offset)
+            .map(|n| n as FileSize)
+            .map_err(wasi_error_to_error_code)
     }
 
     pub fn filesystem_read_directory(
         &self,
-        this: Descriptor,
+        _this: Descriptor,
     ) -> Result<DirectoryEntryStream, ErrorCode> {
-        filesystem::read_directory(this).map_err(wasi_error_to_error_code)
+        // There are no directory entry streams yet
+        Err(wasi_error_to_error_code(WasiError::notsup()))
     }
 
     pub fn filesystem_sync(&self, this: Descriptor) -> Result<(), ErrorCode> {
-        filesystem::sync(this).map_err(wasi_error_to_error_code)
+        filesystem::fd_sync(&self.ctx.lock().fs, this).map_err(wasi_error_to_error_code)
     }
 
     pub fn filesystem_create_directory_at(
@@ -317,16 +419,18 @@ impl Component {
         this: Descriptor,
         path: String,
     ) -> Result<(), ErrorCode> {
-        filesystem::create_directory_at(this, &path).map_err(wasi_error_to_error_code)
+        filesystem::path_create_directory(&self.ctx.lock().fs, this, &path)
+            .map_err(wasi_error_to_error_code)
     }
 
     pub fn filesystem_stat(
         &self,
         this: Descriptor,
-        path_flags: PathFlags,
+        _path_flags: PathFlags,
         path: String,
     ) -> Result<DescriptorStat, ErrorCode> {
-        filesystem::stat(this, path_flags, &path).map_err(wasi_error_to_error_code)
+        filesystem::path_filestat_get(&self.ctx.lock().fs, this, &path)
+            .map_err(wasi_error_to_error_code)
     }
 
     pub fn filesystem_stat_open_directory(
@@ -335,33 +439,44 @@ impl Component {
         path_flags: PathFlags,
         path: String,
     ) -> Result<Descriptor, ErrorCode> {
-        filesystem::stat_open_directory(this, path_flags, &path).map_err(wasi_error_to_error_code)
+        // open-flags `directory`, descriptor-flags `read`
+        self.filesystem_open_at(this, path_flags, path, 0x2, 1)
     }
 
     pub fn filesystem_link(
         &self,
         this: Descriptor,
-        old_path_flags: PathFlags,
+        _old_path_flags: PathFlags,
         old_path: String,
         new_descriptor: Descriptor,
         new_path: String,
     ) -> Result<(), ErrorCode> {
-        filesystem::link(this, old_path_flags, &old_path, new_descriptor, &new_path)
+        let ctx = self.ctx.lock();
+        filesystem::path_link(&ctx.fs, this, &old_path, new_descriptor, &new_path)
             .map_err(wasi_error_to_error_code)
     }
 
     pub fn filesystem_open_at(
         &self,
         this: Descriptor,
-        _path_flags: PathFlags,
+        path_flags: PathFlags,
         path: String,
         open_flags: OpenFlags,
         flags: DescriptorFlags,
     ) -> Result<Descriptor, ErrorCode> {
-        match filesystem::open_at(this, &path, open_flags as u32, flags as u32) {
-            Ok((fd, _)) => Ok(fd),
-            Err(e) => Err(ErrorCode::from(e.errno)),
-        }
+        // open-flags share their bits with the preview1 oflags
+        let rights = descriptor_rights(flags);
+        filesystem::path_open(
+            &mut self.ctx.lock().fs,
+            this,
+            path_flags as LookupFlags,
+            &path,
+            open_flags as OFlags,
+            rights,
+            rights,
+            0,
+        )
+        .map_err(wasi_error_to_error_code)
     }
 
     pub fn filesystem_readlink_at(
@@ -369,7 +484,8 @@ impl Component {
         this: Descriptor,
         path: String,
     ) -> Result<String, ErrorCode> {
-        filesystem::readlink_at(this, &path).map_err(wasi_error_to_error_code)
+        filesystem::path_readlink(&self.ctx.lock().fs, this, &path)
+            .map_err(wasi_error_to_error_code)
     }
 
     pub fn filesystem_remove_directory_at(
@@ -377,7 +493,8 @@ impl Component {
         this: Descriptor,
         path: String,
     ) -> Result<(), ErrorCode> {
-        filesystem::remove_directory_at(this, &path).map_err(wasi_error_to_error_code)
+        filesystem::path_remove_directory(&self.ctx.lock().fs, this, &path)
+            .map_err(wasi_error_to_error_code)
     }
 
     pub fn filesystem_rename_at(
@@ -387,7 +504,8 @@ impl Component {
         new_descriptor: Descriptor,
         new_path: String,
     ) -> Result<(), ErrorCode> {
-        filesystem::rename_at(this, &old_path, new_descriptor, &new_path)
+        let ctx = self.ctx.lock();
+        filesystem::path_rename(&ctx.fs, this, &old_path, new_descriptor, &new_path)
             .map_err(wasi_error_to_error_code)
     }
 
@@ -397,7 +515,8 @@ impl Component {
         old_path: String,
         new_path: String,
     ) -> Result<(), ErrorCode> {
-        filesystem::symlink_at(this, &old_path, &new_path).map_err(wasi_error_to_error_code)
+        filesystem::path_symlink(&self.ctx.lock().fs, &old_path, this, &new_path)
+            .map_err(wasi_error_to_error_code)
     }
 
     pub fn filesystem_unlink_file_at(
@@ -405,31 +524,33 @@ impl Component {
         this: Descriptor,
         path: String,
     ) -> Result<(), ErrorCode> {
-        filesystem::unlink_file_at(this, &path).map_err(wasi_error_to_error_code)
+        filesystem::path_unlink_file(&self.ctx.lock().fs, this, &path)
+            .map_err(wasi_error_to_error_code)
     }
 
     pub fn filesystem_is_same_object(&self, this: Descriptor, other: Descriptor) -> bool {
-        filesystem::is_same_object(this, other).unwrap_or(false)
+        filesystem::is_same_object(&self.ctx.lock().fs, this, other).unwrap_or(false)
     }
 
     pub fn filesystem_metadata_hash(
         &self,
         this: Descriptor,
     ) -> Result<MetadataHashValue, ErrorCode> {
-        filesystem::metadata_hash(this).map_err(wasi_error_to_error_code)
+        filesystem::metadata_hash(&self.ctx.lock().fs, this).map_err(wasi_error_to_error_code)
     }
 
     pub fn filesystem_metadata_hash_at(
         &self,
         this: Descriptor,
-        path_flags: PathFlags,
+        _path_flags: PathFlags,
         path: String,
     ) -> Result<MetadataHashValue, ErrorCode> {
-        filesystem::metadata_hash_at(this, path_flags, &path).map_err(wasi_error_to_error_code)
+        filesystem::metadata_hash_at(&self.ctx.lock().fs, this, &path)
+            .map_err(wasi_error_to_error_code)
     }
 
     pub fn filesystem_drop_descriptor(&self, this: Descriptor) {
-        let _ = filesystem::drop_descriptor(this);
+        let _ = filesystem::fd_close(&mut self.ctx.lock().fs, this);
     }
 
     // WIT bindings for wasi:sockets/network@0.2.0
@@ -449,11 +570,11 @@ impl Component {
         network: Network,
         local_address: IpSocketAddress,
     ) -> Result<(), ErrorCode> {
-        sockets::start_bind(this, network, local_address).map_err(wasi_error_to_error_code)
+        sockets::start_bind(this, network, local_address).map_err(wasi_error_to_socket_error_code)
     }
 
     pub fn sockets_finish_bind(&self, this: TcpSocket) -> Result<(), ErrorCode> {
-        sockets::finish_bind(this).map_err(wasi_error_to_error_code)
+        sockets::finish_bind(this).map_err(wasi_error_to_socket_error_code)
     }
 
     pub fn sockets_start_connect(
@@ -462,37 +583,38 @@ impl Component {
         network: Network,
         remote_address: IpSocketAddress,
     ) -> Result<(), ErrorCode> {
-        sockets::start_connect(this, network, remote_address).map_err(wasi_error_to_error_code)
+        sockets::start_connect(this, network, remote_address)
+            .map_err(wasi_error_to_socket_error_code)
     }
 
     pub fn sockets_finish_connect(
         &self,
         this: TcpSocket,
     ) -> Result<(InputStream, OutputStream), ErrorCode> {
-        sockets::finish_connect(this).map_err(wasi_error_to_error_code)
+        sockets::finish_connect(this).map_err(wasi_error_to_socket_error_code)
     }
 
     pub fn sockets_start_listen(&self, this: TcpSocket) -> Result<(), ErrorCode> {
-        sockets::start_listen(this).map_err(wasi_error_to_error_code)
+        sockets::start_listen(this).map_err(wasi_error_to_socket_error_code)
     }
 
     pub fn sockets_finish_listen(&self, this: TcpSocket) -> Result<(), ErrorCode> {
-        sockets::finish_listen(this).map_err(wasi_error_to_error_code)
+        sockets::finish_listen(this).map_err(wasi_error_to_socket_error_code)
     }
 
     pub fn sockets_accept(
         &self,
         this: TcpSocket,
     ) -> Result<Option<(TcpSocket, InputStream, OutputStream)>, ErrorCode> {
-        sockets::accept_tcp(this).map_err(wasi_error_to_error_code)
+        sockets::accept_tcp(this).map_err(wasi_error_to_socket_error_code)
     }
 
     pub fn sockets_local_address(&self, this: TcpSocket) -> Result<IpSocketAddress, ErrorCode> {
-        sockets::local_address(this).map_err(wasi_error_to_error_code)
+        sockets::local_address(this).map_err(wasi_error_to_socket_error_code)
     }
 
     pub fn sockets_remote_address(&self, this: TcpSocket) -> Result<IpSocketAddress, ErrorCode> {
-        sockets::remote_address(this).map_err(wasi_error_to_error_code)
+        sockets::remote_address(this).map_err(wasi_error_to_socket_error_code)
     }
 
     pub fn sockets_is_listening(&self, this: TcpSocket) -> bool {
@@ -508,11 +630,11 @@ impl Component {
         this: TcpSocket,
         value: u64,
     ) -> Result<(), ErrorCode> {
-        sockets::set_listen_backlog_size(this, value).map_err(wasi_error_to_error_code)
+        sockets::set_listen_backlog_size(this, value).map_err(wasi_error_to_socket_error_code)
     }
 
     pub fn sockets_keep_alive_enabled(&self, this: TcpSocket) -> Result<bool, ErrorCode> {
-        sockets::keep_alive_enabled(this).map_err(wasi_error_to_error_code)
+        sockets::keep_alive_enabled(this).map_err(wasi_error_to_socket_error_code)
     }
 
     pub fn sockets_set_keep_alive_enabled(
@@ -520,7 +642,7 @@ impl Component {
         this: TcpSocket,
         value: bool,
     ) -> Result<(), ErrorCode> {
-        sockets::set_keep_alive_enabled(this, value).map_err(wasi_error_to_error_code)
+        sockets::set_keep_alive_enabled(this, value).map_err(wasi_error_to_socket_error_code)
     }
 
     pub fn sockets_keep_alive_idle_time(&self, this: TcpSocket) -> Result<WasiDuration, ErrorCode> {
@@ -558,7 +680,7 @@ impl Component {
     }
 
     pub fn sockets_keep_alive_count(&self, this: TcpSocket) -> Result<u32, ErrorCode> {
-        sockets::keep_alive_count(this).map_err(wasi_error_to_error_code)
+        sockets::keep_alive_count(this).map_err(wasi_error_to_socket_error_code)
     }
 
     pub fn sockets_set_keep_alive_count(
@@ -566,19 +688,19 @@ impl Component {
         this: TcpSocket,
         value: u32,
     ) -> Result<(), ErrorCode> {
-        sockets::set_keep_alive_count(this, value).map_err(wasi_error_to_error_code)
+        sockets::set_keep_alive_count(this, value).map_err(wasi_error_to_socket_error_code)
     }
 
     pub fn sockets_hop_limit(&self, this: TcpSocket) -> Result<u8, ErrorCode> {
-        sockets::hop_limit(this).map_err(wasi_error_to_error_code)
+        sockets::hop_limit(this).map_err(wasi_error_to_socket_error_code)
     }
 
     pub fn sockets_set_hop_limit(&self, this: TcpSocket, value: u8) -> Result<(), ErrorCode> {
-        sockets::set_hop_limit(this, value).map_err(wasi_error_to_error_code)
+        sockets::set_hop_limit(this, value).map_err(wasi_error_to_socket_error_code)
     }
 
     pub fn sockets_receive_buffer_size(&self, this: TcpSocket) -> Result<u64, ErrorCode> {
-        sockets::receive_buffer_size(this).map_err(wasi_error_to_error_code)
+        sockets::receive_buffer_size(this).map_err(wasi_error_to_socket_error_code)
     }
 
     pub fn sockets_set_receive_buffer_size(
@@ -586,11 +708,11 @@ impl Component {
         this: TcpSocket,
         value: u64,
     ) -> Result<(), ErrorCode> {
-        sockets::set_receive_buffer_size(this, value).map_err(wasi_error_to_error_code)
+        sockets::set_receive_buffer_size(this, value).map_err(wasi_error_to_socket_error_code)
     }
 
     pub fn sockets_send_buffer_size(&self, this: TcpSocket) -> Result<u64, ErrorCode> {
-        sockets::send_buffer_size(this).map_err(wasi_error_to_error_code)
+        sockets::send_buffer_size(this).map_err(wasi_error_to_socket_error_code)
     }
 
     pub fn sockets_set_send_buffer_size(
@@ -598,7 +720,7 @@ impl Component {
         this: TcpSocket,
         value: u64,
     ) -> Result<(), ErrorCode> {
-        sockets::set_send_buffer_size(this, value).map_err(wasi_error_to_error_code)
+        sockets::set_send_buffer_size(this, value).map_err(wasi_error_to_socket_error_code)
     }
 
     pub fn sockets_subscribe(&self, this: TcpSocket) -> Pollable {
@@ -610,7 +732,7 @@ impl Component {
         this: TcpSocket,
         shutdown_type: ShutdownType,
     ) -> Result<(), ErrorCode> {
-        sockets::shutdown_tcp(this, shutdown_type).map_err(wasi_error_to_error_code)
+        sockets::shutdown_tcp(this, shutdown_type).map_err(wasi_error_to_socket_error_code)
     }
 
     pub fn sockets_drop_tcp_socket(&self, this: TcpSocket) {
@@ -631,7 +753,7 @@ impl Component {
     }
 
     // WIT bindings for wasi:cli/exit@0.2.0
-    pub fn cli_exit(&self, status: Result<(), ()>) -> ! {
+    pub fn cli_exit(&self, status: Result<(), ()>) -> wasmi::Error {
         match status {
             Ok(()) => cli::exit_with_code(0),
             Err(()) => cli::exit_with_code(1),
@@ -869,3 +991,728 @@ impl Default for Component {
         Self::new()
     }
 }
+
+// Interfaces linked for component program modules
+const CLI_ENVIRONMENT: &str = "wasi:cli/environment@0.2.0";
+const CLI_EXIT: &str = "wasi:cli/exit@0.2.0";
+const CLI_STDIN: &str = "wasi:cli/stdin@0.2.0";
+const CLI_STDOUT: &str = "wasi:cli/stdout@0.2.0";
+const CLI_STDERR: &str = "wasi:cli/stderr@0.2.0";
+const CLOCKS_MONOTONIC: &str = "wasi:clocks/monotonic-clock@0.2.0";
+const CLOCKS_WALL: &str = "wasi:clocks/wall-clock@0.2.0";
+const RANDOM: &str = "wasi:random/random@0.2.0";
+const RANDOM_INSECURE: &str = "wasi:random/insecure@0.2.0";
+const RANDOM_INSECURE_SEED: &str = "wasi:random/insecure-seed@0.2.0";
+const IO_ERROR: &str = "wasi:io/error@0.2.0";
+const IO_POLL: &str = "wasi:io/poll@0.2.0";
+const IO_STREAMS: &str = "wasi:io/streams@0.2.0";
+const FILESYSTEM_PREOPENS: &str = "wasi:filesystem/preopens@0.2.0";
+const FILESYSTEM_TYPES: &str = "wasi:filesystem/types@0.2.0";
+
+// Largest single `descriptor.read` served at once
+const MAX_READ: u64 = 64 * 1024;
+
+/// Link the preview2 interfaces a component's program module can import,
+/// lowered to core functions with the canonical ABI. Results that do not fit
+/// in one core value are stored at the trailing `retptr` argument.
+pub fn link_preview2_functions<T>(linker: &mut Linker<T>) -> Result<(), wasmi::Error>
+where
+    T: WasiView + 'static,
+{
+    // wasi:cli
+    linker.func_wrap(
+        CLI_ENVIRONMENT,
+        "get-environment",
+        |mut caller: Caller<'_, T>, retptr: i32| -> Result<(), wasmi::Error> {
+            trap(get_environment(&mut caller, retptr as u32))
+        },
+    )?;
+    linker.func_wrap(
+        CLI_ENVIRONMENT,
+        "get-arguments",
+        |mut caller: Caller<'_, T>, retptr: i32| -> Result<(), wasmi::Error> {
+            let args = caller.data_mut().wasi().args.clone();
+            let args: Vec<&[u8]> = args.iter().map(|arg| arg.as_bytes()).collect();
+            trap(lower_strings(&mut caller, &args).and_then(|(ptr, len)| {
+                let (mut memory, _ctx) = memory_and_ctx(&mut caller)?;
+                write_words(&mut memory, retptr as u32, &[ptr, len])
+            }))
+        },
+    )?;
+    linker.func_wrap(
+        CLI_ENVIRONMENT,
+        "initial-cwd",
+        |mut caller: Caller<'_, T>, retptr: i32| -> Result<(), wasmi::Error> {
+            let cwd = cli::initial_cwd().map_err(trap_error)?;
+            trap(
+                lower_bytes(&mut caller, cwd.as_bytes()).and_then(|(ptr, len)| {
+                    let (mut memory, _ctx) = memory_and_ctx(&mut caller)?;
+                    write_words(&mut memory, retptr as u32, &[1, ptr, len])
+                }),
+            )
+        },
+    )?;
+    linker.func_wrap(
+        CLI_EXIT,
+        "exit",
+        |_caller: Caller<'_, T>, status: i32| -> Result<(), wasmi::Error> {
+            Err(cli::proc_exit(if status == 0 { 0 } else { 1 }))
+        },
+    )?;
+    // The standard streams are the app's `stdio`; see `io::STDIN`
+    linker.func_wrap(CLI_STDIN, "get-stdin", |_caller: Caller<'_, T>| -> i32 {
+        io::STDIN as i32
+    })?;
+    linker.func_wrap(CLI_STDOUT, "get-stdout", |_caller: Caller<'_, T>| -> i32 {
+        io::STDOUT as i32
+    })?;
+    linker.func_wrap(CLI_STDERR, "get-stderr", |_caller: Caller<'_, T>| -> i32 {
+        io::STDERR as i32
+    })?;
+
+    // wasi:clocks
+    linker.func_wrap(
+        CLOCKS_MONOTONIC,
+        "now",
+        |_caller: Caller<'_, T>| -> Result<i64, wasmi::Error> {
+            Ok(clocks::monotonic_now().map_err(trap_error)? as i64)
+        },
+    )?;
+    linker.func_wrap(
+        CLOCKS_MONOTONIC,
+        "resolution",
+        |_caller: Caller<'_, T>| -> Result<i64, wasmi::Error> {
+            Ok(clocks::monotonic_resolution().map_err(trap_error)? as i64)
+        },
+    )?;
+    linker.func_wrap(
+        CLOCKS_MONOTONIC,
+        "subscribe-instant",
+        |_caller: Caller<'_, T>, when: i64| -> i32 {
+            clocks::subscribe_monotonic_clock(when as Instant, true) as i32
+        },
+    )?;
+    linker.func_wrap(
+        CLOCKS_MONOTONIC,
+        "subscribe-duration",
+        |_caller: Caller<'_, T>, duration: i64| -> i32 {
+            clocks::subscribe_duration(duration as WasiDuration) as i32
+        },
+    )?;
+    linker.func_wrap(
+        CLOCKS_WALL,
+        "now",
+        |mut caller: Caller<'_, T>, retptr: i32| -> Result<(), wasmi::Error> {
+            let now = clocks::wall_now().map_err(trap_error)?;
+            trap(store_datetime(&mut caller, retptr as u32, now))
+        },
+    )?;
+    linker.func_wrap(
+        CLOCKS_WALL,
+        "resolution",
+        |mut caller: Caller<'_, T>, retptr: i32| -> Result<(), wasmi::Error> {
+            let resolution = clocks::wall_resolution().map_err(trap_error)?;
+            trap(store_datetime(&mut caller, retptr as u32, resolution))
+        },
+    )?;
+
+    // wasi:random
+    linker.func_wrap(
+        RANDOM,
+        "get-random-bytes",
+        |mut caller: Caller<'_, T>, len: i64, retptr: i32| -> Result<(), wasmi::Error> {
+            let bytes = random::get_random_bytes(len as u64).map_err(trap_error)?;
+            trap(store_list(&mut caller, retptr as u32, &bytes))
+        },
+    )?;
+    linker.func_wrap(
+        RANDOM,
+        "get-random-u64",
+        |_caller: Caller<'_, T>| -> Result<i64, wasmi::Error> {
+            Ok(random::get_random_u64().map_err(trap_error)? as i64)
+        },
+    )?;
+    linker.func_wrap(
+        RANDOM_INSECURE,
+        "get-insecure-random-bytes",
+        |mut caller: Caller<'_, T>, len: i64, retptr: i32| -> Result<(), wasmi::Error> {
+            let bytes = random::insecure_random_bytes(len as u64).map_err(trap_error)?;
+            trap(store_list(&mut caller, retptr as u32, &bytes))
+        },
+    )?;
+    linker.func_wrap(
+        RANDOM_INSECURE,
+        "get-insecure-random-u64",
+        |_caller: Caller<'_, T>| -> Result<i64, wasmi::Error> {
+            Ok(random::insecure_random().map_err(trap_error)? as i64)
+        },
+    )?;
+    linker.func_wrap(
+        RANDOM_INSECURE_SEED,
+        "insecure-seed",
+        |mut caller: Caller<'_, T>, retptr: i32| -> Result<(), wasmi::Error> {
+            let low = random::insecure_random().map_err(trap_error)?;
+            let high = random::insecure_random().map_err(trap_error)?;
+            trap(memory_and_ctx(&mut caller).and_then(|(mut memory, _ctx)| {
+                memory.write_u64(retptr as u32, low)?;
+                memory.write_u64(retptr as u32 + 8, high)
+            }))
+        },
+    )?;
+
+    // wasi:io
+    linker.func_wrap(
+        IO_ERROR,
+        "[resource-drop]error",
+        |_caller: Caller<'_, T>, _error: i32| {},
+    )?;
+    linker.func_wrap(
+        IO_POLL,
+        "[resource-drop]pollable",
+        |_caller: Caller<'_, T>, pollable: i32| io::drop_pollable(pollable as Pollable),
+    )?;
+    linker.func_wrap(
+        IO_POLL,
+        "[method]pollable.ready",
+        |_caller: Caller<'_, T>, pollable: i32| -> i32 {
+            io::pollable_ready(pollable as Pollable) as i32
+        },
+    )?;
+    linker.func_wrap(
+        IO_POLL,
+        "[method]pollable.block",
+        |_caller: Caller<'_, T>, pollable: i32| io::pollable_block(pollable as Pollable),
+    )?;
+    linker.func_wrap(
+        IO_POLL,
+        "poll",
+        |mut caller: Caller<'_, T>, ptr: i32, len: i32, retptr: i32| -> Result<(), wasmi::Error> {
+            trap(poll(&mut caller, ptr as u32, len as u32, retptr as u32))
+        },
+    )?;
+    linker.func_wrap(
+        IO_STREAMS,
+        "[resource-drop]input-stream",
+        |mut caller: Caller<'_, T>, stream: i32| {
+            let streams = &mut caller.data_mut().wasi().streams;
+            streams.remove_input_stream(stream as InputStream)
+        },
+    )?;
+    linker.func_wrap(
+        IO_STREAMS,
+        "[resource-drop]output-stream",
+        |mut caller: Caller<'_, T>, stream: i32| {
+            let streams = &mut caller.data_mut().wasi().streams;
+            streams.remove_output_stream(stream as OutputStream)
+        },
+    )?;
+    for name in [
+        "[method]input-stream.read",
+        "[method]input-stream.blocking-read",
+    ] {
+        linker.func_wrap(
+            IO_STREAMS,
+            name,
+            |mut caller: Caller<'_, T>,
+             stream: i32,
+             len: i64,
+             retptr: i32|
+             -> Result<(), wasmi::Error> {
+                trap(input_stream_read(
+                    &mut caller,
+                    stream as InputStream,
+                    len as u64,
+                    retptr as u32,
+                ))
+            },
+        )?;
+    }
+    linker.func_wrap(
+        IO_STREAMS,
+        "[method]input-stream.subscribe",
+        |mut caller: Caller<'_, T>, stream: i32| -> i32 {
+            let ctx = caller.data_mut().wasi();
+            let pollable = match stream as InputStream {
+                io::STDIN => io::POLLABLES.lock().create_pollable(ctx.stdio[0].is_some()),
+                stream => ctx.streams.subscribe_input(stream),
+            };
+            pollable as i32
+        },
+    )?;
+    linker.func_wrap(
+        IO_STREAMS,
+        "[method]output-stream.check-write",
+        |mut caller: Caller<'_, T>, stream: i32, retptr: i32| -> Result<(), wasmi::Error> {
+            let stream = stream as OutputStream;
+            trap(memory_and_ctx(&mut caller).and_then(|(mut memory, ctx)| {
+                let result = if is_stdio(stream) {
+                    Ok(MAX_READ)
+                } else {
+                    ctx.streams.check_write(stream)
+                };
+                let result = result.map(u64::to_le_bytes);
+                store_result(&mut memory, retptr as u32, 8, stream_result(&result))
+            }))
+        },
+    )?;
+    for name in [
+        "[method]output-stream.write",
+        "[method]output-stream.blocking-write-and-flush",
+    ] {
+        linker.func_wrap(
+            IO_STREAMS,
+            name,
+            |mut caller: Caller<'_, T>,
+             stream: i32,
+             ptr: i32,
+             len: i32,
+             retptr: i32|
+             -> Result<(), wasmi::Error> {
+                trap(output_stream_write(
+                    &mut caller,
+                    stream as OutputStream,
+                    ptr as u32,
+                    len as u32,
+                    retptr as u32,
+                ))
+            },
+        )?;
+    }
+    for name in [
+        "[method]output-stream.flush",
+        "[method]output-stream.blocking-flush",
+    ] {
+        linker.func_wrap(
+            IO_STREAMS,
+            name,
+            |mut caller: Caller<'_, T>, stream: i32, retptr: i32| -> Result<(), wasmi::Error> {
+                let stream = stream as OutputStream;
+                trap(memory_and_ctx(&mut caller).and_then(|(mut memory, ctx)| {
+                    let result = if is_stdio(stream) {
+                        Ok([])
+                    } else {
+                        ctx.streams.flush(stream).map(|()| [])
+                    };
+                    store_result(&mut memory, retptr as u32, 4, stream_result(&result))
+                }))
+            },
+        )?;
+    }
+    linker.func_wrap(
+        IO_STREAMS,
+        "[method]output-stream.subscribe",
+        |mut caller: Caller<'_, T>, stream: i32| -> i32 {
+            let stream = stream as OutputStream;
+            let pollable = if is_stdio(stream) {
+                io::POLLABLES.lock().create_pollable(true)
+            } else {
+                caller.data_mut().wasi().streams.subscribe_output(stream)
+            };
+            pollable as i32
+        },
+    )?;
+
+    // wasi:filesystem
+    linker.func_wrap(
+        FILESYSTEM_PREOPENS,
+        "get-directories",
+        |mut caller: Caller<'_, T>, retptr: i32| -> Result<(), wasmi::Error> {
+            trap(get_directories(&mut caller, retptr as u32))
+        },
+    )?;
+    linker.func_wrap(
+        FILESYSTEM_TYPES,
+        "[resource-drop]descriptor",
+        |mut caller: Caller<'_, T>, fd: i32| {
+            // Preopens are not in the open file table and stay open
+            let _ = filesystem::fd_close(&mut caller.data_mut().wasi().fs, fd as Fd);
+        },
+    )?;
+    linker.func_wrap(
+        FILESYSTEM_TYPES,
+        "[method]descriptor.read-via-stream",
+        |mut caller: Caller<'_, T>,
+         fd: i32,
+         offset: i64,
+         retptr: i32|
+         -> Result<(), wasmi::Error> {
+            trap(memory_and_ctx(&mut caller).and_then(|(mut memory, ctx)| {
+                let result = filesystem::read_via_stream(
+                    &mut ctx.fs,
+                    &mut ctx.streams,
+                    fd as Fd,
+                    offset as FileSize,
+                );
+                store_fs_result(&mut memory, retptr as u32, 4, result.map(u32::to_le_bytes))
+            }))
+        },
+    )?;
+    linker.func_wrap(
+        FILESYSTEM_TYPES,
+        "[method]descriptor.write-via-stream",
+        |mut caller: Caller<'_, T>,
+         fd: i32,
+         offset: i64,
+         retptr: i32|
+         -> Result<(), wasmi::Error> {
+            trap(memory_and_ctx(&mut caller).and_then(|(mut memory, ctx)| {
+                let result = filesystem::write_via_stream(
+                    &ctx.fs,
+                    &mut ctx.streams,
+                    fd as Fd,
+                    offset as FileSize,
+                );
+                store_fs_result(&mut memory, retptr as u32, 4, result.map(u32::to_le_bytes))
+            }))
+        },
+    )?;
+    linker.func_wrap(
+        FILESYSTEM_TYPES,
+        "[method]descriptor.append-via-stream",
+        |mut caller: Caller<'_, T>, fd: i32, retptr: i32| -> Result<(), wasmi::Error> {
+            trap(memory_and_ctx(&mut caller).and_then(|(mut memory, ctx)| {
+                let result = filesystem::append_via_stream(&ctx.fs, &mut ctx.streams, fd as Fd);
+                store_fs_result(&mut memory, retptr as u32, 4, result.map(u32::to_le_bytes))
+            }))
+        },
+    )?;
+    linker.func_wrap(
+        FILESYSTEM_TYPES,
+        "[method]descriptor.get-type",
+        |mut caller: Caller<'_, T>, fd: i32, retptr: i32| -> Result<(), wasmi::Error> {
+            trap(memory_and_ctx(&mut caller).and_then(|(mut memory, ctx)| {
+                let fd = fd as Fd;
+                let result = if ctx.fs.preopens().any(|(preopen, _)| preopen == fd) {
+                    Ok(FILETYPE_DIRECTORY)
+                } else {
+                    filesystem::fd_fdstat_get(&ctx.fs, fd).map(|fdstat| fdstat[0])
+                };
+                let result = result.map(|filetype| [descriptor_type(filetype)]);
+                store_fs_result(&mut memory, retptr as u32, 1, result)
+            }))
+        },
+    )?;
+    linker.func_wrap(
+        FILESYSTEM_TYPES,
+        "[method]descriptor.read",
+        |mut caller: Caller<'_, T>,
+         fd: i32,
+         len: i64,
+         offset: i64,
+         retptr: i32|
+         -> Result<(), wasmi::Error> {
+            trap(descriptor_read(
+                &mut caller,
+                fd as Fd,
+                len as u64,
+                offset as FileSize,
+                retptr as u32,
+            ))
+        },
+    )?;
+    linker.func_wrap(
+        FILESYSTEM_TYPES,
+        "[method]descriptor.write",
+        |mut caller: Caller<'_, T>,
+         fd: i32,
+         ptr: i32,
+         len: i32,
+         offset: i64,
+         retptr: i32|
+         -> Result<(), wasmi::Error> {
+            trap(memory_and_ctx(&mut caller).and_then(|(mut memory, ctx)| {
+                let data = memory.read(ptr as u32, len as u32)?.to_vec();
+                let result =
+                    filesystem::fd_pwrite(&mut ctx.fs, fd as Fd, &data, offset as FileSize);
+                let result = result.map(|n| (n as FileSize).to_le_bytes());
+                store_fs_result(&mut memory, retptr as u32, 8, result)
+            }))
+        },
+    )?;
+    linker.func_wrap(
+        FILESYSTEM_TYPES,
+        "[method]descriptor.open-at",
+        |mut caller: Caller<'_, T>,
+         fd: i32,
+         path_flags: i32,
+         path_ptr: i32,
+         path_len: i32,
+         open_flags: i32,
+         flags: i32,
+         retptr: i32|
+         -> Result<(), wasmi::Error> {
+            trap(memory_and_ctx(&mut caller).and_then(|(mut memory, ctx)| {
+                let path = memory.read_str(path_ptr as u32, path_len as u32)?;
+                let rights = descriptor_rights(flags as DescriptorFlags);
+                let result = filesystem::path_open(
+                    &mut ctx.fs,
+                    fd as Fd,
+                    path_flags as LookupFlags,
+                    path,
+                    open_flags as OFlags,
+                    rights,
+                    rights,
+                    0,
+                );
+                store_fs_result(&mut memory, retptr as u32, 4, result.map(u32::to_le_bytes))
+            }))
+        },
+    )?;
+    linker.func_wrap(
+        FILESYSTEM_TYPES,
+        "filesystem-error-code",
+        |mut caller: Caller<'_, T>, _error: i32, retptr: i32| -> Result<(), wasmi::Error> {
+            // Stream errors carry no error code
+            trap(
+                memory_and_ctx(&mut caller)
+                    .and_then(|(mut memory, _ctx)| memory.write(retptr as u32, &[0])),
+            )
+        },
+    )?;
+
+    Ok(())
+}
+
+// Bad guest pointers and failed host calls trap; preview2 has no errno
+fn trap_error(err: WasiError) -> wasmi::Error {
+    wasmi::Error::new(err.to_debug_string())
+}
+
+fn trap(result: WasiResult<()>) -> Result<(), wasmi::Error> {
+    result.map_err(trap_error)
+}
+
+// Store a `result<T, E>` whose payload sits at `offset` from `retptr`;
+// the error is a single-byte enum or variant tag
+fn store_result(
+    memory: &mut GuestMemory<'_>,
+    retptr: u32,
+    offset: u32,
+    result: Result<&[u8], u8>,
+) -> WasiResult<()> {
+    match result {
+        Ok(payload) => {
+            memory.write(retptr, &[0])?;
+            memory.write(retptr + offset, payload)
+        }
+        Err(code) => {
+            memory.write(retptr, &[1])?;
+            memory.write(retptr + offset, &[code])
+        }
+    }
+}
+
+fn store_fs_result<const N: usize>(
+    memory: &mut GuestMemory<'_>,
+    retptr: u32,
+    offset: u32,
+    result: WasiResult<[u8; N]>,
+) -> WasiResult<()> {
+    let result = result
+        .as_ref()
+        .map(|payload| &payload[..])
+        .map_err(|e| wasi_error_to_error_code(e.clone()) as u8);
+    store_result(memory, retptr, offset, result)
+}
+
+// Lower a stream result; failures have no error resource to point at, so
+// they are logged and reported as `closed`
+fn stream_result<const N: usize>(result: &Result<[u8; N], StreamError>) -> Result<&[u8], u8> {
+    const STREAM_ERROR_CLOSED: u8 = 1;
+    match result {
+        Ok(payload) => Ok(&payload[..]),
+        Err(StreamError::LastOperationFailed(error)) => {
+            log::warn!("WASI: stream operation failed: {}", error.message);
+            Err(STREAM_ERROR_CLOSED)
+        }
+        Err(StreamError::Closed) => Err(STREAM_ERROR_CLOSED),
+    }
+}
+
+// stdout and stderr go to the app's stdio pipes or the kernel log rather
+// than a stream buffer
+fn is_stdio(stream: OutputStream) -> bool {
+    stream == io::STDOUT || stream == io::STDERR
+}
+
+// datetime is { seconds: u64, nanoseconds: u32 }
+fn store_datetime<T: WasiView>(
+    caller: &mut Caller<'_, T>,
+    retptr: u32,
+    nanos: Timestamp,
+) -> WasiResult<()> {
+    let (mut memory, _ctx) = memory_and_ctx(caller)?;
+    memory.write_u64(retptr, nanos / 1_000_000_000)?;
+    memory.write_u32(retptr + 8, (nanos % 1_000_000_000) as u32)
+}
+
+// Copy `data` into the guest and store the `list<u8>` at `retptr`
+fn store_list<T: WasiView>(caller: &mut Caller<'_, T>, retptr: u32, data: &[u8]) -> WasiResult<()> {
+    let (ptr, len) = lower_bytes(caller, data)?;
+    let (mut memory, _ctx) = memory_and_ctx(caller)?;
+    write_words(&mut memory, retptr, &[ptr, len])
+}
+
+fn get_environment<T: WasiView>(caller: &mut Caller<'_, T>, retptr: u32) -> WasiResult<()> {
+    let env = caller.data_mut().wasi().env.clone();
+    let mut words = Vec::with_capacity(env.len() * 4);
+    for (key, value) in &env {
+        let (key_ptr, key_len) = lower_bytes(caller, key.as_bytes())?;
+        let (value_ptr, value_len) = lower_bytes(caller, value.as_bytes())?;
+        words.extend([key_ptr, key_len, value_ptr, value_len]);
+    }
+    let ptr = lower_words(caller, &words)?;
+    let (mut memory, _ctx) = memory_and_ctx(caller)?;
+    write_words(&mut memory, retptr, &[ptr, env.len() as u32])
+}
+
+fn get_directories<T: WasiView>(caller: &mut Caller<'_, T>, retptr: u32) -> WasiResult<()> {
+    let preopens: Vec<(Fd, String)> = caller
+        .data_mut()
+        .wasi()
+        .fs
+        .preopens()
+        .map(|(fd, path)| (fd, String::from(path)))
+        .collect();
+    let mut words = Vec::with_capacity(preopens.len() * 3);
+    for (fd, path) in &preopens {
+        let (ptr, len) = lower_bytes(caller, path.as_bytes())?;
+        words.extend([*fd, ptr, len]);
+    }
+    let ptr = lower_words(caller, &words)?;
+    let (mut memory, _ctx) = memory_and_ctx(caller)?;
+    write_words(&mut memory, retptr, &[ptr, preopens.len() as u32])
+}
+
+fn poll<T: WasiView>(
+    caller: &mut Caller<'_, T>,
+    ptr: u32,
+    len: u32,
+    retptr: u32,
+) -> WasiResult<()> {
+    let pollables = {
+        let (memory, _ctx) = memory_and_ctx(caller)?;
+        (0..len)
+            .map(|i| memory.read_u32(ptr + i * 4))
+            .collect::<WasiResult<Vec<Pollable>>>()?
+    };
+    let ready = io::poll(&pollables);
+    let list = lower_words(caller, &ready)?;
+    let (mut memory, _ctx) = memory_and_ctx(caller)?;
+    write_words(&mut memory, retptr, &[list, ready.len() as u32])
+}
+
+fn input_stream_read<T: WasiView>(
+    caller: &mut Caller<'_, T>,
+    stream: InputStream,
+    len: u64,
+    retptr: u32,
+) -> WasiResult<()> {
+    let ctx = caller.data_mut().wasi();
+    let result = match (stream, ctx.stdio[0]) {
+        (io::STDIN, Some(pipe)) => {
+            let mut buf = alloc::vec![0; len.min(MAX_READ) as usize];
+            match ipc::pipe_read(pipe, &mut buf) {
+                Ok(n) => {
+                    buf.truncate(n);
+                    Ok(buf)
+                }
+                Err(AgaveError::WouldBlock) => Ok(Vec::new()),
+                Err(_) => Err(StreamError::Closed),
+            }
+        }
+        // stdin without a pipe is at its end
+        (io::STDIN, None) => Err(StreamError::Closed),
+        _ => ctx.streams.read(stream, len),
+    };
+    let result = match result {
+        Ok(data) => {
+            let (ptr, len) = lower_bytes(caller, &data)?;
+            let mut payload = [0; 8];
+            payload[..4].copy_from_slice(&ptr.to_le_bytes());
+            payload[4..].copy_from_slice(&len.to_le_bytes());
+            Ok(payload)
+        }
+        Err(e) => Err(e),
+    };
+    let (mut memory, _ctx) = memory_and_ctx(caller)?;
+    store_result(&mut memory, retptr, 4, stream_result(&result))
+}
+
+fn output_stream_write<T: WasiView>(
+    caller: &mut Caller<'_, T>,
+    stream: OutputStream,
+    ptr: u32,
+    len: u32,
+    retptr: u32,
+) -> WasiResult<()> {
+    let (mut memory, ctx) = memory_and_ctx(caller)?;
+    let contents = memory.read(ptr, len)?;
+    let result = match ctx.stdio.get(stream as usize).copied().flatten() {
+        Some(pipe) if is_stdio(stream) => match ipc::pipe_write(pipe, contents) {
+            Ok(_) | Err(AgaveError::WouldBlock) => Ok([]),
+            Err(_) => Err(StreamError::Closed),
+        },
+        None if is_stdio(stream) => {
+            log::info!("{}", String::from_utf8_lossy(contents).trim_end());
+            Ok([])
+        }
+        _ => ctx.streams.write(stream, contents.to_vec()).map(|()| []),
+    };
+    store_result(&mut memory, retptr, 4, stream_result(&result))
+}
+
+// result<tuple<list<u8>, bool>, error-code>
+fn descriptor_read<T: WasiView>(
+    caller: &mut Caller<'_, T>,
+    fd: Fd,
+    len: u64,
+    offset: FileSize,
+    retptr: u32,
+) -> WasiResult<()> {
+    let len = len.min(MAX_READ);
+    let mut buf = alloc::vec![0; len as usize];
+    let result = filesystem::fd_pread(&caller.data_mut().wasi().fs, fd, &mut buf, offset);
+    let result = match result {
+        Ok(n) => {
+            let (ptr, n) = lower_bytes(caller, &buf[..n as usize])?;
+            let mut payload = [0; 9];
+            payload[..4].copy_from_slice(&ptr.to_le_bytes());
+            payload[4..8].copy_from_slice(&n.to_le_bytes());
+            payload[8] = ((n as u64) < len) as u8;
+            Ok(payload)
+        }
+        Err(e) => Err(e),
+    };
+    let (mut memory, _ctx) = memory_and_ctx(caller)?;
+    store_fs_result(&mut memory, retptr, 4, result)
+}
+
+// descriptor-type numbering differs from the preview1 filetype
+fn descriptor_type(filetype: u8) -> DescriptorType {
+    match filetype {
+        FILETYPE_BLOCK_DEVICE => 1,
+        FILETYPE_CHARACTER_DEVICE => 2,
+        FILETYPE_DIRECTORY => 3,
+        FILETYPE_SYMBOLIC_LINK => 5,
+        FILETYPE_REGULAR_FILE => 6,
+        FILETYPE_SOCKET_DGRAM | FILETYPE_SOCKET_STREAM => 7,
+        _ => 0,
+    }
+}
+
+// Preview1 rights granted for descriptor-flags `read` (1) and `write` (2)
+fn descriptor_rights(flags: DescriptorFlags) -> Rights {
+    let mut rights = RIGHTS_FD_SEEK
+        | RIGHTS_FD_TELL
+        | RIGHTS_FD_FILESTAT_GET
+        | RIGHTS_FD_READDIR
+        | RIGHTS_PATH_OPEN;
+    if flags & 1 != 0 {
+        rights |= RIGHTS_FD_READ;
+    }
+    if flags & 2 != 0 {
+        rights |= RIGHTS_FD_WRITE | RIGHTS_FD_FILESTAT_SET_SIZE | RIGHTS_PATH_CREATE_FILE;
+    }
+    rights
+}
@@ -19,7 +19,7 @@ pub type PreOpenType = u8;
 pub type DirCookie = u64;
 pub type FdFlags = u16;
 pub type FdStat = [u8; 24];
-pub type FileStat = [u8; 56];
+pub type FileStat = [u8; 64];
 pub type FstFlags = u16;
 pub type LookupFlags = u32;
 pub type OFlags = u16;
@@ -160,6 +160,22 @@ pub const RIGHTS_PATH_UNLINK_FILE: Rights = 67108864;
 pub const RIGHTS_POLL_FD_READWRITE: Rights = 134217728;
 pub const RIGHTS_SOCK_SHUTDOWN: Rights = 268435456;
 
+// Timestamp update flags
+pub const FSTFLAGS_ATIM: FstFlags = 1;
+pub const FSTFLAGS_ATIM_NOW: FstFlags = 2;
+pub const FSTFLAGS_MTIM: FstFlags = 4;
+pub const FSTFLAGS_MTIM_NOW: FstFlags = 8;
+
+// Poll event types
+pub const EVENTTYPE_CLOCK: u8 = 0;
+pub const EVENTTYPE_FD_READ: u8 = 1;
+pub const EVENTTYPE_FD_WRITE: u8 = 2;
+pub const SUBCLOCKFLAGS_SUBSCRIPTION_CLOCK_ABSTIME: u16 = 1;
+
+// Size in bytes of a preview1 subscription and event in guest memory
+pub const SUBSCRIPTION_SIZE: u32 = 48;
+pub const EVENT_SIZE: u32 = 32;
+
 // Preview 2 types (component model)
 #[derive(Debug, Clone)]
 pub struct Error {
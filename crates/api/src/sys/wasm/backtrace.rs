@@ -0,0 +1,599 @@
+/// Symbolicated backtraces of WASM apps
+///
+/// `symbols` reads function names from a module's `name` section (or its
+/// exports) and lines from its `.debug_line`. wasmi does not expose the
+/// guest's call stack, so frames are only recorded for apps whose manifest
+/// sets `backtrace = true`: `instrument` then rewrites the module at load
+/// time to keep a shadow stack of its own. The stack lives in an extra
+/// one-page memory exported as `STACK_EXPORT`, with its top in the global
+/// exported as `TOP_EXPORT`:
+///
+/// - every function pushes an 8 byte frame: its index and a code offset
+/// - before each call and `unreachable` the frame records that instruction's
+///   offset
+/// - after each call the caller drops whatever the callee left above it
+///
+/// Frame addresses wrap around the page, so the innermost 8192 frames are
+/// kept. Offsets are those of the original module relative to its code
+/// section, as DWARF uses them, so they can be looked up in the module's
+/// `name` section and `.debug_line`. The innermost frame points at the last
+/// call or `unreachable` it reached, which is earlier in the function if the
+/// trap came from anything else.
+use super::AppContext;
+use crate::sys::{
+    diagnostics::{add_diagnostic, DiagnosticCategory, DiagnosticLevel},
+    process::ProcessId,
+};
+use alloc::{
+    borrow::ToOwned,
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::Write;
+use gimli::{EndianSlice, LittleEndian, SectionId};
+use wasmi::{AsContext, Extern, Instance, Store, Val};
+use wasmparser::{
+    BinaryReaderError, CompositeInnerType, ExternalKind, FunctionBody, KnownCustom, Name, Operator,
+    Parser, Payload, TypeRef,
+};
+
+/// Memory holding the shadow stack
+pub const STACK_EXPORT: &str = "__agave_backtrace_stack";
+/// Global holding the byte offset of the top of the shadow stack
+pub const TOP_EXPORT: &str = "__agave_backtrace_top";
+
+/// Size in bytes of the stack's single page
+pub const STACK_SIZE: usize = 64 * 1024;
+
+const FRAME_SIZE: usize = 8;
+// Keeps frame addresses inside the stack's single page
+const STACK_MASK: i32 = 0xfff8;
+const MAX_FRAMES: usize = STACK_SIZE / FRAME_SIZE;
+// Frames beyond this are only counted in reports
+const MAX_REPORTED_FRAMES: usize = 32;
+
+const MEMORY_SECTION: u8 = 5;
+const GLOBAL_SECTION: u8 = 6;
+const EXPORT_SECTION: u8 = 7;
+const CODE_SECTION: u8 = 10;
+
+/// One function on the shadow stack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+    pub function: u32,
+    /// Code offset of the last call or `unreachable` the function reached,
+    /// 0 if none
+    pub offset: u32,
+}
+
+/// What a module says about its own code
+#[derive(Debug, Default)]
+pub struct Symbols {
+    // Demangled function names by index, from the `name` section or exports
+    names: BTreeMap<u32, String>,
+    // Code offset of each function body by function index
+    bodies: BTreeMap<u32, u32>,
+    // `.debug_line` rows sorted by address: file index and line, where line
+    // 0 ends a sequence
+    lines: Vec<(u32, u32, u32)>,
+    files: Vec<String>,
+    // Position of the shadow stack among the module's own memories
+    stack_memory: Option<usize>,
+}
+
+impl Symbols {
+    /// Which of the module's own memories, in creation order, is the shadow
+    /// stack; `None` if the module was not instrumented
+    pub fn stack_memory(&self) -> Option<usize> {
+        self.stack_memory
+    }
+
+    /// `name+offset at file:line`, as much of it as is known
+    pub fn describe(&self, frame: Frame) -> String {
+        let mut text = match self.names.get(&frame.function) {
+            Some(name) => name.clone(),
+            None => format!("func[{}]", frame.function),
+        };
+        if frame.offset == 0 {
+            return text;
+        }
+        let start = self.bodies.get(&frame.function).copied().unwrap_or(0);
+        let _ = write!(text, "+{:#x}", frame.offset.saturating_sub(start));
+        if let Some((file, line)) = self.line(frame.offset) {
+            let _ = write!(text, " at {}:{}", file, line);
+        }
+        text
+    }
+
+    fn line(&self, offset: u32) -> Option<(&str, u32)> {
+        let index = self
+            .lines
+            .partition_point(|&(address, ..)| address <= offset);
+        let &(_, file, line) = self.lines.get(index.checked_sub(1)?)?;
+        (line != 0).then(|| (self.files[file as usize].as_str(), line))
+    }
+
+    fn read_lines(&mut self, sections: &BTreeMap<&str, &[u8]>) -> gimli::Result<()> {
+        let dwarf = gimli::Dwarf::load(|id: SectionId| -> gimli::Result<_> {
+            let data = sections.get(id.name()).copied().unwrap_or_default();
+            Ok(EndianSlice::new(data, LittleEndian))
+        })?;
+        let mut units = dwarf.units();
+        while let Some(header) = units.next()? {
+            let unit = dwarf.unit(header)?;
+            let Some(program) = unit.line_program.clone() else {
+                continue;
+            };
+            // Index into `files` of each of the unit's file numbers
+            let mut files = BTreeMap::new();
+            let mut rows = program.rows();
+            while let Some((header, row)) = rows.next_row()? {
+                let address = row.address() as u32;
+                if row.end_sequence() {
+                    self.lines.push((address, 0, 0));
+                    continue;
+                }
+                let file = *files.entry(row.file_index()).or_insert_with(|| {
+                    let path = row
+                        .file(header)
+                        .and_then(|file| dwarf.attr_string(&unit, file.path_name()).ok())
+                        .map_or_else(|| "?".to_string(), |path| path.to_string_lossy().into());
+                    self.files.push(path);
+                    self.files.len() as u32 - 1
+                });
+                let line = row.line().map_or(0, |line| line.get() as u32);
+                self.lines.push((address, file, line));
+            }
+        }
+        self.lines.sort_by_key(|&(address, ..)| address);
+        Ok(())
+    }
+}
+
+// Where the shadow stack lives in an instrumented module
+struct Layout {
+    memory: u32,
+    global: u32,
+}
+
+// Bytecode written into function bodies
+struct Code(Vec<u8>);
+
+impl Code {
+    fn u32(&mut self, mut value: u32) -> &mut Self {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.0.push(byte);
+                return self;
+            }
+            self.0.push(byte | 0x80);
+        }
+    }
+
+    fn i64(&mut self, mut value: i64) -> &mut Self {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            let done = (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0);
+            if done {
+                self.0.push(byte);
+                return self;
+            }
+            self.0.push(byte | 0x80);
+        }
+    }
+
+    fn op(&mut self, opcode: u8) -> &mut Self {
+        self.0.push(opcode);
+        self
+    }
+
+    fn memarg(&mut self, align: u32, memory: u32, offset: u32) -> &mut Self {
+        // A memory index is only encoded when it is not 0, flagged by bit 6
+        if memory == 0 {
+            self.u32(align)
+        } else {
+            self.u32(align | 0x40).u32(memory)
+        }
+        .u32(offset)
+    }
+
+    // Push a frame for `function`, remembering its address in `frame`
+    fn push_frame(&mut self, layout: &Layout, frame: u32, function: u32) -> &mut Self {
+        self.op(0x23) // global.get
+            .u32(layout.global)
+            .op(0x22) // local.tee
+            .u32(frame)
+            .op(0x41) // i32.const
+            .i64(STACK_MASK.into())
+            .op(0x71) // i32.and
+            .op(0x42) // i64.const: the index, and a zero offset above it
+            .i64(function.into())
+            .op(0x37) // i64.store
+            .memarg(3, layout.memory, 0)
+            .truncate(layout, frame)
+    }
+
+    // Record `offset` in the frame at `frame`
+    fn record(&mut self, layout: &Layout, frame: u32, offset: u32) -> &mut Self {
+        self.op(0x20) // local.get
+            .u32(frame)
+            .op(0x41) // i32.const
+            .i64(STACK_MASK.into())
+            .op(0x71) // i32.and
+            .op(0x41) // i32.const
+            .i64(offset.into())
+            .op(0x36) // i32.store
+            .memarg(2, layout.memory, 4)
+    }
+
+    // Drop the frames above the one at `frame`
+    fn truncate(&mut self, layout: &Layout, frame: u32) -> &mut Self {
+        self.op(0x20) // local.get
+            .u32(frame)
+            .op(0x41) // i32.const
+            .i64(FRAME_SIZE as i64)
+            .op(0x6a) // i32.add
+            .op(0x24) // global.set
+            .u32(layout.global)
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> (u32, usize) {
+    let mut value = 0;
+    for (i, &byte) in bytes.iter().enumerate().take(5) {
+        value |= ((byte & 0x7f) as u32) << (7 * i);
+        if byte & 0x80 == 0 {
+            return (value, i + 1);
+        }
+    }
+    (value, bytes.len().min(5))
+}
+
+// Order sections must appear in, by id
+fn section_rank(id: u8) -> u8 {
+    match id {
+        13 => 6, // tag
+        6..=9 => id + 1,
+        12 => 11, // data count
+        10 => 12,
+        11 => 13,
+        _ => id,
+    }
+}
+
+fn append_section(out: &mut Vec<u8>, id: u8, contents: &[u8]) {
+    out.push(id);
+    out.extend_from_slice(&Code(Vec::new()).u32(contents.len() as u32).0);
+    out.extend_from_slice(contents);
+}
+
+// A vector section with `entries` appended to the `existing` one, if any
+fn extend_section(existing: &[u8], added: u32, entries: &[u8]) -> Vec<u8> {
+    let (count, len) = read_u32(existing);
+    let mut contents = Code(Vec::new());
+    contents.u32(count + added);
+    contents.0.extend_from_slice(&existing[len..]);
+    contents.0.extend_from_slice(entries);
+    contents.0
+}
+
+// Add the shadow stack to one function body
+fn instrument_body(
+    wasm: &[u8],
+    body: &FunctionBody,
+    layout: &Layout,
+    function: u32,
+    params: u32,
+    code_start: usize,
+) -> Result<Vec<u8>, BinaryReaderError> {
+    let range = body.range();
+    let mut locals = body.get_locals_reader()?;
+    let groups = locals.get_count();
+    let mut frame = params;
+    for _ in 0..groups {
+        frame += locals.read()?.0;
+    }
+    let (_, count_len) = read_u32(&wasm[range.start..]);
+
+    // The frame address goes in a new local after all the others
+    let mut code = Code(Vec::with_capacity(range.len() * 2));
+    code.u32(groups + 1);
+    code.0
+        .extend_from_slice(&wasm[range.start + count_len..locals.original_position()]);
+    code.u32(1).op(0x7f); // one i32
+    code.push_frame(layout, frame, function);
+
+    let mut operators = body.get_operators_reader()?;
+    let mut copied = operators.original_position();
+    let mut returning = false;
+    while !operators.eof() {
+        let (operator, offset) = operators.read_with_offset()?;
+        if returning {
+            code.0.extend_from_slice(&wasm[copied..offset]);
+            copied = offset;
+            code.truncate(layout, frame);
+            returning = false;
+        }
+        let (record, call) = match operator {
+            Operator::Call { .. } | Operator::CallIndirect { .. } | Operator::CallRef { .. } => {
+                (true, true)
+            }
+            Operator::ReturnCall { .. }
+            | Operator::ReturnCallIndirect { .. }
+            | Operator::ReturnCallRef { .. }
+            | Operator::Unreachable => (true, false),
+            _ => (false, false),
+        };
+        if record {
+            code.0.extend_from_slice(&wasm[copied..offset]);
+            copied = offset;
+            code.record(layout, frame, (offset - code_start) as u32);
+            returning = call;
+        }
+    }
+    code.0.extend_from_slice(&wasm[copied..range.end]);
+    Ok(code.0)
+}
+
+/// Function names and line tables of the core module `wasm`
+pub fn symbols(wasm: &[u8]) -> Result<Symbols, BinaryReaderError> {
+    let mut symbols = Symbols::default();
+    let mut imported_functions = 0;
+    let mut defined = 0;
+    let mut debug = BTreeMap::new();
+    let mut code_start = 0;
+
+    for payload in Parser::new(0).parse_all(wasm) {
+        match payload? {
+            Payload::ImportSection(reader) => {
+                for import in reader {
+                    if let TypeRef::Func(_) = import?.ty {
+                        imported_functions += 1;
+                    }
+                }
+            }
+            Payload::ExportSection(reader) => {
+                for export in reader {
+                    let export = export?;
+                    if export.kind == ExternalKind::Func {
+                        symbols.names.insert(export.index, export.name.to_string());
+                    }
+                }
+            }
+            Payload::CodeSectionStart { range, .. } => code_start = range.start,
+            Payload::CodeSectionEntry(body) => {
+                symbols.bodies.insert(
+                    imported_functions + defined,
+                    (body.range().start - code_start) as u32,
+                );
+                defined += 1;
+            }
+            Payload::CustomSection(reader) => match reader.as_known() {
+                KnownCustom::Name(names) => {
+                    for name in names.into_iter().flatten() {
+                        if let Name::Function(map) = name {
+                            for naming in map.into_iter().flatten() {
+                                let name = format!("{:#}", rustc_demangle::demangle(naming.name));
+                                symbols.names.insert(naming.index, name);
+                            }
+                        }
+                    }
+                }
+                _ if reader.name().starts_with(".debug_") => {
+                    debug.insert(reader.name(), reader.data());
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    if debug.contains_key(".debug_line") {
+        if let Err(e) = symbols.read_lines(&debug) {
+            log::warn!("WASM: unreadable DWARF line table: {}", e);
+        }
+    }
+
+    Ok(symbols)
+}
+
+/// Add the shadow stack to the core module `wasm` and collect its symbols
+pub fn instrument(wasm: &[u8]) -> Result<(Vec<u8>, Symbols), BinaryReaderError> {
+    let mut symbols = symbols(wasm)?;
+    let mut params = Vec::new();
+    let mut function_types = Vec::new();
+    let (mut imported_functions, mut imported_memories, mut globals) = (0, 0, 0);
+    let mut memories = 0;
+    let mut sections = Vec::new();
+    let mut code_start = 0;
+    let mut bodies = Vec::new();
+
+    for payload in Parser::new(0).parse_all(wasm) {
+        let payload = payload?;
+        if let Some(section) = payload.as_section() {
+            sections.push(section);
+        }
+        match payload {
+            Payload::TypeSection(reader) => {
+                for group in reader {
+                    for ty in group?.types() {
+                        params.push(match &ty.composite_type.inner {
+                            CompositeInnerType::Func(func) => func.params().len() as u32,
+                            _ => 0,
+                        });
+                    }
+                }
+            }
+            Payload::ImportSection(reader) => {
+                for import in reader {
+                    match import?.ty {
+                        TypeRef::Func(_) => imported_functions += 1,
+                        TypeRef::Memory(_) => imported_memories += 1,
+                        TypeRef::Global(_) => globals += 1,
+                        _ => {}
+                    }
+                }
+            }
+            Payload::FunctionSection(reader) => {
+                function_types = reader.into_iter().collect::<Result<Vec<u32>, _>>()?;
+            }
+            Payload::MemorySection(reader) => memories = reader.count(),
+            Payload::GlobalSection(reader) => globals += reader.count(),
+            Payload::CodeSectionStart { range, .. } => code_start = range.start,
+            Payload::CodeSectionEntry(body) => {
+                let defined = bodies.len();
+                let function = imported_functions + defined as u32;
+                let ty = function_types.get(defined).copied().unwrap_or_default();
+                let layout = Layout {
+                    memory: imported_memories + memories,
+                    global: globals,
+                };
+                let param_count = params.get(ty as usize).copied().unwrap_or_default();
+                bodies.push(instrument_body(
+                    wasm,
+                    &body,
+                    &layout,
+                    function,
+                    param_count,
+                    code_start,
+                )?);
+            }
+            _ => {}
+        }
+    }
+    // The stack comes after the module's own memories
+    symbols.stack_memory = Some(memories as usize);
+
+    // The new memory (one page, fixed), global (mutable i32 starting at 0)
+    // and exports, added to their sections or in new ones
+    let mut exports = Code(Vec::new());
+    let stack = imported_memories + memories;
+    for (name, kind, index) in [(STACK_EXPORT, 0x02, stack), (TOP_EXPORT, 0x03, globals)] {
+        exports.u32(name.len() as u32);
+        exports.0.extend_from_slice(name.as_bytes());
+        exports.op(kind).u32(index);
+    }
+    let mut added: [(u8, u32, &[u8]); 3] = [
+        (MEMORY_SECTION, 1, &[0x01, 0x01, 0x01]),
+        (GLOBAL_SECTION, 1, &[0x7f, 0x01, 0x41, 0x00, 0x0b]),
+        (EXPORT_SECTION, 2, &exports.0),
+    ];
+    let mut out = wasm[..8].to_vec();
+    for (id, range) in sections {
+        for (added_id, count, entries) in added.iter_mut() {
+            if id != 0 && section_rank(id) > section_rank(*added_id) {
+                append_section(&mut out, *added_id, &extend_section(&[0], *count, entries));
+                // Only added once
+                *added_id = u8::MAX;
+            }
+        }
+        let contents = &wasm[range];
+        match added.iter_mut().find(|(added_id, ..)| *added_id == id) {
+            Some((added_id, count, entries)) => {
+                append_section(&mut out, id, &extend_section(contents, *count, entries));
+                *added_id = u8::MAX;
+            }
+            None if id == CODE_SECTION => {
+                let mut code = Code(Vec::new());
+                code.u32(bodies.len() as u32);
+                for body in &bodies {
+                    code.u32(body.len() as u32);
+                    code.0.extend_from_slice(body);
+                }
+                append_section(&mut out, id, &code.0);
+            }
+            None => append_section(&mut out, id, contents),
+        }
+    }
+    for (added_id, count, entries) in added {
+        if added_id != u8::MAX {
+            append_section(&mut out, added_id, &extend_section(&[0], count, entries));
+        }
+    }
+    Ok((out, symbols))
+}
+
+/// The shadow stack behind the `top` and `stack` exports of an instrumented
+/// instance, innermost frame first; empty if the module was not instrumented
+pub fn frames(ctx: impl AsContext, top: Option<Extern>, stack: Option<Extern>) -> Vec<Frame> {
+    let (Some(Extern::Global(top)), Some(Extern::Memory(stack))) = (top, stack) else {
+        return Vec::new();
+    };
+    let Val::I32(top) = top.get(&ctx) else {
+        return Vec::new();
+    };
+    let data = stack.data(&ctx);
+    let depth = top as u32 as usize / FRAME_SIZE;
+    (depth.saturating_sub(MAX_FRAMES)..depth)
+        .rev()
+        .filter_map(|index| {
+            let at = (index * FRAME_SIZE) & STACK_MASK as usize;
+            let frame = data.get(at..at + FRAME_SIZE)?;
+            Some(Frame {
+                function: u32::from_le_bytes(frame[..4].try_into().unwrap()),
+                offset: u32::from_le_bytes(frame[4..].try_into().unwrap()),
+            })
+        })
+        .collect()
+}
+
+/// Empty the shadow stack before a new call into the instance
+pub fn reset(store: &mut Store<AppContext>, instance: &Instance) {
+    if let Some(Extern::Global(top)) = instance.get_export(&*store, TOP_EXPORT) {
+        let _ = top.set(store, Val::I32(0));
+    }
+}
+
+/// Print `frames` of app `pid` to the serial log and the diagnostics log
+pub fn report(
+    name: &str,
+    pid: ProcessId,
+    reason: &str,
+    symbols: &Symbols,
+    frames: &[Frame],
+    level: DiagnosticLevel,
+) {
+    let mut lines: Vec<String> = frames
+        .iter()
+        .take(MAX_REPORTED_FRAMES)
+        .enumerate()
+        .map(|(i, &frame)| format!("#{} {}", i, symbols.describe(frame)))
+        .collect();
+    if frames.len() > MAX_REPORTED_FRAMES {
+        lines.push(format!(
+            "... {} more frames",
+            frames.len() - MAX_REPORTED_FRAMES
+        ));
+    }
+    if symbols.stack_memory.is_none() {
+        lines.push("no frames recorded, set `backtrace = true` in the app's manifest".to_owned());
+    } else if frames.is_empty() {
+        lines.push("no frames recorded".to_owned());
+    }
+
+    let log_level = match level {
+        DiagnosticLevel::Info => log::Level::Info,
+        DiagnosticLevel::Warning => log::Level::Warn,
+        _ => log::Level::Error,
+    };
+    log::log!(
+        log_level,
+        "WASM: backtrace of app {} ({}), {}:",
+        pid,
+        name,
+        reason
+    );
+    for line in &lines {
+        log::log!(log_level, "WASM:   {}", line);
+    }
+    add_diagnostic(
+        level,
+        DiagnosticCategory::Tasks,
+        format!("Backtrace of WASM app '{}', {}", name, reason),
+        Some(lines.join("\n")),
+    );
+}
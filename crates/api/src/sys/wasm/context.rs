@@ -0,0 +1,71 @@
+/// Per-app host state handed to every host function through the app's `Store`
+use crate::sys::{
+    framebuffer::FB,
+    input::EventQueue,
+    process::{self, ProcessId, ResourceLimits},
+    security::{self, SandboxProfile, SecurityContext, UserId},
+    wasi::{WasiCtx, WasiView},
+    wasm::{backtrace::Symbols, limits::AppLimiter},
+};
+use alloc::{
+    string::{String, ToString},
+    sync::Arc,
+    vec,
+    vec::Vec,
+};
+
+pub struct AppContext {
+    pub pid: ProcessId,
+    pub name: String,
+    /// WASI descriptor table, preopens, args and environment
+    pub wasi: WasiCtx,
+    pub security: SecurityContext,
+    /// Caps linear memory and table growth at the sandbox's limits
+    pub limiter: AppLimiter,
+    /// Input events waiting for `poll_event`
+    pub events: EventQueue,
+    /// Surface the app draws on
+    pub fb: *mut FB,
+    /// State carried across a hot reload, from `agave_save_state` in the old
+    /// module to `agave_load_state` in the new one
+    pub saved_state: Option<Vec<u8>>,
+    /// Names and line tables used to print the app's backtraces
+    pub symbols: Arc<Symbols>,
+}
+
+impl AppContext {
+    /// Host state for an app confined by `profile`: its WASI preopens,
+    /// network policy and limits all come from the profile
+    pub fn new(pid: ProcessId, name: &str, fb: *mut FB, profile: SandboxProfile) -> Self {
+        let wasi = WasiCtx::sandboxed(vec![name.to_string()], &profile);
+        let max_memory = profile.resource_limits.max_memory;
+        let _ = process::set_resource_limits(
+            pid,
+            ResourceLimits {
+                max_memory,
+                max_open_files: profile.resource_limits.max_file_handles as usize,
+                ..ResourceLimits::default()
+            },
+        );
+        let security = security::create_sandboxed_context(UserId::GUEST, profile);
+        security::set_process_security_context(pid, security.clone());
+
+        Self {
+            pid,
+            name: name.to_string(),
+            wasi,
+            security,
+            limiter: AppLimiter::new(pid, max_memory),
+            events: EventQueue::new(),
+            fb,
+            saved_state: None,
+            symbols: Arc::default(),
+        }
+    }
+}
+
+impl WasiView for AppContext {
+    fn wasi(&mut self) -> &mut WasiCtx {
+        &mut self.wasi
+    }
+}
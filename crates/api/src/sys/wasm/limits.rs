@@ -0,0 +1,144 @@
+/// Memory and table limits applied to each app's `Store`
+use crate::sys::{
+    allocator,
+    process::{self, ProcessId},
+};
+use wasmi::core::{LimiterError, ResourceLimiter};
+
+/// Largest number of elements any one table of an app may grow to
+pub const MAX_TABLE_ELEMENTS: usize = 10_000;
+// An app is a single module with at most a handful of tables
+const MAX_INSTANCES: usize = 1;
+const MAX_MEMORIES: usize = 1;
+const MAX_TABLES: usize = 8;
+
+/// Caps an app's linear memory at its `max_memory` and accounts every byte
+/// granted against the kernel heap, so a misbehaving app sees a failed
+/// `memory.grow` instead of exhausting the heap
+#[derive(Debug)]
+pub struct AppLimiter {
+    pid: ProcessId,
+    max_memory: usize,
+    /// Bytes of linear memory currently granted
+    memory: usize,
+    // Growth reserved by the last `memory_growing`, returned if it fails
+    pending: usize,
+    // Position of the backtrace stack among the memories the module creates,
+    // and the bytes held by it once created; these are outside `max_memory`
+    // and `memory`
+    stack_memory: Option<usize>,
+    created: usize,
+    stack: usize,
+}
+
+impl AppLimiter {
+    pub fn new(pid: ProcessId, max_memory: usize) -> Self {
+        Self {
+            pid,
+            max_memory,
+            memory: 0,
+            pending: 0,
+            stack_memory: None,
+            created: 0,
+            stack: 0,
+        }
+    }
+
+    /// Allow the extra memory `backtrace::instrument` adds to a module, the
+    /// `index`th of the memories it creates
+    pub fn expect_backtrace_stack(&mut self, index: usize) {
+        self.stack_memory = Some(index);
+    }
+
+    /// Bytes of linear memory the app currently holds
+    pub fn memory_usage(&self) -> usize {
+        self.memory
+    }
+
+    pub fn max_memory(&self) -> usize {
+        self.max_memory
+    }
+}
+
+impl ResourceLimiter for AppLimiter {
+    fn memory_growing(
+        &mut self,
+        current: usize,
+        desired: usize,
+        maximum: Option<usize>,
+    ) -> Result<bool, LimiterError> {
+        self.pending = 0;
+        if maximum.is_some_and(|max| desired > max) {
+            return Ok(false);
+        }
+        let additional = desired.saturating_sub(current);
+        // Memories are all created while instantiating, before anything can
+        // grow, and the stack is the last of them
+        if let Some(index) = self.stack_memory.filter(|_| self.stack == 0) {
+            if self.created == index {
+                if !allocator::reserve_wasm_memory(desired) {
+                    log::warn!("WASM: app {} denied its backtrace stack", self.pid);
+                    return Ok(false);
+                }
+                self.stack = desired;
+                return Ok(true);
+            }
+            self.created += 1;
+        }
+        if self.memory.saturating_add(additional) > self.max_memory {
+            log::warn!(
+                "WASM: app {} denied memory growth to {} bytes (limit {})",
+                self.pid,
+                desired,
+                self.max_memory
+            );
+            return Ok(false);
+        }
+        if !allocator::reserve_wasm_memory(additional) {
+            log::warn!(
+                "WASM: app {} denied {} bytes, kernel heap is low",
+                self.pid,
+                additional
+            );
+            return Ok(false);
+        }
+        self.memory += additional;
+        self.pending = additional;
+        let _ = process::set_memory_usage(self.pid, self.memory);
+        Ok(true)
+    }
+
+    fn memory_grow_failed(&mut self, _error: &LimiterError) {
+        allocator::release_wasm_memory(self.pending);
+        self.memory -= self.pending;
+        self.pending = 0;
+        let _ = process::set_memory_usage(self.pid, self.memory);
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        maximum: Option<usize>,
+    ) -> Result<bool, LimiterError> {
+        Ok(desired <= MAX_TABLE_ELEMENTS && maximum.is_none_or(|max| desired <= max))
+    }
+
+    fn instances(&self) -> usize {
+        MAX_INSTANCES
+    }
+
+    fn tables(&self) -> usize {
+        MAX_TABLES
+    }
+
+    fn memories(&self) -> usize {
+        MAX_MEMORIES + usize::from(self.stack_memory.is_some())
+    }
+}
+
+impl Drop for AppLimiter {
+    fn drop(&mut self) {
+        allocator::release_wasm_memory(self.memory + self.stack);
+    }
+}
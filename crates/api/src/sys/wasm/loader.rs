@@ -0,0 +1,197 @@
+/// Loads WASM apps from the VFS instead of baking them into the kernel image
+use super::{trust, AppManifest, WasmApp};
+use crate::sys::{
+    error::AgaveResult,
+    fs::{self, FileType},
+    ipc::{self, pipes::MAX_PIPE_BUFFER_SIZE, IpcHandle},
+    process::{self, ProcessId},
+    security,
+};
+use alloc::{
+    collections::VecDeque,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use spin::Mutex;
+
+/// Directory scanned for apps when there is no autostart list
+pub const APP_DIR: &str = "/bin";
+/// Apps started at boot, one absolute path per line (`#` starts a comment)
+pub const AUTOSTART_FILE: &str = "/etc/autostart";
+
+// Apps requested at runtime, started by the main loop on its next frame
+static LAUNCH_QUEUE: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+// Children spawned by running apps, started by the main loop on its next
+// frame. They are created right away so the parent learns the pid.
+struct Spawned(WasmApp);
+
+// Apps are only ever run from the main loop
+unsafe impl Send for Spawned {}
+
+static SPAWN_QUEUE: Mutex<Vec<Spawned>> = Mutex::new(Vec::new());
+
+/// Profile that may hand children the profile their manifest names
+const TRUSTED_PROFILE: &str = "trusted";
+
+/// A program started by `spawn`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Child {
+    pub pid: ProcessId,
+    /// The parent's ends of the child's stdin, stdout and stderr pipes
+    pub stdio: Option<[IpcHandle; 3]>,
+}
+
+/// Load and instantiate the app stored at `path`, sandboxed by the profile
+/// its manifest declares if the module is trusted (see `trust`). The app is
+/// reloaded when the file changes.
+pub fn load_app(path: &str) -> AgaveResult<WasmApp> {
+    let wasm = fs::read_file(path).inspect_err(|e| {
+        log::error!("WASM: cannot read {}: {:?}", path, e);
+    })?;
+    let manifest = AppManifest::load(path)?;
+    let profile = trust::verify(path, &wasm, manifest.sandbox_profile()?)?;
+    let app = WasmApp::new(app_name(path), wasm, profile, manifest.backtrace)?.watch(path);
+    trust::record_load(path, &app);
+    Ok(app)
+}
+
+/// Store an app in the VFS so it can be loaded by path
+pub fn install_app(path: &str, wasm: &[u8]) -> AgaveResult<()> {
+    fs::write_file(path, wasm.to_vec())
+}
+
+/// List the `.wasm` files in `dir`
+pub fn discover_apps(dir: &str) -> AgaveResult<Vec<String>> {
+    let entries = fs::read_dir(dir)?;
+    Ok(entries
+        .into_iter()
+        .filter(|entry| entry.file_type == FileType::Regular && entry.name.ends_with(".wasm"))
+        .map(|entry| format!("{}/{}", dir.trim_end_matches('/'), entry.name))
+        .collect())
+}
+
+/// Paths of the apps to start at boot: the autostart list if present,
+/// otherwise every app in `/bin`
+pub fn autostart_apps() -> Vec<String> {
+    match fs::read_file(AUTOSTART_FILE) {
+        Ok(content) => String::from_utf8_lossy(&content)
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(ToString::to_string)
+            .collect(),
+        Err(_) => discover_apps(APP_DIR).unwrap_or_default(),
+    }
+}
+
+/// Load every autostart app, skipping the ones that fail to load
+pub fn load_autostart_apps() -> Vec<WasmApp> {
+    autostart_apps()
+        .iter()
+        .filter_map(|path| load_app(path).ok())
+        .collect()
+}
+
+/// Ask the main loop to start the app at `path`
+pub fn request_launch(path: &str) {
+    LAUNCH_QUEUE.lock().push_back(path.to_string());
+}
+
+/// Take the pending launch requests
+pub fn take_launch_requests() -> Vec<String> {
+    LAUNCH_QUEUE.lock().drain(..).collect()
+}
+
+/// Load the program at `path` as a child of `parent`, with `args` as its
+/// argv and `env` as its environment. With `piped` its standard streams are
+/// connected to pipes owned by the parent, otherwise stdin is empty and
+/// output goes to the kernel log. The main loop starts the child on its next
+/// frame.
+///
+/// The child runs under the profile its manifest names only if the parent is
+/// trusted; anything else passes its own sandbox on to its children. Either
+/// way an untrusted module is sandboxed.
+pub fn spawn(
+    parent: ProcessId,
+    path: &str,
+    args: Vec<String>,
+    env: Vec<(String, String)>,
+    piped: bool,
+) -> AgaveResult<Child> {
+    let wasm = fs::read_file(path)?;
+    let manifest = AppManifest::load(path)?;
+    let parent_profile = security::get_process_security_context(parent)
+        .map(|context| context.sandbox_profile)
+        .filter(|profile| profile.name != TRUSTED_PROFILE);
+    let profile = match parent_profile {
+        Some(profile) => profile,
+        None => manifest.sandbox_profile()?,
+    };
+    let profile = trust::verify(path, &wasm, profile)?;
+    let name = app_name(path);
+    let mut app = WasmApp::with_parent(name, wasm, profile, manifest.backtrace, Some(parent))?
+        .with_args(args, env);
+    trust::record_load(path, &app);
+
+    let stdio = if piped {
+        match pipe_stdio(parent, app.pid()) {
+            Ok((parent_ends, child_ends)) => {
+                app = app.with_stdio(child_ends.map(Some));
+                Some(parent_ends)
+            }
+            Err(e) => {
+                app.close();
+                let _ = process::reap_child(parent, app.pid());
+                return Err(e);
+            }
+        }
+    } else {
+        None
+    };
+
+    let child = Child {
+        pid: app.pid(),
+        stdio,
+    };
+    SPAWN_QUEUE.lock().push(Spawned(app));
+    Ok(child)
+}
+
+// Create stdin, stdout and stderr pipes owned by `parent` and hand the
+// child's ends to `child`. Returns the parent's ends and the child's ends.
+fn pipe_stdio(
+    parent: ProcessId,
+    child: ProcessId,
+) -> AgaveResult<([IpcHandle; 3], [IpcHandle; 3])> {
+    let (parent_id, child_id) = (parent.as_u64() as u32, child.as_u64() as u32);
+    // The child reads stdin and writes stdout and stderr
+    let pipe = |child_reads: bool| -> AgaveResult<(IpcHandle, IpcHandle)> {
+        let (read, write) = ipc::create_pipe_with_capacity(parent_id, MAX_PIPE_BUFFER_SIZE)?;
+        let (ours, theirs) = if child_reads {
+            (write, read)
+        } else {
+            (read, write)
+        };
+        ipc::transfer_resource(theirs, parent_id, child_id)?;
+        Ok((ours, theirs))
+    };
+    let (stdin, stdout, stderr) = (pipe(true)?, pipe(false)?, pipe(false)?);
+    Ok(([stdin.0, stdout.0, stderr.0], [stdin.1, stdout.1, stderr.1]))
+}
+
+/// Take the children spawned since the last call
+pub fn take_spawned() -> Vec<WasmApp> {
+    SPAWN_QUEUE
+        .lock()
+        .drain(..)
+        .map(|Spawned(app)| app)
+        .collect()
+}
+
+/// App name derived from its path, e.g. `/bin/terminal.wasm` -> `terminal`
+pub fn app_name(path: &str) -> &str {
+    let file = path.rsplit('/').next().unwrap_or(path);
+    file.strip_suffix(".wasm").unwrap_or(file)
+}
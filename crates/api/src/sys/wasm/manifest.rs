@@ -0,0 +1,103 @@
+/// App manifests: per-app settings stored next to the module
+///
+/// `/bin/editor.wasm` is described by `/bin/editor.manifest`, one
+/// `key = value` per line (`#` starts a comment):
+///
+/// ```text
+/// # sandbox profile the app runs under
+/// profile = restrictive
+/// # record call frames for backtraces, at some cost in speed
+/// backtrace = true
+/// ```
+///
+/// An app without a manifest runs under the `default` profile. Modules missing
+/// from the trusted module list run under `sandboxed` instead; see `trust`.
+use crate::sys::{
+    error::{AgaveError, AgaveResult},
+    fs,
+    security::{self, SandboxProfile},
+};
+use alloc::{
+    format,
+    string::{String, ToString},
+};
+
+/// Profile used when an app has no manifest or does not name one
+pub const DEFAULT_PROFILE: &str = "default";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppManifest {
+    /// Name of the `security::SandboxProfile` the app is confined by
+    pub profile: String,
+    /// Whether the app is instrumented to record frames for its backtraces
+    /// (see `backtrace`)
+    pub backtrace: bool,
+}
+
+impl Default for AppManifest {
+    fn default() -> Self {
+        Self {
+            profile: DEFAULT_PROFILE.to_string(),
+            backtrace: false,
+        }
+    }
+}
+
+impl AppManifest {
+    /// Parse manifest text; unknown keys are ignored so older kernels can
+    /// load newer manifests
+    pub fn parse(text: &str) -> AgaveResult<Self> {
+        let mut manifest = Self::default();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or(AgaveError::InvalidParameter)?;
+            match key.trim() {
+                "profile" => manifest.profile = value.trim().to_string(),
+                "backtrace" => {
+                    manifest.backtrace = value
+                        .trim()
+                        .parse()
+                        .map_err(|_| AgaveError::InvalidParameter)?
+                }
+                key => log::warn!("WASM: unknown manifest key '{}'", key),
+            }
+        }
+        Ok(manifest)
+    }
+
+    /// Load the manifest of the app stored at `app_path`, falling back to the
+    /// default manifest when there is none
+    pub fn load(app_path: &str) -> AgaveResult<Self> {
+        match fs::read_file(&manifest_path(app_path)) {
+            Ok(content) => Self::parse(&String::from_utf8_lossy(&content)).inspect_err(|_| {
+                log::error!("WASM: malformed manifest for {}", app_path);
+            }),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    /// Store this manifest for the app at `app_path`
+    pub fn save(&self, app_path: &str) -> AgaveResult<()> {
+        let content = format!(
+            "profile = {}\nbacktrace = {}\n",
+            self.profile, self.backtrace
+        );
+        fs::write_file(&manifest_path(app_path), content.into_bytes())
+    }
+
+    /// The registered sandbox profile this manifest names
+    pub fn sandbox_profile(&self) -> AgaveResult<SandboxProfile> {
+        security::get_sandbox_profile(&self.profile).ok_or_else(|| {
+            log::error!("WASM: unknown sandbox profile '{}'", self.profile);
+            AgaveError::InvalidParameter
+        })
+    }
+}
+
+/// Path of the manifest describing the app at `app_path`
+pub fn manifest_path(app_path: &str) -> String {
+    let stem = app_path.strip_suffix(".wasm").unwrap_or(app_path);
+    format!("{}.manifest", stem)
+}
@@ -0,0 +1,1800 @@
+#![allow(unused_mut)]
+pub mod backtrace;
+pub mod context;
+pub mod limits;
+pub mod loader;
+pub mod manifest;
+pub mod snapshot;
+pub mod sysinfo;
+pub mod trust;
+
+pub use context::AppContext;
+pub use manifest::AppManifest;
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    sync::Arc,
+    vec,
+    vec::Vec,
+};
+use backtrace::Symbols;
+use core::sync::atomic::Ordering;
+use noto_sans_mono_bitmap::FontWeight;
+use sha2::{Digest, Sha256};
+use wasmi::{
+    core::TrapCode, Caller, Config, Engine, Extern, Func, Instance, Linker, Memory, Module, Store,
+    TypedResumableCall, TypedResumableCallHostTrap, TypedResumableCallOutOfFuel, Val,
+};
+
+use super::{
+    diagnostics::{add_diagnostic, DiagnosticCategory, DiagnosticLevel},
+    error::{AgaveError, AgaveResult, WasmError},
+    framebuffer::{
+        compositor,
+        image::{BlitArea, Image, ImageFormat, Scaling},
+        shapes::Coordinate,
+        text::Font,
+        RGBA,
+    },
+    fs,
+    globals::Input,
+    input::{self, Event},
+    interrupts::TIME_MS,
+    ipc::{self, pipes::MAX_PIPE_BUFFER_SIZE, IpcHandle},
+    process::{self, Priority, ProcessId},
+    security::{self, SandboxProfile, SecurityEvent},
+    wasi,
+};
+
+/// What the runtime does with an app after it traps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RestartPolicy {
+    /// Leave the app faulted; the other apps keep running
+    #[default]
+    Never,
+    /// Re-instantiate the app after `backoff_ms`, doubling the delay after
+    /// every failed attempt, and give up after `max_restarts` restarts
+    OnFailure { max_restarts: u32, backoff_ms: u64 },
+}
+
+/// How much guest code an app may run per frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuBudget {
+    /// Fuel (roughly one unit per instruction) granted each frame
+    pub fuel_per_frame: u64,
+    /// Consecutive frames an app may stay suspended before it is killed
+    pub max_overrun_frames: u32,
+}
+
+impl Default for CpuBudget {
+    fn default() -> Self {
+        Self {
+            fuel_per_frame: 10_000_000,
+            max_overrun_frames: 300,
+        }
+    }
+}
+
+// `blit` flags: apply source alpha, and filter when scaling
+const BLIT_BLEND: i32 = 1;
+const BLIT_BILINEAR: i32 = 2;
+
+// `spawn` writes the parent's stdin, stdout and stderr handles as `u64`
+const STDIO_SIZE: i32 = 24;
+// Return values of the pipe host functions besides byte counts
+const PIPE_PENDING: i32 = -1;
+const PIPE_ERROR: i32 = -2;
+
+// Copy `ptr..ptr + len` out of the app's memory
+fn guest_bytes(caller: &Caller<'_, AppContext>, ptr: i32, len: i32) -> Option<Vec<u8>> {
+    let Some(Extern::Memory(mem)) = caller.get_export("memory") else {
+        return None;
+    };
+    let start = ptr as u32 as usize;
+    let bytes = mem
+        .data(caller)
+        .get(start..start.checked_add(len as u32 as usize)?)?;
+    Some(bytes.to_vec())
+}
+
+// Copy the UTF-8 string at `ptr..ptr + len` out of the app's memory
+fn guest_str(caller: &Caller<'_, AppContext>, ptr: i32, len: i32) -> Option<String> {
+    String::from_utf8(guest_bytes(caller, ptr, len)?).ok()
+}
+
+// NUL-separated strings at `ptr..ptr + len`, skipping empty ones
+fn guest_list(caller: &Caller<'_, AppContext>, ptr: i32, len: i32) -> Option<Vec<String>> {
+    let list = guest_str(caller, ptr, len)?;
+    Some(
+        list.split('\0')
+            .filter(|item| !item.is_empty())
+            .map(String::from)
+            .collect(),
+    )
+}
+
+// The pipe behind a guest handle, if the app owns it
+fn owned_pipe(caller: &Caller<'_, AppContext>, handle: i64) -> Option<IpcHandle> {
+    let handle = IpcHandle::from(handle as u64);
+    ipc::is_owner(handle, caller.data().pid.as_u64() as u32).then_some(handle)
+}
+
+// Copy `data` to `ptr..ptr + len` in the app's memory if it fits. Returns
+// the length of `data`, or -1 if the buffer is out of bounds.
+fn write_guest(caller: &mut Caller<'_, AppContext>, ptr: i32, len: i32, data: &[u8]) -> i32 {
+    let Some(Extern::Memory(mem)) = caller.get_export("memory") else {
+        return -1;
+    };
+    let (start, len) = (ptr as u32 as usize, len as u32 as usize);
+    if start
+        .checked_add(len)
+        .is_none_or(|end| end > mem.data(&*caller).len())
+    {
+        return -1;
+    }
+    if data.len() <= len && mem.write(&mut *caller, start, data).is_err() {
+        return -1;
+    }
+    data.len() as i32
+}
+
+// Font for a guest size in pixels and weight: 0 regular, 1 bold, 2 light
+fn text_font(size: i32, weight: i32) -> Font {
+    let weight = match weight {
+        1 => FontWeight::Bold,
+        2 => FontWeight::Light,
+        _ => FontWeight::Regular,
+    };
+    Font::new(size.max(0) as usize, weight)
+}
+
+fn pack_size(width: usize, height: usize) -> i64 {
+    ((height as i64) << 32) | width as i64
+}
+
+// Whether the app's window has input focus
+fn has_focus(caller: &Caller<'_, AppContext>) -> bool {
+    input::focus() == Some(caller.data().pid)
+}
+
+/// Lifecycle state of a WASM app
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppState {
+    Running,
+    /// The app trapped in `function` and is no longer being called
+    Faulted {
+        function: &'static str,
+        trap: String,
+        at_ms: u64,
+    },
+    /// The app called `proc_exit`; its resources have been released
+    Exited {
+        code: i32,
+    },
+}
+
+pub struct WasmApp {
+    pid: ProcessId,
+    engine: Engine,
+    module: Module,
+    store: Store<AppContext>,
+    instance: Instance,
+    memory: Option<Memory>,
+    state: AppState,
+    restart_policy: RestartPolicy,
+    restarts: u32,
+    next_restart_ms: u64,
+    budget: CpuBudget,
+    // Call paused between frames and the export it started in
+    suspended: Option<(&'static str, Suspended)>,
+    overrun_frames: u32,
+    // SHA-256 of the module bytes, which snapshots are tied to
+    module_hash: [u8; 32],
+    source: Option<Source>,
+}
+
+/// Why a call into an app was paused
+enum Suspended {
+    /// It ran out of fuel; resumed on the next frame
+    OutOfFuel(TypedResumableCallOutOfFuel<()>),
+    /// It is waiting in `poll_oneoff`; resumed on the first frame after the
+    /// deadline
+    Sleeping(wasi::clocks::Sleep, TypedResumableCallHostTrap<()>),
+}
+
+/// Module file an app was loaded from, watched for changes
+struct Source {
+    path: String,
+    // Modification time and size when last checked
+    version: (u64, u64),
+}
+
+fn file_version(path: &str) -> AgaveResult<(u64, u64)> {
+    let metadata = fs::metadata(path)?;
+    Ok((metadata.modified_time, metadata.size))
+}
+
+impl WasmApp {
+    /// Validate, compile and instantiate a module confined by `profile`, in
+    /// a new window. With `backtrace` it records call frames for its
+    /// backtraces. Does not run `_start`.
+    pub fn new(
+        name: &str,
+        wasm: Vec<u8>,
+        profile: SandboxProfile,
+        backtrace: bool,
+    ) -> AgaveResult<Self> {
+        Self::with_parent(name, wasm, profile, backtrace, None)
+    }
+
+    /// Like `new`, registering the app as a child of `parent` so the parent
+    /// can collect its exit code
+    pub fn with_parent(
+        name: &str,
+        wasm: Vec<u8>,
+        profile: SandboxProfile,
+        backtrace: bool,
+        parent: Option<ProcessId>,
+    ) -> AgaveResult<Self> {
+        log::info!("WASM: Creating app '{}' from {} bytes", name, wasm.len());
+        let module_hash = Sha256::digest(&wasm).into();
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let (module, symbols) = Self::compile(&engine, &wasm, backtrace)
+            .map_err(|reason| Self::load_error(name, WasmError::InvalidModule, reason))?;
+
+        let pid = process::register_process(name.to_string(), Priority::Normal, parent)?;
+        let budget = CpuBudget::default();
+        let surface = compositor::open_window(pid, name);
+        let mut ctx = AppContext::new(pid, name, surface, profile);
+        ctx.symbols = symbols;
+        let (store, instance, memory) =
+            match Self::instantiate(&engine, &module, ctx, budget.fuel_per_frame) {
+                Ok(instantiated) => instantiated,
+                Err(e) => {
+                    compositor::close_window(pid);
+                    let _ = process::exit_process(pid, -1);
+                    return Err(Self::load_error(
+                        name,
+                        WasmError::InstantiationFailed,
+                        e.to_string(),
+                    ));
+                }
+            };
+
+        Ok(Self {
+            pid,
+            engine,
+            module,
+            store,
+            instance,
+            memory,
+            state: AppState::Running,
+            restart_policy: RestartPolicy::default(),
+            restarts: 0,
+            next_restart_ms: 0,
+            budget,
+            suspended: None,
+            overrun_frames: 0,
+            module_hash,
+            source: None,
+        })
+    }
+
+    /// Validate and compile `wasm` with its symbols, instrumented to record
+    /// frames if `backtrace` is set, or say why it is not a usable module
+    fn compile(
+        engine: &Engine,
+        wasm: &[u8],
+        backtrace: bool,
+    ) -> Result<(Module, Arc<Symbols>), String> {
+        if !wasm.starts_with(b"\0asm") {
+            return Err("missing WebAssembly magic number".to_string());
+        }
+        // Components run through their program module; see `wasi::component`
+        if wasi::component::is_component(wasm) {
+            let module = wasi::component::main_module(engine, wasm).map_err(|e| e.message)?;
+            return Ok((module, Arc::default()));
+        }
+        if !backtrace {
+            let symbols = backtrace::symbols(wasm).unwrap_or_default();
+            return Module::new(engine, wasm)
+                .map(|module| (module, Arc::new(symbols)))
+                .map_err(|e| e.to_string());
+        }
+        match backtrace::instrument(wasm) {
+            Ok((instrumented, symbols)) => Module::new(engine, &instrumented[..])
+                .map(|module| (module, Arc::new(symbols)))
+                .map_err(|e| e.to_string()),
+            // Let wasmi say what is wrong with the module
+            Err(e) => {
+                log::debug!("WASM: cannot add backtraces: {}", e);
+                Module::new(engine, wasm)
+                    .map(|module| (module, Arc::default()))
+                    .map_err(|e| e.to_string())
+            }
+        }
+    }
+
+    /// Report why a module could not be loaded
+    fn load_error(name: &str, kind: WasmError, reason: String) -> AgaveError {
+        log::error!("WASM: failed to load '{}': {}: {}", name, kind, reason);
+        add_diagnostic(
+            DiagnosticLevel::Error,
+            DiagnosticCategory::Tasks,
+            format!("WASM app '{}' failed to load", name),
+            Some(format!("{}: {}", kind, reason)),
+        );
+        AgaveError::WasmError(kind)
+    }
+
+    /// Set the policy applied when this app traps
+    pub fn with_restart_policy(mut self, policy: RestartPolicy) -> Self {
+        self.restart_policy = policy;
+        self
+    }
+
+    /// Set the per-frame CPU budget of this app
+    pub fn with_cpu_budget(mut self, budget: CpuBudget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Replace the arguments and environment the app sees through WASI
+    pub fn with_args(mut self, args: Vec<String>, env: Vec<(String, String)>) -> Self {
+        let wasi = &mut self.store.data_mut().wasi;
+        wasi.args = args;
+        wasi.env = env;
+        self
+    }
+
+    /// Connect the app's stdin, stdout and stderr to pipes
+    pub fn with_stdio(mut self, stdio: [Option<IpcHandle>; 3]) -> Self {
+        self.store.data_mut().wasi.stdio = stdio;
+        self
+    }
+
+    pub fn state(&self) -> &AppState {
+        &self.state
+    }
+
+    pub fn is_faulted(&self) -> bool {
+        matches!(self.state, AppState::Faulted { .. })
+    }
+
+    /// Whether a call ran past its budget and is waiting to be resumed
+    pub fn is_suspended(&self) -> bool {
+        self.suspended.is_some()
+    }
+
+    pub fn pid(&self) -> ProcessId {
+        self.pid
+    }
+
+    pub fn name(&self) -> &str {
+        &self.store.data().name
+    }
+
+    /// SHA-256 of the module bytes the app was created from
+    pub fn module_hash(&self) -> &[u8; 32] {
+        &self.module_hash
+    }
+
+    /// Exit code passed to `proc_exit`, once the app has exited
+    pub fn exit_code(&self) -> Option<i32> {
+        match self.state {
+            AppState::Exited { code } => Some(code),
+            _ => None,
+        }
+    }
+
+    fn instantiate(
+        engine: &Engine,
+        module: &Module,
+        ctx: AppContext,
+        fuel: u64,
+    ) -> Result<(Store<AppContext>, Instance, Option<Memory>), wasmi::Error> {
+        let mut store = Store::new(engine, ctx);
+        store.set_fuel(fuel)?;
+        store.limiter(|ctx| &mut ctx.limiter);
+        if let Some(index) = store.data().symbols.stack_memory() {
+            store.data_mut().limiter.expect_backtrace_stack(index);
+        }
+
+        let mut linker = <Linker<AppContext>>::new(engine);
+
+        log::info!("WASM: Setting up function bindings...");
+
+        // Host function to grow memory from WASM; growth is capped by the
+        // store's limiter like `memory.grow`
+        let grow_memory = Func::wrap(
+            &mut store,
+            |mut caller: Caller<'_, AppContext>, pages: u64| -> i32 {
+                // Try to get the exported memory
+                if let Some(Extern::Memory(mem)) = caller.get_export("memory") {
+                    match mem.grow(&mut caller, pages) {
+                        Ok(_) => 1,
+                        Err(_) => 0,
+                    }
+                } else {
+                    0
+                }
+            },
+        );
+        linker.define("agave", "grow_memory", grow_memory)?;
+        let draw_circle = Func::wrap(
+            &mut store,
+            |caller: Caller<'_, AppContext>,
+             x: i32,
+             y: i32,
+             radius: i32,
+             r: i32,
+             g: i32,
+             b: i32,
+             a: i32| {
+                let fb = unsafe { caller.data().fb.as_mut().unwrap() };
+                fb.draw_circle(
+                    Coordinate {
+                        x: x as isize,
+                        y: y as isize,
+                    },
+                    radius as usize,
+                    RGBA {
+                        r: r as u8,
+                        g: g as u8,
+                        b: b as u8,
+                        a: a as u8,
+                    },
+                );
+            },
+        );
+        linker.define("agave", "draw_circle", draw_circle)?;
+
+        // Add fill_circle function
+        let fill_circle = Func::wrap(
+            &mut store,
+            |caller: Caller<'_, AppContext>,
+             x: i32,
+             y: i32,
+             radius: i32,
+             r: i32,
+             g: i32,
+             b: i32,
+             a: i32| {
+                let fb = unsafe { caller.data().fb.as_mut().unwrap() };
+                // Fill circle using Bresenham circle algorithm
+                for dy in -radius..=radius {
+                    for dx in -radius..=radius {
+                        if dx * dx + dy * dy <= radius * radius {
+                            let px = x + dx;
+                            let py = y + dy;
+                            if px >= 0 && py >= 0 && px < fb.w as i32 && py < fb.h as i32 {
+                                if let Some(pixel) =
+                                    fb.pixels.get_mut((py * fb.w as i32 + px) as usize)
+                                {
+                                    pixel.r = r as u8;
+                                    pixel.g = g as u8;
+                                    pixel.b = b as u8;
+                                    pixel.a = a as u8;
+                                }
+                            }
+                        }
+                    }
+                }
+            },
+        );
+
+        linker.define("agave", "fill_circle", fill_circle)?;
+
+        // Add fill_gradient function
+        let fill_gradient = Func::wrap(
+            &mut store,
+            |caller: Caller<'_, AppContext>,
+             x0: i32,
+             y0: i32,
+             x1: i32,
+             y1: i32,
+             r1: i32,
+             g1: i32,
+             b1: i32,
+             a1: i32,
+             r2: i32,
+             g2: i32,
+             b2: i32,
+             a2: i32| {
+                let fb = unsafe { caller.data().fb.as_mut().unwrap() };
+                fb.fill_gradient(
+                    Coordinate {
+                        x: x0 as isize,
+                        y: y0 as isize,
+                    },
+                    Coordinate {
+                        x: x1 as isize,
+                        y: y1 as isize,
+                    },
+                    RGBA {
+                        r: r1 as u8,
+                        g: g1 as u8,
+                        b: b1 as u8,
+                        a: a1 as u8,
+                    },
+                    RGBA {
+                        r: r2 as u8,
+                        g: g2 as u8,
+                        b: b2 as u8,
+                        a: a2 as u8,
+                    },
+                );
+            },
+        );
+        linker.define("agave", "fill_gradient", fill_gradient)?;
+
+        // Add draw_triangle function
+        let draw_triangle = Func::wrap(
+            &mut store,
+            |caller: Caller<'_, AppContext>,
+             x1: i32,
+             y1: i32,
+             x2: i32,
+             y2: i32,
+             x3: i32,
+             y3: i32,
+             r: i32,
+             g: i32,
+             b: i32,
+             a: i32| {
+                let fb = unsafe { caller.data().fb.as_mut().unwrap() };
+                let color = RGBA {
+                    r: r as u8,
+                    g: g as u8,
+                    b: b as u8,
+                    a: a as u8,
+                };
+
+                // Draw triangle edges
+                fb.draw_line(
+                    Coordinate {
+                        x: x1 as isize,
+                        y: y1 as isize,
+                    },
+                    Coordinate {
+                        x: x2 as isize,
+                        y: y2 as isize,
+                    },
+                    color,
+                );
+                fb.draw_line(
+                    Coordinate {
+                        x: x2 as isize,
+                        y: y2 as isize,
+                    },
+                    Coordinate {
+                        x: x3 as isize,
+                        y: y3 as isize,
+                    },
+                    color,
+                );
+                fb.draw_line(
+                    Coordinate {
+                        x: x3 as isize,
+                        y: y3 as isize,
+                    },
+                    Coordinate {
+                        x: x1 as isize,
+                        y: y1 as isize,
+                    },
+                    color,
+                );
+            },
+        );
+
+        linker.define("agave", "draw_triangle", draw_triangle)?;
+
+        let fill_rectangle = Func::wrap(
+            &mut store,
+            |caller: Caller<'_, AppContext>,
+             x: i32,
+             y: i32,
+             width: i32,
+             height: i32,
+             r: i32,
+             g: i32,
+             b: i32,
+             a: i32| {
+                let fb = unsafe { caller.data().fb.as_mut().unwrap() };
+                fb.fill_rectangle(
+                    Coordinate {
+                        x: x as isize,
+                        y: y as isize,
+                    },
+                    width as usize,
+                    height as usize,
+                    RGBA {
+                        r: r as u8,
+                        g: g as u8,
+                        b: b as u8,
+                        a: a as u8,
+                    },
+                );
+            },
+        );
+
+        linker.define("agave", "fill_rectangle", fill_rectangle)?;
+
+        let draw_rectangle = Func::wrap(
+            &mut store,
+            |caller: Caller<'_, AppContext>,
+             x: i32,
+             y: i32,
+             width: i32,
+             height: i32,
+             r: i32,
+             g: i32,
+             b: i32,
+             a: i32| {
+                let fb = unsafe { caller.data().fb.as_mut().unwrap() };
+                fb.draw_rectangle(
+                    Coordinate {
+                        x: x as isize,
+                        y: y as isize,
+                    },
+                    width as usize,
+                    height as usize,
+                    RGBA {
+                        r: r as u8,
+                        g: g as u8,
+                        b: b as u8,
+                        a: a as u8,
+                    },
+                );
+            },
+        );
+
+        linker.define("agave", "draw_rectangle", draw_rectangle)?;
+
+        let draw_rounded_rectangle = Func::wrap(
+            &mut store,
+            |caller: Caller<'_, AppContext>,
+             x: i32,
+             y: i32,
+             width: i32,
+             height: i32,
+             radius: i32,
+             r: i32,
+             g: i32,
+             b: i32,
+             a: i32| {
+                let fb = unsafe { caller.data().fb.as_mut().unwrap() };
+                fb.draw_rounded_rectangle(
+                    Coordinate {
+                        x: x as isize,
+                        y: y as isize,
+                    },
+                    width as usize,
+                    height as usize,
+                    radius as usize,
+                    RGBA {
+                        r: r as u8,
+                        g: g as u8,
+                        b: b as u8,
+                        a: a as u8,
+                    },
+                );
+            },
+        );
+        linker.define("agave", "draw_rounded_rectangle", draw_rounded_rectangle)?;
+
+        let draw_line = Func::wrap(
+            &mut store,
+            |caller: Caller<'_, AppContext>,
+             x0: i32,
+             y0: i32,
+             x1: i32,
+             y1: i32,
+             r: i32,
+             g: i32,
+             b: i32,
+             a: i32| {
+                let fb = unsafe { caller.data().fb.as_mut().unwrap() };
+                fb.draw_line(
+                    Coordinate {
+                        x: x0 as isize,
+                        y: y0 as isize,
+                    },
+                    Coordinate {
+                        x: x1 as isize,
+                        y: y1 as isize,
+                    },
+                    RGBA {
+                        r: r as u8,
+                        g: g as u8,
+                        b: b as u8,
+                        a: a as u8,
+                    },
+                );
+            },
+        );
+
+        linker.define("agave", "draw_line", draw_line)?;
+
+        // Draw the UTF-8 string at `ptr..ptr + len` with the kernel font;
+        // `color` is 0xRRGGBBAA. Returns the drawn size like `measure_text`,
+        // or -1 if the string is out of bounds or not UTF-8.
+        let draw_text = Func::wrap(
+            &mut store,
+            |caller: Caller<'_, AppContext>,
+             ptr: i32,
+             len: i32,
+             x: i32,
+             y: i32,
+             color: i32,
+             size: i32,
+             weight: i32|
+             -> i64 {
+                let Some(text) = guest_str(&caller, ptr, len) else {
+                    return -1;
+                };
+                let [r, g, b, a] = (color as u32).to_be_bytes();
+                let fb = unsafe { caller.data().fb.as_mut().unwrap() };
+                let (width, height) = fb.draw_string(
+                    Coordinate {
+                        x: x as isize,
+                        y: y as isize,
+                    },
+                    &text,
+                    RGBA { r, g, b, a },
+                    text_font(size, weight),
+                );
+                pack_size(width, height)
+            },
+        );
+
+        linker.define("agave", "draw_text", draw_text)?;
+
+        // Size `draw_text` would draw the string at `ptr..ptr + len` at:
+        // height in the high 32 bits, width in the low 32 bits
+        let measure_text = Func::wrap(
+            &mut store,
+            |caller: Caller<'_, AppContext>, ptr: i32, len: i32, size: i32, weight: i32| -> i64 {
+                let Some(text) = guest_str(&caller, ptr, len) else {
+                    return -1;
+                };
+                let (width, height) = text_font(size, weight).measure(&text);
+                pack_size(width, height)
+            },
+        );
+
+        linker.define("agave", "measure_text", measure_text)?;
+
+        // Copy the (`sx`, `sy`, `sw`, `sh`) part of a `width` x `height`
+        // image at `ptr` to `dw` x `dh` pixels at (`dx`, `dy`); a zero `dw` or
+        // `dh` keeps the source size. `format` is 0 RGBA8888, 1 BGRA8888 or
+        // 2 RGB565, and `flags` combines BLIT_BLEND and BLIT_BILINEAR.
+        // Returns 0, or -1 if the image or rectangle is invalid.
+        let blit = Func::wrap(
+            &mut store,
+            |caller: Caller<'_, AppContext>,
+             ptr: i32,
+             width: i32,
+             height: i32,
+             format: i32,
+             sx: i32,
+             sy: i32,
+             sw: i32,
+             sh: i32,
+             dx: i32,
+             dy: i32,
+             dw: i32,
+             dh: i32,
+             flags: i32|
+             -> i32 {
+                let format = match format {
+                    0 => ImageFormat::Rgba8888,
+                    1 => ImageFormat::Bgra8888,
+                    2 => ImageFormat::Rgb565,
+                    _ => return -1,
+                };
+                let size = |v: i32| usize::try_from(v).ok();
+                let (Some(width), Some(height), Some(src), Some(dest_size)) = (
+                    size(width),
+                    size(height),
+                    size(sx).zip(size(sy)).zip(size(sw).zip(size(sh))),
+                    size(dw).zip(size(dh)),
+                ) else {
+                    return -1;
+                };
+                let ((x, y), (sw, sh)) = src;
+                let src = BlitArea {
+                    x,
+                    y,
+                    width: sw,
+                    height: sh,
+                };
+                let dest_size = match dest_size {
+                    (0, _) | (_, 0) => (sw, sh),
+                    size => size,
+                };
+                let scaling = if flags & BLIT_BILINEAR != 0 {
+                    Scaling::Bilinear
+                } else {
+                    Scaling::Nearest
+                };
+                let Some(Extern::Memory(mem)) = caller.get_export("memory") else {
+                    return -1;
+                };
+                let data = mem.data(&caller).get(ptr as u32 as usize..).unwrap_or(&[]);
+                let Some(image) = Image::new(data, width, height, format) else {
+                    return -1;
+                };
+                let fb = unsafe { caller.data().fb.as_mut().unwrap() };
+                let blitted = fb.blit(
+                    &image,
+                    src,
+                    Coordinate {
+                        x: dx as isize,
+                        y: dy as isize,
+                    },
+                    dest_size,
+                    flags & BLIT_BLEND != 0,
+                    scaling,
+                );
+                if blitted {
+                    0
+                } else {
+                    -1
+                }
+            },
+        );
+
+        linker.define("agave", "blit", blit)?;
+
+        let set_pixel = Func::wrap(
+            &mut store,
+            |caller: Caller<'_, AppContext>, x: i32, y: i32, r: i32, g: i32, b: i32, a: i32| {
+                let fb = unsafe { caller.data().fb.as_mut().unwrap() };
+                fb.pixels
+                    .get_mut((y * (fb.w as i32) + x) as usize)
+                    .map(|p| {
+                        p.r = r as u8;
+                        p.g = g as u8;
+                        p.b = b as u8;
+                        p.a = a as u8;
+                    });
+            },
+        );
+
+        linker.define("agave", "set_pixel", set_pixel)?;
+
+        let set_pixels_from_to = Func::wrap(
+            &mut store,
+            |caller: Caller<'_, AppContext>,
+             x0: i32,
+             y0: i32,
+             x1: i32,
+             y1: i32,
+             r: i32,
+             g: i32,
+             b: i32,
+             a: i32| {
+                let fb = unsafe { caller.data().fb.as_mut().unwrap() };
+                for y in y0..y1 {
+                    for x in x0..x1 {
+                        fb.pixels
+                            .get_mut((y * (fb.w as i32) + x) as usize)
+                            .map(|p| {
+                                p.r = r as u8;
+                                p.g = g as u8;
+                                p.b = b as u8;
+                                p.a = a as u8;
+                            });
+                    }
+                }
+            },
+        );
+
+        linker.define("agave", "set_pixels_from_to", set_pixels_from_to)?;
+
+        let get_width = Func::wrap(&mut store, |caller: Caller<'_, AppContext>| {
+            let fb = unsafe { caller.data().fb.as_mut().unwrap() };
+            fb.w as i32
+        });
+
+        linker.define("agave", "get_width", get_width)?;
+
+        let get_height = Func::wrap(&mut store, |caller: Caller<'_, AppContext>| {
+            let fb = unsafe { caller.data().fb.as_mut().unwrap() };
+            fb.h as i32
+        });
+
+        linker.define("agave", "get_height", get_height)?;
+
+        let get_time_ms = Func::wrap(&mut store, |_caller: Caller<'_, AppContext>| -> u64 {
+            crate::sys::interrupts::TIME_MS.load(core::sync::atomic::Ordering::Relaxed)
+        });
+
+        linker.define("agave", "get_time_ms", get_time_ms)?;
+
+        // Keyboard input functions; they read the global key state, so apps
+        // whose window is not focused see no keys
+        let is_key_pressed = Func::wrap(
+            &mut store,
+            |caller: Caller<'_, AppContext>, key_code: i32| -> i32 {
+                if !has_focus(&caller) {
+                    return 0;
+                }
+                let input = crate::sys::globals::INPUT.read();
+                if key_code >= 0 && (key_code as usize) < input.keys.len() {
+                    match input.keys[key_code as usize] {
+                        crate::sys::globals::KeyState::OnFromOff => 1,
+                        _ => 0,
+                    }
+                } else {
+                    0
+                }
+            },
+        );
+
+        linker.define("agave", "is_key_pressed", is_key_pressed)?;
+
+        let is_key_down = Func::wrap(
+            &mut store,
+            |caller: Caller<'_, AppContext>, key_code: i32| -> i32 {
+                if !has_focus(&caller) {
+                    return 0;
+                }
+                let input = crate::sys::globals::INPUT.read();
+                if key_code >= 0 && (key_code as usize) < input.keys.len() {
+                    match input.keys[key_code as usize] {
+                        crate::sys::globals::KeyState::On
+                        | crate::sys::globals::KeyState::OnFromOff
+                        | crate::sys::globals::KeyState::OnTransientOff => 1,
+                        _ => 0,
+                    }
+                } else {
+                    0
+                }
+            },
+        );
+
+        linker.define("agave", "is_key_down", is_key_down)?;
+
+        let is_key_released = Func::wrap(
+            &mut store,
+            |caller: Caller<'_, AppContext>, key_code: i32| -> i32 {
+                if !has_focus(&caller) {
+                    return 0;
+                }
+                let input = crate::sys::globals::INPUT.read();
+                if key_code >= 0 && (key_code as usize) < input.keys.len() {
+                    match input.keys[key_code as usize] {
+                        crate::sys::globals::KeyState::OffFromOn => 1,
+                        _ => 0,
+                    }
+                } else {
+                    0
+                }
+            },
+        );
+
+        linker.define("agave", "is_key_released", is_key_released)?;
+
+        let get_key_history_count =
+            Func::wrap(&mut store, |caller: Caller<'_, AppContext>| -> i32 {
+                if !has_focus(&caller) {
+                    return 0;
+                }
+                let input = crate::sys::globals::INPUT.read();
+                // Return the number of events we have, up to the buffer size
+                core::cmp::min(input.history_last_index, 64) as i32
+            });
+
+        linker.define("agave", "get_key_history_count", get_key_history_count)?;
+
+        let get_key_history_event = Func::wrap(
+            &mut store,
+            |caller: Caller<'_, AppContext>, index: i32| -> i64 {
+                if !has_focus(&caller) {
+                    return 0;
+                }
+                let input = crate::sys::globals::INPUT.read();
+                if index >= 0
+                    && (index as usize) < 64
+                    && (index as usize) < input.history_last_index
+                {
+                    let event = input.history_ring[index as usize];
+                    // Pack key code in low 32 bits, pressed state in high 32 bits
+                    let pressed_bits = if event.trigger { 1i64 << 32 } else { 0 };
+                    pressed_bits | (event.key as i64)
+                } else {
+                    0
+                }
+            },
+        );
+
+        linker.define("agave", "get_key_history_event", get_key_history_event)?;
+
+        // Write the oldest queued input event to `event_ptr` (see
+        // `input::Event::encode`). Returns 1 if an event was written, 0 if the
+        // queue is empty and -1 if the buffer is out of bounds.
+        let poll_event = Func::wrap(
+            &mut store,
+            |mut caller: Caller<'_, AppContext>, event_ptr: i32| -> i32 {
+                let Some((event, time_ms)) = caller.data_mut().events.pop() else {
+                    return 0;
+                };
+                let written = match caller.get_export("memory") {
+                    Some(Extern::Memory(mem)) => mem
+                        .write(
+                            &mut caller,
+                            event_ptr as u32 as usize,
+                            &event.encode(time_ms),
+                        )
+                        .is_ok(),
+                    _ => false,
+                };
+                if written {
+                    1
+                } else {
+                    caller.data_mut().events.unpop(event, time_ms);
+                    -1
+                }
+            },
+        );
+
+        linker.define("agave", "poll_event", poll_event)?;
+
+        // System statistics in the layouts `sysinfo` documents. Each takes a
+        // buffer and returns the size of the data, which is only written if
+        // it fits; call with a zero length to size the buffer.
+        for (name, source) in sysinfo::HOST_FUNCTIONS {
+            let f = Func::wrap(
+                &mut store,
+                move |mut caller: Caller<'_, AppContext>, ptr: i32, len: i32| -> i32 {
+                    write_guest(&mut caller, ptr, len, &source())
+                },
+            );
+            linker.define("agave", name, f)?;
+        }
+
+        // Start the program at `path` as a child of this app. `argv` and
+        // `env` are NUL-separated lists, the environment as `KEY=VALUE`; an
+        // empty argv passes just the program name. With a non-zero
+        // `stdio_ptr` the child's streams are piped and the handles of this
+        // app's ends of stdin, stdout and stderr are written there as three
+        // `u64`. Returns the child's pid, or -1.
+        let spawn = Func::wrap(
+            &mut store,
+            |mut caller: Caller<'_, AppContext>,
+             path_ptr: i32,
+             path_len: i32,
+             argv_ptr: i32,
+             argv_len: i32,
+             env_ptr: i32,
+             env_len: i32,
+             stdio_ptr: i32|
+             -> i64 {
+                let (Some(path), Some(mut args), Some(env)) = (
+                    guest_str(&caller, path_ptr, path_len),
+                    guest_list(&caller, argv_ptr, argv_len),
+                    guest_list(&caller, env_ptr, env_len),
+                ) else {
+                    return -1;
+                };
+                let Some(env) = env
+                    .iter()
+                    .map(|var| {
+                        let (key, value) = var.split_once('=')?;
+                        Some((key.to_string(), value.to_string()))
+                    })
+                    .collect::<Option<Vec<_>>>()
+                else {
+                    return -1;
+                };
+                let piped = stdio_ptr != 0;
+                // Check the handle buffer before the child is created
+                if piped && write_guest(&mut caller, stdio_ptr, STDIO_SIZE, &[]) < 0 {
+                    return -1;
+                }
+                if args.is_empty() {
+                    args.push(loader::app_name(&path).to_string());
+                }
+
+                match loader::spawn(caller.data().pid, &path, args, env, piped) {
+                    Ok(child) => {
+                        if let Some(stdio) = child.stdio {
+                            let handles: Vec<u8> = stdio
+                                .iter()
+                                .flat_map(|handle| handle.as_u64().to_le_bytes())
+                                .collect();
+                            write_guest(&mut caller, stdio_ptr, STDIO_SIZE, &handles);
+                        }
+                        child.pid.as_u64() as i64
+                    }
+                    Err(e) => {
+                        log::warn!(
+                            "WASM: app {} cannot spawn {}: {:?}",
+                            caller.data().pid,
+                            path,
+                            e
+                        );
+                        -1
+                    }
+                }
+            },
+        );
+        linker.define("agave", "spawn", spawn)?;
+
+        // Collect the exit code of a child: 1 once it has exited, with the
+        // code written to `code_ptr` as an `i32`, 0 while it is running and
+        // -1 if `pid` is not a child of this app or was already collected
+        let wait = Func::wrap(
+            &mut store,
+            |mut caller: Caller<'_, AppContext>, pid: i64, code_ptr: i32| -> i32 {
+                if write_guest(&mut caller, code_ptr, 4, &[]) < 0 {
+                    return -1;
+                }
+                let child = ProcessId::from(pid as u32);
+                match process::reap_child(caller.data().pid, child) {
+                    Ok(Some(code)) => {
+                        write_guest(&mut caller, code_ptr, 4, &code.to_le_bytes());
+                        1
+                    }
+                    Ok(None) => 0,
+                    Err(_) => -1,
+                }
+            },
+        );
+        linker.define("agave", "wait", wait)?;
+
+        // Pipes from `spawn`. Reads return the bytes read and 0 at end of
+        // file, writes the bytes written; both return `PIPE_PENDING` while
+        // the pipe is empty or full and `PIPE_ERROR` on any other failure.
+        let pipe_read = Func::wrap(
+            &mut store,
+            |mut caller: Caller<'_, AppContext>, handle: i64, ptr: i32, len: i32| -> i32 {
+                let Some(handle) = owned_pipe(&caller, handle) else {
+                    return PIPE_ERROR;
+                };
+                // Nothing is taken from the pipe unless the buffer is valid
+                if write_guest(&mut caller, ptr, len, &[]) < 0 {
+                    return PIPE_ERROR;
+                }
+                let mut buf = vec![0; (len as usize).min(MAX_PIPE_BUFFER_SIZE)];
+                match ipc::pipe_read(handle, &mut buf) {
+                    Ok(n) => write_guest(&mut caller, ptr, len, &buf[..n]),
+                    Err(AgaveError::WouldBlock) => PIPE_PENDING,
+                    Err(_) => PIPE_ERROR,
+                }
+            },
+        );
+        linker.define("agave", "pipe_read", pipe_read)?;
+
+        let pipe_write = Func::wrap(
+            &mut store,
+            |caller: Caller<'_, AppContext>, handle: i64, ptr: i32, len: i32| -> i32 {
+                let (Some(handle), Some(data)) =
+                    (owned_pipe(&caller, handle), guest_bytes(&caller, ptr, len))
+                else {
+                    return PIPE_ERROR;
+                };
+                match ipc::pipe_write(handle, &data) {
+                    Ok(n) => n as i32,
+                    Err(AgaveError::WouldBlock) => PIPE_PENDING,
+                    Err(_) => PIPE_ERROR,
+                }
+            },
+        );
+        linker.define("agave", "pipe_write", pipe_write)?;
+
+        // Close this app's end; the other side then sees end of file or a
+        // broken pipe
+        let pipe_close = Func::wrap(
+            &mut store,
+            |caller: Caller<'_, AppContext>, handle: i64| -> i32 {
+                let Some(handle) = owned_pipe(&caller, handle) else {
+                    return PIPE_ERROR;
+                };
+                match ipc::close_resource(handle, caller.data().pid.as_u64() as u32) {
+                    Ok(()) => 0,
+                    Err(_) => PIPE_ERROR,
+                }
+            },
+        );
+        linker.define("agave", "pipe_close", pipe_close)?;
+
+        // Keep state for the next version of the module; called from the
+        // app's `agave_save_state` export during a hot reload
+        let save_state = Func::wrap(
+            &mut store,
+            |mut caller: Caller<'_, AppContext>, ptr: i32, len: i32| -> i32 {
+                let Some(state) = guest_bytes(&caller, ptr, len) else {
+                    return -1;
+                };
+                caller.data_mut().saved_state = Some(state);
+                0
+            },
+        );
+        linker.define("agave", "save_state", save_state)?;
+
+        // Copy the state kept by the previous version to `ptr..ptr + len`;
+        // called from the app's `agave_load_state` export. Returns the size
+        // of the state, or -1 if there is none or the buffer is out of bounds.
+        let load_state = Func::wrap(
+            &mut store,
+            |mut caller: Caller<'_, AppContext>, ptr: i32, len: i32| -> i32 {
+                let Some(state) = caller.data().saved_state.clone() else {
+                    return -1;
+                };
+                let size = write_guest(&mut caller, ptr, len, &state);
+                // Once copied the state is gone
+                if (0..=len).contains(&size) {
+                    caller.data_mut().saved_state = None;
+                }
+                size
+            },
+        );
+        linker.define("agave", "load_state", load_state)?;
+
+        // Host function to log the app's own backtrace, for debugging
+        let print_backtrace = Func::wrap(&mut store, |caller: Caller<'_, AppContext>| {
+            let frames = backtrace::frames(
+                &caller,
+                caller.get_export(backtrace::TOP_EXPORT),
+                caller.get_export(backtrace::STACK_EXPORT),
+            );
+            let ctx = caller.data();
+            backtrace::report(
+                &ctx.name,
+                ctx.pid,
+                "requested by the app",
+                &ctx.symbols,
+                &frames,
+                DiagnosticLevel::Info,
+            );
+        });
+        linker.define("agave", "print_backtrace", print_backtrace)?;
+
+        // Link comprehensive WASI Preview 1 implementation
+        wasi::preview1::link_preview1_functions(&mut linker, &mut store)?;
+        wasi::preview2::link_preview2_functions(&mut linker)?;
+
+        let instance = linker.instantiate(&mut store, module)?.start(&mut store)?;
+
+        // Try to get the exported memory after instantiation
+        let memory = instance
+            .exports(&store)
+            .filter(|e| e.name() != backtrace::STACK_EXPORT)
+            .find_map(|e| match e.into_extern() {
+                Extern::Memory(mem) => Some(mem),
+                _ => None,
+            });
+
+        Ok((store, instance, memory))
+    }
+
+    /// Queue the events `compositor::route_input` addressed to this app, and
+    /// a `Focus` event if its window gained or lost focus
+    pub fn dispatch_input(&mut self, events: &[(ProcessId, Event, u64)]) {
+        let now = TIME_MS.load(Ordering::Relaxed);
+        let focused = input::focus() == Some(self.pid);
+        let queue = &mut self.store.data_mut().events;
+        queue.set_focused(focused, now);
+        for &(_, event, time_ms) in events.iter().filter(|(pid, ..)| *pid == self.pid) {
+            queue.push(event, time_ms);
+        }
+    }
+
+    /// Grow the WASM memory by the given number of pages (64KiB each). Returns true if successful.
+    pub fn grow_memory(&mut self, pages: u64) -> bool {
+        if let Some(mem) = &self.memory {
+            mem.grow(&mut self.store, pages).is_ok()
+        } else {
+            false
+        }
+    }
+
+    pub fn call(&mut self) {
+        if self.state != AppState::Running {
+            return;
+        }
+
+        let start = self
+            .instance
+            .get_typed_func::<(), ()>(&self.store, "_start");
+
+        match start {
+            Ok(start) => {
+                backtrace::reset(&mut self.store, &self.instance);
+                self.run("_start", |store| start.call_resumable(store, ()))
+            }
+            Err(e) => {
+                log::warn!("WASM: No _start function found: {:?}", e);
+            }
+        }
+    }
+
+    pub fn call_update(&mut self, input: Input) {
+        match self.state {
+            AppState::Running => {}
+            AppState::Faulted { .. } => return self.try_restart(),
+            AppState::Exited { .. } => return,
+        }
+
+        // Finish a paused call before starting a new frame
+        if let Some((function, suspended)) = self.suspended.take() {
+            match suspended {
+                Suspended::OutOfFuel(invocation) => {
+                    self.run(function, |store| invocation.resume(store))
+                }
+                Suspended::Sleeping(sleep, call) if sleep.is_over() => {
+                    let errno = Val::I32(wasi::types::ERRNO_SUCCESS as i32);
+                    self.run(function, |store| call.resume(store, &[errno]))
+                }
+                sleeping => self.suspended = Some((function, sleeping)),
+            }
+            return;
+        }
+
+        let update = self
+            .instance
+            .get_typed_func::<(i32, i32), ()>(&self.store, "update");
+
+        // The pointer is passed relative to the app's window
+        let (x, y) =
+            compositor::local_pointer(self.pid, input.mouse_x as i32, input.mouse_y as i32);
+        match update {
+            Ok(update) => {
+                backtrace::reset(&mut self.store, &self.instance);
+                self.run("update", |store| update.call_resumable(store, (x, y)))
+            }
+            Err(e) => {
+                log::trace!("WASM: No update function found: {:?}", e);
+            }
+        }
+    }
+
+    /// Run one slice of guest code with a fresh frame budget
+    fn run(
+        &mut self,
+        function: &'static str,
+        call: impl FnOnce(&mut Store<AppContext>) -> Result<TypedResumableCall<()>, wasmi::Error>,
+    ) {
+        let fuel = self.budget.fuel_per_frame;
+        if let Err(e) = self.store.set_fuel(fuel) {
+            log::warn!("WASM: failed to refuel app {}: {}", self.pid, e);
+        }
+
+        let started = TIME_MS.load(Ordering::Relaxed);
+        process::set_current_pid(Some(self.pid));
+        let result = call(&mut self.store);
+        process::set_current_pid(None);
+
+        let elapsed = TIME_MS.load(Ordering::Relaxed).saturating_sub(started);
+        let consumed = fuel.saturating_sub(self.store.get_fuel().unwrap_or(0));
+        let overran = matches!(result, Ok(TypedResumableCall::OutOfFuel(_)));
+        process::record_cpu_slice(self.pid, elapsed, consumed, overran);
+
+        match result {
+            Ok(TypedResumableCall::Finished(())) => self.overrun_frames = 0,
+            Ok(TypedResumableCall::OutOfFuel(invocation)) => self.overrun(function, invocation),
+            Ok(TypedResumableCall::HostTrap(trap)) => {
+                if let Some(&sleep) = trap.host_error().downcast_ref::<wasi::clocks::Sleep>() {
+                    self.suspended = Some((function, Suspended::Sleeping(sleep, trap)));
+                    return;
+                }
+                // Other host errors are never resumed
+                self.on_error(function, trap.host_error());
+            }
+            Err(e) => self.on_error(function, &e),
+        }
+    }
+
+    /// Suspend a call that used up its frame budget, or kill the app if it
+    /// has been running over budget for too long
+    fn overrun(&mut self, function: &'static str, invocation: TypedResumableCallOutOfFuel<()>) {
+        self.overrun_frames += 1;
+        let limit = self.budget.fuel_per_frame;
+
+        if self.overrun_frames == 1 || self.overrun_frames > self.budget.max_overrun_frames {
+            security::record_security_event(SecurityEvent::ResourceExhaustion {
+                process_id: self.pid,
+                resource_type: "cpu_fuel".to_string(),
+                limit,
+                requested: limit.saturating_add(invocation.required_fuel()),
+            });
+        }
+
+        if self.overrun_frames > self.budget.max_overrun_frames {
+            log::warn!(
+                "WASM: app {} ran over budget for {} frames in `{}`, killing it",
+                self.pid,
+                self.overrun_frames,
+                function
+            );
+            self.overrun_frames = 0;
+            self.fault(function, &wasmi::Error::from(TrapCode::OutOfFuel));
+        } else {
+            log::debug!(
+                "WASM: app {} exceeded {} fuel in `{}`, suspending",
+                self.pid,
+                limit,
+                function
+            );
+            self.suspended = Some((function, Suspended::OutOfFuel(invocation)));
+        }
+    }
+
+    /// Route an error out of the guest: `proc_exit` ends the app cleanly,
+    /// anything else is a fault
+    fn on_error(&mut self, function: &'static str, error: &wasmi::Error) {
+        match error.i32_exit_status() {
+            Some(code) => self.exit(code),
+            None => self.fault(function, error),
+        }
+    }
+
+    /// Stop the app as if it had called `proc_exit(0)`, e.g. because its
+    /// window was closed
+    pub fn close(&mut self) {
+        if !matches!(self.state, AppState::Exited { .. }) {
+            self.suspended = None;
+            self.exit(0);
+        }
+    }
+
+    /// Stop calling into an app that exited and release what it held
+    fn exit(&mut self, code: i32) {
+        log::info!("WASM: app {} exited with code {}", self.pid, code);
+        self.state = AppState::Exited { code };
+        self.release_resources(false);
+        compositor::close_window(self.pid);
+        let _ = process::exit_process(self.pid, code);
+    }
+
+    /// Close the WASI descriptors and IPC handles owned by this app. With
+    /// `keep_stdio` its standard streams stay open for the next instance.
+    fn release_resources(&mut self, keep_stdio: bool) {
+        let ctx = &mut self.store.data_mut().wasi;
+        let fds = ctx.fs.close_all();
+        for fd in core::mem::take(&mut ctx.sockets) {
+            let _ = wasi::sockets::close_socket(fd);
+        }
+        let pid = self.pid.as_u64() as u32;
+        if keep_stdio {
+            let stdio = self.store.data().wasi.stdio;
+            let keep: Vec<IpcHandle> = stdio.into_iter().flatten().collect();
+            ipc::cleanup_process_resources_except(pid, &keep);
+        } else {
+            ipc::cleanup_process_resources(pid);
+        }
+        log::debug!("WASM: released {} descriptors of app {}", fds, self.pid);
+    }
+
+    /// Record a trap raised by the guest and stop calling into it
+    fn fault(&mut self, function: &'static str, error: &wasmi::Error) {
+        let now = TIME_MS.load(Ordering::Relaxed);
+        let trap = match error.as_trap_code() {
+            Some(code) => format!("{:?}", code),
+            None => error.to_string(),
+        };
+
+        log::error!("WASM: app trapped in `{}`: {}", function, trap);
+        add_diagnostic(
+            DiagnosticLevel::Error,
+            DiagnosticCategory::Tasks,
+            format!("WASM app trapped in `{}`", function),
+            Some(trap.clone()),
+        );
+        let frames = backtrace::frames(
+            &self.store,
+            self.instance.get_export(&self.store, backtrace::TOP_EXPORT),
+            self.instance
+                .get_export(&self.store, backtrace::STACK_EXPORT),
+        );
+        let ctx = self.store.data();
+        backtrace::report(
+            &ctx.name,
+            self.pid,
+            &format!("trapped in `{}`: {}", function, trap),
+            &ctx.symbols,
+            &frames,
+            DiagnosticLevel::Error,
+        );
+
+        let mut restarting = false;
+        if let RestartPolicy::OnFailure {
+            max_restarts,
+            backoff_ms,
+        } = self.restart_policy
+        {
+            if self.restarts < max_restarts {
+                restarting = true;
+                let delay = backoff_ms.saturating_mul(1 << self.restarts.min(16));
+                self.next_restart_ms = now + delay;
+                log::info!(
+                    "WASM: restarting app in {}ms (attempt {}/{})",
+                    delay,
+                    self.restarts + 1,
+                    max_restarts
+                );
+            } else {
+                log::warn!(
+                    "WASM: app exceeded {} restarts, leaving it faulted",
+                    max_restarts
+                );
+            }
+        }
+
+        self.state = AppState::Faulted {
+            function,
+            trap,
+            at_ms: now,
+        };
+        self.release_resources(restarting);
+    }
+
+    /// Save the app's state to `path`; see the `snapshot` module for what is
+    /// kept. Only a running app between frames can be saved.
+    pub fn snapshot(&self, path: &str) -> AgaveResult<()> {
+        if self.state != AppState::Running || self.suspended.is_some() {
+            return Err(AgaveError::InvalidState);
+        }
+        let data = snapshot::capture(&self.store, &self.instance, &self.module_hash);
+        log::info!(
+            "WASM: saving {} byte snapshot of app {} to {}",
+            data.len(),
+            self.pid,
+            path
+        );
+        fs::write_file(path, data)
+    }
+
+    /// Replace the app's state with the snapshot at `path`, which must come
+    /// from the same module. The app continues from the saved state with its
+    /// next `update`; `_start` is not run again.
+    pub fn restore(&mut self, path: &str) -> AgaveResult<()> {
+        // An exited app has already given up its standard streams
+        if matches!(self.state, AppState::Exited { .. }) {
+            return Err(AgaveError::InvalidState);
+        }
+        let data = fs::read_file(path)?;
+        if snapshot::module_hash(&data)? != self.module_hash {
+            log::error!(
+                "WASM: snapshot {} does not belong to app {}",
+                path,
+                self.pid
+            );
+            return Err(AgaveError::WasmError(WasmError::SnapshotMismatch));
+        }
+
+        let mut ctx = AppContext::new(
+            self.pid,
+            self.name(),
+            self.store.data().fb,
+            self.store.data().security.sandbox_profile.clone(),
+        );
+        ctx.wasi.stdio = self.store.data().wasi.stdio;
+        ctx.symbols = self.store.data().symbols.clone();
+        let (mut store, instance, memory) =
+            Self::instantiate(&self.engine, &self.module, ctx, self.budget.fuel_per_frame)
+                .map_err(|e| {
+                    log::error!("WASM: failed to instantiate app {}: {}", self.pid, e);
+                    AgaveError::WasmError(WasmError::InstantiationFailed)
+                })?;
+        snapshot::apply(&data, &mut store, &instance)?;
+
+        log::info!("WASM: restored app {} from {}", self.pid, path);
+        self.release_resources(true);
+        self.store = store;
+        self.instance = instance;
+        self.memory = memory;
+        self.suspended = None;
+        self.overrun_frames = 0;
+        self.state = AppState::Running;
+        Ok(())
+    }
+
+    /// Reload the app from its module file whenever the file is rewritten
+    /// with different content; see `poll_reload`
+    pub fn watch(mut self, path: &str) -> Self {
+        self.source = Some(Source {
+            path: path.to_string(),
+            version: file_version(path).unwrap_or_default(),
+        });
+        self
+    }
+
+    /// Reload the app if its watched module file changed since the last
+    /// check. The file is only read when its modification time or size
+    /// changed, and only reloaded when its hash differs from the running
+    /// module's.
+    pub fn poll_reload(&mut self) {
+        if matches!(self.state, AppState::Exited { .. }) {
+            return;
+        }
+        let Some(source) = &mut self.source else {
+            return;
+        };
+        let Ok(version) = file_version(&source.path) else {
+            return;
+        };
+        if version == source.version {
+            return;
+        }
+        source.version = version;
+        let path = source.path.clone();
+        let Ok(wasm) = fs::read_file(&path) else {
+            return;
+        };
+        if Sha256::digest(&wasm)[..] == self.module_hash {
+            return;
+        }
+        log::info!("WASM: {} changed, reloading app {}", path, self.pid);
+        // The new module is checked against the trusted list like a new app
+        let Ok(manifest) = AppManifest::load(&path) else {
+            return;
+        };
+        let profile = manifest
+            .sandbox_profile()
+            .and_then(|profile| trust::verify(&path, &wasm, profile));
+        if let Ok(profile) = profile {
+            if self.reload(wasm, profile, manifest.backtrace).is_ok() {
+                trust::record_load(&path, self);
+            }
+        }
+    }
+
+    /// Replace the app's module with `wasm` in place, confined by `profile`
+    /// and recording frames if `backtrace` is set. The app keeps its window,
+    /// arguments and standard streams, and the new `_start` runs.
+    ///
+    /// If the old module exports `agave_save_state`, it is called first and
+    /// may pass bytes to `save_state`; the new module's `agave_load_state`
+    /// then gets their length and reads them with `load_state`. A module
+    /// that does not compile or link is reported over the app's window and
+    /// the old version keeps running.
+    pub fn reload(
+        &mut self,
+        wasm: Vec<u8>,
+        profile: SandboxProfile,
+        backtrace: bool,
+    ) -> AgaveResult<()> {
+        let (module, symbols) = Self::compile(&self.engine, &wasm, backtrace)
+            .map_err(|reason| self.reload_error(WasmError::InvalidModule, reason))?;
+
+        let mut ctx = AppContext::new(self.pid, self.name(), self.store.data().fb, profile);
+        let wasi = &self.store.data().wasi;
+        ctx.wasi.args = wasi.args.clone();
+        ctx.wasi.env = wasi.env.clone();
+        ctx.wasi.stdio = wasi.stdio;
+        ctx.symbols = symbols;
+        let (store, instance, memory) =
+            Self::instantiate(&self.engine, &module, ctx, self.budget.fuel_per_frame)
+                .map_err(|e| self.reload_error(WasmError::InstantiationFailed, e.to_string()))?;
+
+        let state = self.save_state();
+        self.store.data_mut().wasi.fs.close_all();
+        self.module = module;
+        self.module_hash = Sha256::digest(&wasm).into();
+        self.store = store;
+        self.store.data_mut().saved_state = state;
+        self.instance = instance;
+        self.memory = memory;
+        self.suspended = None;
+        self.overrun_frames = 0;
+        self.restarts = 0;
+        self.state = AppState::Running;
+        compositor::hide_overlay(self.pid);
+        log::info!("WASM: reloaded app {}", self.pid);
+
+        self.call();
+        self.load_state();
+        Ok(())
+    }
+
+    /// Show why a reload failed over the app's window
+    fn reload_error(&self, kind: WasmError, reason: String) -> AgaveError {
+        log::error!(
+            "WASM: failed to reload app {}: {}: {}",
+            self.pid,
+            kind,
+            reason
+        );
+        add_diagnostic(
+            DiagnosticLevel::Error,
+            DiagnosticCategory::Tasks,
+            format!("WASM app '{}' failed to reload", self.name()),
+            Some(format!("{}: {}", kind, reason)),
+        );
+        compositor::show_overlay(self.pid, &format!("Reload failed: {}\n{}", kind, reason));
+        AgaveError::WasmError(kind)
+    }
+
+    // Collect what the running module's `agave_save_state` hands to
+    // `save_state`, if it exports one
+    fn save_state(&mut self) -> Option<Vec<u8>> {
+        if self.state != AppState::Running || self.suspended.is_some() {
+            return None;
+        }
+        let save = self
+            .instance
+            .get_typed_func::<(), ()>(&self.store, "agave_save_state")
+            .ok()?;
+        self.store.data_mut().saved_state = None;
+        backtrace::reset(&mut self.store, &self.instance);
+        if let Err(e) = self.store.set_fuel(self.budget.fuel_per_frame) {
+            log::warn!("WASM: failed to refuel app {}: {}", self.pid, e);
+        }
+        process::set_current_pid(Some(self.pid));
+        let result = save.call(&mut self.store, ());
+        process::set_current_pid(None);
+        if let Err(e) = result {
+            log::warn!("WASM: agave_save_state of app {} failed: {}", self.pid, e);
+            return None;
+        }
+        self.store.data_mut().saved_state.take()
+    }
+
+    // Offer the state saved before a reload to the new module's
+    // `agave_load_state`, once `_start` has finished
+    fn load_state(&mut self) {
+        let Some(len) = self.store.data().saved_state.as_ref().map(Vec::len) else {
+            return;
+        };
+        let load = self
+            .instance
+            .get_typed_func::<i32, ()>(&self.store, "agave_load_state");
+        match load {
+            Ok(load) if self.state == AppState::Running && self.suspended.is_none() => {
+                backtrace::reset(&mut self.store, &self.instance);
+                self.run("agave_load_state", |store| {
+                    load.call_resumable(store, len as i32)
+                });
+            }
+            Ok(_) => log::warn!("WASM: app {} did not start, dropping its state", self.pid),
+            Err(_) => log::debug!("WASM: app {} does not take saved state", self.pid),
+        }
+        if self.suspended.is_none() {
+            self.store.data_mut().saved_state = None;
+        }
+    }
+
+    /// Re-instantiate a faulted app once its restart backoff has elapsed
+    fn try_restart(&mut self) {
+        let RestartPolicy::OnFailure { max_restarts, .. } = self.restart_policy else {
+            return;
+        };
+        if self.restarts >= max_restarts || TIME_MS.load(Ordering::Relaxed) < self.next_restart_ms {
+            return;
+        }
+
+        self.restarts += 1;
+        let mut ctx = AppContext::new(
+            self.pid,
+            self.name(),
+            self.store.data().fb,
+            self.store.data().security.sandbox_profile.clone(),
+        );
+        // A restarted app keeps its command line
+        let wasi = &self.store.data().wasi;
+        ctx.wasi.args = wasi.args.clone();
+        ctx.wasi.env = wasi.env.clone();
+        ctx.wasi.stdio = wasi.stdio;
+        ctx.symbols = self.store.data().symbols.clone();
+        match Self::instantiate(&self.engine, &self.module, ctx, self.budget.fuel_per_frame) {
+            Ok((store, instance, memory)) => {
+                log::info!("WASM: app restarted (attempt {})", self.restarts);
+                self.store = store;
+                self.instance = instance;
+                self.memory = memory;
+                self.suspended = None;
+                self.overrun_frames = 0;
+                self.state = AppState::Running;
+                self.call();
+            }
+            Err(e) => self.on_error("instantiate", &e),
+        }
+    }
+}
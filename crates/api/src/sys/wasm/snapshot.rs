@@ -0,0 +1,291 @@
+/// Snapshots of a running app, so a session can outlive a reboot
+///
+/// A snapshot holds the exported memories, mutable globals and table sizes
+/// of an instance, plus its WASI arguments, environment, working directory
+/// and open descriptors. It is only valid for the module it was taken from,
+/// which is identified by its SHA-256 hash.
+///
+/// Everything is little-endian; strings and byte runs are prefixed with
+/// their `u32` length:
+///
+/// ```text
+/// magic "AGSN", version u32, module hash [u8; 32]
+/// args: count u32, strings
+/// env: count u32, key and value strings
+/// cwd string
+/// descriptors: count u32, each fd u32, path string, flags u16,
+///     rights_base u64, rights_inheriting u64, file_type u8, offset u64,
+///     is_directory u8
+/// exports: count u32, each name string, kind u8, then
+///     0 memory: size u64, count u32 of (chunk index u32, CHUNK_SIZE bytes)
+///     1 global: type u8 (0 i32, 1 i64, 2 f32, 3 f64), bits u64
+///     2 table: size u64
+/// ```
+///
+/// Memory is stored as its non-zero chunks. Globals that are not exported,
+/// such as the shadow stack pointer, are not saved; snapshots are only taken
+/// between calls, when the stack is back where instantiation left it. Table
+/// entries come from the module's element segments and are not saved either.
+use super::AppContext;
+use crate::sys::{
+    error::{AgaveError, AgaveResult, FsError, WasmError},
+    wasi::{
+        filesystem::FileDescriptor,
+        types::{Fd, FdFlags, FileSize, Rights},
+    },
+};
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use wasmi::{
+    core::{F32, F64},
+    Extern, Instance, Store, Val,
+};
+
+const MAGIC: &[u8; 4] = b"AGSN";
+const VERSION: u32 = 1;
+/// Memory is saved in chunks of this many bytes, skipping all-zero ones
+const CHUNK_SIZE: usize = 4096;
+const WASM_PAGE_SIZE: u64 = 64 * 1024;
+
+const KIND_MEMORY: u8 = 0;
+const KIND_GLOBAL: u8 = 1;
+const KIND_TABLE: u8 = 2;
+
+/// The part of a snapshot that identifies the module
+pub fn module_hash(data: &[u8]) -> AgaveResult<[u8; 32]> {
+    let mut r = Reader(data);
+    if r.take(MAGIC.len())? != MAGIC || r.u32()? != VERSION {
+        return Err(corrupted());
+    }
+    Ok(r.take(32)?.try_into().unwrap())
+}
+
+/// Serialize the state of `instance`, created from the module whose SHA-256
+/// is `module_hash`
+pub fn capture(store: &Store<AppContext>, instance: &Instance, module_hash: &[u8; 32]) -> Vec<u8> {
+    let mut w = Writer(Vec::new());
+    w.bytes(MAGIC).u32(VERSION).bytes(module_hash);
+
+    let wasi = &store.data().wasi;
+    w.u32(wasi.args.len() as u32);
+    for arg in &wasi.args {
+        w.str(arg);
+    }
+    w.u32(wasi.env.len() as u32);
+    for (key, value) in &wasi.env {
+        w.str(key).str(value);
+    }
+    w.str(wasi.fs.cwd());
+    let descriptors: Vec<_> = wasi.fs.descriptors().collect();
+    w.u32(descriptors.len() as u32);
+    for (fd, desc) in descriptors {
+        w.u32(fd)
+            .str(&desc.path)
+            .u16(desc.flags)
+            .u64(desc.rights_base)
+            .u64(desc.rights_inheriting)
+            .u8(desc.file_type)
+            .u64(desc.offset)
+            .u8(desc.is_directory as u8);
+    }
+
+    let mut exports = Writer(Vec::new());
+    let mut count = 0;
+    for export in instance.exports(store) {
+        let name = export.name();
+        match export.into_extern() {
+            Extern::Memory(mem) => {
+                let data = mem.data(store);
+                exports.str(name).u8(KIND_MEMORY).u64(data.len() as u64);
+                let chunks: Vec<_> = data
+                    .chunks(CHUNK_SIZE)
+                    .enumerate()
+                    .filter(|(_, chunk)| chunk.iter().any(|&b| b != 0))
+                    .collect();
+                exports.u32(chunks.len() as u32);
+                for (index, chunk) in chunks {
+                    exports.u32(index as u32).bytes(chunk);
+                }
+            }
+            Extern::Global(global) if global.ty(store).mutability().is_mut() => {
+                let (ty, bits) = match global.get(store) {
+                    Val::I32(v) => (0, v as u32 as u64),
+                    Val::I64(v) => (1, v as u64),
+                    Val::F32(v) => (2, v.to_bits() as u64),
+                    Val::F64(v) => (3, v.to_bits()),
+                    _ => continue,
+                };
+                exports.str(name).u8(KIND_GLOBAL).u8(ty).u64(bits);
+            }
+            Extern::Table(table) => {
+                exports.str(name).u8(KIND_TABLE).u64(table.size(store));
+            }
+            _ => continue,
+        }
+        count += 1;
+    }
+    w.u32(count).bytes(&exports.0);
+    w.0
+}
+
+/// Apply a snapshot to `instance`, freshly instantiated from the module the
+/// snapshot was taken from
+pub fn apply(data: &[u8], store: &mut Store<AppContext>, instance: &Instance) -> AgaveResult<()> {
+    let mut r = Reader(data);
+    r.take(MAGIC.len() + 4 + 32)?;
+
+    let args = (0..r.u32()?)
+        .map(|_| r.string())
+        .collect::<AgaveResult<Vec<_>>>()?;
+    let env = (0..r.u32()?)
+        .map(|_| Ok((r.string()?, r.string()?)))
+        .collect::<AgaveResult<Vec<_>>>()?;
+    let cwd = r.string()?;
+    let wasi = &mut store.data_mut().wasi;
+    wasi.args = args;
+    wasi.env = env;
+    wasi.fs
+        .set_cwd(&cwd)
+        .map_err(|_| AgaveError::PermissionDenied)?;
+    for _ in 0..r.u32()? {
+        let fd: Fd = r.u32()?;
+        let desc = FileDescriptor {
+            path: r.string()?,
+            flags: r.u16()? as FdFlags,
+            rights_base: r.u64()? as Rights,
+            rights_inheriting: r.u64()? as Rights,
+            file_type: r.u8()?,
+            offset: r.u64()? as FileSize,
+            is_directory: r.u8()? != 0,
+        };
+        // Files that are gone or outside the sandbox stay closed
+        if let Err(e) = wasi.fs.restore_descriptor(fd, desc) {
+            log::warn!("WASM: cannot restore descriptor {}: {}", fd, e.message);
+        }
+    }
+
+    for _ in 0..r.u32()? {
+        let name = r.string()?;
+        let kind = r.u8()?;
+        let export = instance.get_export(&*store, &name);
+        match (kind, export) {
+            (KIND_MEMORY, Some(Extern::Memory(mem))) => {
+                let size = r.u64()?;
+                let current = mem.data(&*store).len() as u64;
+                // Memory only grows, so it cannot be smaller than at startup
+                if size < current || !size.is_multiple_of(WASM_PAGE_SIZE) {
+                    return Err(corrupted());
+                }
+                mem.grow(&mut *store, (size - current) / WASM_PAGE_SIZE)
+                    .map_err(|_| AgaveError::OutOfMemory)?;
+                let memory = mem.data_mut(&mut *store);
+                memory.fill(0);
+                for _ in 0..r.u32()? {
+                    let start = r.u32()? as usize * CHUNK_SIZE;
+                    if start >= memory.len() {
+                        return Err(corrupted());
+                    }
+                    let end = (start + CHUNK_SIZE).min(memory.len());
+                    memory[start..end].copy_from_slice(r.take(end - start)?);
+                }
+            }
+            (KIND_GLOBAL, Some(Extern::Global(global))) => {
+                let (ty, bits) = (r.u8()?, r.u64()?);
+                let value = match ty {
+                    0 => Val::I32(bits as u32 as i32),
+                    1 => Val::I64(bits as i64),
+                    2 => Val::F32(F32::from_bits(bits as u32)),
+                    3 => Val::F64(F64::from_bits(bits)),
+                    _ => return Err(corrupted()),
+                };
+                global.set(&mut *store, value).map_err(|_| corrupted())?;
+            }
+            (KIND_TABLE, Some(Extern::Table(table))) => {
+                let size = r.u64()?;
+                let current = table.size(&*store);
+                let init = Val::default(table.ty(&*store).element());
+                if size > current {
+                    table
+                        .grow(&mut *store, size - current, init)
+                        .map_err(|_| AgaveError::OutOfMemory)?;
+                }
+            }
+            _ => {
+                log::error!("WASM: snapshot export '{}' does not match the module", name);
+                return Err(AgaveError::WasmError(WasmError::SnapshotMismatch));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn corrupted() -> AgaveError {
+    AgaveError::FileSystemError(FsError::CorruptedData)
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        self.0.extend_from_slice(bytes);
+        self
+    }
+
+    fn u8(&mut self, value: u8) -> &mut Self {
+        self.bytes(&[value])
+    }
+
+    fn u16(&mut self, value: u16) -> &mut Self {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    fn u32(&mut self, value: u32) -> &mut Self {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    fn u64(&mut self, value: u64) -> &mut Self {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    fn str(&mut self, value: &str) -> &mut Self {
+        self.u32(value.len() as u32).bytes(value.as_bytes())
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> AgaveResult<&'a [u8]> {
+        if len > self.0.len() {
+            return Err(corrupted());
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> AgaveResult<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> AgaveResult<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> AgaveResult<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> AgaveResult<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> AgaveResult<String> {
+        let len = self.u32()? as usize;
+        core::str::from_utf8(self.take(len)?)
+            .map(ToString::to_string)
+            .map_err(|_| corrupted())
+    }
+}
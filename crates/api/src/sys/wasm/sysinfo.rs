@@ -0,0 +1,214 @@
+/// System state serialized for the `sys_*` host functions
+///
+/// Every record is little-endian with fixed offsets, so apps can decode it
+/// without sharing types with the kernel. Names are UTF-8, NUL-padded and cut
+/// to their field. Lists are records packed back to back.
+use crate::sys::{
+    allocator, fs, network, power,
+    process::{self, ProcessState},
+    security,
+};
+use alloc::vec::Vec;
+
+/// heap_size, allocated, peak_allocated, allocation_count,
+/// deallocation_count, failed_allocations and wasm_memory, all `u64`
+pub const MEMORY_SIZE: usize = 56;
+/// pid, parent (0 for none), created_ms, cpu_time_ms and memory_bytes as
+/// `u64`, state and priority (`Priority` discriminant) as `u32`, then a 32
+/// byte name
+pub const PROCESS_SIZE: usize = 80;
+/// 16 byte type name, then total_size, used_size, free_size, total_files,
+/// total_dirs, block_size, mount_time and is_persistent as `u64`
+pub const FILESYSTEM_SIZE: usize = 80;
+/// 16 byte interface name, then bytes_sent, bytes_received, packets_sent,
+/// packets_received, errors and dropped as `u64`
+pub const NETWORK_SIZE: usize = 64;
+/// state, policy, cpu_mhz, min_mhz, max_mhz and throttling as `u32`,
+/// temperature_c, cpu_watts and total_watts as `f32`, battery_minutes as
+/// `i32` (-1 for none), then frequency_changes, sleep_events, wake_events
+/// and thermal_events as `u64`
+pub const POWER_SIZE: usize = 72;
+/// total_events, blocked_processes and active_policies, all `u64`
+pub const SECURITY_SIZE: usize = 24;
+
+/// Produces the data one host function returns
+pub type Source = fn() -> Vec<u8>;
+
+/// Host function names and their data
+pub const HOST_FUNCTIONS: [(&str, Source); 6] = [
+    ("sys_memory", memory),
+    ("sys_processes", processes),
+    ("sys_filesystem", filesystem),
+    ("sys_network", network),
+    ("sys_power", power),
+    ("sys_security", security),
+];
+
+const PROCESS_NAME: usize = 32;
+const SHORT_NAME: usize = 16;
+
+#[derive(Default)]
+struct Record(Vec<u8>);
+
+impl Record {
+    fn u32(&mut self, value: u32) -> &mut Self {
+        self.0.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    fn i32(&mut self, value: i32) -> &mut Self {
+        self.0.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    fn f32(&mut self, value: f32) -> &mut Self {
+        self.0.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    fn u64(&mut self, value: u64) -> &mut Self {
+        self.0.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    fn name(&mut self, name: &str, len: usize) -> &mut Self {
+        let mut end = name.len().min(len);
+        while !name.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.0.extend_from_slice(&name.as_bytes()[..end]);
+        self.0.resize(self.0.len() + len - end, 0);
+        self
+    }
+}
+
+pub fn memory() -> Vec<u8> {
+    let stats = allocator::memory_stats();
+    let mut record = Record::default();
+    record
+        .u64(stats.heap_size as u64)
+        .u64(stats.allocated as u64)
+        .u64(stats.peak_allocated as u64)
+        .u64(stats.allocation_count)
+        .u64(stats.deallocation_count)
+        .u64(stats.failed_allocations)
+        .u64(stats.wasm_memory as u64);
+    record.0
+}
+
+fn state_code(state: &ProcessState) -> u32 {
+    match state {
+        ProcessState::Created => 0,
+        ProcessState::Running => 1,
+        ProcessState::Sleeping => 2,
+        ProcessState::Waiting => 3,
+        ProcessState::Zombie => 4,
+        ProcessState::Terminated => 5,
+    }
+}
+
+/// Every process in the process table, by pid
+pub fn processes() -> Vec<u8> {
+    let mut processes = process::list_processes();
+    processes.sort_by_key(|p| p.pid);
+    let mut record = Record::default();
+    for p in &processes {
+        record
+            .u64(p.pid.as_u64())
+            .u64(p.parent_pid.map_or(0, |pid| pid.as_u64()))
+            .u64(p.created_time)
+            .u64(p.cpu_time_ms)
+            .u64(p.memory_usage as u64)
+            .u32(state_code(&p.state))
+            .u32(p.priority as u32)
+            .name(&p.name, PROCESS_NAME);
+    }
+    record.0
+}
+
+/// Empty if the filesystem cannot report its usage
+pub fn filesystem() -> Vec<u8> {
+    let Ok(stats) = fs::get_filesystem_stats() else {
+        return Vec::new();
+    };
+    let mut record = Record::default();
+    record
+        .name(&stats.fs_type, SHORT_NAME)
+        .u64(stats.total_size)
+        .u64(stats.used_size)
+        .u64(stats.free_size)
+        .u64(stats.total_files)
+        .u64(stats.total_dirs)
+        .u64(stats.block_size)
+        .u64(stats.mount_time)
+        .u64(stats.is_persistent as u64);
+    record.0
+}
+
+/// One record per network interface
+pub fn network() -> Vec<u8> {
+    let mut record = Record::default();
+    for (name, stats) in network::get_network_stats() {
+        record
+            .name(&name, SHORT_NAME)
+            .u64(stats.bytes_sent)
+            .u64(stats.bytes_received)
+            .u64(stats.packets_sent)
+            .u64(stats.packets_received)
+            .u64(stats.errors)
+            .u64(stats.dropped);
+    }
+    record.0
+}
+
+pub fn power() -> Vec<u8> {
+    let state = match power::get_power_state() {
+        power::PowerState::Active => 0,
+        power::PowerState::Reduced => 1,
+        power::PowerState::Sleep => 2,
+        power::PowerState::DeepSleep => 3,
+        power::PowerState::Hibernate => 4,
+    };
+    let policy = match power::get_power_policy() {
+        power::PowerPolicy::Performance => 0,
+        power::PowerPolicy::Balanced => 1,
+        power::PowerPolicy::PowerSaver => 2,
+        power::PowerPolicy::Adaptive => 3,
+        power::PowerPolicy::Custom(_) => 4,
+    };
+    let (cpu_mhz, min_mhz, max_mhz) = power::get_cpu_frequency_info();
+    let (temperature, throttling) = power::get_thermal_info();
+    let stats = power::get_power_statistics();
+    let consumption = &stats.power_consumption_estimates;
+    let mut record = Record::default();
+    record
+        .u32(state)
+        .u32(policy)
+        .u32(cpu_mhz)
+        .u32(min_mhz)
+        .u32(max_mhz)
+        .u32(throttling as u32)
+        .f32(temperature)
+        .f32(consumption.cpu_watts)
+        .f32(consumption.total_watts)
+        .i32(
+            consumption
+                .battery_life_estimate_minutes
+                .map_or(-1, |m| m as i32),
+        )
+        .u64(stats.frequency_changes)
+        .u64(stats.sleep_events)
+        .u64(stats.wake_events)
+        .u64(stats.thermal_events);
+    record.0
+}
+
+pub fn security() -> Vec<u8> {
+    let stats = security::get_security_statistics();
+    let mut record = Record::default();
+    record
+        .u64(stats.total_events as u64)
+        .u64(stats.blocked_processes as u64)
+        .u64(stats.active_policies as u64);
+    record.0
+}
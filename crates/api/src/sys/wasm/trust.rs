@@ -0,0 +1,108 @@
+/// Trusted module list: which WASM modules may run above the `Sandboxed`
+/// security level
+///
+/// `/etc/trusted-modules` holds one module per line, as `sha256sum` prints
+/// it: the hex SHA-256 of the module, optionally followed by its path. `#`
+/// starts a comment.
+///
+/// A module that is not listed still loads, but under the `sandboxed`
+/// profile whatever its manifest asks for. Every decision is recorded as a
+/// `SecurityEvent::ModuleVerification`.
+use super::WasmApp;
+use crate::sys::{
+    error::{AgaveError, AgaveResult},
+    fs,
+    security::{self, ModuleDecision, SandboxProfile, SecurityEvent, SecurityLevel},
+};
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use sha2::{Digest, Sha256};
+
+pub const TRUSTED_MODULES_FILE: &str = "/etc/trusted-modules";
+/// Profile that modules missing from the list run under
+pub const UNVERIFIED_PROFILE: &str = "sandboxed";
+
+/// Lowercase hex of a SHA-256 hash
+pub fn hash_hex(hash: &[u8; 32]) -> String {
+    hash.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn module_hex(wasm: &[u8]) -> String {
+    hash_hex(&Sha256::digest(wasm).into())
+}
+
+/// Hashes on the trusted module list; empty if there is no list
+pub fn trusted_hashes() -> Vec<String> {
+    let Ok(content) = fs::read_file(TRUSTED_MODULES_FILE) else {
+        return Vec::new();
+    };
+    String::from_utf8_lossy(&content)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_whitespace().next())
+        .map(str::to_ascii_lowercase)
+        .collect()
+}
+
+/// Add `wasm` to the trusted module list, noting `path` next to its hash
+pub fn trust_module(path: &str, wasm: &[u8]) -> AgaveResult<()> {
+    let hash = module_hex(wasm);
+    if trusted_hashes().contains(&hash) {
+        return Ok(());
+    }
+    let mut content = fs::read_file(TRUSTED_MODULES_FILE).unwrap_or_default();
+    content.extend_from_slice(format!("{}  {}\n", hash, path).as_bytes());
+    fs::write_file(TRUSTED_MODULES_FILE, content)
+}
+
+/// The profile the module `wasm`, stored at `path`, may run under:
+/// `requested` if the module is on the trusted list or asks for no more than
+/// the sandbox, otherwise the unverified profile
+pub fn verify(path: &str, wasm: &[u8], requested: SandboxProfile) -> AgaveResult<SandboxProfile> {
+    let hash = module_hex(wasm);
+    let trusted = trusted_hashes().contains(&hash);
+    let decision = if trusted {
+        ModuleDecision::Verified
+    } else {
+        ModuleDecision::Unverified
+    };
+    record(path, &hash, decision);
+
+    let level = security::profile_level(&requested);
+    if trusted || level == SecurityLevel::Sandboxed {
+        return Ok(requested);
+    }
+    log::warn!(
+        "WASM: {} is not a trusted module, running it sandboxed instead of {:?}",
+        path,
+        level
+    );
+    record(path, &hash, ModuleDecision::Denied { requested: level });
+    security::get_sandbox_profile(UNVERIFIED_PROFILE).ok_or_else(|| {
+        log::error!("WASM: unknown sandbox profile '{}'", UNVERIFIED_PROFILE);
+        AgaveError::InvalidParameter
+    })
+}
+
+/// Record that the module at `path` now runs as `app`
+pub fn record_load(path: &str, app: &WasmApp) {
+    let level = security::get_process_security_context(app.pid())
+        .map_or(SecurityLevel::Sandboxed, |context| context.security_level);
+    let decision = ModuleDecision::Loaded {
+        process_id: app.pid(),
+        level,
+    };
+    record(path, &hash_hex(app.module_hash()), decision);
+}
+
+fn record(path: &str, hash: &str, decision: ModuleDecision) {
+    security::record_security_event(SecurityEvent::ModuleVerification {
+        path: path.to_string(),
+        hash: hash.to_string(),
+        decision,
+    });
+}
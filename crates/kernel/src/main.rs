@@ -5,15 +5,15 @@ use acpi::{AcpiTables, HpetInfo, InterruptModel};
 use agave_api::sys::{
     allocator, diagnostics, drivers,
     drivers::virtio_block::BlockDevice,
-    framebuffer::{FB, RGBA},
+    framebuffer::{compositor, FB, RGBA},
     fs::{self, disk::BLOCK_SIZE},
-    gdt, globals, interrupts, ioapic, local_apic,
+    gdt, globals, input, interrupts, ioapic, keymap, local_apic,
     logger::init_logger,
     memory::{self, BootInfoFrameAllocator},
     monitor, network, pci, power, process, security,
     task::{self, executor::yield_once},
     virtio::{DeviceType, Virtio},
-    wasm::WasmApp,
+    wasm::{loader, trust, AppManifest, RestartPolicy, WasmApp},
     with_mapper_framealloc, ACPI_HANDLER, FRAME_ALLOCATOR, MAPPER, VIRTUAL_MAPPING_OFFSET,
 };
 use alloc::sync::Arc;
@@ -44,6 +44,14 @@ const CONFIG: BootloaderConfig = {
 };
 entry_point!(main, config = &CONFIG);
 
+// Restart policy applied to every WASM app
+const APP_RESTART_POLICY: RestartPolicy = RestartPolicy::OnFailure {
+    max_restarts: 3,
+    backoff_ms: 1000,
+};
+// How often apps check whether their module file changed, in frames
+const RELOAD_CHECK_FRAMES: u64 = 100;
+
 fn main(boot_info: &'static mut BootInfo) -> ! {
     // Initialize framebuffer and logger FIRST
     let framebuffer = boot_info.framebuffer.as_mut().unwrap();
@@ -254,6 +262,8 @@ fn main(boot_info: &'static mut BootInfo) -> ! {
     let mut fb = Box::new(FB::new(&fbinfo));
     let fb_clone: *mut FB = &mut *fb;
     log::info!("Framebuffer created at {:?}", fb_clone);
+    input::set_screen_size(fb.w, fb.h);
+    compositor::init(fb_clone);
 
     // Show loading screen now that framebuffer is available
     show_loading_screen("Basic initialization complete...", 25, &mut *fb);
@@ -404,20 +414,40 @@ fn main(boot_info: &'static mut BootInfo) -> ! {
         log::info!("Setting up WASM application task...");
         spawner.run(async move {
             log::info!("WASM task started - loading applications...");
-            let mut apps: Vec<WasmApp> = Vec::new();
-            let apps_raw = [
-                &include_bytes!("../../../apps/terminal/target/wasm32-wasip1/release/terminal_app.wasm")
-                    [..],
-                // &include_bytes!("../../../apps/zig-app/zig-out/lib/zig-app.wasm")[..],
-                // &include_bytes!("../../../disk/bin/hello.wasm")[..],
-                // &include_bytes!("../../../disk/bin/sqlite.wasm")[..],
-            ];
-            log::info!("Creating WASM app instances...");
-            for app_bytes in apps_raw.iter() {
-                log::info!("Creating WASM app from {} bytes", app_bytes.len());
-                apps.push(WasmApp::new(app_bytes.to_vec(), fb_clone));
+            // Bundled apps are installed into the VFS; everything else in
+            // /bin can be dropped in without rebuilding the kernel
+            // The terminal is the system shell, so it is not sandboxed
+            let bundled: [(&str, &[u8], &str); 1] = [(
+                "/bin/terminal.wasm",
+                &include_bytes!("../../../apps/terminal/target/wasm32-wasip1/release/terminal_app.wasm")[..],
+                "trusted",
+            )];
+            for (path, bytes, profile) in bundled {
+                let manifest = AppManifest {
+                    profile: profile.into(),
+                    ..AppManifest::default()
+                };
+                // Bundled apps are part of the kernel image, so they are trusted
+                let installed = loader::install_app(path, bytes)
+                    .and_then(|_| manifest.save(path))
+                    .and_then(|_| trust::trust_module(path, bytes));
+                if let Err(e) = installed {
+                    log::error!("Failed to install {}: {:?}", path, e);
+                }
             }
+
+            keymap::load_config();
+
+            log::info!("Creating WASM app instances...");
+            let mut apps: Vec<WasmApp> = loader::load_autostart_apps()
+                .into_iter()
+                .map(|app| app.with_restart_policy(APP_RESTART_POLICY))
+                .collect();
             log::info!("Created {} WASM apps", apps.len());
+            // The most recently started app has input focus
+            if let Some(app) = apps.last() {
+                compositor::focus_window(app.pid());
+            }
 
             log::info!("Initializing WASM applications...");
             for app in apps.iter_mut() {
@@ -444,12 +474,40 @@ fn main(boot_info: &'static mut BootInfo) -> ! {
 
                 // Record system activity for power management
                 power::record_system_activity();
+                for path in loader::take_launch_requests() {
+                    if let Ok(mut app) = loader::load_app(&path) {
+                        app = app.with_restart_policy(APP_RESTART_POLICY);
+                        app.call();
+                        apps.push(app);
+                    }
+                }
+                for mut app in loader::take_spawned() {
+                    app.call();
+                    apps.push(app);
+                }
+                let events = compositor::route_input(input::take_events());
+                for pid in compositor::take_close_requests() {
+                    if let Some(app) = apps.iter_mut().find(|app| app.pid() == pid) {
+                        app.close();
+                    }
+                }
                 for app in apps.iter_mut() {
+                    app.dispatch_input(&events);
                     app.call_update(input);
                 }
+                // Exited apps already closed their windows and released
+                // their resources; dropping them frees their stores
+                apps.retain(|app| app.exit_code().is_none());
+                compositor::compose();
 
                 frame_counter += 1;
 
+                if frame_counter % RELOAD_CHECK_FRAMES == 0 {
+                    for app in apps.iter_mut() {
+                        app.poll_reload();
+                    }
+                }
+
                 // Update power management every 10 frames (~100Hz)
                 if frame_counter % 10 == 0 {
                     if let Err(e) = power::update_power_management() {
@@ -1,4 +1,8 @@
+pub mod process;
 mod raw;
+pub mod reload;
+pub mod system;
+pub mod ui;
 
 /// RGBA color
 #[derive(Debug, Clone, Copy)]
@@ -22,10 +26,15 @@ impl RGBA {
     pub const WHITE: RGBA = RGBA::new(255, 255, 255, 255);
     pub const BLACK: RGBA = RGBA::new(0, 0, 0, 255);
     pub const TRANSPARENT: RGBA = RGBA::new(0, 0, 0, 0);
+
+    // 0xRRGGBBAA, as the text host functions take colors
+    fn packed(&self) -> i32 {
+        u32::from_be_bytes([self.r as u8, self.g as u8, self.b as u8, self.a as u8]) as i32
+    }
 }
 
 /// Position on the screen
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Position {
     pub x: i32,
     pub y: i32,
@@ -154,137 +163,152 @@ pub fn draw_triangle(p1: Position, p2: Position, p3: Position, color: RGBA) {
     }
 }
 
-/// Draw text at the given position with the given color using a simple bitmap font
+/// Draw text at the given position with the given color in the regular
+/// 16 pixel kernel font
 pub fn draw_text(pos: Position, text: &str, color: RGBA) {
-    let char_width = 8;
-    let char_height = 12;
-
-    for (i, c) in text.chars().enumerate() {
-        let char_x = pos.x + i as i32 * char_width;
-        let char_y = pos.y;
-
-        // Get bitmap for character
-        let bitmap = get_char_bitmap(c);
-
-        // Draw character bitmap
-        for row in 0..char_height {
-            for col in 0..char_width {
-                if row < 8 && col < 8 {
-                    // Our bitmap is 8x8
-                    // Extract the byte for this row (bitmap stored with top row in MSB)
-                    let row_byte = ((bitmap >> (8 * (7 - row))) & 0xFF) as u8;
-                    // Check the bit for this column (LSB is leftmost pixel to fix horizontal flip)
-                    if (row_byte & (1 << col)) != 0 {
-                        fill_rectangle(Position::new(char_x + col, char_y + row), 1, 1, color);
-                    }
-                }
-            }
+    draw_styled_text(pos, text, color, 16, FontWeight::Regular);
+}
+
+/// Memory layout of the pixels of an `Image`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// Bytes r, g, b, a
+    Rgba8888 = 0,
+    /// Bytes b, g, r, a
+    Bgra8888 = 1,
+    /// Little-endian u16 with red in the top 5 bits
+    Rgb565 = 2,
+}
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            PixelFormat::Rgba8888 | PixelFormat::Bgra8888 => 4,
+            PixelFormat::Rgb565 => 2,
         }
     }
 }
 
-/// Get a simple 8x8 bitmap for ASCII characters
-fn get_char_bitmap(c: char) -> u64 {
-    match c {
-        ' ' => 0x0000000000000000,
-        '!' => 0x1818181818001800,
-        '"' => 0x3636000000000000,
-        '#' => 0x36367F367F363600,
-        '$' => 0x0C3E031E301F0C00,
-        '%' => 0x006333180C664300,
-        '&' => 0x1C361C6E3B336E00,
-        '\'' => 0x0606030000000000,
-        '(' => 0x180C0606060C1800,
-        ')' => 0x060C1818180C0600,
-        '*' => 0x00663CFF3C660000,
-        '+' => 0x000C0C3F0C0C0000,
-        ',' => 0x00000000000C0C06,
-        '-' => 0x0000003F00000000,
-        '.' => 0x00000000000C0C00,
-        '/' => 0x6030180C06030100,
-        '0' => 0x3E63737B6F673E00,
-        '1' => 0x0C0E0C0C0C0C3F00,
-        '2' => 0x1E33301C06333F00,
-        '3' => 0x1E33301C30331E00,
-        '4' => 0x383C363F33303800,
-        '5' => 0x3F031F3030331E00,
-        '6' => 0x1C06031F33331E00,
-        '7' => 0x3F3330180C0C0C00,
-        '8' => 0x1E33331E33331E00,
-        '9' => 0x1E33333E30180E00,
-        ':' => 0x000C0C00000C0C00,
-        ';' => 0x000C0C00000C0C06,
-        '<' => 0x180C0603060C1800,
-        '=' => 0x00003F00003F0000,
-        '>' => 0x060C1830180C0600,
-        '?' => 0x1E3330180C000C00,
-        '@' => 0x3E637B7B7B031E00,
-        'A' => 0x0C1E33333F333300,
-        'B' => 0x3F66663E66663F00,
-        'C' => 0x3C66030303663C00,
-        'D' => 0x1F36666666361F00,
-        'E' => 0x7F46161E16467F00,
-        'F' => 0x7F46161E16060F00,
-        'G' => 0x3C66030373663C00,
-        'H' => 0x3333333F33333300,
-        'I' => 0x1E0C0C0C0C0C1E00,
-        'J' => 0x7830303033331E00,
-        'K' => 0x6766361E36666700,
-        'L' => 0x0F06060646667F00,
-        'M' => 0x63777F7F6B636300,
-        'N' => 0x63676F7B73636300,
-        'O' => 0x1C36636363361C00,
-        'P' => 0x3F66663E06060F00,
-        'Q' => 0x1E33333B39331E00,
-        'R' => 0x3F66663E36666700,
-        'S' => 0x1E33070E38331E00,
-        'T' => 0x3F2D0C0C0C0C1E00,
-        'U' => 0x3333333333331E00,
-        'V' => 0x33333333331E0C00,
-        'W' => 0x6363636B7F773600,
-        'X' => 0x6363361C1C366300,
-        'Y' => 0x3333331E0C0C1E00,
-        'Z' => 0x7F6331184C667F00,
-        '[' => 0x1E06060606061E00,
-        '\\' => 0x03060C1830604000,
-        ']' => 0x1E18181818181E00,
-        '^' => 0x081C366300000000,
-        '_' => 0x00000000000000FF,
-        '`' => 0x0C0C180000000000,
-        'a' => 0x00001E303E333E00,
-        'b' => 0x0706063E66663B00,
-        'c' => 0x00001E3303331E00,
-        'd' => 0x3830303E33333E00,
-        'e' => 0x00001E333F031E00,
-        'f' => 0x1C36060F06060F00,
-        'g' => 0x00003E33333E301F,
-        'h' => 0x0706366E66666700,
-        'i' => 0x0C000E0C0C0C1E00,
-        'j' => 0x300030303033331E,
-        'k' => 0x070606663E366700,
-        'l' => 0x0E0C0C0C0C0C1E00,
-        'm' => 0x0000337F7F6B6300,
-        'n' => 0x00001F3333333300,
-        'o' => 0x00001E3333331E00,
-        'p' => 0x00003B66663E060F,
-        'q' => 0x00003E33333E3078,
-        'r' => 0x00003B6E66060F00,
-        's' => 0x00001E031E301F00,
-        't' => 0x080C3E0C0C2C1800,
-        'u' => 0x0000333333333E00,
-        'v' => 0x00003333331E0C00,
-        'w' => 0x0000636B7F7F3600,
-        'x' => 0x000033361C366300,
-        'y' => 0x00003333333E301F,
-        'z' => 0x00003F190C263F00,
-        '{' => 0x380C0C070C0C3800,
-        '|' => 0x1818181818181800,
-        '}' => 0x070C0C380C0C0700,
-        '~' => 0x6E3B000000000000,
-        _ => 0x0000000000000000, // Default for unknown characters
+/// Pixels in app memory, rows packed back to back
+#[derive(Debug, Clone, Copy)]
+pub struct Image<'a> {
+    pub data: &'a [u8],
+    pub width: i32,
+    pub height: i32,
+    pub format: PixelFormat,
+}
+
+/// How `blit_scaled` combines and resamples pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BlitOptions {
+    /// Blend with the screen using the source alpha instead of copying
+    pub blend: bool,
+    /// Filter when scaling instead of picking the nearest pixel
+    pub bilinear: bool,
+}
+
+/// Draw all of `image` unscaled at `pos`, blending by its alpha. Returns
+/// false if `data` is too small for the image.
+pub fn blit(image: &Image, pos: Position) -> bool {
+    blit_scaled(
+        image,
+        Position::new(0, 0),
+        Dimensions {
+            width: image.width,
+            height: image.height,
+        },
+        pos,
+        Dimensions {
+            width: image.width,
+            height: image.height,
+        },
+        BlitOptions {
+            blend: true,
+            bilinear: false,
+        },
+    )
+}
+
+/// Draw the `src_size` part of `image` at `src` scaled to `dest_size` at
+/// `dest`, in one host call. Returns false if the source rectangle is not
+/// within the image or `data` is too small.
+pub fn blit_scaled(
+    image: &Image,
+    src: Position,
+    src_size: Dimensions,
+    dest: Position,
+    dest_size: Dimensions,
+    options: BlitOptions,
+) -> bool {
+    let needed =
+        image.width.max(0) as usize * image.height.max(0) as usize * image.format.bytes_per_pixel();
+    if image.data.len() < needed {
+        return false;
+    }
+    let flags = options.blend as i32 | (options.bilinear as i32) << 1;
+    unsafe {
+        raw::blit(
+            image.data.as_ptr(),
+            image.width,
+            image.height,
+            image.format as i32,
+            src.x,
+            src.y,
+            src_size.width,
+            src_size.height,
+            dest.x,
+            dest.y,
+            dest_size.width,
+            dest_size.height,
+            flags,
+        ) == 0
     }
 }
 
+/// Font weights the kernel can render
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FontWeight {
+    #[default]
+    Regular = 0,
+    Bold = 1,
+    Light = 2,
+}
+
+// Split a packed (height << 32 | width) size
+fn unpack_size(size: i64) -> (i32, i32) {
+    ((size & 0xFFFFFFFF) as i32, (size >> 32) as i32)
+}
+
+/// Draw UTF-8 text with the kernel font at `size` pixels (16, 20, 24 or 32;
+/// other sizes round down) and `weight`. `\n` starts a new line. Returns the
+/// width and height of the drawn text.
+pub fn draw_styled_text(
+    pos: Position,
+    text: &str,
+    color: RGBA,
+    size: i32,
+    weight: FontWeight,
+) -> (i32, i32) {
+    let size = unsafe {
+        raw::draw_text(
+            text.as_ptr(),
+            text.len() as i32,
+            pos.x,
+            pos.y,
+            color.packed(),
+            size,
+            weight as i32,
+        )
+    };
+    unpack_size(size.max(0))
+}
+
+/// Width and height `draw_styled_text` would take to draw `text`
+pub fn measure_text(text: &str, size: i32, weight: FontWeight) -> (i32, i32) {
+    let size = unsafe { raw::measure_text(text.as_ptr(), text.len() as i32, size, weight as i32) };
+    unpack_size(size.max(0))
+}
+
 // Keyboard key codes (standard Linux input event codes)
 pub const KEY_ESC: i32 = 1;
 pub const KEY_1: i32 = 2;
@@ -341,6 +365,130 @@ pub const KEY_DOT: i32 = 52;
 pub const KEY_SLASH: i32 = 53;
 pub const KEY_RIGHTSHIFT: i32 = 54;
 pub const KEY_SPACE: i32 = 57;
+pub const KEY_HOME: i32 = 102;
+pub const KEY_UP: i32 = 103;
+pub const KEY_PAGEUP: i32 = 104;
+pub const KEY_LEFT: i32 = 105;
+pub const KEY_RIGHT: i32 = 106;
+pub const KEY_END: i32 = 107;
+pub const KEY_DOWN: i32 = 108;
+pub const KEY_PAGEDOWN: i32 = 109;
+pub const KEY_DELETE: i32 = 111;
+
+// Mouse button codes, reported by `Event::MouseButton`
+pub const BTN_LEFT: i32 = 0x110;
+pub const BTN_RIGHT: i32 = 0x111;
+pub const BTN_MIDDLE: i32 = 0x112;
+
+/// Modifier keys held when an event happened
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Modifiers(pub u32);
+
+impl Modifiers {
+    pub const SHIFT: u32 = 1 << 0;
+    pub const CTRL: u32 = 1 << 1;
+    pub const ALT: u32 = 1 << 2;
+    pub const META: u32 = 1 << 3;
+    pub const CAPS_LOCK: u32 = 1 << 4;
+    pub const ALTGR: u32 = 1 << 5;
+
+    pub fn shift(&self) -> bool {
+        self.0 & Self::SHIFT != 0
+    }
+
+    pub fn ctrl(&self) -> bool {
+        self.0 & Self::CTRL != 0
+    }
+
+    pub fn alt(&self) -> bool {
+        self.0 & Self::ALT != 0
+    }
+
+    pub fn meta(&self) -> bool {
+        self.0 & Self::META != 0
+    }
+
+    pub fn caps_lock(&self) -> bool {
+        self.0 & Self::CAPS_LOCK != 0
+    }
+
+    /// Right Alt, which the kernel keymap uses for a layout's third level
+    pub fn altgr(&self) -> bool {
+        self.0 & Self::ALTGR != 0
+    }
+}
+
+/// Input event delivered to the focused app
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// `repeat` marks presses generated by holding the key down
+    Key {
+        code: i32,
+        pressed: bool,
+        repeat: bool,
+        modifiers: Modifiers,
+    },
+    /// Pointer moved to (`x`, `y`) by (`dx`, `dy`)
+    MouseMove { x: i32, y: i32, dx: i32, dy: i32 },
+    MouseButton {
+        button: i32,
+        pressed: bool,
+        position: Position,
+        modifiers: Modifiers,
+    },
+    /// Wheel ticks; positive `dy` scrolls up, positive `dx` scrolls right
+    Wheel {
+        dx: i32,
+        dy: i32,
+        position: Position,
+    },
+    /// The app gained or lost input focus
+    Focus(bool),
+    /// Character typed, translated by the kernel keymap; Enter, Tab and
+    /// Backspace type `'\n'`, `'\t'` and `'\u{8}'`
+    Text(char),
+}
+
+/// Take the next input event queued for this app, with the time it happened
+/// in milliseconds
+pub fn poll_event() -> Option<(Event, u64)> {
+    // kind, five fields, then a u64 timestamp
+    let mut raw = [0u8; 32];
+    if unsafe { raw::poll_event(raw.as_mut_ptr()) } != 1 {
+        return None;
+    }
+    let field = |i: usize| i32::from_le_bytes(raw[i * 4..i * 4 + 4].try_into().unwrap());
+    let time_ms = u64::from_le_bytes(raw[24..32].try_into().unwrap());
+    let event = match field(0) {
+        1 => Event::Key {
+            code: field(1),
+            pressed: field(2) != 0,
+            repeat: field(4) != 0,
+            modifiers: Modifiers(field(3) as u32),
+        },
+        2 => Event::MouseMove {
+            x: field(1),
+            y: field(2),
+            dx: field(3),
+            dy: field(4),
+        },
+        3 => Event::MouseButton {
+            button: field(1),
+            pressed: field(2) != 0,
+            position: Position::new(field(3), field(4)),
+            modifiers: Modifiers(field(5) as u32),
+        },
+        4 => Event::Wheel {
+            dx: field(1),
+            dy: field(2),
+            position: Position::new(field(3), field(4)),
+        },
+        5 => Event::Focus(field(1) != 0),
+        6 => Event::Text(char::from_u32(field(1) as u32)?),
+        _ => return None,
+    };
+    Some((event, time_ms))
+}
 
 /// Check if a key was just pressed this frame
 pub fn is_key_pressed(key_code: i32) -> bool {
@@ -372,7 +520,8 @@ pub fn get_key_history_event(index: i32) -> (i32, bool) {
     }
 }
 
-/// Convert key code to ASCII character (simple mapping)
+/// Convert key code to ASCII character (simple mapping). US layout only;
+/// `Event::Text` carries characters translated with the user's layout
 pub fn key_code_to_char(key_code: i32, shift_pressed: bool) -> Option<char> {
     match key_code {
         KEY_A => Some(if shift_pressed { 'A' } else { 'a' }),
@@ -436,3 +585,9 @@ pub fn get_time_ms() -> u64 {
 pub fn grow_memory(pages: u64) -> i32 {
     unsafe { raw::grow_memory(pages) }
 }
+
+/// Log the current call stack to the kernel's serial log and diagnostics,
+/// with function names and source lines when the module has them
+pub fn print_backtrace() {
+    unsafe { raw::print_backtrace() }
+}
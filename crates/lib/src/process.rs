@@ -0,0 +1,152 @@
+//! Child programs and the pipes connected to their standard streams
+use crate::raw;
+use std::io::{self, Read, Write};
+
+// Returned by the pipe host functions while a pipe is empty or full
+const PIPE_PENDING: i32 = -1;
+
+/// How a child's stdin, stdout and stderr are connected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Stdio {
+    /// Empty stdin, with output going to the kernel log
+    #[default]
+    Null,
+    /// Pipes owned by the parent, see `Child::stdin` and friends
+    Piped,
+}
+
+/// Where a child stands after a `wait`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitStatus {
+    Running,
+    Exited(i32),
+    /// Not a child of this app, or its exit code was already collected
+    NoChild,
+}
+
+/// One end of a pipe to a child, closed when dropped. Reads and writes
+/// never block; they fail with `WouldBlock` while the pipe is empty or full.
+#[derive(Debug)]
+pub struct Pipe(i64);
+
+impl Pipe {
+    fn result(n: i32) -> io::Result<usize> {
+        match n {
+            PIPE_PENDING => Err(io::ErrorKind::WouldBlock.into()),
+            n if n < 0 => Err(io::ErrorKind::BrokenPipe.into()),
+            n => Ok(n as usize),
+        }
+    }
+}
+
+impl Read for Pipe {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Self::result(unsafe { raw::pipe_read(self.0, buf.as_mut_ptr(), buf.len() as i32) })
+    }
+}
+
+impl Write for Pipe {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Self::result(unsafe { raw::pipe_write(self.0, buf.as_ptr(), buf.len() as i32) })
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        unsafe { raw::pipe_close(self.0) };
+    }
+}
+
+/// A program started with `spawn`
+#[derive(Debug)]
+pub struct Child {
+    pid: u64,
+    status: Option<i32>,
+    /// Write end of the child's stdin; drop it to signal end of input
+    pub stdin: Option<Pipe>,
+    pub stdout: Option<Pipe>,
+    pub stderr: Option<Pipe>,
+}
+
+impl Child {
+    pub fn pid(&self) -> u64 {
+        self.pid
+    }
+
+    /// Check whether the child has exited, without blocking
+    pub fn try_wait(&mut self) -> WaitStatus {
+        if let Some(code) = self.status {
+            return WaitStatus::Exited(code);
+        }
+        let status = wait(self.pid);
+        if let WaitStatus::Exited(code) = status {
+            self.status = Some(code);
+        }
+        status
+    }
+}
+
+// Strings each followed by a NUL, as `spawn` takes lists
+fn nul_list<'a>(items: impl IntoIterator<Item = &'a str>) -> Vec<u8> {
+    let mut list = Vec::new();
+    for item in items {
+        list.extend_from_slice(item.as_bytes());
+        list.push(0);
+    }
+    list
+}
+
+/// Start the program at `path` as a child of this app, passing `args` after
+/// the program name. The child starts on the next frame. `None` if the
+/// program cannot be loaded.
+pub fn spawn(path: &str, args: &[&str], env: &[(&str, &str)], stdio: Stdio) -> Option<Child> {
+    let file = path.rsplit('/').next().unwrap_or(path);
+    let name = file.strip_suffix(".wasm").unwrap_or(file);
+    let argv = nul_list(std::iter::once(name).chain(args.iter().copied()));
+    let vars: Vec<String> = env
+        .iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect();
+    let env = nul_list(vars.iter().map(String::as_str));
+    let mut handles = [0u64; 3];
+    let stdio_ptr = match stdio {
+        Stdio::Null => std::ptr::null_mut(),
+        Stdio::Piped => handles.as_mut_ptr(),
+    };
+    let pid = unsafe {
+        raw::spawn(
+            path.as_ptr(),
+            path.len() as i32,
+            argv.as_ptr(),
+            argv.len() as i32,
+            env.as_ptr(),
+            env.len() as i32,
+            stdio_ptr,
+        )
+    };
+    if pid < 0 {
+        return None;
+    }
+    let pipe = |handle: u64| (stdio == Stdio::Piped).then_some(Pipe(handle as i64));
+    Some(Child {
+        pid: pid as u64,
+        status: None,
+        stdin: pipe(handles[0]),
+        stdout: pipe(handles[1]),
+        stderr: pipe(handles[2]),
+    })
+}
+
+/// Collect the exit code of child `pid` if it has exited
+pub fn wait(pid: u64) -> WaitStatus {
+    let mut code = 0;
+    match unsafe { raw::wait(pid as i64, &mut code) } {
+        1 => WaitStatus::Exited(code),
+        0 => WaitStatus::Running,
+        _ => WaitStatus::NoChild,
+    }
+}
@@ -46,6 +46,31 @@ unsafe extern "C" {
         a: i32,
     );
     pub fn draw_line(x0: i32, y0: i32, x1: i32, y1: i32, r: i32, g: i32, b: i32, a: i32);
+    pub fn draw_text(
+        ptr: *const u8,
+        len: i32,
+        x: i32,
+        y: i32,
+        color: i32,
+        size: i32,
+        weight: i32,
+    ) -> i64;
+    pub fn measure_text(ptr: *const u8, len: i32, size: i32, weight: i32) -> i64;
+    pub fn blit(
+        ptr: *const u8,
+        width: i32,
+        height: i32,
+        format: i32,
+        sx: i32,
+        sy: i32,
+        sw: i32,
+        sh: i32,
+        dx: i32,
+        dy: i32,
+        dw: i32,
+        dh: i32,
+        flags: i32,
+    ) -> i32;
     pub fn get_time_ms() -> u64;
 
     pub fn is_key_pressed(key_code: i32) -> bool;
@@ -53,7 +78,38 @@ unsafe extern "C" {
     pub fn is_key_released(key_code: i32) -> bool;
     pub fn get_key_history_count() -> i32;
     pub fn get_key_history_event(index: i32) -> i64;
+    pub fn poll_event(event_ptr: *mut u8) -> i32;
+
+    // system statistics
+    pub fn sys_memory(ptr: *mut u8, len: i32) -> i32;
+    pub fn sys_processes(ptr: *mut u8, len: i32) -> i32;
+    pub fn sys_filesystem(ptr: *mut u8, len: i32) -> i32;
+    pub fn sys_network(ptr: *mut u8, len: i32) -> i32;
+    pub fn sys_power(ptr: *mut u8, len: i32) -> i32;
+    pub fn sys_security(ptr: *mut u8, len: i32) -> i32;
+
+    // child processes
+    pub fn spawn(
+        path_ptr: *const u8,
+        path_len: i32,
+        argv_ptr: *const u8,
+        argv_len: i32,
+        env_ptr: *const u8,
+        env_len: i32,
+        stdio_ptr: *mut u64,
+    ) -> i64;
+    pub fn wait(pid: i64, code_ptr: *mut i32) -> i32;
+    pub fn pipe_read(handle: i64, ptr: *mut u8, len: i32) -> i32;
+    pub fn pipe_write(handle: i64, ptr: *const u8, len: i32) -> i32;
+    pub fn pipe_close(handle: i64) -> i32;
+
+    // hot reload
+    pub fn save_state(ptr: *const u8, len: i32) -> i32;
+    pub fn load_state(ptr: *mut u8, len: i32) -> i32;
 
     // memory
     pub fn grow_memory(pages: u64) -> i32;
+
+    // debugging
+    pub fn print_backtrace();
 }
@@ -0,0 +1,37 @@
+//! State carried across a hot reload
+//!
+//! When an app's module file changes the kernel swaps in the new module and
+//! runs its `_start`. An app that wants to keep its state exports two
+//! functions for the kernel to call around the swap:
+//!
+//! ```ignore
+//! #[no_mangle]
+//! pub extern "C" fn agave_save_state() {
+//!     agave_lib::reload::save_state(&encode(&APP));
+//! }
+//!
+//! #[no_mangle]
+//! pub extern "C" fn agave_load_state(len: i32) {
+//!     if let Some(state) = agave_lib::reload::load_state(len as usize) {
+//!         APP = decode(&state);
+//!     }
+//! }
+//! ```
+//!
+//! `agave_load_state` runs after the new `_start`. The encoding is up to the
+//! app; keep it stable, or versioned, across the builds being swapped.
+use crate::raw;
+
+/// Hand `state` to the next version of the app; only meaningful inside
+/// `agave_save_state`
+pub fn save_state(state: &[u8]) -> bool {
+    unsafe { raw::save_state(state.as_ptr(), state.len() as i32) == 0 }
+}
+
+/// The `len` bytes saved by the previous version; only meaningful inside
+/// `agave_load_state`, which receives `len`
+pub fn load_state(len: usize) -> Option<Vec<u8>> {
+    let mut state = vec![0; len];
+    let size = unsafe { raw::load_state(state.as_mut_ptr(), len as i32) };
+    (size as usize == len).then_some(state)
+}
@@ -0,0 +1,338 @@
+//! Live system statistics reported by the kernel
+use crate::raw;
+
+/// Kernel heap usage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MemoryStats {
+    pub heap_size: u64,
+    pub allocated: u64,
+    pub peak_allocated: u64,
+    pub allocation_count: u64,
+    pub deallocation_count: u64,
+    pub failed_allocations: u64,
+    /// Bytes of heap granted to apps for linear memory
+    pub wasm_memory: u64,
+}
+
+impl MemoryStats {
+    pub fn free(&self) -> u64 {
+        self.heap_size.saturating_sub(self.allocated)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessState {
+    Created,
+    Running,
+    Sleeping,
+    Waiting,
+    Zombie,
+    Terminated,
+}
+
+impl ProcessState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProcessState::Created => "created",
+            ProcessState::Running => "running",
+            ProcessState::Sleeping => "sleeping",
+            ProcessState::Waiting => "waiting",
+            ProcessState::Zombie => "zombie",
+            ProcessState::Terminated => "terminated",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    Critical,
+    High,
+    Normal,
+    Low,
+    Idle,
+}
+
+/// An entry of the kernel's process table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessInfo {
+    pub pid: u64,
+    pub parent: Option<u64>,
+    pub name: String,
+    pub state: ProcessState,
+    pub priority: Priority,
+    /// Milliseconds since boot when the process was created
+    pub created_ms: u64,
+    pub cpu_time_ms: u64,
+    pub memory_bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilesystemStats {
+    pub fs_type: String,
+    pub total_size: u64,
+    pub used_size: u64,
+    pub free_size: u64,
+    pub total_files: u64,
+    pub total_dirs: u64,
+    pub block_size: u64,
+    pub mount_time: u64,
+    pub persistent: bool,
+}
+
+/// Traffic counters of one network interface
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkStats {
+    pub interface: String,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub packets_sent: u64,
+    pub packets_received: u64,
+    pub errors: u64,
+    pub dropped: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerState {
+    Active,
+    Reduced,
+    Sleep,
+    DeepSleep,
+    Hibernate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerPolicy {
+    Performance,
+    Balanced,
+    PowerSaver,
+    Adaptive,
+    Custom,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PowerStats {
+    pub state: PowerState,
+    pub policy: PowerPolicy,
+    pub cpu_mhz: u32,
+    pub min_mhz: u32,
+    pub max_mhz: u32,
+    pub throttling: bool,
+    pub temperature_c: f32,
+    /// Estimated draw of the CPU and of the whole system
+    pub cpu_watts: f32,
+    pub total_watts: f32,
+    pub battery_minutes: Option<u32>,
+    pub frequency_changes: u64,
+    pub sleep_events: u64,
+    pub wake_events: u64,
+    pub thermal_events: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SecurityStats {
+    pub total_events: u64,
+    pub blocked_processes: u64,
+    pub active_policies: u64,
+}
+
+// Record sizes of the `sys_*` host functions
+const MEMORY_SIZE: usize = 56;
+const PROCESS_SIZE: usize = 80;
+const FILESYSTEM_SIZE: usize = 80;
+const NETWORK_SIZE: usize = 64;
+const POWER_SIZE: usize = 72;
+const SECURITY_SIZE: usize = 24;
+
+// Call a `sys_*` host function, growing the buffer until the data fits
+fn fetch(source: unsafe extern "C" fn(*mut u8, i32) -> i32) -> Vec<u8> {
+    let mut buf = Vec::new();
+    loop {
+        let size = unsafe { source(buf.as_mut_ptr(), buf.len() as i32) };
+        if size < 0 {
+            return Vec::new();
+        }
+        if size as usize <= buf.len() {
+            buf.truncate(size as usize);
+            return buf;
+        }
+        buf.resize(size as usize, 0);
+    }
+}
+
+// Reads the little-endian fields of a record in order
+struct Fields<'a>(&'a [u8]);
+
+impl Fields<'_> {
+    fn take<const N: usize>(&mut self) -> [u8; N] {
+        let (field, rest) = self.0.split_at(N);
+        self.0 = rest;
+        field.try_into().unwrap()
+    }
+
+    fn u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.take())
+    }
+
+    fn i32(&mut self) -> i32 {
+        i32::from_le_bytes(self.take())
+    }
+
+    fn f32(&mut self) -> f32 {
+        f32::from_le_bytes(self.take())
+    }
+
+    fn u64(&mut self) -> u64 {
+        u64::from_le_bytes(self.take())
+    }
+
+    // NUL-padded UTF-8
+    fn name<const N: usize>(&mut self) -> String {
+        let bytes = self.take::<N>();
+        let len = bytes.iter().position(|&b| b == 0).unwrap_or(N);
+        String::from_utf8_lossy(&bytes[..len]).into_owned()
+    }
+}
+
+/// Kernel heap usage, or `None` if the kernel does not report it
+pub fn memory() -> Option<MemoryStats> {
+    let data = fetch(raw::sys_memory);
+    let mut f = Fields(data.get(..MEMORY_SIZE)?);
+    Some(MemoryStats {
+        heap_size: f.u64(),
+        allocated: f.u64(),
+        peak_allocated: f.u64(),
+        allocation_count: f.u64(),
+        deallocation_count: f.u64(),
+        failed_allocations: f.u64(),
+        wasm_memory: f.u64(),
+    })
+}
+
+/// Every process in the kernel's process table, by pid
+pub fn processes() -> Vec<ProcessInfo> {
+    let data = fetch(raw::sys_processes);
+    data.as_chunks::<PROCESS_SIZE>()
+        .0
+        .iter()
+        .filter_map(|record| {
+            let mut f = Fields(record);
+            let pid = f.u64();
+            let parent = Some(f.u64()).filter(|&parent| parent != 0);
+            let (created_ms, cpu_time_ms, memory_bytes) = (f.u64(), f.u64(), f.u64());
+            let state = match f.u32() {
+                0 => ProcessState::Created,
+                1 => ProcessState::Running,
+                2 => ProcessState::Sleeping,
+                3 => ProcessState::Waiting,
+                4 => ProcessState::Zombie,
+                5 => ProcessState::Terminated,
+                _ => return None,
+            };
+            let priority = match f.u32() {
+                0 => Priority::Critical,
+                1 => Priority::High,
+                2 => Priority::Normal,
+                3 => Priority::Low,
+                4 => Priority::Idle,
+                _ => return None,
+            };
+            Some(ProcessInfo {
+                pid,
+                parent,
+                name: f.name::<32>(),
+                state,
+                priority,
+                created_ms,
+                cpu_time_ms,
+                memory_bytes,
+            })
+        })
+        .collect()
+}
+
+/// Usage of the mounted filesystem, or `None` if it cannot report it
+pub fn filesystem() -> Option<FilesystemStats> {
+    let data = fetch(raw::sys_filesystem);
+    let mut f = Fields(data.get(..FILESYSTEM_SIZE)?);
+    Some(FilesystemStats {
+        fs_type: f.name::<16>(),
+        total_size: f.u64(),
+        used_size: f.u64(),
+        free_size: f.u64(),
+        total_files: f.u64(),
+        total_dirs: f.u64(),
+        block_size: f.u64(),
+        mount_time: f.u64(),
+        persistent: f.u64() != 0,
+    })
+}
+
+/// Counters of every network interface
+pub fn network() -> Vec<NetworkStats> {
+    let data = fetch(raw::sys_network);
+    data.as_chunks::<NETWORK_SIZE>()
+        .0
+        .iter()
+        .map(|record| {
+            let mut f = Fields(record);
+            NetworkStats {
+                interface: f.name::<16>(),
+                bytes_sent: f.u64(),
+                bytes_received: f.u64(),
+                packets_sent: f.u64(),
+                packets_received: f.u64(),
+                errors: f.u64(),
+                dropped: f.u64(),
+            }
+        })
+        .collect()
+}
+
+/// Power management state, or `None` if the kernel does not report it
+pub fn power() -> Option<PowerStats> {
+    let data = fetch(raw::sys_power);
+    let mut f = Fields(data.get(..POWER_SIZE)?);
+    let state = match f.u32() {
+        0 => PowerState::Active,
+        1 => PowerState::Reduced,
+        2 => PowerState::Sleep,
+        3 => PowerState::DeepSleep,
+        4 => PowerState::Hibernate,
+        _ => return None,
+    };
+    let policy = match f.u32() {
+        0 => PowerPolicy::Performance,
+        1 => PowerPolicy::Balanced,
+        2 => PowerPolicy::PowerSaver,
+        3 => PowerPolicy::Adaptive,
+        4 => PowerPolicy::Custom,
+        _ => return None,
+    };
+    Some(PowerStats {
+        state,
+        policy,
+        cpu_mhz: f.u32(),
+        min_mhz: f.u32(),
+        max_mhz: f.u32(),
+        throttling: f.u32() != 0,
+        temperature_c: f.f32(),
+        cpu_watts: f.f32(),
+        total_watts: f.f32(),
+        battery_minutes: u32::try_from(f.i32()).ok(),
+        frequency_changes: f.u64(),
+        sleep_events: f.u64(),
+        wake_events: f.u64(),
+        thermal_events: f.u64(),
+    })
+}
+
+/// Security monitor counters, or `None` if the kernel does not report them
+pub fn security() -> Option<SecurityStats> {
+    let data = fetch(raw::sys_security);
+    let mut f = Fields(data.get(..SECURITY_SIZE)?);
+    Some(SecurityStats {
+        total_events: f.u64(),
+        blocked_processes: f.u64(),
+        active_policies: f.u64(),
+    })
+}
@@ -0,0 +1,250 @@
+//! Placing widgets by splitting rectangles
+//!
+//! Layouts do not own widgets: they cut a `Rect` into smaller ones, and
+//! each widget is then drawn into one of them.
+use crate::Position;
+
+/// Area of the window, in pixels relative to its top left corner
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Rect {
+    pub const fn new(x: i32, y: i32, width: i32, height: i32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// The whole window
+    pub fn window() -> Self {
+        let size = crate::get_dimensions();
+        Self::new(0, 0, size.width, size.height)
+    }
+
+    pub fn position(&self) -> Position {
+        Position::new(self.x, self.y)
+    }
+
+    pub fn right(&self) -> i32 {
+        self.x + self.width
+    }
+
+    pub fn bottom(&self) -> i32 {
+        self.y + self.height
+    }
+
+    pub fn contains(&self, point: Position) -> bool {
+        point.x >= self.x && point.x < self.right() && point.y >= self.y && point.y < self.bottom()
+    }
+
+    /// The rectangle with `amount` pixels taken off every side
+    pub fn shrink(&self, amount: i32) -> Self {
+        Self::new(
+            self.x + amount,
+            self.y + amount,
+            (self.width - 2 * amount).max(0),
+            (self.height - 2 * amount).max(0),
+        )
+    }
+
+    /// A `width` by `height` rectangle centered in this one
+    pub fn center(&self, width: i32, height: i32) -> Self {
+        Self::new(
+            self.x + (self.width - width) / 2,
+            self.y + (self.height - height) / 2,
+            width,
+            height,
+        )
+    }
+
+    /// The top `height` pixels and the rest
+    pub fn split_top(&self, height: i32) -> (Self, Self) {
+        let height = height.clamp(0, self.height);
+        (
+            Self::new(self.x, self.y, self.width, height),
+            Self::new(self.x, self.y + height, self.width, self.height - height),
+        )
+    }
+
+    /// The left `width` pixels and the rest
+    pub fn split_left(&self, width: i32) -> (Self, Self) {
+        let width = width.clamp(0, self.width);
+        (
+            Self::new(self.x, self.y, width, self.height),
+            Self::new(self.x + width, self.y, self.width - width, self.height),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Row,
+    Column,
+}
+
+/// Size of one item of a `Flex` along its direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Size {
+    /// Exactly this many pixels
+    Fixed(i32),
+    /// A share of the space the fixed items leave, by weight
+    Grow(u32),
+}
+
+/// Items in a row or column, filling the other axis
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Flex {
+    direction: Direction,
+    gap: i32,
+    padding: i32,
+}
+
+impl Flex {
+    pub fn new(direction: Direction) -> Self {
+        Self {
+            direction,
+            gap: 0,
+            padding: 0,
+        }
+    }
+
+    pub fn row() -> Self {
+        Self::new(Direction::Row)
+    }
+
+    pub fn column() -> Self {
+        Self::new(Direction::Column)
+    }
+
+    /// Space between items
+    pub fn gap(mut self, gap: i32) -> Self {
+        self.gap = gap;
+        self
+    }
+
+    /// Space around all items
+    pub fn padding(mut self, padding: i32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Cut `rect` into one rectangle per size, in order
+    pub fn split<const N: usize>(&self, rect: Rect, sizes: [Size; N]) -> [Rect; N] {
+        let mut rects = [Rect::default(); N];
+        self.place(rect, &sizes, &mut rects);
+        rects
+    }
+
+    /// Like `split`, for a number of items only known at run time
+    pub fn layout(&self, rect: Rect, sizes: &[Size]) -> Vec<Rect> {
+        let mut rects = vec![Rect::default(); sizes.len()];
+        self.place(rect, sizes, &mut rects);
+        rects
+    }
+
+    fn place(&self, rect: Rect, sizes: &[Size], rects: &mut [Rect]) {
+        let inner = rect.shrink(self.padding);
+        let (start, length) = match self.direction {
+            Direction::Row => (inner.x, inner.width),
+            Direction::Column => (inner.y, inner.height),
+        };
+        let gaps = self.gap * (sizes.len() as i32 - 1).max(0);
+        let mut free = length - gaps;
+        let mut weights = 0;
+        for size in sizes {
+            match *size {
+                Size::Fixed(pixels) => free -= pixels,
+                Size::Grow(weight) => weights += weight,
+            }
+        }
+        let mut free = free.max(0);
+
+        let mut offset = start;
+        for (size, rect) in sizes.iter().zip(rects.iter_mut()) {
+            let length = match *size {
+                Size::Fixed(pixels) => pixels,
+                // Each item takes its share of what is left, so rounding
+                // errors end up in the last one
+                Size::Grow(weight) if weights > 0 => {
+                    let share = (free as i64 * weight as i64 / weights as i64) as i32;
+                    free -= share;
+                    weights -= weight;
+                    share
+                }
+                Size::Grow(_) => 0,
+            };
+            *rect = match self.direction {
+                Direction::Row => Rect::new(offset, inner.y, length, inner.height),
+                Direction::Column => Rect::new(inner.x, offset, inner.width, length),
+            };
+            offset += length + self.gap;
+        }
+    }
+}
+
+/// Equal cells in rows and columns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Grid {
+    columns: i32,
+    rows: i32,
+    gap: i32,
+    padding: i32,
+}
+
+impl Grid {
+    pub fn new(columns: i32, rows: i32) -> Self {
+        Self {
+            columns: columns.max(1),
+            rows: rows.max(1),
+            gap: 0,
+            padding: 0,
+        }
+    }
+
+    /// Space between cells
+    pub fn gap(mut self, gap: i32) -> Self {
+        self.gap = gap;
+        self
+    }
+
+    /// Space around all cells
+    pub fn padding(mut self, padding: i32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// The cell at `column` and `row` of `rect`
+    pub fn cell(&self, rect: Rect, column: i32, row: i32) -> Rect {
+        self.span(rect, column, row, 1, 1)
+    }
+
+    /// The area covering `columns` by `rows` cells from `column` and `row`
+    pub fn span(&self, rect: Rect, column: i32, row: i32, columns: i32, rows: i32) -> Rect {
+        let inner = rect.shrink(self.padding);
+        // Edges are computed from the whole area so cells do not drift
+        let edge = |start: i32, length: i32, count: i32, index: i32| {
+            let cells = length - self.gap * (count - 1);
+            start + cells * index / count + self.gap * index
+        };
+        let x = edge(inner.x, inner.width, self.columns, column);
+        let y = edge(inner.y, inner.height, self.rows, row);
+        let right = edge(inner.x, inner.width, self.columns, column + columns) - self.gap;
+        let bottom = edge(inner.y, inner.height, self.rows, row + rows) - self.gap;
+        Rect::new(x, y, (right - x).max(0), (bottom - y).max(0))
+    }
+
+    /// Every cell of `rect`, row by row
+    pub fn cells(&self, rect: Rect) -> impl Iterator<Item = Rect> {
+        let grid = *self;
+        (0..self.rows)
+            .flat_map(move |row| (0..grid.columns).map(move |column| grid.cell(rect, column, row)))
+    }
+}
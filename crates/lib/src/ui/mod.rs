@@ -0,0 +1,348 @@
+//! Immediate-mode widgets built on the drawing and input functions
+//!
+//! Widgets are methods of `Ui` called every frame from `update`. Each one
+//! draws itself into a `Rect`, usually cut out with `Flex` or `Grid`, and
+//! returns a `Response` saying what the user did to it. State that outlives
+//! a frame, like the text of a field or the scroll position of a list, is
+//! owned by the app and passed in by reference.
+//!
+//! Widgets are told apart by their label, which must be unique within a
+//! `scope`. Tab and Shift+Tab move the keyboard focus between widgets.
+//!
+//! ```ignore
+//! use agave_lib::ui::{Flex, Rect, Size, TextInput, Theme, Ui};
+//!
+//! let mut ui = Ui::new(Theme::default());
+//! let mut name = TextInput::new();
+//!
+//! // In `update`
+//! ui.begin();
+//! agave_lib::clear_screen(ui.theme.palette.bg_primary);
+//! let [title, field, ok] = Flex::column().gap(8).padding(12).split(
+//!     Rect::window(),
+//!     [Size::Fixed(24), Size::Fixed(32), Size::Fixed(32)],
+//! );
+//! ui.label(title, "Who are you?");
+//! ui.text_field(field, "Name", &mut name);
+//! if ui.button(ok, "OK").clicked {
+//!     greet(name.text());
+//! }
+//! ui.end();
+//! ```
+pub mod layout;
+mod text_field;
+pub mod theme;
+mod widgets;
+
+pub use layout::{Direction, Flex, Grid, Rect, Size};
+pub use text_field::TextInput;
+pub use theme::{Palette, Theme};
+pub use widgets::ListState;
+
+use crate::{
+    draw_rectangle, draw_styled_text, fill_rectangle, get_time_ms, measure_text, poll_event, Event,
+    Modifiers, Position, BTN_LEFT, KEY_TAB, RGBA,
+};
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// Identifies a widget from one frame to the next
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Id(u64);
+
+/// What the user did to a widget this frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Response {
+    /// The pointer is over the widget
+    pub hovered: bool,
+    /// The widget has the keyboard focus
+    pub focused: bool,
+    /// Pressed and released with the left button, or with Enter or Space
+    /// while focused
+    pub clicked: bool,
+    /// The value the widget edits changed
+    pub changed: bool,
+    /// Enter was pressed in a text field or list
+    pub submitted: bool,
+}
+
+// Keyboard input in the order it was typed
+#[derive(Debug, Clone, Copy)]
+enum Typed {
+    Key(i32, Modifiers),
+    Char(char),
+}
+
+/// Input and focus state shared by all widgets
+pub struct Ui {
+    pub theme: Theme,
+    pointer: Position,
+    // Left button held now, and pressed or released during this frame
+    down: bool,
+    pressed: bool,
+    released: bool,
+    // Wheel ticks this frame; positive scrolls up
+    wheel: i32,
+    typed: Vec<Typed>,
+    // Whether the last key pressed was a Ctrl, Alt or Meta chord
+    chord: bool,
+    // Some(shift) if Tab was pressed
+    tab: Option<bool>,
+    // Widget the left button went down on, until it is released
+    active: Option<Id>,
+    focus: Option<Id>,
+    // Focusable widgets in the order they were drawn this frame
+    focus_order: Vec<Id>,
+    // Whether a widget took this frame's button press
+    claimed: bool,
+    scopes: Vec<u64>,
+    line_height: i32,
+    time_ms: u64,
+}
+
+impl Ui {
+    pub fn new(theme: Theme) -> Self {
+        Self {
+            theme,
+            pointer: Position::new(-1, -1),
+            down: false,
+            pressed: false,
+            released: false,
+            wheel: 0,
+            typed: Vec::new(),
+            chord: false,
+            tab: None,
+            active: None,
+            focus: None,
+            focus_order: Vec::new(),
+            claimed: false,
+            scopes: Vec::new(),
+            line_height: 0,
+            time_ms: 0,
+        }
+    }
+
+    /// Start a frame with the events queued for the app
+    pub fn begin(&mut self) {
+        self.begin_with(std::iter::from_fn(|| poll_event().map(|(event, _)| event)));
+    }
+
+    /// Start a frame with `events`, for apps that also handle events
+    /// themselves
+    pub fn begin_with(&mut self, events: impl IntoIterator<Item = Event>) {
+        self.pressed = false;
+        self.released = false;
+        self.wheel = 0;
+        self.typed.clear();
+        self.tab = None;
+        self.focus_order.clear();
+        self.claimed = false;
+        self.scopes.clear();
+        self.line_height = measure_text("Ag", self.theme.text_size, self.theme.text_weight)
+            .1
+            .max(1);
+        self.time_ms = get_time_ms();
+        for event in events {
+            self.handle(event);
+        }
+    }
+
+    fn handle(&mut self, event: Event) {
+        match event {
+            Event::MouseMove { x, y, .. } => self.pointer = Position::new(x, y),
+            Event::MouseButton {
+                button: BTN_LEFT,
+                pressed,
+                position,
+                ..
+            } => {
+                self.pointer = position;
+                self.down = pressed;
+                if pressed {
+                    self.pressed = true;
+                } else {
+                    self.released = true;
+                }
+            }
+            Event::Wheel { dy, position, .. } => {
+                self.pointer = position;
+                self.wheel += dy;
+            }
+            Event::Key {
+                code,
+                pressed: true,
+                modifiers,
+                ..
+            } => {
+                self.chord = modifiers.ctrl() || modifiers.alt() || modifiers.meta();
+                if code == KEY_TAB && !self.chord {
+                    self.tab = Some(modifiers.shift());
+                } else {
+                    self.typed.push(Typed::Key(code, modifiers));
+                }
+            }
+            // Shortcuts and Tab are not text
+            Event::Text(ch) if !self.chord && ch != '\t' => self.typed.push(Typed::Char(ch)),
+            Event::Focus(false) => {
+                self.down = false;
+                self.active = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Finish a frame: a click outside every widget clears the focus, and
+    /// Tab moves it to the next focusable widget
+    pub fn end(&mut self) {
+        if self.pressed && !self.claimed {
+            self.focus = None;
+        }
+        if !self.down {
+            self.active = None;
+        }
+        if let Some(backwards) = self.tab {
+            let count = self.focus_order.len();
+            let current = self
+                .focus
+                .and_then(|id| self.focus_order.iter().position(|&other| other == id));
+            let next = match (current, backwards) {
+                _ if count == 0 => None,
+                (Some(i), false) => Some((i + 1) % count),
+                (Some(i), true) => Some((i + count - 1) % count),
+                (None, false) => Some(0),
+                (None, true) => Some(count - 1),
+            };
+            self.focus = next.map(|i| self.focus_order[i]);
+        }
+    }
+
+    /// Id of the widget labelled `label` in the current scope
+    pub fn id(&self, label: &str) -> Id {
+        let mut hasher = DefaultHasher::new();
+        self.scopes.last().hash(&mut hasher);
+        label.hash(&mut hasher);
+        Id(hasher.finish())
+    }
+
+    /// Draw widgets whose labels only need to be unique within `key`, such
+    /// as one row of widgets per item
+    pub fn scope<R>(&mut self, key: impl Hash, f: impl FnOnce(&mut Ui) -> R) -> R {
+        let mut hasher = DefaultHasher::new();
+        self.scopes.last().hash(&mut hasher);
+        key.hash(&mut hasher);
+        self.scopes.push(hasher.finish());
+        let result = f(self);
+        self.scopes.pop();
+        result
+    }
+
+    /// Give the keyboard focus to the widget with `id`
+    pub fn request_focus(&mut self, id: Id) {
+        self.focus = Some(id);
+    }
+
+    pub fn has_focus(&self, id: Id) -> bool {
+        self.focus == Some(id)
+    }
+
+    /// Pointer position at the end of the frame's events
+    pub fn pointer(&self) -> Position {
+        self.pointer
+    }
+
+    /// Height of a line of text in the theme's font
+    pub fn line_height(&self) -> i32 {
+        self.line_height
+    }
+
+    /// Width of `text` in the theme's font
+    pub fn text_width(&self, text: &str) -> i32 {
+        measure_text(text, self.theme.text_size, self.theme.text_weight).0
+    }
+
+    // Hover, press and focus handling common to all interactive widgets
+    fn interact(&mut self, id: Id, rect: Rect, focusable: bool) -> Response {
+        let hovered = rect.contains(self.pointer);
+        if focusable {
+            self.focus_order.push(id);
+        }
+        if self.pressed && hovered && !self.claimed {
+            self.claimed = true;
+            self.active = Some(id);
+            if focusable {
+                self.focus = Some(id);
+            }
+        }
+        Response {
+            hovered,
+            focused: self.focus == Some(id),
+            clicked: self.released && self.active == Some(id) && hovered,
+            ..Response::default()
+        }
+    }
+
+    // Whether the left button went down on `id` and is still held
+    fn held(&self, id: Id) -> bool {
+        self.down && self.active == Some(id)
+    }
+
+    // Keys pressed this frame, for the focused widget
+    fn keys(&self) -> impl Iterator<Item = (i32, Modifiers)> + '_ {
+        self.typed.iter().filter_map(|typed| match *typed {
+            Typed::Key(code, modifiers) => Some((code, modifiers)),
+            Typed::Char(_) => None,
+        })
+    }
+
+    // Longest start of `text` no wider than `width`
+    fn fit<'a>(&self, text: &'a str, width: i32) -> &'a str {
+        if width <= 0 {
+            return "";
+        }
+        if self.text_width(text) <= width {
+            return text;
+        }
+        let ends: Vec<usize> = text
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain([text.len()])
+            .collect();
+        // ends[low] always fits, ends[high] never does
+        let (mut low, mut high) = (0, ends.len() - 1);
+        while high - low > 1 {
+            let mid = (low + high) / 2;
+            if self.text_width(&text[..ends[mid]]) <= width {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        &text[..ends[low]]
+    }
+
+    // Draw one line of text at `x`, centered vertically in `rect` and cut
+    // at its right edge
+    fn text(&self, rect: Rect, x: i32, text: &str, color: RGBA) -> i32 {
+        let text = self.fit(text, rect.right() - x);
+        let y = rect.y + (rect.height - self.line_height) / 2;
+        draw_styled_text(
+            Position::new(x, y),
+            text,
+            color,
+            self.theme.text_size,
+            self.theme.text_weight,
+        )
+        .0
+    }
+
+    // Filled rectangle with a one pixel border
+    fn frame(&self, rect: Rect, fill: RGBA, border: RGBA) {
+        fill_rectangle(rect.position(), rect.width, rect.height, fill);
+        draw_rectangle(rect.position(), rect.width, rect.height, border);
+    }
+}
+
+impl Default for Ui {
+    fn default() -> Self {
+        Ui::new(Theme::default())
+    }
+}
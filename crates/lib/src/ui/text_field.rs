@@ -0,0 +1,281 @@
+//! Single-line text entry
+use super::{Rect, Response, Typed, Ui};
+use crate::{fill_rectangle, Position, KEY_A, KEY_DELETE, KEY_END, KEY_HOME, KEY_LEFT, KEY_RIGHT};
+use std::ops::Range;
+
+const CURSOR_WIDTH: i32 = 2;
+const BLINK_MS: u64 = 500;
+
+/// Text, cursor and selection of a text field
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TextInput {
+    text: String,
+    // Byte offsets of the cursor and of the other end of the selection
+    cursor: usize,
+    anchor: usize,
+    // Byte offset of the first character shown
+    scroll: usize,
+}
+
+impl TextInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Input holding `text`, with the cursor at its end
+    pub fn with_text(text: impl Into<String>) -> Self {
+        let mut input = Self::new();
+        input.set_text(text);
+        input
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replace the text, moving the cursor to its end
+    pub fn set_text(&mut self, text: impl Into<String>) {
+        self.text = text.into();
+        self.cursor = self.text.len();
+        self.anchor = self.cursor;
+        self.scroll = 0;
+    }
+
+    pub fn clear(&mut self) {
+        self.set_text(String::new());
+    }
+
+    /// Byte offset of the cursor
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Byte range of the selected text, empty if nothing is selected
+    pub fn selection(&self) -> Range<usize> {
+        self.cursor.min(self.anchor)..self.cursor.max(self.anchor)
+    }
+
+    pub fn selected_text(&self) -> &str {
+        &self.text[self.selection()]
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = 0;
+        self.cursor = self.text.len();
+    }
+
+    /// Type `text` at the cursor, replacing the selection
+    pub fn insert(&mut self, text: &str) {
+        self.delete_selection();
+        self.text.insert_str(self.cursor, text);
+        self.cursor += text.len();
+        self.anchor = self.cursor;
+    }
+
+    // Remove the selected text; false if nothing was selected
+    fn delete_selection(&mut self) -> bool {
+        let selection = self.selection();
+        if selection.is_empty() {
+            return false;
+        }
+        self.cursor = selection.start;
+        self.anchor = selection.start;
+        self.text.replace_range(selection, "");
+        true
+    }
+
+    // Delete the selection, or the character before the cursor (`back`) or
+    // after it
+    fn delete(&mut self, back: bool) -> bool {
+        if self.delete_selection() {
+            return true;
+        }
+        let range = if back {
+            self.previous(self.cursor)..self.cursor
+        } else {
+            self.cursor..self.next(self.cursor)
+        };
+        if range.is_empty() {
+            return false;
+        }
+        self.cursor = range.start;
+        self.anchor = range.start;
+        self.text.replace_range(range, "");
+        true
+    }
+
+    // Move the cursor, keeping the anchor where it is if `select`
+    fn move_to(&mut self, offset: usize, select: bool) {
+        self.cursor = offset;
+        if !select {
+            self.anchor = offset;
+        }
+    }
+
+    fn previous(&self, offset: usize) -> usize {
+        self.text[..offset]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    fn next(&self, offset: usize) -> usize {
+        self.text[offset..]
+            .chars()
+            .next()
+            .map_or(offset, |ch| offset + ch.len_utf8())
+    }
+
+    // Start of the word before `offset`
+    fn previous_word(&self, offset: usize) -> usize {
+        let before = self.text[..offset].trim_end();
+        before
+            .rfind(char::is_whitespace)
+            .map_or(0, |i| self.next(i))
+    }
+
+    // End of the word after `offset`
+    fn next_word(&self, offset: usize) -> usize {
+        let after = &self.text[offset..];
+        let start = after.len() - after.trim_start().len();
+        after[start..]
+            .find(char::is_whitespace)
+            .map_or(self.text.len(), |i| offset + start + i)
+    }
+}
+
+impl Ui {
+    /// Field editing `input`, showing `hint` while it is empty and not
+    /// focused. Clicking places the cursor and dragging selects; the arrow
+    /// keys move by character, or by word with Ctrl, and extend the
+    /// selection with Shift. Ctrl+A selects everything.
+    pub fn text_field(&mut self, rect: Rect, hint: &str, input: &mut TextInput) -> Response {
+        let id = self.id(hint);
+        let mut response = self.interact(id, rect, true);
+        let inner = rect.shrink(self.theme.padding);
+        // The cursor may move past the end of the text if the app changed it
+        input.cursor = input.cursor.min(input.text.len());
+        input.anchor = input.anchor.min(input.text.len());
+        input.scroll = input.scroll.min(input.cursor);
+
+        if self.held(id) || (self.pressed && self.active == Some(id)) {
+            let offset = self.offset_at(input, inner.x, self.pointer.x);
+            input.move_to(offset, !self.pressed);
+        }
+        if response.focused {
+            for &typed in &self.typed {
+                match typed {
+                    Typed::Char('\n') => response.submitted = true,
+                    Typed::Char('\u{8}') => response.changed |= input.delete(true),
+                    Typed::Char(ch) if !ch.is_control() => {
+                        input.insert(ch.encode_utf8(&mut [0; 4]));
+                        response.changed = true;
+                    }
+                    Typed::Char(_) => {}
+                    Typed::Key(code, modifiers) => {
+                        let select = modifiers.shift();
+                        let word = modifiers.ctrl();
+                        let cursor = input.cursor;
+                        match code {
+                            KEY_LEFT if word => input.move_to(input.previous_word(cursor), select),
+                            KEY_LEFT => input.move_to(input.previous(cursor), select),
+                            KEY_RIGHT if word => input.move_to(input.next_word(cursor), select),
+                            KEY_RIGHT => input.move_to(input.next(cursor), select),
+                            KEY_HOME => input.move_to(0, select),
+                            KEY_END => input.move_to(input.text.len(), select),
+                            KEY_DELETE => response.changed |= input.delete(false),
+                            KEY_A if word => input.select_all(),
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+        self.scroll_to_cursor(input, inner.width - CURSOR_WIDTH);
+
+        let palette = self.theme.palette;
+        let border = if response.focused {
+            self.theme.accent
+        } else {
+            palette.border_color
+        };
+        self.frame(rect, palette.bg_primary, border);
+        if input.text.is_empty() && !response.focused {
+            self.text(inner, inner.x, hint, palette.text_muted);
+            return response;
+        }
+
+        // The visible text in up to three runs: before, inside and after
+        // the selection
+        let shown = self.fit(&input.text[input.scroll..], inner.width);
+        let shown = input.scroll..input.scroll + shown.len();
+        let selection = input.selection();
+        let selected = selection.start.clamp(shown.start, shown.end)
+            ..selection.end.clamp(shown.start, shown.end);
+        let mut x = inner.x;
+        for (range, is_selected) in [
+            (shown.start..selected.start, false),
+            (selected.clone(), true),
+            (selected.end..shown.end, false),
+        ] {
+            if range.is_empty() {
+                continue;
+            }
+            let run = &input.text[range];
+            let width = self.text_width(run);
+            let color = if is_selected {
+                fill_rectangle(
+                    Position::new(x, inner.y),
+                    width,
+                    inner.height,
+                    self.theme.accent,
+                );
+                palette.bg_primary
+            } else {
+                palette.text_primary
+            };
+            self.text(inner, x, run, color);
+            x += width;
+        }
+
+        if response.focused && (self.time_ms / BLINK_MS).is_multiple_of(2) {
+            let x = inner.x + self.text_width(&input.text[input.scroll..input.cursor]);
+            let y = inner.y + (inner.height - self.line_height) / 2;
+            fill_rectangle(
+                Position::new(x, y),
+                CURSOR_WIDTH,
+                self.line_height,
+                palette.text_primary,
+            );
+        }
+        response
+    }
+
+    // Byte offset of the character boundary closest to `x`, for text drawn
+    // from `left`
+    fn offset_at(&self, input: &TextInput, left: i32, x: i32) -> usize {
+        let mut offset = input.scroll;
+        let mut right = left;
+        for ch in input.text[input.scroll..].chars() {
+            let width = self.text_width(ch.encode_utf8(&mut [0; 4]));
+            if x < right + width / 2 {
+                break;
+            }
+            right += width;
+            offset += ch.len_utf8();
+        }
+        offset
+    }
+
+    // Scroll so that the cursor is inside the `width` pixels shown
+    fn scroll_to_cursor(&self, input: &mut TextInput, width: i32) {
+        if input.cursor < input.scroll {
+            input.scroll = input.cursor;
+        }
+        while input.scroll < input.cursor
+            && self.text_width(&input.text[input.scroll..input.cursor]) > width
+        {
+            input.scroll = input.next(input.scroll);
+        }
+    }
+}
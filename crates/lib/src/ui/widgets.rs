@@ -0,0 +1,276 @@
+//! Labels, buttons, checkboxes, tabs and lists
+use super::{Rect, Response, Ui};
+use crate::{
+    draw_line, draw_rectangle, fill_rectangle, Position, KEY_DOWN, KEY_END, KEY_ENTER, KEY_HOME,
+    KEY_PAGEDOWN, KEY_PAGEUP, KEY_SPACE, KEY_UP,
+};
+
+const SCROLLBAR_WIDTH: i32 = 6;
+const MIN_THUMB_HEIGHT: i32 = 12;
+// Rows scrolled per wheel tick
+const WHEEL_ROWS: i32 = 3;
+
+/// Selection and scroll position of a list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ListState {
+    pub selected: Option<usize>,
+    // First row shown
+    first: usize,
+    // Whether the selection was set by the app and may be out of view
+    pending_reveal: bool,
+}
+
+impl ListState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Select `index` and scroll it into view when the list is next drawn
+    pub fn select(&mut self, index: Option<usize>) {
+        self.selected = index;
+        self.pending_reveal = true;
+    }
+
+    /// Index of the first row shown
+    pub fn first_visible(&self) -> usize {
+        self.first
+    }
+
+    // Scroll so that the selected row is among the `rows` shown
+    fn reveal(&mut self, rows: usize) {
+        self.pending_reveal = false;
+        if let Some(selected) = self.selected {
+            if selected < self.first {
+                self.first = selected;
+            } else if selected >= self.first + rows {
+                self.first = selected + 1 - rows;
+            }
+        }
+    }
+}
+
+impl Ui {
+    /// One line of text
+    pub fn label(&mut self, rect: Rect, text: &str) {
+        self.text(rect, rect.x, text, self.theme.palette.text_primary);
+    }
+
+    /// Background for a group of widgets
+    pub fn panel(&mut self, rect: Rect) {
+        let palette = &self.theme.palette;
+        self.frame(rect, palette.bg_secondary, palette.border_color);
+    }
+
+    /// Push button, clicked with the pointer or with Enter or Space while
+    /// focused
+    pub fn button(&mut self, rect: Rect, label: &str) -> Response {
+        let id = self.id(label);
+        let mut response = self.interact(id, rect, true);
+        if response.focused
+            && self
+                .keys()
+                .any(|(code, _)| code == KEY_ENTER || code == KEY_SPACE)
+        {
+            response.clicked = true;
+        }
+
+        let palette = &self.theme.palette;
+        let (fill, text) = if self.held(id) && response.hovered {
+            (self.theme.accent, palette.bg_primary)
+        } else {
+            (palette.bg_accent, palette.text_primary)
+        };
+        let border = if response.hovered || response.focused {
+            self.theme.accent
+        } else {
+            palette.border_color
+        };
+        self.frame(rect, fill, border);
+        let width = self.text_width(label).min(rect.width);
+        self.text(rect, rect.x + (rect.width - width) / 2, label, text);
+        response
+    }
+
+    /// Box toggling `checked`, with `label` after it
+    pub fn checkbox(&mut self, rect: Rect, label: &str, checked: &mut bool) -> Response {
+        let id = self.id(label);
+        let mut response = self.interact(id, rect, true);
+        if response.focused && self.keys().any(|(code, _)| code == KEY_SPACE) {
+            response.clicked = true;
+        }
+        if response.clicked {
+            *checked = !*checked;
+            response.changed = true;
+        }
+
+        let palette = &self.theme.palette;
+        let size = self.line_height.min(rect.height);
+        let mark = Rect::new(rect.x, rect.y + (rect.height - size) / 2, size, size);
+        let border = if response.hovered || response.focused {
+            self.theme.accent
+        } else {
+            palette.border_color
+        };
+        if *checked {
+            self.frame(mark, self.theme.accent, border);
+            // A tick from the left middle down to the bottom third and up
+            // to the top right
+            let color = palette.bg_primary;
+            let left = Position::new(mark.x + size / 4, mark.y + size / 2);
+            let bottom = Position::new(mark.x + size * 2 / 5, mark.y + size * 3 / 4);
+            let right = Position::new(mark.x + size * 3 / 4, mark.y + size / 4);
+            draw_line(left, bottom, color);
+            draw_line(bottom, right, color);
+        } else {
+            self.frame(mark, palette.bg_secondary, border);
+        }
+        let x = mark.right() + self.theme.padding;
+        self.text(rect, x, label, palette.text_primary);
+        response
+    }
+
+    /// Row of tabs along the top of `rect`, one per title. Clicking a tab
+    /// selects it. Returns the area below the tabs for the selected page.
+    pub fn tabs(&mut self, rect: Rect, selected: &mut usize, titles: &[&str]) -> Rect {
+        let padding = self.theme.padding;
+        let (bar, page) = rect.split_top(self.line_height + 2 * padding);
+        let palette = self.theme.palette;
+        fill_rectangle(bar.position(), bar.width, bar.height, palette.bg_secondary);
+
+        let mut x = bar.x;
+        for (index, title) in titles.iter().enumerate() {
+            let width = (self.text_width(title) + 2 * padding).min(bar.right() - x);
+            if width <= 0 {
+                break;
+            }
+            let tab = Rect::new(x, bar.y, width, bar.height);
+            let hovered = tab.contains(self.pointer);
+            if self.pressed && hovered && !self.claimed {
+                self.claimed = true;
+                *selected = index;
+            }
+            let color = if index == *selected {
+                fill_rectangle(tab.position(), tab.width, tab.height, palette.bg_primary);
+                fill_rectangle(tab.position(), tab.width, 2, self.theme.accent);
+                palette.text_primary
+            } else if hovered {
+                palette.text_secondary
+            } else {
+                palette.text_muted
+            };
+            self.text(tab, tab.x + padding, title, color);
+            x += width;
+        }
+        draw_line(
+            Position::new(bar.x, bar.bottom() - 1),
+            Position::new(bar.right() - 1, bar.bottom() - 1),
+            palette.border_color,
+        );
+        page
+    }
+
+    /// Scrollable list of `items`, one line each. Clicking an item or moving
+    /// with the arrow, Page Up/Down, Home and End keys selects it; Enter
+    /// submits the selection. Scrolls with the wheel.
+    pub fn list<S: AsRef<str>>(
+        &mut self,
+        rect: Rect,
+        label: &str,
+        state: &mut ListState,
+        items: &[S],
+    ) -> Response {
+        let id = self.id(label);
+        let mut response = self.interact(id, rect, true);
+        let inner = rect.shrink(1);
+        let row_height = self.line_height + self.theme.padding;
+        let rows = (inner.height / row_height).max(1) as usize;
+        let scrollable = items.len() > rows;
+        let text_area = if scrollable {
+            inner.split_left(inner.width - SCROLLBAR_WIDTH).0
+        } else {
+            inner
+        };
+        let previous = state.selected;
+        let last = items.len().saturating_sub(1);
+        let first = state.first;
+        let row_at = |y: i32| first + ((y - inner.y) / row_height) as usize;
+
+        if self.pressed && self.active == Some(id) && text_area.contains(self.pointer) {
+            let row = row_at(self.pointer.y);
+            if row < items.len() {
+                state.selected = Some(row);
+            }
+        }
+        response.clicked &=
+            text_area.contains(self.pointer) && row_at(self.pointer.y) < items.len();
+        if response.focused && !items.is_empty() {
+            for (code, _) in self.keys() {
+                let current = state.selected;
+                state.selected = match code {
+                    KEY_UP => Some(current.map_or(last, |i| i.saturating_sub(1))),
+                    KEY_DOWN => Some(current.map_or(0, |i| (i + 1).min(last))),
+                    KEY_PAGEUP => Some(current.map_or(0, |i| i.saturating_sub(rows))),
+                    KEY_PAGEDOWN => Some(current.map_or(0, |i| (i + rows).min(last))),
+                    KEY_HOME => Some(0),
+                    KEY_END => Some(last),
+                    KEY_ENTER => {
+                        response.submitted |= current.is_some();
+                        current
+                    }
+                    _ => current,
+                };
+            }
+        }
+        response.changed = state.selected != previous;
+        if response.changed || state.pending_reveal {
+            state.reveal(rows);
+        }
+        if response.hovered && self.wheel != 0 {
+            let first = state.first as i32 - self.wheel * WHEEL_ROWS;
+            state.first = first.max(0) as usize;
+        }
+        state.first = state.first.min(items.len().saturating_sub(rows));
+
+        let palette = self.theme.palette;
+        let border = if response.focused {
+            self.theme.accent
+        } else {
+            palette.border_color
+        };
+        self.frame(rect, palette.bg_secondary, border);
+        for (row, item) in items.iter().enumerate().skip(state.first).take(rows) {
+            let y = inner.y + (row - state.first) as i32 * row_height;
+            let line = Rect::new(text_area.x, y, text_area.width, row_height);
+            let color = if state.selected == Some(row) {
+                fill_rectangle(line.position(), line.width, line.height, self.theme.accent);
+                palette.bg_primary
+            } else {
+                if line.contains(self.pointer) {
+                    fill_rectangle(line.position(), line.width, line.height, palette.bg_accent);
+                }
+                palette.text_primary
+            };
+            self.text(line, line.x + self.theme.padding, item.as_ref(), color);
+        }
+
+        if scrollable {
+            let track = Rect::new(text_area.right(), inner.y, SCROLLBAR_WIDTH, inner.height);
+            let length = (track.height * rows as i32 / items.len() as i32).max(MIN_THUMB_HEIGHT);
+            let range = items.len() - rows;
+            let offset = (track.height - length) * state.first as i32 / range as i32;
+            fill_rectangle(
+                Position::new(track.x, track.y + offset),
+                track.width,
+                length,
+                palette.border_color,
+            );
+            draw_rectangle(
+                track.position(),
+                track.width,
+                track.height,
+                palette.bg_accent,
+            );
+        }
+        response
+    }
+}